@@ -0,0 +1,57 @@
+//! Response serialization: global allocator vs per-message arena
+//!
+//! `cargo bench --features arena --bench arena`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mcp_jsonrpc::arena::MessageArena;
+use mcp_jsonrpc::{JsonRpcId, JsonRpcResponse};
+use serde_json::json;
+
+fn single_response() -> JsonRpcResponse {
+    JsonRpcResponse::success(
+        JsonRpcId::Number(42),
+        json!({
+            "content": [{ "type": "text", "text": "The quick brown fox jumps over the lazy dog" }],
+            "isError": false,
+        }),
+    )
+}
+
+fn batch_response() -> Vec<JsonRpcResponse> {
+    (0..100)
+        .map(|i| JsonRpcResponse::success(JsonRpcId::Number(i), json!({ "value": i, "ok": true })))
+        .collect()
+}
+
+fn serialize(c: &mut Criterion) {
+    let single = single_response();
+    let batch = batch_response();
+
+    let mut group = c.benchmark_group("serialize_response");
+    group.bench_function("global/single", |b| {
+        b.iter(|| black_box(serde_json::to_string(&single).unwrap()).len())
+    });
+    group.bench_function("arena/single", |b| {
+        let mut arena = MessageArena::new();
+        b.iter(|| {
+            let len = black_box(arena.to_json(&single).unwrap()).len();
+            arena.reset();
+            len
+        })
+    });
+    group.bench_function("global/batch100", |b| {
+        b.iter(|| black_box(serde_json::to_string(&batch).unwrap()).len())
+    });
+    group.bench_function("arena/batch100", |b| {
+        let mut arena = MessageArena::new();
+        b.iter(|| {
+            let len = black_box(arena.to_json(&batch).unwrap()).len();
+            arena.reset();
+            len
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
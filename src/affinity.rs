@@ -0,0 +1,403 @@
+//! Session affinity across server processes
+//!
+//! Behind `SO_REUSEPORT` or a load balancer, a reconnecting client may land on
+//! any of several server processes, while the state of its session (caches,
+//! subscriptions, uploads in progress) lives in the one it talked to before.
+//! This module defines how a client finds its way back:
+//!
+//! - `initialize` results carry an affinity key in `_meta.affinityKey`. The
+//!   key names the process that issued it: `<node>:<token>`.
+//! - A reconnecting client sends the key back in the `_meta.affinityKey` of
+//!   its `initialize` params.
+//! - The process owning the key resumes it, and answers with the same key.
+//!   A key it no longer knows (expired, or issued before a restart) is
+//!   replaced with a new one, so the client starts over.
+//! - A process receiving a key of another node asks its locator where that
+//!   node is, and answers with a redirect error (-32006) naming it in
+//!   `data.redirect`:
+//!
+//! ```text
+//! {"jsonrpc":"2.0","error":{"code":-32006,"message":"Session owned by another server","data":{"redirect":{"node":"b","address":"10.0.0.2:7000"},...}},"id":1}
+//! ```
+//!
+//! Keys of a node the locator does not know are replaced as well, so a node
+//! leaving the deployment does not strand its clients. Keys are not signed: a
+//! forged key is either unknown to its node or redirects to a real one.
+//!
+//! [`Affinity`] is given to a processor with
+//! [`with_affinity`](crate::processor::JsonRpcProcessor::with_affinity); tools
+//! key their per-session state with [`session_key`]. [`StickySession`] is the
+//! client middleware sending the key back; the client follows a redirect by
+//! connecting to the address of [`redirect_of`] itself.
+//!
+//! ```rust,ignore
+//! // Server: node "a" of a deployment
+//! let affinity = Affinity::new("a").with_locator(|node: &str| nodes.address_of(node));
+//! let processor = server.processor(transport).with_affinity(affinity.clone());
+//!
+//! // Client: keep the middleware across connections
+//! let sticky = StickySession::new();
+//! let mut client = JsonRpcClient::new(transport).with_middleware(sticky.clone());
+//! match client.call("initialize", params).await {
+//!     Err(e) => match redirect_of(&e) {
+//!         Some(redirect) => { /* connect to redirect.address and initialize again */ }
+//!         None => return Err(e),
+//!     },
+//!     Ok(result) => { /* sticky.key() is now set */ }
+//! }
+//! ```
+
+use crate::client::middleware::{AfterResponse, ClientMiddleware};
+use crate::context::SessionStore;
+use crate::error::{error_details, helpers, is_redirect};
+use crate::mcp::methods;
+use crate::protocol::JsonRpcRequest;
+use async_trait::async_trait;
+use mcp_error::{Error as McpError, Result as McpResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Field of `_meta` holding the key, in `initialize` params and results
+pub const META_FIELD: &str = "affinityKey";
+
+/// Session store key holding the affinity key of the session
+pub const SESSION_KEY: &str = "affinity.key";
+
+/// Keys remembered by a process unless configured otherwise
+pub const DEFAULT_MAX_KEYS: usize = 65_536;
+
+/// Affinity key, naming the process owning a session
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AffinityKey {
+    /// Node that issued the key
+    pub node: String,
+    /// Token of the session on that node
+    pub token: String,
+}
+
+impl AffinityKey {
+    /// Parse a `<node>:<token>` key
+    pub fn parse(key: &str) -> Option<Self> {
+        let (node, token) = key.rsplit_once(':')?;
+        (!node.is_empty() && !token.is_empty()).then(|| Self {
+            node: node.to_string(),
+            token: token.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for AffinityKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.node, self.token)
+    }
+}
+
+/// Where a client should reconnect, sent as `data.redirect`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redirect {
+    /// Node owning the session
+    pub node: String,
+    /// Address of that node, in whatever form the deployment's clients connect to
+    pub address: String,
+}
+
+/// Finds the address of another node of the deployment
+pub trait NodeLocator: Send + Sync {
+    /// Address of the node, or `None` if it is no longer part of the deployment
+    fn locate(&self, node: &str) -> Option<String>;
+}
+
+impl<F> NodeLocator for F
+where
+    F: Fn(&str) -> Option<String> + Send + Sync,
+{
+    fn locate(&self, node: &str) -> Option<String> {
+        self(node)
+    }
+}
+
+/// Keys issued by a process, oldest first, forgotten beyond a bound
+#[derive(Debug, Default)]
+struct KnownKeys {
+    keys: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+#[derive(Debug)]
+struct State {
+    known: Mutex<KnownKeys>,
+    next_token: AtomicU64,
+    random: RandomState,
+}
+
+/// Server side of the convention: issues keys at `initialize` and checks
+/// those sent back
+///
+/// Clones share the keys issued, so give one instance to every processor of
+/// the process.
+#[derive(Clone)]
+pub struct Affinity {
+    node: String,
+    locator: Option<Arc<dyn NodeLocator>>,
+    max_keys: usize,
+    state: Arc<State>,
+}
+
+impl Affinity {
+    /// Issue keys for the given node, which must be unique in the deployment
+    ///
+    /// Keys of other nodes are replaced until a locator is set.
+    pub fn new(node: impl Into<String>) -> Self {
+        Self {
+            node: node.into(),
+            locator: None,
+            max_keys: DEFAULT_MAX_KEYS,
+            state: Arc::new(State {
+                known: Mutex::new(KnownKeys::default()),
+                next_token: AtomicU64::new(1),
+                random: RandomState::new(),
+            }),
+        }
+    }
+
+    /// Redirect clients presenting keys of other nodes to the address the
+    /// locator gives
+    pub fn with_locator<L: NodeLocator + 'static>(mut self, locator: L) -> Self {
+        self.locator = Some(Arc::new(locator));
+        self
+    }
+
+    /// Set how many keys are remembered; the oldest are forgotten first
+    pub fn with_max_keys(mut self, max: usize) -> Self {
+        self.max_keys = max.max(1);
+        self
+    }
+
+    /// Node of this process
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Whether a key was issued by this process and is still remembered
+    pub fn is_known(&self, key: &str) -> bool {
+        self.lock().keys.contains(key)
+    }
+
+    /// Forget a key, once the state of its session is dropped; a client
+    /// presenting it again gets a new one
+    pub fn forget(&self, key: &str) -> bool {
+        let mut known = self.lock();
+        if !known.keys.remove(key) {
+            return false;
+        }
+        known.order.retain(|k| k != key);
+        true
+    }
+
+    /// Take the key out of `initialize` params and settle the key of the
+    /// session: the same one if this process owns it, a new one otherwise
+    ///
+    /// Fails with a redirect error if another node owns the key, and with an
+    /// invalid params error if the key is malformed.
+    pub(crate) fn admit(
+        &self,
+        params: &mut Option<Value>,
+        session: &SessionStore,
+    ) -> McpResult<()> {
+        let presented = match take_key(params) {
+            None => None,
+            Some(Value::String(key)) => Some(key),
+            Some(_) => return Err(helpers::invalid_params("Affinity key must be a string")),
+        };
+        let key = match presented {
+            None => self.issue(),
+            Some(key) => {
+                let parsed = AffinityKey::parse(&key)
+                    .ok_or_else(|| helpers::invalid_params("Malformed affinity key"))?;
+                if parsed.node == self.node {
+                    if self.is_known(&key) {
+                        key
+                    } else {
+                        self.issue()
+                    }
+                } else {
+                    match self.locate(&parsed.node) {
+                        Some(redirect) => {
+                            return Err(helpers::redirect(
+                                &format!("Session owned by node '{}'", redirect.node),
+                                serde_json::to_value(&redirect).unwrap_or(Value::Null),
+                            ))
+                        }
+                        None => self.issue(),
+                    }
+                }
+            }
+        };
+        session.set(SESSION_KEY, Value::String(key));
+        Ok(())
+    }
+
+    /// Add the key of the session to an `initialize` result
+    pub(crate) fn annotate(&self, mut result: Value, session: &SessionStore) -> Value {
+        if let Some(key) = session.get(SESSION_KEY) {
+            insert_key(&mut result, key);
+        }
+        result
+    }
+
+    fn locate(&self, node: &str) -> Option<Redirect> {
+        let address = self.locator.as_ref()?.locate(node)?;
+        Some(Redirect {
+            node: node.to_string(),
+            address,
+        })
+    }
+
+    fn issue(&self) -> String {
+        let counter = self.state.next_token.fetch_add(1, Ordering::Relaxed);
+        let mut hasher = self.state.random.build_hasher();
+        hasher.write_u64(counter);
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        let key = AffinityKey {
+            node: self.node.clone(),
+            token: format!("{:016x}{:x}", hasher.finish(), counter),
+        }
+        .to_string();
+
+        let mut known = self.lock();
+        while known.order.len() >= self.max_keys {
+            if let Some(oldest) = known.order.pop_front() {
+                known.keys.remove(&oldest);
+            }
+        }
+        known.keys.insert(key.clone());
+        known.order.push_back(key.clone());
+        key
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, KnownKeys> {
+        self.state.known.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Affinity key of a session, set once `initialize` succeeded
+///
+/// Tools keep the state surviving reconnects under this key.
+pub fn session_key(session: &SessionStore) -> Option<String> {
+    session
+        .get(SESSION_KEY)
+        .and_then(|key| key.as_str().map(str::to_string))
+}
+
+/// Where a redirect error, raised locally or received from a server, sends
+/// the client
+pub fn redirect_of(err: &McpError) -> Option<Redirect> {
+    if !is_redirect(err) {
+        return None;
+    }
+    let details = error_details(err)?;
+    // Errors received from a peer keep the whole error object
+    let redirect = details
+        .get("redirect")
+        .or_else(|| details.get("data")?.get("redirect"))?;
+    serde_json::from_value(redirect.clone()).ok()
+}
+
+/// Client middleware sending the affinity key back when initializing again
+///
+/// Clones share the key, so a clone kept by the application carries it over
+/// to the client of the next connection.
+#[derive(Clone, Default)]
+pub struct StickySession {
+    key: Arc<Mutex<Option<String>>>,
+}
+
+impl StickySession {
+    /// Start without a key
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start with a key saved from an earlier run
+    pub fn with_key(key: impl Into<String>) -> Self {
+        Self {
+            key: Arc::new(Mutex::new(Some(key.into()))),
+        }
+    }
+
+    /// Key of the session, once a server issued one
+    pub fn key(&self) -> Option<String> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<String>> {
+        self.key.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl ClientMiddleware for StickySession {
+    async fn before_request(&self, request: &mut JsonRpcRequest) -> Option<McpResult<Value>> {
+        if request.method == methods::INITIALIZE {
+            if let (Some(key), Some(params)) = (self.key(), request.params.as_mut()) {
+                insert_key(params, Value::String(key));
+            }
+        }
+        None
+    }
+
+    async fn after_response(
+        &self,
+        request: &JsonRpcRequest,
+        result: &mut McpResult<Value>,
+        _attempt: u32,
+    ) -> AfterResponse {
+        if request.method != methods::INITIALIZE {
+            return AfterResponse::Return;
+        }
+        if let Ok(result) = result {
+            let issued = result
+                .get("_meta")
+                .and_then(|meta| meta.get(META_FIELD))
+                .and_then(Value::as_str);
+            if let Some(issued) = issued {
+                *self.lock() = Some(issued.to_string());
+            }
+        }
+        AfterResponse::Return
+    }
+}
+
+/// Set the key in the `_meta` of an object, creating `_meta` as needed
+fn insert_key(value: &mut Value, key: Value) {
+    let meta = match value {
+        Value::Object(value) => value
+            .entry("_meta")
+            .or_insert_with(|| Value::Object(Map::new())),
+        _ => return,
+    };
+    if let Value::Object(meta) = meta {
+        meta.insert(META_FIELD.to_string(), key);
+    }
+}
+
+/// Remove the key from `_meta`, dropping `_meta` once empty
+fn take_key(params: &mut Option<Value>) -> Option<Value> {
+    let params = params.as_mut()?.as_object_mut()?;
+    let meta = params.get_mut("_meta")?.as_object_mut()?;
+    let key = meta.remove(META_FIELD)?;
+    if meta.is_empty() {
+        params.remove("_meta");
+    }
+    Some(key)
+}
@@ -0,0 +1,440 @@
+//! Bearer token authentication in `_meta`
+//!
+//! Raw socket transports have no headers to carry credentials, so this module
+//! defines where they go in the messages themselves: the client puts a bearer
+//! token in the `_meta` object of the params of every request and
+//! notification, under `authorization` unless configured otherwise.
+//!
+//! ```text
+//! {"jsonrpc":"2.0","method":"tools/call","params":{"name":"search","arguments":{},"_meta":{"authorization":"Bearer eyJhbGciOi..."}},"id":1}
+//! ```
+//!
+//! - Messages without params get params holding only `_meta`; positional
+//!   (array) params cannot carry a token and are sent without one.
+//! - The server checks the token before dispatching, and answers requests
+//!   without a valid token with an unauthorized error (-32005). Notifications
+//!   without a valid token are dropped.
+//! - `initialize`, `notifications/initialized` and `ping` are public by default,
+//!   so a peer can connect and learn about the server before authenticating.
+//! - The server removes the token before the method sees the params, dropping
+//!   `_meta` when nothing else is left in it. The default redacted fields of
+//!   the [history](crate::history) already include `authorization`.
+//!
+//! With [`AnonymousAccess`], peers without a token are served a restricted
+//! set of methods and tools instead of being rejected, and `initialize` and
+//! `tools/list` advertise only what they may use. Authentication then happens
+//! in-band: the first message carrying a valid token upgrades the session for
+//! the rest of the connection, and the processor follows its response with
+//! `notifications/tools/list_changed` so the peer lists the tools again.
+//!
+//! [`BearerToken`] is the client middleware adding the token;
+//! [`BearerAuthenticator`] is given to a processor with
+//! [`with_authenticator`](crate::processor::JsonRpcProcessor::with_authenticator).
+//!
+//! ```rust,ignore
+//! // Client: fetch a token before the first call and again once rejected
+//! let client = JsonRpcClient::new(transport)
+//!     .with_middleware(BearerToken::refreshing(|| async { fetch_token().await }));
+//!
+//! // Server
+//! let processor = server
+//!     .processor(transport)
+//!     .with_authenticator(BearerAuthenticator::new(|token: &str| token == expected));
+//! ```
+
+use crate::client::middleware::{AfterResponse, ClientMiddleware};
+use crate::context::{RequestContext, SessionStore};
+use crate::error::{helpers, is_unauthorized};
+use crate::mcp::{methods, ServerCapabilities};
+use crate::protocol::{JsonRpcNotification, JsonRpcRequest};
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Field of `_meta` holding the token unless configured otherwise
+pub const DEFAULT_META_FIELD: &str = "authorization";
+
+/// Methods served without a token unless configured otherwise
+pub const DEFAULT_PUBLIC_METHODS: &[&str] =
+    &[methods::INITIALIZE, methods::INITIALIZED, methods::PING];
+
+/// Session store key set once a session with anonymous access is authenticated
+pub const AUTHENTICATED_KEY: &str = "auth.authenticated";
+
+/// Scheme prefixing the token in the `_meta` field
+const SCHEME: &str = "Bearer";
+
+type TokenFuture = Pin<Box<dyn Future<Output = McpResult<String>> + Send>>;
+type RefreshFn = Arc<dyn Fn() -> TokenFuture + Send + Sync>;
+
+/// Client middleware adding a bearer token to every request and notification
+///
+/// Clones share the same token.
+#[derive(Clone)]
+pub struct BearerToken {
+    field: String,
+    token: Arc<Mutex<Option<String>>>,
+    refresh: Option<RefreshFn>,
+}
+
+impl BearerToken {
+    /// Send a fixed token
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            field: DEFAULT_META_FIELD.to_string(),
+            token: Arc::new(Mutex::new(Some(token.into()))),
+            refresh: None,
+        }
+    }
+
+    /// Fetch the token with the given function before the first message
+    pub fn refreshing<F, Fut>(refresh: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = McpResult<String>> + Send + 'static,
+    {
+        Self {
+            field: DEFAULT_META_FIELD.to_string(),
+            token: Arc::new(Mutex::new(None)),
+            refresh: None,
+        }
+        .with_refresh(refresh)
+    }
+
+    /// Fetch a new token with the given function when a call is rejected as
+    /// unauthorized, then send the call again once
+    pub fn with_refresh<F, Fut>(mut self, refresh: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = McpResult<String>> + Send + 'static,
+    {
+        self.refresh = Some(Arc::new(move || Box::pin(refresh())));
+        self
+    }
+
+    /// Put the token under another field of `_meta`
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = field.into();
+        self
+    }
+
+    /// Token to send, fetching one if there is none yet
+    async fn current(&self) -> McpResult<Option<String>> {
+        let mut token = self.token.lock().await;
+        if let (None, Some(refresh)) = (token.as_ref(), &self.refresh) {
+            *token = Some(refresh().await?);
+        }
+        Ok(token.clone())
+    }
+
+    /// Forget the token a rejected call was sent with, unless it was already replaced
+    async fn invalidate(&self, rejected: Option<&str>) {
+        let mut token = self.token.lock().await;
+        if token.as_deref() == rejected {
+            *token = None;
+        }
+    }
+}
+
+#[async_trait]
+impl ClientMiddleware for BearerToken {
+    async fn before_request(&self, request: &mut JsonRpcRequest) -> Option<McpResult<Value>> {
+        match self.current().await {
+            Ok(Some(token)) => {
+                insert_token(&mut request.params, &self.field, &token);
+                None
+            }
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    async fn after_response(
+        &self,
+        request: &JsonRpcRequest,
+        result: &mut McpResult<Value>,
+        attempt: u32,
+    ) -> AfterResponse {
+        let rejected = matches!(result, Err(e) if is_unauthorized(e));
+        if !rejected || attempt > 1 || self.refresh.is_none() {
+            return AfterResponse::Return;
+        }
+        let sent = meta_field(&request.params, &self.field)
+            .and_then(Value::as_str)
+            .and_then(bearer_token);
+        self.invalidate(sent).await;
+        AfterResponse::Retry(Duration::ZERO)
+    }
+
+    async fn before_notification(&self, notification: &mut JsonRpcNotification) -> bool {
+        // Sent without a token when none can be fetched; the server drops it
+        if let Ok(Some(token)) = self.current().await {
+            insert_token(&mut notification.params, &self.field, &token);
+        }
+        true
+    }
+}
+
+/// Checks the tokens received by a [`BearerAuthenticator`]
+#[async_trait]
+pub trait TokenValidator: Send + Sync {
+    /// Accept the token, or reject it, typically with [`helpers::unauthorized`]
+    async fn validate(&self, token: &str, context: &RequestContext) -> McpResult<()>;
+}
+
+#[async_trait]
+impl<F> TokenValidator for F
+where
+    F: Fn(&str) -> bool + Send + Sync,
+{
+    async fn validate(&self, token: &str, _context: &RequestContext) -> McpResult<()> {
+        if self(token) {
+            Ok(())
+        } else {
+            Err(helpers::unauthorized("Invalid bearer token"))
+        }
+    }
+}
+
+/// What sessions without a token may use
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnonymousAccess {
+    /// Methods served without a token, besides the public ones
+    pub methods: HashSet<String>,
+    /// Tools listed and callable without a token, directly or through `tools/call`
+    pub tools: HashSet<String>,
+    /// Capabilities advertised by `initialize`, those of the server if `None`
+    pub capabilities: Option<ServerCapabilities>,
+}
+
+impl AnonymousAccess {
+    /// Serve nothing beyond the public methods
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also serve a method without a token
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.methods.insert(method.into());
+        self
+    }
+
+    /// Also list and serve a tool without a token
+    pub fn with_tool(mut self, tool: impl Into<String>) -> Self {
+        self.tools.insert(tool.into());
+        self
+    }
+
+    /// Advertise the given capabilities in `initialize`
+    pub fn with_capabilities(mut self, capabilities: ServerCapabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Whether a call may be served without a token
+    fn allows(&self, method: &str, params: &Option<Value>) -> bool {
+        let tool = match method {
+            methods::TOOLS_LIST => return true,
+            methods::TOOLS_CALL => params
+                .as_ref()
+                .and_then(|params| params.get("name"))
+                .and_then(Value::as_str),
+            method => Some(method),
+        };
+        self.methods.contains(method) || tool.is_some_and(|tool| self.tools.contains(tool))
+    }
+}
+
+/// Whether the session was upgraded by a valid token; see [`AnonymousAccess`]
+pub fn is_authenticated(session: &SessionStore) -> bool {
+    session.get(AUTHENTICATED_KEY) == Some(Value::Bool(true))
+}
+
+/// Server side of the convention: checks the token of every message
+///
+/// Clones share the same validator.
+#[derive(Clone)]
+pub struct BearerAuthenticator {
+    validator: Arc<dyn TokenValidator>,
+    field: String,
+    public: Arc<HashSet<String>>,
+    anonymous: Option<Arc<AnonymousAccess>>,
+}
+
+impl BearerAuthenticator {
+    /// Check tokens with the given validator
+    pub fn new<V: TokenValidator + 'static>(validator: V) -> Self {
+        Self {
+            validator: Arc::new(validator),
+            field: DEFAULT_META_FIELD.to_string(),
+            public: Arc::new(
+                DEFAULT_PUBLIC_METHODS
+                    .iter()
+                    .map(|method| method.to_string())
+                    .collect(),
+            ),
+            anonymous: None,
+        }
+    }
+
+    /// Read the token from another field of `_meta`
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = field.into();
+        self
+    }
+
+    /// Also serve a method without a token
+    pub fn with_public_method(mut self, method: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.public).insert(method.into());
+        self
+    }
+
+    /// Serve only the given methods without a token
+    pub fn with_public_methods<I, S>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.public = Arc::new(methods.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Serve sessions without a token a restricted set of methods, until a
+    /// valid token authenticates them
+    pub fn with_anonymous_access(mut self, access: AnonymousAccess) -> Self {
+        self.anonymous = Some(Arc::new(access));
+        self
+    }
+
+    /// Whether a method is served without a token
+    pub fn is_public(&self, method: &str) -> bool {
+        self.public.contains(method)
+    }
+
+    /// Take the token out of the params and check it, unless the call may be
+    /// served without one
+    ///
+    /// Returns whether the call upgraded an anonymous session.
+    pub(crate) async fn authenticate(
+        &self,
+        params: &mut Option<Value>,
+        context: &RequestContext,
+    ) -> McpResult<bool> {
+        let token = take_token(params, &self.field);
+        let public = self.is_public(&context.method);
+        let anonymous = match &self.anonymous {
+            Some(_) if is_authenticated(&context.session) => return Ok(false),
+            Some(access) => access,
+            None if public => return Ok(false),
+            None => {
+                self.check(token, context).await?;
+                return Ok(false);
+            }
+        };
+
+        // A bad token does not stop a call that needs none
+        let allowed = public || anonymous.allows(&context.method, params);
+        match (token, allowed) {
+            (None, true) => Ok(false),
+            (token, allowed) => match self.check(token, context).await {
+                Ok(()) => {
+                    context.session.set(AUTHENTICATED_KEY, Value::Bool(true));
+                    Ok(true)
+                }
+                Err(_) if allowed => Ok(false),
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    async fn check(&self, token: Option<Value>, context: &RequestContext) -> McpResult<()> {
+        let token = token.ok_or_else(|| {
+            helpers::unauthorized(&format!("Missing bearer token in _meta.{}", self.field))
+        })?;
+        let token = token
+            .as_str()
+            .and_then(bearer_token)
+            .ok_or_else(|| helpers::unauthorized("Malformed bearer token"))?;
+        self.validator.validate(token, context).await
+    }
+
+    /// Narrow what `initialize` and `tools/list` advertise to anonymous sessions
+    pub(crate) fn restrict_result(
+        &self,
+        method: &str,
+        mut result: Value,
+        session: &SessionStore,
+    ) -> Value {
+        let anonymous = match &self.anonymous {
+            Some(access) if !is_authenticated(session) => access,
+            _ => return result,
+        };
+        match method {
+            methods::INITIALIZE => {
+                let capabilities = anonymous
+                    .capabilities
+                    .as_ref()
+                    .and_then(|capabilities| serde_json::to_value(capabilities).ok());
+                if let (Some(capabilities), Value::Object(result)) = (capabilities, &mut result) {
+                    result.insert("capabilities".to_string(), capabilities);
+                }
+            }
+            methods::TOOLS_LIST => {
+                if let Some(Value::Array(tools)) = result.get_mut("tools") {
+                    tools.retain(|tool| {
+                        tool.get("name")
+                            .and_then(Value::as_str)
+                            .is_some_and(|name| anonymous.tools.contains(name))
+                    });
+                }
+            }
+            _ => {}
+        }
+        result
+    }
+}
+
+/// Token of a `Bearer <token>` value, the scheme matched case-insensitively
+fn bearer_token(value: &str) -> Option<&str> {
+    let (scheme, token) = value.split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case(SCHEME) && !token.is_empty()).then_some(token)
+}
+
+fn meta_field<'a>(params: &'a Option<Value>, field: &str) -> Option<&'a Value> {
+    params.as_ref()?.get("_meta")?.get(field)
+}
+
+/// Set the field of `_meta`, creating params and `_meta` as needed
+fn insert_token(params: &mut Option<Value>, field: &str, token: &str) {
+    let params = params.get_or_insert_with(|| Value::Object(Map::new()));
+    let meta = match params {
+        Value::Object(params) => params
+            .entry("_meta")
+            .or_insert_with(|| Value::Object(Map::new())),
+        _ => return,
+    };
+    if let Value::Object(meta) = meta {
+        meta.insert(
+            field.to_string(),
+            Value::String(format!("{} {}", SCHEME, token)),
+        );
+    }
+}
+
+/// Remove the field from `_meta`, dropping `_meta` once empty
+fn take_token(params: &mut Option<Value>, field: &str) -> Option<Value> {
+    let params = params.as_mut()?.as_object_mut()?;
+    let meta = params.get_mut("_meta")?.as_object_mut()?;
+    let token = meta.remove(field)?;
+    if meta.is_empty() {
+        params.remove("_meta");
+    }
+    Some(token)
+}
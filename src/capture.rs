@@ -0,0 +1,218 @@
+//! JSON Lines capture format
+//!
+//! A capture is a file with one JSON object per line, each wrapping one
+//! message in a small envelope:
+//!
+//! ```json
+//! {"timestampMs":1700000000000,"direction":"inbound","connection":"10.0.0.7:5123","message":{"jsonrpc":"2.0","method":"ping","id":1}}
+//! ```
+//!
+//! `connection` is omitted when unknown, and a line that was not valid JSON
+//! is kept as a string `message`. [`CaptureWriter`] produces captures and
+//! [`CaptureReader`] reads them back, filtering by direction, connection or
+//! method, and can replay them over a transport at the original pace or
+//! scaled. [`MessageHistory::export_capture`] writes a history in this format.
+
+use crate::error::helpers;
+use crate::history::Direction;
+use crate::transport::Transport;
+use mcp_error::Result as McpResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// One captured message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureRecord {
+    /// Milliseconds since the Unix epoch when the message was captured
+    pub timestamp_ms: u64,
+    /// Whether the message was received or sent
+    pub direction: Direction,
+    /// Connection the message was exchanged on, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection: Option<String>,
+    /// The message itself
+    pub message: Value,
+}
+
+impl CaptureRecord {
+    /// Capture a raw message now
+    pub fn new(direction: Direction, connection: Option<String>, message: &str) -> Self {
+        Self {
+            timestamp_ms: now_ms(),
+            direction,
+            connection,
+            message: serde_json::from_str(message)
+                .unwrap_or_else(|_| Value::String(message.to_string())),
+        }
+    }
+
+    /// Method of the message, for requests and notifications
+    pub fn method(&self) -> Option<&str> {
+        self.message.get("method").and_then(Value::as_str)
+    }
+
+    /// The message as sent on the wire
+    pub fn raw(&self) -> String {
+        match &self.message {
+            Value::String(raw) => raw.clone(),
+            message => message.to_string(),
+        }
+    }
+}
+
+/// Writes captures
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+    connection: Option<String>,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Write a capture to the given writer
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            connection: None,
+        }
+    }
+
+    /// Tag the messages written with [`write`](Self::write) with a connection
+    pub fn with_connection(mut self, connection: impl Into<String>) -> Self {
+        self.connection = Some(connection.into());
+        self
+    }
+
+    /// Capture a raw message now
+    pub fn write(&mut self, direction: Direction, message: &str) -> McpResult<()> {
+        let record = CaptureRecord::new(direction, self.connection.clone(), message);
+        self.write_record(&record)
+    }
+
+    /// Append a record
+    pub fn write_record(&mut self, record: &CaptureRecord) -> McpResult<()> {
+        serde_json::to_writer(&mut self.writer, record).map_err(helpers::json_error)?;
+        self.writer.write_all(b"\n").map_err(io_error)
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> McpResult<()> {
+        self.writer.flush().map_err(io_error)
+    }
+
+    /// Return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads captures, keeping the records that pass the filters
+///
+/// Iterating yields the records in file order; blank lines are skipped and a
+/// malformed line yields an error without ending the iteration.
+pub struct CaptureReader<R: BufRead> {
+    lines: std::io::Lines<R>,
+    direction: Option<Direction>,
+    connection: Option<String>,
+    method: Option<String>,
+}
+
+impl<R: BufRead> CaptureReader<R> {
+    /// Read a capture from the given reader
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            direction: None,
+            connection: None,
+            method: None,
+        }
+    }
+
+    /// Keep only the messages going in the given direction
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Keep only the messages of the given connection
+    pub fn with_connection(mut self, connection: impl Into<String>) -> Self {
+        self.connection = Some(connection.into());
+        self
+    }
+
+    /// Keep only the requests and notifications of the given method
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+
+    /// Send the kept messages over a transport, reproducing the original gaps
+    ///
+    /// Gaps are divided by `speed`: 2.0 replays twice as fast, while 0 or an
+    /// infinite speed sends the messages back to back. Nothing is read from
+    /// the transport. Returns the number of messages sent.
+    pub async fn replay<T: Transport>(self, transport: &mut T, speed: f64) -> McpResult<usize> {
+        let mut previous: Option<u64> = None;
+        let mut sent = 0;
+        for record in self {
+            let record = record?;
+            if let Some(previous) = previous {
+                let gap = record.timestamp_ms.saturating_sub(previous);
+                if speed > 0.0 && speed.is_finite() && gap > 0 {
+                    tokio::time::sleep(Duration::from_secs_f64(gap as f64 / 1000.0 / speed)).await;
+                }
+            }
+            previous = Some(record.timestamp_ms);
+
+            transport.send(&record.raw()).await?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    fn keeps(&self, record: &CaptureRecord) -> bool {
+        self.direction.is_none_or(|d| d == record.direction)
+            && self
+                .connection
+                .as_deref()
+                .is_none_or(|c| record.connection.as_deref() == Some(c))
+            && self
+                .method
+                .as_deref()
+                .is_none_or(|m| record.method() == Some(m))
+    }
+}
+
+impl<R: BufRead> Iterator for CaptureReader<R> {
+    type Item = McpResult<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(io_error(e))),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<CaptureRecord>(&line) {
+                Ok(record) if self.keeps(&record) => return Some(Ok(record)),
+                Ok(_) => continue,
+                Err(e) => return Some(Err(helpers::json_error(e))),
+            }
+        }
+    }
+}
+
+fn io_error(err: std::io::Error) -> mcp_error::Error {
+    helpers::transport_error(&format!("Capture I/O failed: {}", err))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
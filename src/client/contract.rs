@@ -0,0 +1,538 @@
+//! Result contracts
+//!
+//! A client can register the JSON Schema it expects the result of a method to
+//! match with [`JsonRpcClient::with_result_schema`](super::JsonRpcClient::with_result_schema).
+//! Results that do not match fail the call with a [`ContractViolation`]
+//! instead of reaching the caller, so a server drifting from its contract is
+//! caught where the result is consumed. The violation keeps the raw result.
+//!
+//! Validation covers the keywords that describe the shape of a value: `type`,
+//! `enum`, `const`, `properties`, `required`, `additionalProperties`, `items`,
+//! `prefixItems`, `minLength`, `maxLength`, `minItems`, `maxItems`,
+//! `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum` (in their
+//! numeric form), `multipleOf`, `allOf`, `anyOf`, `oneOf` and local `$ref`s.
+//! Other keywords, such as `format`, `pattern`, `minProperties` or
+//! `uniqueItems`, are ignored. Schemas nested deeper than 64 levels, e.g.
+//! through a `$ref` cycle, fail validation rather than being skipped.
+
+use crate::error::{error_details, helpers, reference_codes};
+use mcp_error::{Error as McpError, Severity};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Depth past which nested schemas are not followed, against `$ref` cycles
+const MAX_SCHEMA_DEPTH: usize = 64;
+
+/// Result that did not match the schema registered for its method
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractViolation {
+    /// Method that was called
+    pub method: String,
+    /// Mismatches, each prefixed with the JSON pointer of the offending value
+    pub violations: Vec<String>,
+    /// The result as received
+    pub result: Value,
+}
+
+impl ContractViolation {
+    /// Get the violation reported by an error, if it is one
+    pub fn from_error(err: &McpError) -> Option<Self> {
+        if !err.reference.contains(reference_codes::CONTRACT) {
+            return None;
+        }
+        serde_json::from_value(error_details(err)?.clone()).ok()
+    }
+
+    /// Turn the violation into an error, keeping it in the error details
+    pub fn into_error(self) -> McpError {
+        let err = McpError::new(
+            Severity::Error,
+            reference_codes::CONTRACT,
+            format!(
+                "Result of '{}' violates its contract: {}",
+                self.method,
+                self.violations.join("; ")
+            ),
+        );
+        helpers::with_details(err, serde_json::to_value(self).unwrap_or(Value::Null))
+    }
+}
+
+/// Check a value against a JSON Schema, returning the mismatches found
+pub fn schema_violations(schema: &Value, value: &Value) -> Vec<String> {
+    let mut violations = Vec::new();
+    check(schema, schema, value, "", 0, &mut violations);
+    violations
+}
+
+fn check(
+    schema: &Value,
+    root: &Value,
+    value: &Value,
+    path: &str,
+    depth: usize,
+    violations: &mut Vec<String>,
+) {
+    if depth > MAX_SCHEMA_DEPTH {
+        let message = format!("schema nested deeper than {} levels", MAX_SCHEMA_DEPTH);
+        return report(violations, path, message);
+    }
+    let schema = match schema {
+        Value::Bool(false) => return report(violations, path, "no value is allowed".to_string()),
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match reference
+            .strip_prefix('#')
+            .and_then(percent_decode)
+            .and_then(|pointer| root.pointer(&pointer))
+        {
+            Some(target) => check(target, root, value, path, depth + 1, violations),
+            None => report(
+                violations,
+                path,
+                format!("unresolvable reference '{}'", reference),
+            ),
+        }
+        return;
+    }
+
+    let kinds: Vec<&str> = match schema.get("type") {
+        Some(Value::String(kind)) => vec![kind.as_str()],
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !kinds.is_empty() && !kinds.iter().any(|kind| has_type(value, kind)) {
+        let message = format!("expected {}, got {}", kinds.join(" or "), type_of(value));
+        return report(violations, path, message);
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            report(violations, path, format!("expected {}", expected));
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            let message = format!("{} is not one of the allowed values", value);
+            report(violations, path, message);
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(name) {
+                        let message = format!("missing required property '{}'", name);
+                        report(violations, path, message);
+                    }
+                }
+            }
+            for (name, member) in object {
+                let member_path = format!("{}/{}", path, escape(name));
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(property) => {
+                        check(property, root, member, &member_path, depth + 1, violations)
+                    }
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            report(violations, path, format!("unexpected property '{}'", name))
+                        }
+                        Some(additional) => check(
+                            additional,
+                            root,
+                            member,
+                            &member_path,
+                            depth + 1,
+                            violations,
+                        ),
+                        None => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            let prefix = schema
+                .get("prefixItems")
+                .and_then(Value::as_array)
+                .map_or(&[][..], Vec::as_slice);
+            for (index, item) in items.iter().enumerate() {
+                let item_schema = prefix.get(index).or_else(|| schema.get("items"));
+                if let Some(item_schema) = item_schema {
+                    let item_path = format!("{}/{}", path, index);
+                    check(item_schema, root, item, &item_path, depth + 1, violations);
+                }
+            }
+            check_bounds(
+                schema,
+                "minItems",
+                "maxItems",
+                items.len(),
+                path,
+                violations,
+            );
+        }
+        Value::String(text) => {
+            let length = text.chars().count();
+            check_bounds(schema, "minLength", "maxLength", length, path, violations);
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or(f64::NAN);
+            let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+            if let Some(minimum) = bound("minimum") {
+                if number < minimum {
+                    report(violations, path, format!("below minimum {}", minimum));
+                }
+            }
+            if let Some(maximum) = bound("maximum") {
+                if number > maximum {
+                    report(violations, path, format!("above maximum {}", maximum));
+                }
+            }
+            if let Some(minimum) = bound("exclusiveMinimum") {
+                if number <= minimum {
+                    let message = format!("not above exclusive minimum {}", minimum);
+                    report(violations, path, message);
+                }
+            }
+            if let Some(maximum) = bound("exclusiveMaximum") {
+                if number >= maximum {
+                    let message = format!("not below exclusive maximum {}", maximum);
+                    report(violations, path, message);
+                }
+            }
+            if let Some(divisor) = bound("multipleOf").filter(|divisor| *divisor > 0.0) {
+                let quotient = number / divisor;
+                if (quotient - quotient.round()).abs() > 1e-9 * quotient.abs().max(1.0) {
+                    report(violations, path, format!("not a multiple of {}", divisor));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        for schema in schemas {
+            check(schema, root, value, path, depth + 1, violations);
+        }
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(schemas)) = schema.get(key) {
+            let matching = schemas
+                .iter()
+                .filter(|schema| {
+                    let mut nested = Vec::new();
+                    check(schema, root, value, path, depth + 1, &mut nested);
+                    nested.is_empty()
+                })
+                .count();
+            let ok = match key {
+                "anyOf" => matching > 0,
+                _ => matching == 1,
+            };
+            if !ok {
+                let message = format!(
+                    "{} of the {} alternatives match ({})",
+                    matching,
+                    schemas.len(),
+                    key
+                );
+                report(violations, path, message);
+            }
+        }
+    }
+}
+
+fn check_bounds(
+    schema: &serde_json::Map<String, Value>,
+    min_key: &str,
+    max_key: &str,
+    size: usize,
+    path: &str,
+    violations: &mut Vec<String>,
+) {
+    if let Some(min) = schema.get(min_key).and_then(Value::as_u64) {
+        if (size as u64) < min {
+            report(
+                violations,
+                path,
+                format!("{} is below {} {}", size, min_key, min),
+            );
+        }
+    }
+    if let Some(max) = schema.get(max_key).and_then(Value::as_u64) {
+        if size as u64 > max {
+            report(
+                violations,
+                path,
+                format!("{} is above {} {}", size, max_key, max),
+            );
+        }
+    }
+}
+
+fn has_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        // Any number with a zero fractional part, 1.0 included
+        "integer" => match value {
+            Value::Number(number) => {
+                number.is_i64()
+                    || number.is_u64()
+                    || number
+                        .as_f64()
+                        .is_some_and(|n| n.is_finite() && n.fract() == 0.0)
+            }
+            _ => false,
+        },
+        "number" => value.is_number(),
+        kind => type_of(value) == kind,
+    }
+}
+
+fn type_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Decode the percent-escapes of a URI fragment, giving the JSON pointer it holds
+fn percent_decode(fragment: &str) -> Option<String> {
+    let bytes = fragment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = fragment.get(index + 1..index + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Escape a member name for use in a JSON pointer
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+/// Record a mismatch found at the given path
+fn report(violations: &mut Vec<String>, path: &str, message: String) {
+    let path = if path.is_empty() { "/" } else { path };
+    violations.push(format!("{}: {}", path, message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Check every `(value, valid)` case against `schema`
+    fn check_cases(schema: Value, cases: &[(Value, bool)]) {
+        for (value, valid) in cases {
+            let violations = schema_violations(&schema, value);
+            assert_eq!(
+                violations.is_empty(),
+                *valid,
+                "{} against {}: {:?}",
+                value,
+                schema,
+                violations
+            );
+        }
+    }
+
+    #[test]
+    fn type_keyword() {
+        check_cases(
+            json!({"type": "integer"}),
+            &[
+                (json!(1), true),
+                (json!(-1), true),
+                (json!(u64::MAX), true),
+                (json!(1.0), true),
+                (json!(1.5), false),
+                (json!("1"), false),
+            ],
+        );
+        check_cases(
+            json!({"type": ["string", "null"]}),
+            &[(json!("a"), true), (json!(null), true), (json!(0), false)],
+        );
+        check_cases(
+            json!({"type": "number"}),
+            &[(json!(1), true), (json!(0.5), true), (json!(true), false)],
+        );
+    }
+
+    #[test]
+    fn enum_and_const() {
+        check_cases(
+            json!({"enum": ["a", 1]}),
+            &[(json!("a"), true), (json!(1), true), (json!("b"), false)],
+        );
+        check_cases(
+            json!({"const": {"a": 1}}),
+            &[(json!({"a": 1}), true), (json!({"a": 2}), false)],
+        );
+    }
+
+    #[test]
+    fn object_keywords() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"a": {"type": "integer"}},
+            "required": ["a"],
+            "additionalProperties": false,
+        });
+        check_cases(
+            schema,
+            &[
+                (json!({"a": 1}), true),
+                (json!({}), false),
+                (json!({"a": "x"}), false),
+                (json!({"a": 1, "b": 2}), false),
+            ],
+        );
+        check_cases(
+            json!({"additionalProperties": {"type": "string"}}),
+            &[(json!({"b": "x"}), true), (json!({"b": 2}), false)],
+        );
+    }
+
+    #[test]
+    fn array_keywords() {
+        check_cases(
+            json!({
+                "prefixItems": [{"type": "string"}],
+                "items": {"type": "integer"},
+                "minItems": 1,
+                "maxItems": 3,
+            }),
+            &[
+                (json!(["a"]), true),
+                (json!(["a", 1, 2]), true),
+                (json!([1]), false),
+                (json!(["a", "b"]), false),
+                (json!([]), false),
+                (json!(["a", 1, 2, 3]), false),
+            ],
+        );
+    }
+
+    #[test]
+    fn string_length() {
+        check_cases(
+            json!({"minLength": 2, "maxLength": 3}),
+            &[
+                (json!("ab"), true),
+                (json!("é€😀"), true),
+                (json!("a"), false),
+                (json!("abcd"), false),
+            ],
+        );
+    }
+
+    #[test]
+    fn numeric_bounds() {
+        check_cases(
+            json!({"minimum": 1, "maximum": 3}),
+            &[
+                (json!(1), true),
+                (json!(3), true),
+                (json!(0), false),
+                (json!(4), false),
+            ],
+        );
+        check_cases(
+            json!({"exclusiveMinimum": 1, "exclusiveMaximum": 3}),
+            &[
+                (json!(2), true),
+                (json!(1.5), true),
+                (json!(1), false),
+                (json!(3), false),
+            ],
+        );
+        check_cases(
+            json!({"multipleOf": 0.1}),
+            &[(json!(0.3), true), (json!(2), true), (json!(0.35), false)],
+        );
+        check_cases(
+            json!({"multipleOf": 3}),
+            &[(json!(9), true), (json!(-3), true), (json!(10), false)],
+        );
+    }
+
+    #[test]
+    fn combinators() {
+        check_cases(
+            json!({"allOf": [{"minimum": 1}, {"maximum": 2}]}),
+            &[(json!(1), true), (json!(0), false), (json!(3), false)],
+        );
+        check_cases(
+            json!({"anyOf": [{"type": "string"}, {"minimum": 1}]}),
+            &[(json!("a"), true), (json!(2), true), (json!(0), false)],
+        );
+        check_cases(
+            json!({"oneOf": [{"type": "integer"}, {"minimum": 1}]}),
+            &[(json!(0), true), (json!(1.5), true), (json!(2), false)],
+        );
+    }
+
+    #[test]
+    fn local_references() {
+        let schema = json!({
+            "$defs": {
+                "a/b": {"type": "integer"},
+                "c~d": {"type": "string"},
+                "e%f": {"type": "boolean"},
+            },
+            "properties": {
+                "slash": {"$ref": "#/$defs/a~1b"},
+                "tilde": {"$ref": "#/$defs/c~0d"},
+                "percent": {"$ref": "#/$defs/e%25f"},
+                "missing": {"$ref": "#/$defs/missing"},
+            },
+        });
+        check_cases(
+            schema,
+            &[
+                (json!({"slash": 1, "tilde": "x", "percent": true}), true),
+                (json!({"slash": "x"}), false),
+                (json!({"tilde": 1}), false),
+                (json!({"percent": 1}), false),
+                (json!({"missing": 1}), false),
+            ],
+        );
+    }
+
+    #[test]
+    fn reference_cycles_fail_instead_of_matching() {
+        check_cases(json!({"$ref": "#"}), &[(json!(1), false)]);
+        // The cut-off alternative does not count as a match
+        check_cases(
+            json!({
+                "$defs": {"loop": {"$ref": "#/$defs/loop"}},
+                "oneOf": [{"$ref": "#/$defs/loop"}, {"type": "integer"}],
+            }),
+            &[(json!(1), true), (json!("a"), false)],
+        );
+    }
+
+    #[test]
+    fn violations_name_the_offending_value() {
+        let schema = json!({"properties": {"a/b": {"items": {"type": "string"}}}});
+        let violations = schema_violations(&schema, &json!({"a/b": ["x", 1]}));
+        assert_eq!(violations, vec!["/a~1b/1: expected string, got number"]);
+    }
+}
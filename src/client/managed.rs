@@ -0,0 +1,388 @@
+//! Self-healing MCP client
+//!
+//! [`ManagedClient`] owns the whole lifecycle of a connection: it connects
+//! through a [`Connector`], performs the `initialize` handshake, replays the
+//! configured setup calls and re-subscribes to resources, then serves
+//! operations. When the connection fails, the next operation (or the
+//! [`maintain`](ManagedClient::maintain) task) reconnects with exponential
+//! backoff and restores the session before going on.
+//!
+//! Failed operations are not retried, since a call may have taken effect
+//! before the connection dropped. State transitions are broadcast to the
+//! receivers returned by [`ManagedClient::subscribe`].
+
+use super::McpClient;
+use crate::error::{self, helpers};
+use crate::mcp::{CallToolResult, GetPromptResult, Implementation, ReadResourceResult, ToolInfo};
+use crate::transport::Transport;
+use async_trait::async_trait;
+use mcp_error::{Error as McpError, Result as McpResult};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex};
+
+/// Default interval between keepalive pings
+pub const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(30);
+
+/// Capacity of the state transition channel
+const STATE_CHANNEL_CAPACITY: usize = 32;
+
+/// Opens new connections for a [`ManagedClient`]
+#[async_trait]
+pub trait Connector: Send + Sync {
+    /// Transport produced by the connector
+    type Transport: Transport;
+
+    /// Open a new connection
+    async fn connect(&self) -> McpResult<Self::Transport>;
+}
+
+#[async_trait]
+impl<F, Fut, T> Connector for F
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = McpResult<T>> + Send,
+    T: Transport,
+{
+    type Transport = T;
+
+    async fn connect(&self) -> McpResult<T> {
+        self().await
+    }
+}
+
+/// How reconnection attempts are spaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Delay before the second attempt
+    pub initial_delay: Duration,
+    /// Upper bound for the delay, which doubles after each failed attempt
+    pub max_delay: Duration,
+    /// Give up after this many consecutive failed attempts (`None` retries forever)
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+/// Connection state transitions of a [`ManagedClient`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionState {
+    /// A connection attempt started (`attempt` counts from 1)
+    Connecting {
+        /// Attempt number since the connection was lost
+        attempt: u32,
+    },
+    /// Connected, initialized and set up; operations can be served
+    Ready,
+    /// The connection or a connection attempt failed
+    Disconnected {
+        /// Description of the failure
+        error: String,
+    },
+    /// The client was closed and will not reconnect
+    Closed,
+}
+
+/// Call replayed after each `initialize`
+#[derive(Debug, Clone)]
+struct SetupCall {
+    method: String,
+    params: Option<Value>,
+}
+
+/// Future returned by an operation run on the current connection
+type Operation<'a, R> = Pin<Box<dyn Future<Output = McpResult<R>> + Send + 'a>>;
+
+struct Session<T: Transport> {
+    client: Option<McpClient<T>>,
+    subscriptions: BTreeSet<String>,
+    closed: bool,
+}
+
+/// MCP client keeping an initialized connection available
+///
+/// Clones share the same connection, so one clone can run
+/// [`maintain`](Self::maintain) in a background task while others serve calls.
+pub struct ManagedClient<C: Connector> {
+    connector: Arc<C>,
+    client_info: Implementation,
+    policy: ReconnectPolicy,
+    keepalive: Duration,
+    setup: Arc<Vec<SetupCall>>,
+    session: Arc<Mutex<Session<C::Transport>>>,
+    states: broadcast::Sender<ConnectionState>,
+}
+
+impl<C: Connector> Clone for ManagedClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            connector: self.connector.clone(),
+            client_info: self.client_info.clone(),
+            policy: self.policy,
+            keepalive: self.keepalive,
+            setup: self.setup.clone(),
+            session: self.session.clone(),
+            states: self.states.clone(),
+        }
+    }
+}
+
+impl<C: Connector + 'static> ManagedClient<C> {
+    /// Create a client connecting through the given connector
+    ///
+    /// No connection is opened until the first operation or [`connect`](Self::connect).
+    pub fn new(connector: C, client_info: Implementation) -> Self {
+        let (states, _) = broadcast::channel(STATE_CHANNEL_CAPACITY);
+        Self {
+            connector: Arc::new(connector),
+            client_info,
+            policy: ReconnectPolicy::default(),
+            keepalive: DEFAULT_KEEPALIVE,
+            setup: Arc::new(Vec::new()),
+            session: Arc::new(Mutex::new(Session {
+                client: None,
+                subscriptions: BTreeSet::new(),
+                closed: false,
+            })),
+            states,
+        }
+    }
+
+    /// Set how reconnection attempts are spaced
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the interval between pings sent by [`maintain`](Self::maintain)
+    pub fn with_keepalive(mut self, interval: Duration) -> Self {
+        self.keepalive = interval;
+        self
+    }
+
+    /// Call a method after each `initialize`, before serving operations
+    ///
+    /// Setup calls run in the order they were added; a failing setup call fails
+    /// the connection attempt.
+    pub fn with_setup_call(mut self, method: impl Into<String>, params: Option<Value>) -> Self {
+        Arc::make_mut(&mut self.setup).push(SetupCall {
+            method: method.into(),
+            params,
+        });
+        self
+    }
+
+    /// Receive connection state transitions from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ConnectionState> {
+        self.states.subscribe()
+    }
+
+    /// Connect now instead of on the first operation
+    pub async fn connect(&self) -> McpResult<()> {
+        let mut session = self.session.lock().await;
+        self.ensure_connected(&mut session).await
+    }
+
+    /// Ping the server every keepalive interval, reconnecting when it fails
+    ///
+    /// Runs until the client is closed, or fails once the reconnect policy
+    /// gives up. Meant to be spawned:
+    /// `tokio::spawn({ let client = client.clone(); async move { client.maintain().await } })`.
+    pub async fn maintain(&self) -> McpResult<()> {
+        loop {
+            tokio::time::sleep(self.keepalive).await;
+
+            let mut session = self.session.lock().await;
+            if session.closed {
+                return Ok(());
+            }
+            if let Some(client) = session.client.as_mut() {
+                if let Err(e) = client.ping().await {
+                    self.disconnected(&mut session, &e);
+                }
+            }
+            self.ensure_connected(&mut session).await?;
+        }
+    }
+
+    /// Call a method and return its raw result
+    pub async fn call(&self, method: &str, params: Option<Value>) -> McpResult<Value> {
+        let method = method.to_string();
+        self.run(move |client| {
+            Box::pin(async move { client.inner_mut().call(&method, params).await })
+        })
+        .await
+    }
+
+    /// List all tools
+    pub async fn list_tools(&self) -> McpResult<Vec<ToolInfo>> {
+        self.run(|client| Box::pin(client.list_tools())).await
+    }
+
+    /// Call a tool
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> McpResult<CallToolResult> {
+        let name = name.to_string();
+        self.run(move |client| Box::pin(async move { client.call_tool(&name, arguments).await }))
+            .await
+    }
+
+    /// Read a resource
+    pub async fn read_resource(&self, uri: &str) -> McpResult<ReadResourceResult> {
+        let uri = uri.to_string();
+        self.run(move |client| Box::pin(async move { client.read_resource(&uri).await }))
+            .await
+    }
+
+    /// Get a prompt, filling in its arguments
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: Map<String, Value>,
+    ) -> McpResult<GetPromptResult> {
+        let name = name.to_string();
+        self.run(move |client| Box::pin(async move { client.get_prompt(&name, arguments).await }))
+            .await
+    }
+
+    /// Subscribe to a resource; the subscription is restored after reconnecting
+    pub async fn subscribe_resource(&self, uri: &str) -> McpResult<()> {
+        let owned = uri.to_string();
+        self.run(move |client| Box::pin(async move { client.subscribe_resource(&owned).await }))
+            .await?;
+        self.session
+            .lock()
+            .await
+            .subscriptions
+            .insert(uri.to_string());
+        Ok(())
+    }
+
+    /// Cancel a resource subscription
+    pub async fn unsubscribe_resource(&self, uri: &str) -> McpResult<()> {
+        self.session.lock().await.subscriptions.remove(uri);
+        let uri = uri.to_string();
+        self.run(move |client| Box::pin(async move { client.unsubscribe_resource(&uri).await }))
+            .await
+    }
+
+    /// Close the connection; later operations fail and `maintain` returns
+    pub async fn close(&self) -> McpResult<()> {
+        let mut session = self.session.lock().await;
+        session.closed = true;
+        let result = match session.client.take() {
+            Some(mut client) => client.inner_mut().transport_mut().close().await,
+            None => Ok(()),
+        };
+        self.emit(ConnectionState::Closed);
+        result
+    }
+
+    /// Run an operation on the current connection, connecting first if needed
+    async fn run<R, F>(&self, operation: F) -> McpResult<R>
+    where
+        F: for<'a> FnOnce(&'a mut McpClient<C::Transport>) -> Operation<'a, R>,
+    {
+        let mut session = self.session.lock().await;
+        self.ensure_connected(&mut session).await?;
+
+        let client = match session.client.as_mut() {
+            Some(client) => client,
+            None => {
+                return Err(helpers::internal_error(
+                    "Managed client lost its connection",
+                ))
+            }
+        };
+        let result = operation(client).await;
+
+        if let Err(e) = &result {
+            if error::is_connection_error(e) {
+                self.disconnected(&mut session, e);
+            }
+        }
+        result
+    }
+
+    /// Reconnect, following the reconnect policy, unless already connected
+    async fn ensure_connected(&self, session: &mut Session<C::Transport>) -> McpResult<()> {
+        if session.closed {
+            return Err(helpers::protocol_error("Managed client is closed"));
+        }
+        if session.client.is_some() {
+            return Ok(());
+        }
+
+        let mut delay = self.policy.initial_delay;
+        let mut attempt = 1;
+        loop {
+            self.emit(ConnectionState::Connecting { attempt });
+            match self.establish(&session.subscriptions).await {
+                Ok(client) => {
+                    session.client = Some(client);
+                    self.emit(ConnectionState::Ready);
+                    return Ok(());
+                }
+                Err(e) => {
+                    self.emit(ConnectionState::Disconnected {
+                        error: e.to_string(),
+                    });
+                    if self.policy.max_attempts.is_some_and(|max| attempt >= max) {
+                        return Err(e);
+                    }
+                }
+            }
+
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(self.policy.max_delay);
+            attempt += 1;
+        }
+    }
+
+    /// Open a connection and restore the session on it
+    async fn establish(
+        &self,
+        subscriptions: &BTreeSet<String>,
+    ) -> McpResult<McpClient<C::Transport>> {
+        let transport = self.connector.connect().await?;
+        let mut client = McpClient::new(transport, self.client_info.clone());
+        client.initialize().await?;
+
+        for call in self.setup.iter() {
+            client
+                .inner_mut()
+                .call(&call.method, call.params.clone())
+                .await?;
+        }
+        for uri in subscriptions {
+            client.subscribe_resource(uri).await?;
+        }
+
+        Ok(client)
+    }
+
+    /// Drop the current connection after a failure
+    fn disconnected(&self, session: &mut Session<C::Transport>, err: &McpError) {
+        session.client = None;
+        self.emit(ConnectionState::Disconnected {
+            error: err.to_string(),
+        });
+    }
+
+    fn emit(&self, state: ConnectionState) {
+        // Nobody listening is fine
+        let _ = self.states.send(state);
+    }
+}
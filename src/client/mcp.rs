@@ -0,0 +1,220 @@
+//! Typed client for the standard MCP methods
+//!
+//! [`McpClient`] wraps a [`JsonRpcClient`] and exposes the standard MCP
+//! operations with typed results. Every operation checks the capabilities the
+//! server advertised during [`McpClient::initialize`] and fails locally, without
+//! sending anything, when the server does not support it.
+
+use super::{BatchLimits, JsonRpcClient};
+use crate::error::helpers;
+use crate::mcp::{
+    methods, CallToolResult, GetPromptResult, Implementation, InitializeResult, ListToolsResult,
+    LoggingLevel, ReadResourceResult, ServerCapabilities, ToolInfo, PROTOCOL_VERSION,
+};
+use crate::transport::Transport;
+use mcp_error::Result as McpResult;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+
+/// Capabilities an operation may require
+#[derive(Debug, Clone, Copy)]
+enum Capability {
+    Tools,
+    Resources,
+    Prompts,
+    Logging,
+}
+
+impl Capability {
+    fn name(self) -> &'static str {
+        match self {
+            Capability::Tools => "tools",
+            Capability::Resources => "resources",
+            Capability::Prompts => "prompts",
+            Capability::Logging => "logging",
+        }
+    }
+
+    fn offered_by(self, capabilities: &ServerCapabilities) -> bool {
+        match self {
+            Capability::Tools => capabilities.tools.is_some(),
+            Capability::Resources => capabilities.resources.is_some(),
+            Capability::Prompts => capabilities.prompts.is_some(),
+            Capability::Logging => capabilities.logging.is_some(),
+        }
+    }
+}
+
+/// Capability-aware MCP client
+pub struct McpClient<T: Transport> {
+    client: JsonRpcClient<T>,
+    client_info: Implementation,
+    server: Option<InitializeResult>,
+}
+
+impl<T: Transport> McpClient<T> {
+    /// Create a client over the given transport
+    pub fn new(transport: T, client_info: Implementation) -> Self {
+        Self::from_client(JsonRpcClient::new(transport), client_info)
+    }
+
+    /// Wrap an existing JSON-RPC client
+    pub fn from_client(client: JsonRpcClient<T>, client_info: Implementation) -> Self {
+        Self {
+            client,
+            client_info,
+            server: None,
+        }
+    }
+
+    /// Access the underlying JSON-RPC client
+    pub fn inner_mut(&mut self) -> &mut JsonRpcClient<T> {
+        &mut self.client
+    }
+
+    /// Consume the client and return the underlying JSON-RPC client
+    pub fn into_inner(self) -> JsonRpcClient<T> {
+        self.client
+    }
+
+    /// Result of the `initialize` handshake, once performed
+    pub fn server_info(&self) -> Option<&InitializeResult> {
+        self.server.as_ref()
+    }
+
+    /// Capabilities negotiated with the server, once initialized
+    pub fn server_capabilities(&self) -> Option<&ServerCapabilities> {
+        self.server.as_ref().map(|server| &server.capabilities)
+    }
+
+    /// Perform the `initialize` handshake
+    ///
+    /// Batch limits advertised under `experimental.batch` are applied to the
+    /// underlying client.
+    pub async fn initialize(&mut self) -> McpResult<&InitializeResult> {
+        let params = json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": self.client_info,
+        });
+        let result: InitializeResult = self.request(methods::INITIALIZE, Some(params)).await?;
+
+        let capabilities =
+            serde_json::to_value(&result.capabilities).map_err(helpers::json_error)?;
+        let limits = BatchLimits::from_capabilities(&capabilities);
+        if limits != BatchLimits::default() {
+            self.client.set_batch_limits(limits);
+        }
+
+        self.client.notify(methods::INITIALIZED, None).await?;
+        Ok(self.server.insert(result))
+    }
+
+    /// Check that the server is responsive
+    ///
+    /// `ping` is part of the base protocol, so it does not require initialization.
+    pub async fn ping(&mut self) -> McpResult<()> {
+        self.client.call(methods::PING, None).await.map(|_| ())
+    }
+
+    /// List all tools, following pagination cursors
+    pub async fn list_tools(&mut self) -> McpResult<Vec<ToolInfo>> {
+        self.require(Capability::Tools)?;
+
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = cursor.map(|cursor| json!({ "cursor": cursor }));
+            let page: ListToolsResult = self.request(methods::TOOLS_LIST, params).await?;
+            tools.extend(page.tools);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(tools),
+            }
+        }
+    }
+
+    /// Call a tool
+    ///
+    /// A tool reporting a failure through `isError` is returned as a result, not
+    /// as an error; check [`CallToolResult::is_error`].
+    pub async fn call_tool(&mut self, name: &str, arguments: Value) -> McpResult<CallToolResult> {
+        self.require(Capability::Tools)?;
+        let params = json!({ "name": name, "arguments": arguments });
+        self.request(methods::TOOLS_CALL, Some(params)).await
+    }
+
+    /// Read a resource
+    pub async fn read_resource(&mut self, uri: &str) -> McpResult<ReadResourceResult> {
+        self.require(Capability::Resources)?;
+        let params = json!({ "uri": uri });
+        self.request(methods::RESOURCES_READ, Some(params)).await
+    }
+
+    /// Subscribe to change notifications for a resource
+    pub async fn subscribe_resource(&mut self, uri: &str) -> McpResult<()> {
+        self.require(Capability::Resources)?;
+        let params = json!({ "uri": uri });
+        self.client
+            .call(methods::RESOURCES_SUBSCRIBE, Some(params))
+            .await
+            .map(|_| ())
+    }
+
+    /// Cancel a resource subscription
+    pub async fn unsubscribe_resource(&mut self, uri: &str) -> McpResult<()> {
+        self.require(Capability::Resources)?;
+        let params = json!({ "uri": uri });
+        self.client
+            .call(methods::RESOURCES_UNSUBSCRIBE, Some(params))
+            .await
+            .map(|_| ())
+    }
+
+    /// Get a prompt, filling in its arguments
+    pub async fn get_prompt(
+        &mut self,
+        name: &str,
+        arguments: Map<String, Value>,
+    ) -> McpResult<GetPromptResult> {
+        self.require(Capability::Prompts)?;
+        let params = json!({ "name": name, "arguments": arguments });
+        self.request(methods::PROMPTS_GET, Some(params)).await
+    }
+
+    /// Set the minimum level of log messages the server sends
+    pub async fn set_log_level(&mut self, level: LoggingLevel) -> McpResult<()> {
+        self.require(Capability::Logging)?;
+        let params = json!({ "level": level });
+        self.client
+            .call(methods::LOGGING_SET_LEVEL, Some(params))
+            .await
+            .map(|_| ())
+    }
+
+    /// Fail unless the server was initialized and offers the capability
+    fn require(&self, capability: Capability) -> McpResult<()> {
+        let capabilities = self.server_capabilities().ok_or_else(|| {
+            helpers::protocol_error("Client is not initialized; call initialize() first")
+        })?;
+
+        if capability.offered_by(capabilities) {
+            Ok(())
+        } else {
+            Err(helpers::protocol_error(&format!(
+                "Server does not support the '{}' capability",
+                capability.name()
+            )))
+        }
+    }
+
+    /// Call a method and deserialize its result
+    async fn request<R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: Option<Value>,
+    ) -> McpResult<R> {
+        let result = self.client.call(method, params).await?;
+        serde_json::from_value(result).map_err(helpers::json_error)
+    }
+}
@@ -0,0 +1,145 @@
+//! Client middleware
+//!
+//! Middlewares added to a client with
+//! [`with_middleware`](super::JsonRpcClient::with_middleware) see every call
+//! and notification, so cross-cutting concerns such as token injection,
+//! caching or telemetry are written once instead of around every call.
+//!
+//! Like layers around a server, they are nested in registration order: before
+//! hooks run first to last, and after hooks last to first. A middleware
+//! answering a call from [`before_request`](ClientMiddleware::before_request)
+//! stops it from being sent; the middlewares registered before it still see
+//! the result in their after hooks, the others never see the call.
+//!
+//! ```rust,ignore
+//! struct BearerToken(String);
+//!
+//! #[async_trait]
+//! impl ClientMiddleware for BearerToken {
+//!     async fn before_request(&self, request: &mut JsonRpcRequest) -> Option<McpResult<Value>> {
+//!         if let Some(Value::Object(params)) = &mut request.params {
+//!             params.insert("token".into(), Value::String(self.0.clone()));
+//!         }
+//!         None
+//!     }
+//! }
+//!
+//! let client = JsonRpcClient::new(transport).with_middleware(BearerToken(token));
+//! ```
+
+use crate::protocol::{JsonRpcNotification, JsonRpcRequest};
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What to do with a call once the after hooks ran
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AfterResponse {
+    /// Return the result to the caller
+    Return,
+    /// Send the call again under a new id after the given delay
+    Retry(Duration),
+}
+
+/// Hooks around the calls and notifications of a client
+///
+/// Every hook does nothing by default.
+#[async_trait]
+pub trait ClientMiddleware: Send + Sync {
+    /// Inspect or change a request before it is sent
+    ///
+    /// Returning a result answers the call without sending it.
+    async fn before_request(&self, _request: &mut JsonRpcRequest) -> Option<McpResult<Value>> {
+        None
+    }
+
+    /// Inspect or change the result of a call
+    ///
+    /// `attempt` counts from 1 and lets a middleware bound its retries.
+    /// Retries are ignored for calls of a batch.
+    async fn after_response(
+        &self,
+        _request: &JsonRpcRequest,
+        _result: &mut McpResult<Value>,
+        _attempt: u32,
+    ) -> AfterResponse {
+        AfterResponse::Return
+    }
+
+    /// Inspect or change a notification before it is sent; `false` drops it
+    async fn before_notification(&self, _notification: &mut JsonRpcNotification) -> bool {
+        true
+    }
+
+    /// Inspect or change a received notification; `false` drops it
+    async fn on_notification(&self, _notification: &mut JsonRpcNotification) -> bool {
+        true
+    }
+}
+
+/// Middlewares of a client, in registration order
+pub(crate) type Middlewares = Vec<Arc<dyn ClientMiddleware>>;
+
+/// Run the before hooks of a request
+///
+/// Returns the number of middlewares whose after hooks must run, and the
+/// result if a middleware answered the call.
+pub(crate) async fn before_request(
+    middlewares: &[Arc<dyn ClientMiddleware>],
+    request: &mut JsonRpcRequest,
+) -> (usize, Option<McpResult<Value>>) {
+    for (index, middleware) in middlewares.iter().enumerate() {
+        if let Some(result) = middleware.before_request(request).await {
+            return (index, Some(result));
+        }
+    }
+    (middlewares.len(), None)
+}
+
+/// Run the after hooks of a call in reverse order, returning the delay before
+/// the first retry asked for
+pub(crate) async fn after_response(
+    middlewares: &[Arc<dyn ClientMiddleware>],
+    request: &JsonRpcRequest,
+    result: &mut McpResult<Value>,
+    attempt: u32,
+) -> Option<Duration> {
+    let mut retry = None;
+    for middleware in middlewares.iter().rev() {
+        if let AfterResponse::Retry(delay) =
+            middleware.after_response(request, result, attempt).await
+        {
+            retry.get_or_insert(delay);
+        }
+    }
+    retry
+}
+
+/// Run the hooks of an outgoing notification, returning whether to send it
+pub(crate) async fn before_notification(
+    middlewares: &[Arc<dyn ClientMiddleware>],
+    notification: &mut JsonRpcNotification,
+) -> bool {
+    for middleware in middlewares {
+        if !middleware.before_notification(notification).await {
+            return false;
+        }
+    }
+    true
+}
+
+/// Run the hooks of a received notification in reverse order, returning
+/// whether to keep it
+pub(crate) async fn on_notification(
+    middlewares: &[Arc<dyn ClientMiddleware>],
+    notification: &mut JsonRpcNotification,
+) -> bool {
+    for middleware in middlewares.iter().rev() {
+        if !middleware.on_notification(notification).await {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,697 @@
+//! JSON-RPC client
+//!
+//! [`JsonRpcClient`] sends requests over any [`Transport`] and correlates the
+//! responses by id. Notifications received while waiting for a response are
+//! buffered and can be drained with [`JsonRpcClient::take_notifications`]. The
+//! buffer holds [`DEFAULT_NOTIFICATION_CAPACITY`] notifications unless set with
+//! [`JsonRpcClient::with_notification_capacity`]; once it is full the oldest
+//! notification is dropped, and drops are counted in
+//! [`JsonRpcClient::dropped_notifications`], so a chatty server cannot grow a
+//! client that never drains it.
+//!
+//! Batches passed to [`JsonRpcClient::call_batch`] are split into several wire
+//! batches when they exceed the configured [`BatchLimits`], and results are
+//! reassembled in the original order. If the server rejects a whole batch, the
+//! client halves it and retries; when the rejection says the batch was too
+//! large, the smaller size is remembered for later batches.
+//!
+//! The client serves no methods: requests the server sends are answered with
+//! "Method not found" (`-32601`).
+//!
+//! Some servers answer with a `null` or missing id. With
+//! [`IdCorrelation::Lenient`], such responses are matched to outstanding
+//! requests in the order they were sent, and each occurrence is reported as a
+//! [`CorrelationWarning`] so the spec violation does not go unnoticed.
+//!
+//! Results can be checked against the schema the caller expects for their
+//! method, failing the call with a [`ContractViolation`] when a server drifts
+//! from its contract (see [`contract`]).
+//!
+//! Calls failing with errors the server marks as retryable can be retried
+//! automatically with a [`RetryPolicy`] (see [`retry`]).
+//!
+//! A chain of [`ClientMiddleware`] can change, answer or retry calls and
+//! filter notifications in both directions (see [`middleware`]).
+//!
+//! [`McpClient`] builds the standard MCP operations on top of [`JsonRpcClient`],
+//! and [`ManagedClient`] keeps an initialized [`McpClient`] connected.
+
+use crate::error::{error_codes, helpers, json_rpc_to_error};
+use crate::protocol::{
+    JsonRpcError, JsonRpcId, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+    ValidationPolicy,
+};
+use crate::transport::Transport;
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod contract;
+pub mod managed;
+pub mod mcp;
+pub mod middleware;
+pub mod retry;
+#[cfg(test)]
+mod tests;
+
+pub use contract::ContractViolation;
+pub use managed::ManagedClient;
+pub use mcp::McpClient;
+pub use middleware::{AfterResponse, ClientMiddleware};
+pub use retry::RetryPolicy;
+
+/// Default number of received notifications buffered until drained
+pub const DEFAULT_NOTIFICATION_CAPACITY: usize = 1024;
+
+/// Limits applied when sending batches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchLimits {
+    /// Maximum number of requests per wire batch
+    pub max_len: Option<usize>,
+    /// Maximum serialized size of a wire batch in bytes
+    pub max_bytes: Option<usize>,
+}
+
+impl BatchLimits {
+    /// Read limits advertised by a server under `experimental.batch`
+    ///
+    /// The convention is `{"experimental": {"batch": {"maxLength": 50, "maxBytes": 1048576}}}`
+    /// in the server capabilities; missing fields mean no limit.
+    pub fn from_capabilities(capabilities: &Value) -> Self {
+        let batch = capabilities.pointer("/experimental/batch");
+        let limit = |name: &str| {
+            batch
+                .and_then(|b| b.get(name))
+                .and_then(Value::as_u64)
+                .map(|n| n as usize)
+        };
+        Self {
+            max_len: limit("maxLength"),
+            max_bytes: limit("maxBytes"),
+        }
+    }
+
+    /// Split serialized requests into groups respecting the limits
+    ///
+    /// A single request larger than `max_bytes` is sent on its own.
+    fn split<E>(&self, items: Vec<(E, usize)>) -> Vec<Vec<E>> {
+        let max_len = self.max_len.unwrap_or(usize::MAX).max(1);
+        let max_bytes = self.max_bytes.unwrap_or(usize::MAX);
+
+        let mut groups = Vec::new();
+        let mut current = Vec::new();
+        let mut current_bytes: usize = 2; // surrounding brackets
+        for (item, size) in items {
+            let added = size + usize::from(!current.is_empty());
+            if !current.is_empty()
+                && (current.len() >= max_len || current_bytes.saturating_add(added) > max_bytes)
+            {
+                groups.push(std::mem::take(&mut current));
+                current_bytes = 2;
+            }
+            current_bytes += size + usize::from(!current.is_empty());
+            current.push(item);
+        }
+        if !current.is_empty() {
+            groups.push(current);
+        }
+        groups
+    }
+}
+
+/// Outcome of sending one wire batch
+enum BatchOutcome {
+    /// Responses keyed by id
+    Responses(HashMap<JsonRpcId, JsonRpcResponse>),
+    /// The server answered the whole batch with a single error
+    Rejected(JsonRpcError),
+}
+
+/// How responses are matched to the requests they answer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdCorrelation {
+    /// Match by id only, as the specification requires
+    #[default]
+    Strict,
+    /// Also match responses with a `null` or missing id to outstanding
+    /// requests, oldest first
+    Lenient,
+}
+
+/// Spec violation tolerated by lenient id correlation
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CorrelationWarning {
+    /// A response had no `id` member
+    MissingId,
+    /// A response with a `null` or missing id was taken as the answer to a request
+    MatchedByOrder {
+        /// Id of the request the response was matched to
+        id: JsonRpcId,
+    },
+}
+
+/// Callback receiving correlation warnings
+pub type CorrelationWarningHandler = Arc<dyn Fn(&CorrelationWarning) + Send + Sync>;
+
+/// JSON-RPC client over a transport
+pub struct JsonRpcClient<T: Transport> {
+    transport: T,
+    next_id: i64,
+    notifications: VecDeque<JsonRpcNotification>,
+    notification_capacity: usize,
+    dropped_notifications: u64,
+    batch_limits: BatchLimits,
+    correlation: IdCorrelation,
+    warning_handler: Option<CorrelationWarningHandler>,
+    correlation_warnings: u64,
+    send_validation: ValidationPolicy,
+    result_schemas: HashMap<String, Value>,
+    retry: Option<RetryPolicy>,
+    middlewares: middleware::Middlewares,
+}
+
+impl<T: Transport> JsonRpcClient<T> {
+    /// Create a client over the given transport
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            next_id: 1,
+            notifications: VecDeque::new(),
+            notification_capacity: DEFAULT_NOTIFICATION_CAPACITY,
+            dropped_notifications: 0,
+            batch_limits: BatchLimits::default(),
+            correlation: IdCorrelation::default(),
+            warning_handler: None,
+            correlation_warnings: 0,
+            send_validation: ValidationPolicy::default(),
+            result_schemas: HashMap::new(),
+            retry: None,
+            middlewares: Vec::new(),
+        }
+    }
+
+    /// Set how responses are matched to requests
+    pub fn with_id_correlation(mut self, correlation: IdCorrelation) -> Self {
+        self.correlation = correlation;
+        self
+    }
+
+    /// Change how responses are matched to requests on this connection
+    pub fn set_id_correlation(&mut self, correlation: IdCorrelation) {
+        self.correlation = correlation;
+    }
+
+    /// Call the given function for each correlation warning
+    pub fn with_correlation_warning_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&CorrelationWarning) + Send + Sync + 'static,
+    {
+        self.warning_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Number of correlation warnings raised so far
+    pub fn correlation_warnings(&self) -> u64 {
+        self.correlation_warnings
+    }
+
+    /// Set how strictly requests and notifications are checked before sending
+    pub fn with_send_validation(mut self, validation: ValidationPolicy) -> Self {
+        self.send_validation = validation;
+        self
+    }
+
+    /// Check the results of a method against the given JSON Schema
+    pub fn with_result_schema(mut self, method: impl Into<String>, schema: Value) -> Self {
+        self.set_result_schema(method, schema);
+        self
+    }
+
+    /// Check the results of a method against a schema derived from `R`
+    #[cfg(feature = "schemars")]
+    pub fn with_result_type<R: schemars::JsonSchema>(self, method: impl Into<String>) -> Self {
+        self.with_result_schema(method, crate::typed::derive_schema::<R>())
+    }
+
+    /// Check the results of a method against the given JSON Schema from now on
+    pub fn set_result_schema(&mut self, method: impl Into<String>, schema: Value) {
+        self.result_schemas.insert(method.into(), schema);
+    }
+
+    /// Set the limits applied when sending batches
+    pub fn with_batch_limits(mut self, limits: BatchLimits) -> Self {
+        self.batch_limits = limits;
+        self
+    }
+
+    /// Update the batch limits, e.g. after reading server capabilities
+    pub fn set_batch_limits(&mut self, limits: BatchLimits) {
+        self.batch_limits = limits;
+    }
+
+    /// Current batch limits (including sizes learned from rejected batches)
+    pub fn batch_limits(&self) -> BatchLimits {
+        self.batch_limits
+    }
+
+    /// Access the underlying transport
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Consume the client and return the underlying transport
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Set how many received notifications are buffered until drained; the
+    /// oldest is dropped once the buffer is full
+    pub fn with_notification_capacity(mut self, capacity: usize) -> Self {
+        self.notification_capacity = capacity;
+        self
+    }
+
+    /// Number of notifications dropped because the buffer was full
+    pub fn dropped_notifications(&self) -> u64 {
+        self.dropped_notifications
+    }
+
+    /// Drain the notifications received so far
+    pub fn take_notifications(&mut self) -> Vec<JsonRpcNotification> {
+        self.notifications.drain(..).collect()
+    }
+
+    fn next_id(&mut self) -> JsonRpcId {
+        let id = JsonRpcId::Number(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Retry calls failing with retryable errors; see [`retry`]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Change how calls are retried on this connection
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.retry = policy;
+    }
+
+    /// Add a middleware around the calls and notifications; see [`middleware`]
+    pub fn with_middleware<M: ClientMiddleware + 'static>(mut self, middleware: M) -> Self {
+        self.add_middleware(middleware);
+        self
+    }
+
+    /// Add a middleware on this connection, nested inside those added before
+    pub fn add_middleware<M: ClientMiddleware + 'static>(&mut self, middleware: M) {
+        self.middlewares.push(Arc::new(middleware));
+    }
+
+    /// Call a method and wait for its result
+    ///
+    /// With a retry policy, retryable failures are retried under a new id, as
+    /// are calls a middleware asks to retry.
+    pub async fn call(&mut self, method: &str, params: Option<Value>) -> McpResult<Value> {
+        let mut attempt = 1;
+        loop {
+            let (result, retry) = self.call_once(method, params.clone(), attempt).await;
+            let delay = retry.or_else(|| match (&result, &self.retry) {
+                (Err(e), Some(policy)) => policy.retry_delay(attempt, e),
+                _ => None,
+            });
+            match delay {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return result,
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Make one attempt of a call through the middlewares, returning its
+    /// result and the delay before retrying if a middleware asked for it
+    async fn call_once(
+        &mut self,
+        method: &str,
+        params: Option<Value>,
+        attempt: u32,
+    ) -> (McpResult<Value>, Option<Duration>) {
+        let middlewares = self.middlewares.clone();
+        let mut request = JsonRpcRequest::new(method, params, self.next_id());
+        let (entered, answered) = middleware::before_request(&middlewares, &mut request).await;
+        let mut result = match answered {
+            Some(result) => result,
+            None => self.send_request(&request).await,
+        };
+        let retry =
+            middleware::after_response(&middlewares[..entered], &request, &mut result, attempt)
+                .await;
+        (result, retry)
+    }
+
+    async fn send_request(&mut self, request: &JsonRpcRequest) -> McpResult<Value> {
+        self.send_validation.check_request(request)?;
+
+        let message = serde_json::to_string(request).map_err(helpers::json_error)?;
+        self.transport.send(&message).await?;
+
+        let response = self.wait_for_response(&request.id).await?;
+        self.check_result(&request.method, response_result(response))
+    }
+
+    /// Send a notification, unless a middleware drops it
+    pub async fn notify(&mut self, method: &str, params: Option<Value>) -> McpResult<()> {
+        let mut notification = JsonRpcNotification::new(method, params);
+        let middlewares = self.middlewares.clone();
+        if !middleware::before_notification(&middlewares, &mut notification).await {
+            return Ok(());
+        }
+        self.send_validation.check_notification(&notification)?;
+
+        let message = serde_json::to_string(&notification).map_err(helpers::json_error)?;
+        self.transport.send(&message).await
+    }
+
+    /// Call several methods as a batch, returning one result per call in order
+    ///
+    /// Calls answered by a middleware are left out of the wire batch.
+    pub async fn call_batch(
+        &mut self,
+        calls: Vec<(String, Option<Value>)>,
+    ) -> McpResult<Vec<McpResult<Value>>> {
+        let middlewares = self.middlewares.clone();
+        let mut order = Vec::with_capacity(calls.len());
+        let mut items = Vec::with_capacity(calls.len());
+        let mut results: HashMap<JsonRpcId, McpResult<Value>> = HashMap::new();
+        for (method, params) in calls {
+            let mut request = JsonRpcRequest::new(method, params, self.next_id());
+            let (entered, answered) = middleware::before_request(&middlewares, &mut request).await;
+            if let Some(result) = answered {
+                results.insert(request.id.clone(), result);
+                order.push((request, entered));
+                continue;
+            }
+            self.send_validation.check_request(&request)?;
+            let size = serde_json::to_string(&request)
+                .map_err(helpers::json_error)?
+                .len();
+            order.push((request.clone(), entered));
+            items.push((request, size));
+        }
+
+        let mut queue: VecDeque<Vec<JsonRpcRequest>> =
+            self.batch_limits.split(items).into_iter().collect();
+
+        while let Some(chunk) = queue.pop_front() {
+            match self.send_wire_batch(&chunk).await? {
+                BatchOutcome::Responses(mut responses) => {
+                    for request in &chunk {
+                        let result = match responses.remove(&request.id) {
+                            Some(response) => {
+                                self.check_result(&request.method, response_result(response))
+                            }
+                            None => Err(helpers::protocol_error(&format!(
+                                "No response for request {}",
+                                request.id.to_string()
+                            ))),
+                        };
+                        results.insert(request.id.clone(), result);
+                    }
+                }
+                BatchOutcome::Rejected(error) if chunk.len() > 1 => {
+                    // Halve it, remembering the smaller size only when the
+                    // server said the batch was too large: other rejections
+                    // may come from a single bad element
+                    let half = chunk.len() / 2;
+                    if is_size_rejection(&error) {
+                        self.batch_limits.max_len = Some(half);
+                    }
+                    let mut first = chunk;
+                    let second = first.split_off(half);
+                    queue.push_front(second);
+                    queue.push_front(first);
+                }
+                BatchOutcome::Rejected(error) => {
+                    for request in &chunk {
+                        results.insert(request.id.clone(), Err(json_rpc_to_error(&error)));
+                    }
+                }
+            }
+        }
+
+        let mut ordered = Vec::with_capacity(order.len());
+        for (request, entered) in &order {
+            let mut result = results
+                .remove(&request.id)
+                .unwrap_or_else(|| Err(helpers::internal_error("Missing batch result")));
+            middleware::after_response(&middlewares[..*entered], request, &mut result, 1).await;
+            ordered.push(result);
+        }
+        Ok(ordered)
+    }
+
+    /// Check a successful result against the schema registered for its method
+    fn check_result(&self, method: &str, result: McpResult<Value>) -> McpResult<Value> {
+        let (result, schema) = match (result, self.result_schemas.get(method)) {
+            (Ok(result), Some(schema)) => (result, schema),
+            (result, _) => return result,
+        };
+        let violations = contract::schema_violations(schema, &result);
+        if violations.is_empty() {
+            return Ok(result);
+        }
+        Err(ContractViolation {
+            method: method.to_string(),
+            violations,
+            result,
+        }
+        .into_error())
+    }
+
+    /// Send one wire batch and collect its responses
+    async fn send_wire_batch(&mut self, chunk: &[JsonRpcRequest]) -> McpResult<BatchOutcome> {
+        let message = serde_json::to_string(chunk).map_err(helpers::json_error)?;
+        self.transport.send(&message).await?;
+
+        loop {
+            match self.receive_message().await? {
+                JsonRpcMessage::Batch(messages) => {
+                    let mut responses = HashMap::new();
+                    let mut unidentified = VecDeque::new();
+                    let mut refused = Vec::new();
+                    for message in messages {
+                        match message {
+                            JsonRpcMessage::Response(response)
+                                if self.matches_by_order(&response) =>
+                            {
+                                unidentified.push_back(response);
+                            }
+                            JsonRpcMessage::Response(response) => {
+                                responses.insert(response.id.clone(), response);
+                            }
+                            JsonRpcMessage::Notification(notification) => {
+                                self.buffer_notification(notification).await
+                            }
+                            JsonRpcMessage::Request(request) => refused.push(refusal(request)),
+                            JsonRpcMessage::Batch(_) => {}
+                        }
+                    }
+                    if !refused.is_empty() {
+                        self.send_refusals(&refused).await?;
+                    }
+
+                    // Hand out responses without an id to unanswered requests, in order
+                    for request in chunk {
+                        if unidentified.is_empty() {
+                            break;
+                        }
+                        if !responses.contains_key(&request.id) {
+                            if let Some(mut response) = unidentified.pop_front() {
+                                self.warn(CorrelationWarning::MatchedByOrder {
+                                    id: request.id.clone(),
+                                });
+                                response.id = request.id.clone();
+                                responses.insert(response.id.clone(), response);
+                            }
+                        }
+                    }
+                    return Ok(BatchOutcome::Responses(responses));
+                }
+                JsonRpcMessage::Response(response) if response.id == JsonRpcId::Null => {
+                    let error = response.error.unwrap_or_else(|| {
+                        JsonRpcError::new(error_codes::INVALID_REQUEST, "Batch rejected", None)
+                    });
+                    return Ok(BatchOutcome::Rejected(error));
+                }
+                JsonRpcMessage::Response(response) if chunk.len() == 1 => {
+                    // Some servers answer single-element batches with a bare response
+                    let mut responses = HashMap::new();
+                    responses.insert(response.id.clone(), response);
+                    return Ok(BatchOutcome::Responses(responses));
+                }
+                JsonRpcMessage::Notification(notification) => {
+                    self.buffer_notification(notification).await
+                }
+                message => self.refuse_requests(message).await?,
+            }
+        }
+    }
+
+    /// Wait for the response with the given id, buffering notifications
+    async fn wait_for_response(&mut self, id: &JsonRpcId) -> McpResult<JsonRpcResponse> {
+        loop {
+            match self.receive_message().await? {
+                JsonRpcMessage::Response(response) if response.id == *id => return Ok(response),
+                JsonRpcMessage::Response(mut response) if self.matches_by_order(&response) => {
+                    self.warn(CorrelationWarning::MatchedByOrder { id: id.clone() });
+                    response.id = id.clone();
+                    return Ok(response);
+                }
+                JsonRpcMessage::Notification(notification) => {
+                    self.buffer_notification(notification).await
+                }
+                message => self.refuse_requests(message).await?,
+            }
+        }
+    }
+
+    /// Answer the requests the server sent in `message` with "Method not
+    /// found", so the server does not wait for answers that never come
+    ///
+    /// Responses nobody waits for are dropped.
+    async fn refuse_requests(&mut self, message: JsonRpcMessage) -> McpResult<()> {
+        match message {
+            JsonRpcMessage::Request(request) => {
+                let refused = refusal(request);
+                let message = serde_json::to_string(&refused).map_err(helpers::json_error)?;
+                self.transport.send(&message).await
+            }
+            JsonRpcMessage::Batch(messages) => {
+                let mut refused = Vec::new();
+                for message in messages {
+                    match message {
+                        JsonRpcMessage::Request(request) => refused.push(refusal(request)),
+                        JsonRpcMessage::Notification(notification) => {
+                            self.buffer_notification(notification).await
+                        }
+                        _ => {}
+                    }
+                }
+                if refused.is_empty() {
+                    return Ok(());
+                }
+                self.send_refusals(&refused).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn send_refusals(&mut self, refused: &[JsonRpcResponse]) -> McpResult<()> {
+        let message = serde_json::to_string(refused).map_err(helpers::json_error)?;
+        self.transport.send(&message).await
+    }
+
+    /// Keep a received notification for [`take_notifications`](Self::take_notifications)
+    /// unless a middleware drops it, dropping the oldest when the buffer is full
+    async fn buffer_notification(&mut self, mut notification: JsonRpcNotification) {
+        let middlewares = self.middlewares.clone();
+        if !middleware::on_notification(&middlewares, &mut notification).await {
+            return;
+        }
+        if self.notifications.len() >= self.notification_capacity {
+            self.dropped_notifications += 1;
+            if self.notifications.pop_front().is_none() {
+                return;
+            }
+        }
+        self.notifications.push_back(notification);
+    }
+
+    async fn receive_message(&mut self) -> McpResult<JsonRpcMessage> {
+        if self.correlation == IdCorrelation::Strict {
+            return self.transport.receive_message().await;
+        }
+        let message = self.transport.receive().await?;
+
+        let mut value: Value = serde_json::from_str(&message).map_err(helpers::json_error)?;
+        let mut missing = 0;
+        let responses = match &mut value {
+            Value::Array(items) => items.iter_mut().collect(),
+            object => vec![object],
+        };
+        for response in responses {
+            if let Value::Object(object) = response {
+                if !object.contains_key("method") && !object.contains_key("id") {
+                    object.insert("id".to_string(), Value::Null);
+                    missing += 1;
+                }
+            }
+        }
+        for _ in 0..missing {
+            self.warn(CorrelationWarning::MissingId);
+        }
+        JsonRpcMessage::from_value(value)
+    }
+
+    /// Whether a response lacks a usable id and may be matched by order
+    fn matches_by_order(&self, response: &JsonRpcResponse) -> bool {
+        self.correlation == IdCorrelation::Lenient && response.id == JsonRpcId::Null
+    }
+
+    fn warn(&mut self, warning: CorrelationWarning) {
+        self.correlation_warnings += 1;
+        if let Some(handler) = &self.warning_handler {
+            handler(&warning);
+        }
+    }
+}
+
+/// "Method not found" answer to a request sent by the server
+fn refusal(request: JsonRpcRequest) -> JsonRpcResponse {
+    JsonRpcResponse::failure(
+        request.id,
+        JsonRpcError::new(
+            error_codes::METHOD_NOT_FOUND,
+            "Method not found",
+            Some(Value::String(request.method)),
+        ),
+    )
+}
+
+/// Words of a batch rejection reason naming a size limit
+const SIZE_REASONS: &[&str] = &[
+    "too large",
+    "too long",
+    "too many",
+    "exceeds",
+    "limit",
+    "size",
+];
+
+/// Whether a whole-batch rejection says the batch was too large
+///
+/// Servers answer `-32600` (or `-32700` when they stop reading) with a reason
+/// naming the limit; other rejections are not about the size.
+fn is_size_rejection(error: &JsonRpcError) -> bool {
+    if error.code != error_codes::INVALID_REQUEST && error.code != error_codes::PARSE_ERROR {
+        return false;
+    }
+    let reason = match &error.data {
+        Some(data) => format!("{} {}", error.message, data),
+        None => error.message.clone(),
+    }
+    .to_lowercase();
+    SIZE_REASONS.iter().any(|word| reason.contains(word))
+}
+
+/// Turn a response into the call result, lifting error objects with [`json_rpc_to_error`]
+fn response_result(response: JsonRpcResponse) -> McpResult<Value> {
+    match response.error {
+        Some(error) => Err(json_rpc_to_error(&error)),
+        None => Ok(response.result.unwrap_or(Value::Null)),
+    }
+}
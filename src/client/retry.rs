@@ -0,0 +1,162 @@
+//! Client retries
+//!
+//! With a [`RetryPolicy`], [`JsonRpcClient::call`](super::JsonRpcClient::call)
+//! sends a request again when it fails with an error the server marks as
+//! retryable: `error.data.retryable`, or an overloaded error (-32001) from
+//! servers not sending the flag (see [`retry_hint`]). The client waits for
+//! `error.data.retryAfterMs` when the server gives it, up to
+//! [`RetryPolicy::max_retry_after`], and for an exponential backoff otherwise. Transport errors, contract violations and batches are
+//! never retried.
+
+use crate::error::retry_hint;
+use mcp_error::Error as McpError;
+use std::time::Duration;
+
+/// How failed calls are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry when the server gives none, doubled for
+    /// each further retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the backoff
+    pub max_backoff: Duration,
+    /// Upper bound of the delay asked by the server, so a server cannot stall
+    /// the caller indefinitely
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            max_retry_after: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Make at most `max_attempts` attempts with the default backoff
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::default()
+        }
+    }
+
+    /// Set the backoff used when the server gives no delay
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Set the longest delay asked by the server that is honored; longer
+    /// delays are shortened to it
+    pub fn with_max_retry_after(mut self, max: Duration) -> Self {
+        self.max_retry_after = max;
+        self
+    }
+
+    /// Delay before retrying a call whose attempt number `attempt` (from 1)
+    /// failed with the given error, or `None` if it is not retried
+    pub fn retry_delay(&self, attempt: u32, err: &McpError) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let hint = retry_hint(err);
+        if !hint.retryable {
+            return None;
+        }
+        Some(match hint.retry_after {
+            Some(after) => after.min(self.max_retry_after),
+            None => {
+                let factor = 2u32.saturating_pow(attempt - 1);
+                self.initial_backoff
+                    .saturating_mul(factor)
+                    .min(self.max_backoff)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{helpers, json_rpc_to_error};
+    use crate::protocol::JsonRpcError;
+    use serde_json::json;
+
+    fn remote(data: serde_json::Value) -> McpError {
+        json_rpc_to_error(&JsonRpcError::new(-32000, "Busy", Some(data)))
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new(4).with_backoff(Duration::from_millis(100), Duration::from_millis(250))
+    }
+
+    #[test]
+    fn counts_attempts() {
+        let err = remote(json!({"retryable": true}));
+        let policy = policy();
+        assert!(policy.retry_delay(1, &err).is_some());
+        assert!(policy.retry_delay(3, &err).is_some());
+        assert_eq!(policy.retry_delay(4, &err), None);
+        assert_eq!(RetryPolicy::new(1).retry_delay(1, &err), None);
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_cap() {
+        let err = remote(json!({"retryable": true}));
+        let policy = RetryPolicy::new(40)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(250));
+        let delays: Vec<_> = (1..=4)
+            .map(|attempt| policy.retry_delay(attempt, &err).unwrap().as_millis())
+            .collect();
+        assert_eq!(delays, vec![100, 200, 250, 250]);
+        // No overflow far past the cap
+        assert_eq!(
+            policy.retry_delay(39, &err),
+            Some(Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn server_delay_is_used_and_bounded() {
+        let policy = policy().with_max_retry_after(Duration::from_secs(10));
+        let err = remote(json!({"retryable": true, "retryAfterMs": 1500}));
+        assert_eq!(
+            policy.retry_delay(1, &err),
+            Some(Duration::from_millis(1500))
+        );
+
+        let err = remote(json!({"retryable": true, "retryAfterMs": 1_000_000_000_000u64}));
+        assert_eq!(policy.retry_delay(1, &err), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn missing_hint_is_not_retried() {
+        let policy = policy();
+        assert_eq!(policy.retry_delay(1, &remote(json!({}))), None);
+        assert_eq!(
+            policy.retry_delay(1, &remote(json!({"retryable": false}))),
+            None
+        );
+        assert_eq!(
+            policy.retry_delay(1, &helpers::transport_error("reset")),
+            None
+        );
+    }
+
+    #[test]
+    fn overloaded_errors_use_the_backoff() {
+        let err = helpers::overloaded("Too many pending requests");
+        assert_eq!(
+            policy().retry_delay(2, &err),
+            Some(Duration::from_millis(200))
+        );
+    }
+}
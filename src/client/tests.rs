@@ -0,0 +1,437 @@
+//! Batch splitting, correlation and middlewares against scripted servers
+
+use super::{AfterResponse, ClientMiddleware, CorrelationWarning, IdCorrelation, JsonRpcClient};
+use crate::error::helpers;
+use crate::protocol::{JsonRpcNotification, JsonRpcRequest};
+use crate::transport::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+type Respond = Box<dyn FnMut(Value) -> Vec<Value> + Send>;
+
+/// Transport answering each sent message with the replies of a function
+struct ScriptedServer {
+    respond: Respond,
+    replies: VecDeque<String>,
+    sent: Arc<Mutex<Vec<Value>>>,
+}
+
+impl ScriptedServer {
+    fn new<F>(respond: F) -> (Self, Arc<Mutex<Vec<Value>>>)
+    where
+        F: FnMut(Value) -> Vec<Value> + Send + 'static,
+    {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let server = Self {
+            respond: Box::new(respond),
+            replies: VecDeque::new(),
+            sent: sent.clone(),
+        };
+        (server, sent)
+    }
+}
+
+#[async_trait]
+impl Transport for ScriptedServer {
+    async fn receive(&mut self) -> McpResult<String> {
+        self.replies
+            .pop_front()
+            .ok_or_else(helpers::connection_closed)
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        let message: Value = serde_json::from_str(message).unwrap();
+        self.sent.lock().unwrap().push(message.clone());
+        let replies = (self.respond)(message);
+        self.replies.extend(replies.iter().map(Value::to_string));
+        Ok(())
+    }
+}
+
+fn result(request: &Value) -> Value {
+    json!({"jsonrpc": "2.0", "result": request["method"], "id": request["id"]})
+}
+
+fn rejection(message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "error": {"code": -32600, "message": message}, "id": null})
+}
+
+/// Answers every request of a batch, unless `reject` says otherwise
+fn batch_server<F>(reject: F) -> (ScriptedServer, Arc<Mutex<Vec<Value>>>)
+where
+    F: Fn(&[Value]) -> Option<Value> + Send + 'static,
+{
+    ScriptedServer::new(move |message| match message {
+        Value::Array(requests) => match reject(&requests) {
+            Some(rejection) => vec![rejection],
+            None => vec![Value::Array(requests.iter().map(result).collect())],
+        },
+        request => vec![result(&request)],
+    })
+}
+
+fn calls(methods: &[&str]) -> Vec<(String, Option<Value>)> {
+    methods.iter().map(|m| (m.to_string(), None)).collect()
+}
+
+#[tokio::test]
+async fn size_rejection_lowers_the_batch_limit() {
+    let (server, sent) = batch_server(|requests| {
+        (requests.len() > 2).then(|| rejection("Batch too large: at most 2 requests"))
+    });
+    let mut client = JsonRpcClient::new(server);
+
+    let results = client
+        .call_batch(calls(&["a", "b", "c", "d"]))
+        .await
+        .unwrap();
+    let results: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+    assert_eq!(results, vec!["a", "b", "c", "d"]);
+    assert_eq!(client.batch_limits().max_len, Some(2));
+
+    // Later batches are split up front
+    sent.lock().unwrap().clear();
+    client.call_batch(calls(&["f", "g", "h"])).await.unwrap();
+    assert_eq!(sent.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn other_rejections_split_only_the_call() {
+    let (server, _) = batch_server(|requests| {
+        requests
+            .iter()
+            .any(|request| request["method"] == "bad")
+            .then(|| rejection("Invalid Request"))
+    });
+    let mut client = JsonRpcClient::new(server);
+
+    let results = client
+        .call_batch(calls(&["a", "bad", "c", "d"]))
+        .await
+        .unwrap();
+    assert_eq!(results[0].as_ref().unwrap(), "a");
+    assert!(results[1].is_err());
+    assert_eq!(results[2].as_ref().unwrap(), "c");
+    assert_eq!(results[3].as_ref().unwrap(), "d");
+    assert_eq!(client.batch_limits().max_len, None);
+}
+
+#[tokio::test]
+async fn server_requests_are_refused() {
+    let (server, sent) = ScriptedServer::new(|message| {
+        if message.get("method").is_none() {
+            return Vec::new();
+        }
+        vec![
+            json!({"jsonrpc": "2.0", "method": "roots/list", "id": "s1"}),
+            json!([
+                {"jsonrpc": "2.0", "method": "sampling/createMessage", "id": "s2"},
+                {"jsonrpc": "2.0", "method": "notifications/progress"},
+            ]),
+            result(&message),
+        ]
+    });
+    let mut client = JsonRpcClient::new(server);
+
+    assert_eq!(client.call("ping", None).await.unwrap(), "ping");
+    let sent = sent.lock().unwrap().clone();
+    assert_eq!(sent[1]["id"], "s1");
+    assert_eq!(sent[1]["error"]["code"], -32601);
+    assert_eq!(sent[2][0]["id"], "s2");
+    assert_eq!(sent[2][0]["error"]["code"], -32601);
+    assert_eq!(client.take_notifications().len(), 1);
+}
+
+/// Sends `count` progress notifications before answering each request
+fn chatty_server(count: usize) -> ScriptedServer {
+    ScriptedServer::new(move |request| {
+        let mut replies: Vec<Value> = (0..count)
+            .map(|n| json!({"jsonrpc": "2.0", "method": "progress", "params": {"n": n}}))
+            .collect();
+        replies.push(result(&request));
+        replies
+    })
+    .0
+}
+
+#[tokio::test]
+async fn notification_buffer_drops_oldest() {
+    let mut client = JsonRpcClient::new(chatty_server(5)).with_notification_capacity(3);
+    client.call("ping", None).await.unwrap();
+
+    let kept: Vec<_> = client
+        .take_notifications()
+        .into_iter()
+        .map(|notification| notification.params.unwrap()["n"].clone())
+        .collect();
+    assert_eq!(kept, vec![2, 3, 4]);
+    assert_eq!(client.dropped_notifications(), 2);
+}
+
+#[tokio::test]
+async fn zero_capacity_keeps_no_notification() {
+    let mut client = JsonRpcClient::new(chatty_server(2)).with_notification_capacity(0);
+    client.call("ping", None).await.unwrap();
+    assert!(client.take_notifications().is_empty());
+    assert_eq!(client.dropped_notifications(), 2);
+}
+
+/// Answers like [`result`], but without a usable id
+fn anonymous_server(without_id: bool) -> ScriptedServer {
+    ScriptedServer::new(move |message| {
+        let anonymize = |request: &Value| {
+            let mut response = result(request);
+            if without_id {
+                response.as_object_mut().unwrap().remove("id");
+            } else {
+                response["id"] = Value::Null;
+            }
+            response
+        };
+        match message {
+            Value::Array(requests) => vec![Value::Array(requests.iter().map(anonymize).collect())],
+            request => vec![anonymize(&request)],
+        }
+    })
+    .0
+}
+
+#[tokio::test]
+async fn strict_correlation_ignores_null_ids() {
+    let mut client = JsonRpcClient::new(anonymous_server(false));
+    // The response is dropped, so the call waits until the connection ends
+    let error = client.call("ping", None).await.unwrap_err();
+    assert!(crate::error::is_connection_closed(&error));
+    assert_eq!(client.correlation_warnings(), 0);
+}
+
+#[tokio::test]
+async fn lenient_correlation_matches_null_ids_in_order() {
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let seen = warnings.clone();
+    let mut client = JsonRpcClient::new(anonymous_server(false))
+        .with_id_correlation(IdCorrelation::Lenient)
+        .with_correlation_warning_handler(move |warning| {
+            seen.lock().unwrap().push(warning.clone())
+        });
+
+    assert_eq!(client.call("ping", None).await.unwrap(), "ping");
+    let results = client.call_batch(calls(&["a", "b"])).await.unwrap();
+    let results: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+    assert_eq!(results, vec!["a", "b"]);
+
+    let warnings = warnings.lock().unwrap();
+    assert_eq!(warnings.len(), 3);
+    assert!(warnings
+        .iter()
+        .all(|warning| matches!(warning, CorrelationWarning::MatchedByOrder { .. })));
+    assert_eq!(client.correlation_warnings(), 3);
+}
+
+#[tokio::test]
+async fn lenient_correlation_reports_missing_ids() {
+    let mut client =
+        JsonRpcClient::new(anonymous_server(true)).with_id_correlation(IdCorrelation::Lenient);
+    assert_eq!(client.call("ping", None).await.unwrap(), "ping");
+    // One warning for the missing member, one for matching it by order
+    assert_eq!(client.correlation_warnings(), 2);
+}
+
+#[tokio::test]
+async fn lenient_correlation_prefers_matching_ids() {
+    let (server, _) = ScriptedServer::new(|message| {
+        let requests = message.as_array().unwrap();
+        let mut unanswered = result(&requests[0]);
+        unanswered["id"] = Value::Null;
+        vec![json!([result(&requests[1]), unanswered])]
+    });
+    let mut client = JsonRpcClient::new(server).with_id_correlation(IdCorrelation::Lenient);
+
+    let results = client.call_batch(calls(&["a", "b"])).await.unwrap();
+    assert_eq!(results[0].as_ref().unwrap(), "a");
+    assert_eq!(results[1].as_ref().unwrap(), "b");
+    assert_eq!(client.correlation_warnings(), 1);
+}
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// Logs its hooks, answers calls to `cached` and drops notifications named
+/// `quiet`
+struct Recorder {
+    name: &'static str,
+    log: Log,
+}
+
+#[async_trait]
+impl ClientMiddleware for Recorder {
+    async fn before_request(&self, request: &mut JsonRpcRequest) -> Option<McpResult<Value>> {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{} before {}", self.name, request.method));
+        (request.method == "cached" && self.name == "inner").then(|| Ok(json!("from cache")))
+    }
+
+    async fn after_response(
+        &self,
+        request: &JsonRpcRequest,
+        _result: &mut McpResult<Value>,
+        _attempt: u32,
+    ) -> AfterResponse {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{} after {}", self.name, request.method));
+        AfterResponse::Return
+    }
+
+    async fn before_notification(&self, notification: &mut JsonRpcNotification) -> bool {
+        notification.method != "quiet"
+    }
+
+    async fn on_notification(&self, notification: &mut JsonRpcNotification) -> bool {
+        notification.method != "quiet"
+    }
+}
+
+fn recorders() -> (Recorder, Recorder, Log) {
+    let log = Log::default();
+    let outer = Recorder {
+        name: "outer",
+        log: log.clone(),
+    };
+    let inner = Recorder {
+        name: "inner",
+        log: log.clone(),
+    };
+    (outer, inner, log)
+}
+
+#[tokio::test]
+async fn middlewares_nest_in_registration_order() {
+    let (server, _) = batch_server(|_| None);
+    let (outer, inner, log) = recorders();
+    let mut client = JsonRpcClient::new(server)
+        .with_middleware(outer)
+        .with_middleware(inner);
+
+    client.call("ping", None).await.unwrap();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "outer before ping",
+            "inner before ping",
+            "inner after ping",
+            "outer after ping",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn answered_calls_are_not_sent() {
+    let (server, sent) = batch_server(|_| None);
+    let (outer, inner, log) = recorders();
+    let mut client = JsonRpcClient::new(server)
+        .with_middleware(outer)
+        .with_middleware(inner);
+
+    assert_eq!(client.call("cached", None).await.unwrap(), "from cache");
+    assert!(sent.lock().unwrap().is_empty());
+    // Only the middlewares registered before the answering one see the result
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "outer before cached",
+            "inner before cached",
+            "outer after cached"
+        ]
+    );
+
+    let results = client.call_batch(calls(&["a", "cached"])).await.unwrap();
+    assert_eq!(results[0].as_ref().unwrap(), "a");
+    assert_eq!(results[1].as_ref().unwrap(), "from cache");
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].as_array().map(Vec::len), Some(1));
+}
+
+/// Retries failed calls until the given attempt
+struct RetryUntil(u32);
+
+#[async_trait]
+impl ClientMiddleware for RetryUntil {
+    async fn after_response(
+        &self,
+        _request: &JsonRpcRequest,
+        result: &mut McpResult<Value>,
+        attempt: u32,
+    ) -> AfterResponse {
+        if result.is_err() && attempt < self.0 {
+            return AfterResponse::Retry(std::time::Duration::ZERO);
+        }
+        AfterResponse::Return
+    }
+}
+
+#[tokio::test]
+async fn middlewares_can_retry_calls() {
+    let mut failures = 2;
+    let (server, sent) = ScriptedServer::new(move |request| {
+        if failures == 0 {
+            return vec![result(&request)];
+        }
+        failures -= 1;
+        vec![json!({
+            "jsonrpc": "2.0",
+            "error": {"code": -32603, "message": "busy"},
+            "id": request["id"],
+        })]
+    });
+    let mut client = JsonRpcClient::new(server).with_middleware(RetryUntil(3));
+
+    assert_eq!(client.call("ping", None).await.unwrap(), "ping");
+    let ids: Vec<_> = sent
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| request["id"].clone())
+        .collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn middlewares_filter_notifications_both_ways() {
+    let (server, sent) = ScriptedServer::new(|request| {
+        if request.get("id").is_none() {
+            return Vec::new();
+        }
+        vec![
+            json!({"jsonrpc": "2.0", "method": "quiet"}),
+            json!({"jsonrpc": "2.0", "method": "progress"}),
+            result(&request),
+        ]
+    });
+    let (outer, _, _) = recorders();
+    let mut client = JsonRpcClient::new(server).with_middleware(outer);
+
+    client.notify("quiet", None).await.unwrap();
+    client.notify("initialized", None).await.unwrap();
+    client.call("ping", None).await.unwrap();
+
+    let methods: Vec<_> = sent
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|message| message["method"].clone())
+        .collect();
+    assert_eq!(methods, vec!["initialized", "ping"]);
+    let received: Vec<_> = client
+        .take_notifications()
+        .into_iter()
+        .map(|notification| notification.method)
+        .collect();
+    assert_eq!(received, vec!["progress"]);
+}
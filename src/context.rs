@@ -0,0 +1,227 @@
+//! Per-request context passed to tools
+//!
+//! [`RequestContext`] carries information about the request being served that
+//! is not part of the params, such as the method name and the request id. Tools
+//! receive it through [`Tool::execute_with_context`](crate::processor::Tool::execute_with_context).
+//!
+//! [`ConnectionLabels`] tag a connection (listener name, peer identity, tenant);
+//! they are attached to every request context and history record produced for
+//! messages on that connection.
+//!
+//! A [`SessionStore`] is a small key/value store shared by every request of a
+//! connection, so stateless tools can keep lightweight state such as
+//! pagination cursors or cached credentials between calls. Entries may expire
+//! after a time to live; the store is dropped with the connection.
+
+use crate::protocol::JsonRpcId;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Conventional label names
+pub mod labels {
+    /// Name of the listener that accepted the connection
+    pub const LISTENER: &str = "listener";
+    /// Identity of the peer (address, credentials, certificate subject)
+    pub const PEER: &str = "peer";
+    /// Tenant the connection belongs to
+    pub const TENANT: &str = "tenant";
+    /// User id of the peer process, on local sockets
+    pub const PEER_UID: &str = "peer_uid";
+    /// Group id of the peer process, on local sockets
+    pub const PEER_GID: &str = "peer_gid";
+    /// Process id of the peer, on local sockets where the OS reports it
+    pub const PEER_PID: &str = "peer_pid";
+}
+
+/// Labels describing a connection
+///
+/// Cloning is cheap; labels are fixed once the connection is being served.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionLabels(Arc<BTreeMap<String, String>>);
+
+impl ConnectionLabels {
+    /// Create an empty set of labels
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a label
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.0).insert(name.into(), value.into());
+        self
+    }
+
+    /// Value of a label
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Labels sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Whether no label is set
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Serialize for ConnectionLabels {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.as_ref().serialize(serializer)
+    }
+}
+
+impl std::fmt::Display for ConnectionLabels {
+    /// Formats as `name=value` pairs separated by spaces
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, (name, value)) in self.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+struct SessionEntry {
+    value: Value,
+    expires: Option<Instant>,
+}
+
+impl SessionEntry {
+    fn is_live(&self, now: Instant) -> bool {
+        self.expires.is_none_or(|expires| now < expires)
+    }
+}
+
+/// Key/value store scoped to a connection
+///
+/// Clones share the same entries. Expired entries are never returned and are
+/// purged as the store is written to.
+#[derive(Clone, Default)]
+pub struct SessionStore(Arc<Mutex<HashMap<String, SessionEntry>>>);
+
+impl SessionStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Value stored under a key, unless it expired
+    pub fn get(&self, key: &str) -> Option<Value> {
+        let entries = self.lock();
+        let entry = entries.get(key)?;
+        entry.is_live(Instant::now()).then(|| entry.value.clone())
+    }
+
+    /// Store a value that does not expire, replacing any previous one
+    pub fn set(&self, key: impl Into<String>, value: Value) {
+        self.insert(key.into(), value, None);
+    }
+
+    /// Store a value that expires after the given time, replacing any previous one
+    pub fn set_with_ttl(&self, key: impl Into<String>, value: Value, ttl: Duration) {
+        self.insert(key.into(), value, Some(Instant::now() + ttl));
+    }
+
+    /// Remove a value, returning it unless it expired
+    pub fn remove(&self, key: &str) -> Option<Value> {
+        let entry = self.lock().remove(key)?;
+        entry.is_live(Instant::now()).then_some(entry.value)
+    }
+
+    /// Remove every value
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Number of values that have not expired
+    pub fn len(&self) -> usize {
+        let now = Instant::now();
+        self.lock()
+            .values()
+            .filter(|entry| entry.is_live(now))
+            .count()
+    }
+
+    /// Whether no value is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert(&self, key: String, value: Value, expires: Option<Instant>) {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        entries.retain(|_, entry| entry.is_live(now));
+        entries.insert(key, SessionEntry { value, expires });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, SessionEntry>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for SessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionStore")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Context of the request a tool is executed for
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    /// Method the request was sent to
+    pub method: String,
+    /// Request id, `None` for notifications
+    pub id: Option<JsonRpcId>,
+    /// Whether the call is a rehearsal that must not have side effects
+    ///
+    /// Only tools reporting [`Tool::supports_dry_run`](crate::processor::Tool::supports_dry_run)
+    /// are called with this flag set.
+    pub dry_run: bool,
+    /// Labels of the connection the request arrived on
+    pub labels: ConnectionLabels,
+    /// Store shared by the requests of the connection
+    pub session: SessionStore,
+}
+
+impl RequestContext {
+    /// Create the context for a call to the given method
+    pub fn new(method: impl Into<String>, id: Option<JsonRpcId>) -> Self {
+        Self {
+            method: method.into(),
+            id,
+            dry_run: false,
+            labels: ConnectionLabels::default(),
+            session: SessionStore::default(),
+        }
+    }
+
+    /// Attach the labels of the connection
+    pub fn with_labels(mut self, labels: ConnectionLabels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Attach the session store of the connection
+    pub fn with_session(mut self, session: SessionStore) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// Mark the call as a dry run
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+}
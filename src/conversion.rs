@@ -1,6 +1,7 @@
+use crate::error::{DefaultErrorData, ErrorContext, ErrorDataFormatter};
 use crate::protocol::{parse_id, JsonRpcError, JsonRpcRequest, JsonRpcResponse};
 use mcp_error::{Error as McpError, Result as McpResult, Severity};
-use serde_json::{json, Value};
+use serde_json::Value;
 
 /// A trait representing a domain request from mcp-core
 /// This trait defines the interface for domain requests that will be used
@@ -96,8 +97,29 @@ pub fn json_rpc_to_domain_request(req: &JsonRpcRequest) -> McpResult<SimpleDomai
 /// Convert a domain response to a JSON-RPC response
 ///
 /// This function takes a domain response and creates a valid JSON-RPC response
-/// that can be sent back to the client.
+/// that can be sent back to the client. Error data follows the [`DefaultErrorData`] schema.
 pub fn domain_to_json_rpc_response<T: DomainResponse>(resp: &T) -> McpResult<JsonRpcResponse> {
+    domain_to_json_rpc_response_with(resp, &DefaultErrorData::new())
+}
+
+/// Convert a domain response to a JSON-RPC response, shaping `error.data` with the given formatter
+pub fn domain_to_json_rpc_response_with<T: DomainResponse>(
+    resp: &T,
+    formatter: &dyn ErrorDataFormatter,
+) -> McpResult<JsonRpcResponse> {
+    domain_to_json_rpc_response_with_context(resp, formatter, &ErrorContext::default())
+}
+
+/// Convert the response to a known call, letting error messages and data reference it
+///
+/// Domain ids are strings, so `"7"` and `7` look alike once converted; the id
+/// of the context, when set, is echoed back as received instead.
+pub fn domain_to_json_rpc_response_with_context<T: DomainResponse>(
+    resp: &T,
+    formatter: &dyn ErrorDataFormatter,
+    context: &ErrorContext,
+) -> McpResult<JsonRpcResponse> {
+    let id = context.id.clone().unwrap_or_else(|| parse_id(resp.id()));
     match resp.result() {
         Ok(value) => {
             // Success response
@@ -105,7 +127,7 @@ pub fn domain_to_json_rpc_response<T: DomainResponse>(resp: &T) -> McpResult<Jso
                 jsonrpc: "2.0".to_string(),
                 result: Some(value.clone()),
                 error: None,
-                id: parse_id(resp.id()),
+                id,
             };
 
             // Ensure the response is valid
@@ -114,11 +136,13 @@ pub fn domain_to_json_rpc_response<T: DomainResponse>(resp: &T) -> McpResult<Jso
             Ok(response)
         }
         Err(err) => {
-            // Create an McpError from the string error
-            let domain_error = McpError::new(Severity::Error, "TOOL-ERROR", err.to_string());
-
-            // Map the domain error to a JSON-RPC error code
-            let (code, message) = crate::error::error_to_json_rpc(&domain_error);
+            // Errors with a dedicated mapping keep their code; anything else is a tool failure
+            let (code, message) = if crate::error::has_dedicated_mapping(err) {
+                crate::error::error_to_json_rpc_with_context(err, context)
+            } else {
+                let domain_error = McpError::new(Severity::Error, "TOOL-ERROR", err.to_string());
+                crate::error::error_to_json_rpc_with_context(&domain_error, context)
+            };
 
             // Create the error response
             let response = JsonRpcResponse {
@@ -127,9 +151,9 @@ pub fn domain_to_json_rpc_response<T: DomainResponse>(resp: &T) -> McpResult<Jso
                 error: Some(JsonRpcError {
                     code,
                     message,
-                    data: Some(json!({ "error_type": err })),
+                    data: Some(formatter.format_with_context(err, context)),
                 }),
-                id: parse_id(resp.id()),
+                id,
             };
 
             // Ensure the response is valid
@@ -1,4 +1,6 @@
 use mcp_error::{Error as McpError, Result as McpResult, Severity};
+use serde_json::{Map, Value};
+use std::sync::Arc;
 
 /// A specialized Result type for JSON-RPC operations
 pub type Result<T> = McpResult<T>;
@@ -18,6 +20,35 @@ pub mod error_codes {
     /// Reserved for implementation-defined server-errors.
     pub const SERVER_ERROR_START: i32 = -32000;
     pub const SERVER_ERROR_END: i32 = -32099;
+    /// The server is shedding load for this method (implementation-defined).
+    pub const SERVER_OVERLOADED: i32 = -32001;
+    /// The method name exceeds the configured length limit (implementation-defined).
+    pub const METHOD_TOO_LONG: i32 = -32002;
+    /// The string id exceeds the configured length limit (implementation-defined).
+    pub const ID_TOO_LONG: i32 = -32003;
+    /// The request was not executed because its atomic batch was rejected (implementation-defined).
+    pub const BATCH_ABORTED: i32 = -32004;
+    /// The request lacks valid credentials (implementation-defined).
+    pub const UNAUTHORIZED: i32 = -32005;
+    /// The session is owned by another server process, named in `data.redirect` (implementation-defined).
+    pub const REDIRECT: i32 = -32006;
+    /// Start of the range reserved by the specification; codes outside it are
+    /// application-defined.
+    pub const RESERVED_START: i32 = -32768;
+    pub const RESERVED_END: i32 = -32000;
+
+    /// Whether a code is defined by the specification or in the server error range
+    pub fn is_defined(code: i32) -> bool {
+        matches!(
+            code,
+            PARSE_ERROR | INVALID_REQUEST | METHOD_NOT_FOUND | INVALID_PARAMS | INTERNAL_ERROR
+        ) || (SERVER_ERROR_END..=SERVER_ERROR_START).contains(&code)
+    }
+
+    /// Whether a code is free for application-defined errors
+    pub fn is_application_defined(code: i32) -> bool {
+        !(RESERVED_START..=RESERVED_END).contains(&code)
+    }
 }
 
 /// Reference codes for JSON-RPC adapter errors
@@ -34,6 +65,36 @@ pub mod reference_codes {
     pub const INTERNAL: &str = "JSONRPC-005";
     /// Domain errors from mcp-core
     pub const DOMAIN: &str = "JSONRPC-006";
+    /// Request rejected to shed load
+    pub const OVERLOADED: &str = "JSONRPC-007";
+    /// Error returned by the remote peer
+    pub const REMOTE: &str = "JSONRPC-008";
+    /// Inconsistent server or client configuration
+    pub const CONFIG: &str = "JSONRPC-009";
+    /// No message received from the peer within the idle timeout
+    pub const IDLE: &str = "JSONRPC-010";
+    /// Method name longer than the configured limit
+    pub const METHOD_TOO_LONG: &str = "JSONRPC-011";
+    /// String id longer than the configured limit
+    pub const ID_TOO_LONG: &str = "JSONRPC-012";
+    /// Result not matching the schema the client expects
+    pub const CONTRACT: &str = "JSONRPC-013";
+    /// Request left unexecuted since another element of its atomic batch failed
+    pub const BATCH_ABORTED: &str = "JSONRPC-014";
+    /// No message received within the receive timeout
+    pub const RECEIVE_TIMEOUT: &str = "JSONRPC-015";
+    /// Message not written within the send timeout
+    pub const SEND_TIMEOUT: &str = "JSONRPC-016";
+    /// Missing, malformed or rejected credentials
+    pub const UNAUTHORIZED: &str = "JSONRPC-017";
+    /// Error carrying its own JSON-RPC error object, see [`helpers::coded_error`](super::helpers::coded_error)
+    pub const CODED: &str = "JSONRPC-018";
+    /// Session owned by another server process, see [`affinity`](crate::affinity)
+    pub const REDIRECT: &str = "JSONRPC-019";
+    /// Incoming message larger than the transport accepts
+    pub const MESSAGE_TOO_LARGE: &str = "JSONRPC-020";
+    /// Connection closed, by the peer or locally
+    pub const CONNECTION_CLOSED: &str = "JSONRPC-021";
 }
 
 /// Domain error reference codes
@@ -50,6 +111,230 @@ pub mod domain_reference_codes {
     pub const INTERNAL: &str = "INTERNAL";
 }
 
+/// Structured details attached to an error, surfaced in the JSON-RPC `error.data`
+///
+/// Attach with [`helpers::with_details`] and read back with [`error_details`].
+#[derive(Debug, Clone)]
+pub struct ErrorDetails(pub Value);
+
+impl std::fmt::Display for ErrorDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ErrorDetails {}
+
+/// Get the structured details attached to an error, if any
+pub fn error_details(err: &McpError) -> Option<&Value> {
+    std::error::Error::source(err)?
+        .downcast_ref::<ErrorDetails>()
+        .map(|details| &details.0)
+}
+
+/// Whether and when a failed call may be sent again
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryHint {
+    /// Whether the same call may succeed if sent again
+    pub retryable: bool,
+    /// How long to wait first, when known
+    pub retry_after: Option<std::time::Duration>,
+}
+
+/// Get the retry hint of an error
+///
+/// Errors received from a peer use the `retryable` and `retryAfterMs` members
+/// of their `error.data`. Otherwise, overloaded errors and errors given a delay
+/// with [`helpers::with_retry_after`] are retryable.
+pub fn retry_hint(err: &McpError) -> RetryHint {
+    let details = error_details(err);
+    // Errors received from a peer keep the whole error object as details
+    let data = details.and_then(|details| details.get("data"));
+    let retry_after = details
+        .and_then(|details| details.get("retryAfterMs"))
+        .or_else(|| data.and_then(|data| data.get("retryAfterMs")))
+        .and_then(Value::as_u64)
+        .map(std::time::Duration::from_millis);
+    let retryable = data
+        .and_then(|data| data.get("retryable"))
+        .and_then(Value::as_bool)
+        .unwrap_or(retry_after.is_some() || err.reference.contains(reference_codes::OVERLOADED));
+    RetryHint {
+        retryable,
+        retry_after,
+    }
+}
+
+/// Stage of message handling at which an error occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorPhase {
+    /// The message could not be parsed
+    Parse,
+    /// The message violates the specification or the configured limits
+    Validation,
+    /// The method could not be resolved or its params converted
+    Dispatch,
+    /// The method ran and failed
+    Execution,
+    /// The response could not be built
+    Response,
+}
+
+impl ErrorPhase {
+    /// Lowercase name, as found in the default `error.data`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorPhase::Parse => "parse",
+            ErrorPhase::Validation => "validation",
+            ErrorPhase::Dispatch => "dispatch",
+            ErrorPhase::Execution => "execution",
+            ErrorPhase::Response => "response",
+        }
+    }
+}
+
+/// Call an error occurred on, letting messages and data reference it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorContext {
+    /// Method of the failing call, when known
+    pub method: Option<String>,
+    /// Id of the failing request, when known
+    pub id: Option<crate::protocol::JsonRpcId>,
+    /// Stage at which the call failed, when known
+    pub phase: Option<ErrorPhase>,
+}
+
+impl ErrorContext {
+    /// Create a context for an error raised at the given phase
+    pub fn new(phase: ErrorPhase) -> Self {
+        Self {
+            method: None,
+            id: None,
+            phase: Some(phase),
+        }
+    }
+
+    /// Set the method of the failing call
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+
+    /// Set the id of the failing request
+    pub fn with_id(mut self, id: crate::protocol::JsonRpcId) -> Self {
+        self.id = Some(id);
+        self
+    }
+}
+
+/// Shapes the `error.data` member of error responses
+///
+/// Implement this to customize what clients see; the processor uses
+/// [`DefaultErrorData`] unless configured otherwise.
+pub trait ErrorDataFormatter: Send + Sync {
+    /// Build the `error.data` value for the given error
+    fn format(&self, err: &McpError) -> Value;
+
+    /// Build the `error.data` value for an error raised on a known call
+    ///
+    /// Defaults to [`format`](Self::format), ignoring the context.
+    fn format_with_context(&self, err: &McpError, _context: &ErrorContext) -> Value {
+        self.format(err)
+    }
+}
+
+/// Function deriving an embedder-specific category from an error
+pub type ErrorCategorizer = Arc<dyn Fn(&McpError) -> Option<String> + Send + Sync>;
+
+/// Default `error.data` schema
+///
+/// ```json
+/// {
+///   "error": "[TOOL-ERROR] disk full",  // human-readable error
+///   "severity": "error",                // lowercase McpError severity
+///   "reference": "TOOL-ERROR",          // McpError reference code
+///   "category": "storage",              // only with a categorizer returning Some
+///   "error_type": { ... },              // deprecated, see below
+///   "details": { ... },                 // only when details are attached
+///   "redirect": { ... },                // only for redirect errors, see [`crate::affinity`]
+///   "method": "tools/call",             // only when the failing method is known
+///   "phase": "execution",               // only when the failing phase is known
+///   "retryable": false,                 // whether the call may succeed if sent again
+///   "retryAfterMs": 250                 // only when a delay is known, see [`retry_hint`]
+/// }
+/// ```
+///
+/// `error_type` holds the serialized [`McpError`], which was the whole of
+/// `error.data` before this schema. It is kept for existing clients and will
+/// be removed in the next release: read `severity`, `reference` and `error`
+/// instead.
+#[derive(Clone, Default)]
+pub struct DefaultErrorData {
+    categorizer: Option<ErrorCategorizer>,
+}
+
+impl DefaultErrorData {
+    /// Create the default formatter without categories
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a `category` field computed by the given function
+    pub fn with_categorizer<F>(mut self, categorizer: F) -> Self
+    where
+        F: Fn(&McpError) -> Option<String> + Send + Sync + 'static,
+    {
+        self.categorizer = Some(Arc::new(categorizer));
+        self
+    }
+}
+
+impl ErrorDataFormatter for DefaultErrorData {
+    fn format(&self, err: &McpError) -> Value {
+        let mut data = Map::new();
+        data.insert("error".to_string(), Value::String(err.to_string()));
+        data.insert(
+            "severity".to_string(),
+            Value::String(format!("{:?}", err.severity).to_lowercase()),
+        );
+        data.insert(
+            "reference".to_string(),
+            Value::String(err.reference.to_string()),
+        );
+        if let Some(category) = self.categorizer.as_ref().and_then(|c| c(err)) {
+            data.insert("category".to_string(), Value::String(category));
+        }
+        data.insert(
+            "error_type".to_string(),
+            serde_json::to_value(err).unwrap_or(Value::Null),
+        );
+        if let Some(details) = error_details(err) {
+            data.insert("details".to_string(), details.clone());
+            if let Some(redirect) = details.get("redirect").filter(|_| is_redirect(err)) {
+                data.insert("redirect".to_string(), redirect.clone());
+            }
+        }
+        let hint = retry_hint(err);
+        data.insert("retryable".to_string(), Value::Bool(hint.retryable));
+        if let Some(after) = hint.retry_after {
+            data.insert("retryAfterMs".to_string(), Value::from(after.as_millis() as u64));
+        }
+        Value::Object(data)
+    }
+
+    fn format_with_context(&self, err: &McpError, context: &ErrorContext) -> Value {
+        let mut data = self.format(err);
+        if let Some(method) = &context.method {
+            data["method"] = Value::String(method.clone());
+        }
+        if let Some(phase) = context.phase {
+            data["phase"] = Value::String(phase.as_str().to_string());
+        }
+        data
+    }
+}
+
 /// Convert from domain error to JSON-RPC error codes
 ///
 /// This function maps domain error reference codes to the appropriate JSON-RPC error codes
@@ -58,6 +343,10 @@ pub fn error_to_json_rpc(err: &McpError) -> (i32, String) {
     // Extract reference code from the error to determine JSON-RPC error code
     let reference = &err.reference;
 
+    if let Some((code, message)) = coded(err) {
+        return (code, message);
+    }
+
     // Map domain error reference codes to JSON-RPC error codes
     match reference {
         ref_code if ref_code.contains(domain_reference_codes::TOOL_NOT_FOUND) => (
@@ -84,10 +373,41 @@ pub fn error_to_json_rpc(err: &McpError) -> (i32, String) {
             (error_codes::PARSE_ERROR, "Parse error".to_string())
         }
 
+        ref_code if ref_code.contains(reference_codes::MESSAGE_TOO_LARGE) => {
+            (error_codes::PARSE_ERROR, "Message too large".to_string())
+        }
+
+        ref_code if ref_code.contains(reference_codes::METHOD_TOO_LONG) => (
+            error_codes::METHOD_TOO_LONG,
+            "Method name too long".to_string(),
+        ),
+
+        ref_code if ref_code.contains(reference_codes::ID_TOO_LONG) => {
+            (error_codes::ID_TOO_LONG, "Id too long".to_string())
+        }
+
+        ref_code if ref_code.contains(reference_codes::BATCH_ABORTED) => {
+            (error_codes::BATCH_ABORTED, "Batch aborted".to_string())
+        }
+
+        ref_code if ref_code.contains(reference_codes::UNAUTHORIZED) => {
+            (error_codes::UNAUTHORIZED, "Unauthorized".to_string())
+        }
+
+        ref_code if ref_code.contains(reference_codes::REDIRECT) => (
+            error_codes::REDIRECT,
+            "Session owned by another server".to_string(),
+        ),
+
         ref_code if ref_code.contains(reference_codes::PROTOCOL) => {
             (error_codes::INVALID_REQUEST, "Invalid Request".to_string())
         }
 
+        ref_code if ref_code.contains(reference_codes::OVERLOADED) => (
+            error_codes::SERVER_OVERLOADED,
+            "Server overloaded".to_string(),
+        ),
+
         _ => (
             error_codes::INTERNAL_ERROR,
             format!("Internal error: {}", err),
@@ -95,6 +415,192 @@ pub fn error_to_json_rpc(err: &McpError) -> (i32, String) {
     }
 }
 
+/// Convert an error raised on a known call to JSON-RPC error codes
+///
+/// Same codes as [`error_to_json_rpc`]; when the method is known, the messages
+/// of method-specific codes name it, e.g. `Method 'tools/call' not found`.
+pub fn error_to_json_rpc_with_context(err: &McpError, context: &ErrorContext) -> (i32, String) {
+    // Coded errors keep the message chosen with their code
+    if let Some((code, message)) = coded(err) {
+        return (code, message);
+    }
+    let (code, message) = error_to_json_rpc(err);
+    let method = match &context.method {
+        Some(method) => method,
+        None => return (code, message),
+    };
+
+    let message = match code {
+        error_codes::METHOD_NOT_FOUND => format!("Method '{}' not found", method),
+        error_codes::INVALID_PARAMS => format!("Invalid params for method '{}'", method),
+        error_codes::SERVER_ERROR_START => format!("Method '{}' failed", method),
+        error_codes::SERVER_OVERLOADED => format!("Method '{}' is overloaded", method),
+        _ => message,
+    };
+    (code, message)
+}
+
+/// Code and message of an error created with [`helpers::coded_error`]
+fn coded(err: &McpError) -> Option<(i32, String)> {
+    if !err.reference.contains(reference_codes::CODED) {
+        return None;
+    }
+    let details = error_details(err)?;
+    let code = details.get("code")?.as_i64()?;
+    let message = details.get("message")?.as_str()?;
+    Some((i32::try_from(code).ok()?, message.to_string()))
+}
+
+/// Convert a JSON-RPC error object received from a peer back into an error
+///
+/// The message is kept as is and the whole error object (code, message and
+/// data) is attached as details, so [`error_details`] returns it. The
+/// reference code is a best guess:
+///
+/// | Received                                   | Reference                            |
+/// |--------------------------------------------|--------------------------------------|
+/// | `data.reference` set by a peer using the default `error.data` schema | that reference |
+/// | -32700 parse error                         | [`reference_codes::JSON`]            |
+/// | -32600 invalid request                     | [`reference_codes::PROTOCOL`]        |
+/// | -32601 method not found                    | [`domain_reference_codes::TOOL_NOT_FOUND`] |
+/// | -32602 invalid params                      | [`domain_reference_codes::INVALID_PARAMS`] |
+/// | -32603 internal error                      | [`reference_codes::INTERNAL`]        |
+/// | -32000 server error                        | [`domain_reference_codes::TOOL_ERROR`] |
+/// | -32001 server overloaded                   | [`reference_codes::OVERLOADED`]      |
+/// | -32002 method name too long                | [`reference_codes::METHOD_TOO_LONG`] |
+/// | -32003 id too long                         | [`reference_codes::ID_TOO_LONG`]     |
+/// | -32004 batch aborted                       | [`reference_codes::BATCH_ABORTED`]   |
+/// | -32005 unauthorized                        | [`reference_codes::UNAUTHORIZED`]    |
+/// | -32006 redirect                            | [`reference_codes::REDIRECT`]        |
+/// | any other code                             | [`reference_codes::REMOTE`]          |
+///
+/// Transport and idle references reported by the peer describe the peer's own
+/// connections, so they are never lifted: [`is_connection_error`] stays false
+/// for errors received from a healthy connection. For every code of the table,
+/// [`error_to_json_rpc`] maps the result back to the code received.
+pub fn json_rpc_to_error(error: &crate::protocol::JsonRpcError) -> McpError {
+    let data = error.data.as_ref();
+    let reference = data
+        .and_then(|data| data.get("reference"))
+        .and_then(Value::as_str)
+        .filter(|reference| {
+            !reference.contains(reference_codes::TRANSPORT)
+                && !reference.contains(reference_codes::CONNECTION_CLOSED)
+                && !reference.contains(reference_codes::IDLE)
+                && !reference.contains(reference_codes::RECEIVE_TIMEOUT)
+                && !reference.contains(reference_codes::SEND_TIMEOUT)
+        })
+        .unwrap_or(match error.code {
+            error_codes::PARSE_ERROR => reference_codes::JSON,
+            error_codes::INVALID_REQUEST => reference_codes::PROTOCOL,
+            error_codes::METHOD_NOT_FOUND => domain_reference_codes::TOOL_NOT_FOUND,
+            error_codes::INVALID_PARAMS => domain_reference_codes::INVALID_PARAMS,
+            error_codes::INTERNAL_ERROR => reference_codes::INTERNAL,
+            error_codes::SERVER_ERROR_START => domain_reference_codes::TOOL_ERROR,
+            error_codes::SERVER_OVERLOADED => reference_codes::OVERLOADED,
+            error_codes::METHOD_TOO_LONG => reference_codes::METHOD_TOO_LONG,
+            error_codes::ID_TOO_LONG => reference_codes::ID_TOO_LONG,
+            error_codes::BATCH_ABORTED => reference_codes::BATCH_ABORTED,
+            error_codes::UNAUTHORIZED => reference_codes::UNAUTHORIZED,
+            error_codes::REDIRECT => reference_codes::REDIRECT,
+            _ => reference_codes::REMOTE,
+        });
+    let severity = match data.and_then(|data| data.get("severity")).and_then(Value::as_str) {
+        Some("critical") => Severity::Critical,
+        _ => Severity::Error,
+    };
+
+    helpers::with_details(
+        McpError::new(severity, reference, &error.message),
+        serde_json::to_value(error).unwrap_or(Value::Null),
+    )
+}
+
+/// Whether the error reports a connection closed for inactivity
+pub fn is_idle_timeout(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::IDLE)
+}
+
+/// Whether the error reports a receive that timed out
+///
+/// The connection is still usable: receiving again waits for the same message.
+pub fn is_receive_timeout(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::RECEIVE_TIMEOUT)
+}
+
+/// Whether the error reports a send that timed out
+///
+/// Part of the message may have been written, so the connection is no longer usable.
+pub fn is_send_timeout(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::SEND_TIMEOUT)
+}
+
+/// Whether the error means the connection to the peer is no longer usable
+pub fn is_connection_error(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::TRANSPORT)
+        || is_connection_closed(err)
+        || is_idle_timeout(err)
+        || is_send_timeout(err)
+}
+
+/// Whether the error reports the end of the connection rather than a failure
+///
+/// Transports report the end of their input this way, with
+/// [`helpers::connection_closed`], and a processor's run ends normally on it.
+pub fn is_connection_closed(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::CONNECTION_CLOSED)
+}
+
+/// Whether the error reports a result that does not match its expected schema
+///
+/// See [`ContractViolation`](crate::client::ContractViolation).
+pub fn is_contract_violation(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::CONTRACT)
+}
+
+/// Whether the error reports a method name or id over the configured limits
+pub fn is_limit_exceeded(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::METHOD_TOO_LONG)
+        || err.reference.contains(reference_codes::ID_TOO_LONG)
+}
+
+/// Whether the error reports missing or rejected credentials
+///
+/// See [`auth`](crate::auth).
+pub fn is_unauthorized(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::UNAUTHORIZED)
+}
+
+/// Whether the error reports an incoming message over the size limit of the
+/// transport
+///
+/// The message was skipped, so the connection is still usable.
+pub fn is_message_too_large(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::MESSAGE_TOO_LARGE)
+}
+
+/// Whether the error sends the client to another server process
+///
+/// See [`affinity::redirect_of`](crate::affinity::redirect_of).
+pub fn is_redirect(err: &McpError) -> bool {
+    err.reference.contains(reference_codes::REDIRECT)
+}
+
+/// Whether an error returned by a tool keeps its own JSON-RPC mapping
+///
+/// Any other tool error is reported as a generic tool failure (-32000).
+pub fn has_dedicated_mapping(err: &McpError) -> bool {
+    [
+        domain_reference_codes::INVALID_PARAMS,
+        reference_codes::OVERLOADED,
+        reference_codes::UNAUTHORIZED,
+        reference_codes::CODED,
+        reference_codes::REDIRECT,
+    ]
+    .iter()
+    .any(|code| err.reference.contains(code))
+}
+
 /// Helper functions for creating common error types
 pub mod helpers {
     use super::*;
@@ -115,6 +621,25 @@ pub mod helpers {
         McpError::new(Severity::Error, reference_codes::TRANSPORT, msg)
     }
 
+    /// Create the error reporting the end of a connection, see
+    /// [`is_connection_closed`](super::is_connection_closed)
+    pub fn connection_closed() -> McpError {
+        McpError::new(
+            Severity::Error,
+            reference_codes::CONNECTION_CLOSED,
+            "Connection closed",
+        )
+    }
+
+    /// Create the error reporting the end of a connection, for the given reason
+    pub fn connection_closed_because(reason: &str) -> McpError {
+        McpError::new(
+            Severity::Error,
+            reference_codes::CONNECTION_CLOSED,
+            format!("Connection closed: {}", reason),
+        )
+    }
+
     /// Create a conversion error
     pub fn conversion_error(msg: &str) -> McpError {
         McpError::new(Severity::Error, reference_codes::CONVERSION, msg)
@@ -125,6 +650,145 @@ pub mod helpers {
         McpError::new(Severity::Error, reference_codes::PROTOCOL, msg)
     }
 
+    /// Create an invalid params error (maps to -32602)
+    pub fn invalid_params(msg: &str) -> McpError {
+        McpError::new(Severity::Error, domain_reference_codes::INVALID_PARAMS, msg)
+    }
+
+    /// Attach structured details to an error (replaces any existing source)
+    pub fn with_details(err: McpError, details: Value) -> McpError {
+        err.with_source(Box::new(ErrorDetails(details)))
+    }
+
+    /// Mark an error as retryable after the given delay
+    ///
+    /// Sets `retryAfterMs` in the error details, keeping the other members of
+    /// object details; see [`retry_hint`](super::retry_hint).
+    pub fn with_retry_after(err: McpError, after: std::time::Duration) -> McpError {
+        let mut details = match error_details(&err) {
+            Some(Value::Object(details)) => details.clone(),
+            _ => Map::new(),
+        };
+        details.insert("retryAfterMs".to_string(), Value::from(after.as_millis() as u64));
+        with_details(err, Value::Object(details))
+    }
+
+    /// Create an overloaded error (maps to -32001)
+    pub fn overloaded(msg: &str) -> McpError {
+        McpError::new(Severity::Error, reference_codes::OVERLOADED, msg)
+    }
+
+    /// Wrap an error object received from the remote peer
+    ///
+    /// The original error object is kept in the error details.
+    pub fn remote_error(error: &crate::protocol::JsonRpcError) -> McpError {
+        with_details(
+            McpError::new(
+                Severity::Error,
+                reference_codes::REMOTE,
+                format!("{} ({})", error.message, error.code),
+            ),
+            serde_json::to_value(error).unwrap_or(Value::Null),
+        )
+    }
+
+    /// Create an idle timeout error
+    pub fn idle_timeout(idle: std::time::Duration) -> McpError {
+        McpError::new(
+            Severity::Error,
+            reference_codes::IDLE,
+            format!("Connection idle for {:?}", idle),
+        )
+    }
+
+    /// Create a receive timeout error
+    pub fn receive_timeout(timeout: std::time::Duration) -> McpError {
+        McpError::new(
+            Severity::Error,
+            reference_codes::RECEIVE_TIMEOUT,
+            format!("No message received within {:?}", timeout),
+        )
+    }
+
+    /// Create a send timeout error
+    pub fn send_timeout(timeout: std::time::Duration) -> McpError {
+        McpError::new(
+            Severity::Error,
+            reference_codes::SEND_TIMEOUT,
+            format!("Message not sent within {:?}", timeout),
+        )
+    }
+
+    /// Create an error for a method name over the length limit
+    pub fn method_too_long(len: usize, max: usize) -> McpError {
+        McpError::new(
+            Severity::Error,
+            reference_codes::METHOD_TOO_LONG,
+            format!("Method name is {} bytes long, limit is {}", len, max),
+        )
+    }
+
+    /// Create an error for a string id over the length limit
+    pub fn id_too_long(len: usize, max: usize) -> McpError {
+        McpError::new(
+            Severity::Error,
+            reference_codes::ID_TOO_LONG,
+            format!("Id is {} bytes long, limit is {}", len, max),
+        )
+    }
+
+    /// Create an error for a request left unexecuted by an atomic batch (maps to -32004)
+    pub fn batch_aborted(msg: &str) -> McpError {
+        McpError::new(Severity::Error, reference_codes::BATCH_ABORTED, msg)
+    }
+
+    /// Create an error for missing or rejected credentials (maps to -32005)
+    pub fn unauthorized(msg: &str) -> McpError {
+        McpError::new(Severity::Error, reference_codes::UNAUTHORIZED, msg)
+    }
+
+    /// Create an error sending the client to another server process (maps to -32006)
+    ///
+    /// The target is surfaced as `data.redirect` by the default `error.data` schema.
+    pub fn redirect(msg: &str, target: Value) -> McpError {
+        let mut details = Map::new();
+        details.insert("redirect".to_string(), target);
+        with_details(
+            McpError::new(Severity::Error, reference_codes::REDIRECT, msg),
+            Value::Object(details),
+        )
+    }
+
+    /// Create an error for an incoming message over `max_bytes` (maps to -32700)
+    pub fn message_too_large(max_bytes: usize) -> McpError {
+        let mut details = Map::new();
+        details.insert("maxBytes".to_string(), Value::from(max_bytes));
+        with_details(
+            McpError::new(
+                Severity::Error,
+                reference_codes::MESSAGE_TOO_LARGE,
+                format!("Message exceeds {} bytes", max_bytes),
+            ),
+            Value::Object(details),
+        )
+    }
+
+    /// Create an error answered with the given error object, e.g. one built
+    /// with [`JsonRpcError::application`](crate::protocol::JsonRpcError::application)
+    ///
+    /// The object is attached as details, like errors received from a peer.
+    pub fn coded_error(error: crate::protocol::JsonRpcError) -> McpError {
+        with_details(
+            McpError::new(Severity::Error, reference_codes::CODED, error.message.clone()),
+            serde_json::to_value(&error).unwrap_or(Value::Null),
+        )
+    }
+
+    /// Create a configuration error
+    pub fn config_error(msg: &str) -> McpError {
+        McpError::new(Severity::Error, reference_codes::CONFIG, msg)
+    }
+
     /// Create an internal error
     pub fn internal_error(msg: &str) -> McpError {
         McpError::new(Severity::Critical, reference_codes::INTERNAL, msg)
@@ -135,3 +799,134 @@ pub mod helpers {
         McpError::new(Severity::Error, reference_codes::DOMAIN, msg).with_source(Box::new(err))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::JsonRpcError;
+    use serde_json::json;
+
+    /// Rows of the [`json_rpc_to_error`] table with a code of their own
+    const TABLE: &[(i32, &str)] = &[
+        (error_codes::PARSE_ERROR, reference_codes::JSON),
+        (error_codes::INVALID_REQUEST, reference_codes::PROTOCOL),
+        (
+            error_codes::METHOD_NOT_FOUND,
+            domain_reference_codes::TOOL_NOT_FOUND,
+        ),
+        (
+            error_codes::INVALID_PARAMS,
+            domain_reference_codes::INVALID_PARAMS,
+        ),
+        (error_codes::INTERNAL_ERROR, reference_codes::INTERNAL),
+        (
+            error_codes::SERVER_ERROR_START,
+            domain_reference_codes::TOOL_ERROR,
+        ),
+        (error_codes::SERVER_OVERLOADED, reference_codes::OVERLOADED),
+        (
+            error_codes::METHOD_TOO_LONG,
+            reference_codes::METHOD_TOO_LONG,
+        ),
+        (error_codes::ID_TOO_LONG, reference_codes::ID_TOO_LONG),
+        (error_codes::BATCH_ABORTED, reference_codes::BATCH_ABORTED),
+        (error_codes::UNAUTHORIZED, reference_codes::UNAUTHORIZED),
+        (error_codes::REDIRECT, reference_codes::REDIRECT),
+    ];
+
+    #[test]
+    fn every_code_of_the_table_round_trips() {
+        for &(code, reference) in TABLE {
+            let received = JsonRpcError::new(code, "from the peer", None);
+            let err = json_rpc_to_error(&received);
+            assert!(err.reference.contains(reference), "code {}", code);
+            assert_eq!(error_to_json_rpc(&err).0, code, "code {}", code);
+        }
+    }
+
+    #[test]
+    fn other_codes_are_remote() {
+        for code in [-32050, -1, 42] {
+            let received = JsonRpcError::new(code, "application error", Some(json!({"a": 1})));
+            let err = json_rpc_to_error(&received);
+            assert!(err.reference.contains(reference_codes::REMOTE));
+            assert!(!is_connection_error(&err));
+        }
+    }
+
+    #[test]
+    fn error_object_is_kept_as_details() {
+        let data = json!({"reference": reference_codes::UNAUTHORIZED, "why": "expired"});
+        let received = JsonRpcError::new(-32050, "token expired", Some(data.clone()));
+        let err = json_rpc_to_error(&received);
+        let details = error_details(&err).unwrap();
+        assert_eq!(details["code"], -32050);
+        assert_eq!(details["message"], "token expired");
+        assert_eq!(details["data"], data);
+    }
+
+    #[test]
+    fn data_reference_overrides_the_code() {
+        let received = JsonRpcError::new(
+            error_codes::SERVER_ERROR_START,
+            "Unauthorized",
+            Some(json!({"reference": reference_codes::UNAUTHORIZED})),
+        );
+        let err = json_rpc_to_error(&received);
+        assert!(err.reference.contains(reference_codes::UNAUTHORIZED));
+        assert_eq!(error_to_json_rpc(&err).0, error_codes::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn connection_references_of_the_peer_are_not_lifted() {
+        let references = [
+            reference_codes::TRANSPORT,
+            reference_codes::CONNECTION_CLOSED,
+            reference_codes::IDLE,
+            reference_codes::RECEIVE_TIMEOUT,
+            reference_codes::SEND_TIMEOUT,
+        ];
+        for reference in references {
+            let received = JsonRpcError::new(
+                error_codes::INTERNAL_ERROR,
+                "Internal error",
+                Some(json!({"reference": reference})),
+            );
+            let err = json_rpc_to_error(&received);
+            assert!(
+                err.reference.contains(reference_codes::INTERNAL),
+                "{}",
+                reference
+            );
+            assert!(!is_connection_error(&err), "{}", reference);
+            assert!(!is_connection_closed(&err), "{}", reference);
+            assert!(!is_idle_timeout(&err), "{}", reference);
+            assert!(!is_receive_timeout(&err), "{}", reference);
+            assert_eq!(error_to_json_rpc(&err).0, error_codes::INTERNAL_ERROR);
+        }
+    }
+
+    #[test]
+    fn critical_severity_is_kept() {
+        let received = JsonRpcError::new(
+            error_codes::INTERNAL_ERROR,
+            "Internal error",
+            Some(json!({"severity": "critical"})),
+        );
+        assert!(matches!(
+            json_rpc_to_error(&received).severity,
+            Severity::Critical
+        ));
+    }
+
+    #[test]
+    fn default_error_data_keeps_deprecated_error_type() {
+        let err = helpers::internal_error("disk full");
+        let data = DefaultErrorData::new()
+            .with_categorizer(|_| Some("storage".to_string()))
+            .format(&err);
+        assert_eq!(data["reference"], reference_codes::INTERNAL);
+        assert_eq!(data["category"], "storage");
+        assert_eq!(data["error_type"], serde_json::to_value(&err).unwrap());
+    }
+}
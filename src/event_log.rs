@@ -0,0 +1,236 @@
+//! Structured event log of processed messages
+//!
+//! [`EventLog`], given to a processor with
+//! [`with_event_log`](crate::processor::JsonRpcProcessor::with_event_log),
+//! writes one JSON object per line for every message processed, meant for log
+//! pipelines rather than for reading back like the [history](crate::history).
+//! A request gives an inbound record when it is received and an outbound one
+//! when it is answered; a notification gives a single inbound record once
+//! executed. Each element of a batch is recorded on its own.
+//!
+//! ```text
+//! {"timestamp_ms":1718000000000,"direction":"inbound","method":"search","id":7,"bytes":61}
+//! {"timestamp_ms":1718000000042,"direction":"outbound","method":"search","id":7,"code":-32602,"duration_ms":41.7,"bytes":118}
+//! ```
+//!
+//! Payloads are never logged. Records are written by a background task; when
+//! it falls more than [`DEFAULT_BUFFER`] records behind, new records are
+//! dropped and counted rather than slowing down processing.
+
+use crate::context::ConnectionLabels;
+use crate::error::helpers;
+use crate::history::{now_ms, Direction};
+use crate::protocol::JsonRpcId;
+use mcp_error::Result as McpResult;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+
+/// Default number of records waiting to be written before new ones are dropped
+pub const DEFAULT_BUFFER: usize = 4096;
+
+/// A single logged message
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MessageEvent {
+    /// Milliseconds since the Unix epoch when the message was received or sent
+    pub timestamp_ms: u64,
+    /// Whether the message was received or sent
+    pub direction: Direction,
+    /// Method called, also given on responses
+    pub method: String,
+    /// Request id, absent for notifications
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<JsonRpcId>,
+    /// JSON-RPC error code of a failed call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<i32>,
+    /// Processing time in milliseconds, on responses and notifications
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<f64>,
+    /// Size of the compact JSON serialization of the message
+    pub bytes: usize,
+    /// Labels of the connection the message was exchanged on
+    #[serde(skip_serializing_if = "ConnectionLabels::is_empty")]
+    pub labels: ConnectionLabels,
+}
+
+enum Command {
+    Write(String),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Shared JSON-lines log of processed messages
+///
+/// Clones write to the same destination, so one log can record the messages of
+/// every connection. The background writer stops once all clones are dropped.
+#[derive(Clone)]
+pub struct EventLog {
+    sender: mpsc::Sender<Command>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl EventLog {
+    /// Log to any writer, e.g. stdout or a socket
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn new<W: AsyncWrite + Send + Unpin + 'static>(writer: W) -> Self {
+        Self::with_buffer(writer, DEFAULT_BUFFER)
+    }
+
+    /// Log to any writer, keeping at most `buffer` records waiting to be written
+    pub fn with_buffer<W: AsyncWrite + Send + Unpin + 'static>(writer: W, buffer: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        tokio::spawn(write_lines(writer, receiver, dropped.clone()));
+        Self { sender, dropped }
+    }
+
+    /// Log to a file, appending to it if it exists
+    pub async fn to_file(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|e| {
+                helpers::config_error(&format!("Cannot open event log {}: {}", path.display(), e))
+            })?;
+        Ok(Self::new(file))
+    }
+
+    /// Queue a record for writing, dropping it if the writer is too far behind
+    pub fn log(&self, event: &MessageEvent) {
+        let line = match serde_json::to_string(event) {
+            Ok(mut line) => {
+                line.push('\n');
+                line
+            }
+            Err(_) => return,
+        };
+        if self.sender.try_send(Command::Write(line)).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Wait until the records logged so far are written and flushed
+    pub async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        if self.sender.send(Command::Flush(done)).await.is_ok() {
+            let _ = flushed.await;
+        }
+    }
+
+    /// Number of records dropped since the writer was too far behind or failed
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Record a received request, returning the record to complete once it is answered
+    pub(crate) fn request(
+        &self,
+        method: &str,
+        id: &JsonRpcId,
+        bytes: usize,
+        labels: &ConnectionLabels,
+    ) -> PendingRequest {
+        let received = MessageEvent {
+            timestamp_ms: now_ms(),
+            direction: Direction::Inbound,
+            method: method.to_string(),
+            id: Some(id.clone()),
+            code: None,
+            duration_ms: None,
+            bytes,
+            labels: labels.clone(),
+        };
+        self.log(&received);
+        PendingRequest {
+            log: self.clone(),
+            started: std::time::Instant::now(),
+            received,
+        }
+    }
+
+    /// Record an executed notification
+    pub(crate) fn notification(
+        &self,
+        method: &str,
+        code: Option<i32>,
+        elapsed: Duration,
+        bytes: usize,
+        labels: &ConnectionLabels,
+    ) {
+        self.log(&MessageEvent {
+            timestamp_ms: now_ms(),
+            direction: Direction::Inbound,
+            method: method.to_string(),
+            id: None,
+            code,
+            duration_ms: Some(millis(elapsed)),
+            bytes,
+            labels: labels.clone(),
+        });
+    }
+}
+
+/// Request logged as received, waiting for its response
+pub(crate) struct PendingRequest {
+    log: EventLog,
+    started: std::time::Instant,
+    received: MessageEvent,
+}
+
+impl PendingRequest {
+    /// Record the response
+    pub(crate) fn answered(self, code: Option<i32>, bytes: usize) {
+        self.log.log(&MessageEvent {
+            timestamp_ms: now_ms(),
+            direction: Direction::Outbound,
+            code,
+            duration_ms: Some(millis(self.started.elapsed())),
+            bytes,
+            ..self.received
+        });
+    }
+}
+
+fn millis(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64() * 1000.0
+}
+
+/// Write queued lines until every sender is dropped, flushing when idle
+async fn write_lines<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut receiver: mpsc::Receiver<Command>,
+    dropped: Arc<AtomicU64>,
+) {
+    let mut failed = false;
+    while let Some(command) = receiver.recv().await {
+        match command {
+            Command::Write(line) if !failed => {
+                failed = writer.write_all(line.as_bytes()).await.is_err();
+                if failed {
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                } else if receiver.is_empty() {
+                    failed = writer.flush().await.is_err();
+                }
+            }
+            // Lines queued after a write error are discarded
+            Command::Write(_) => {
+                dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Command::Flush(done) => {
+                if !failed {
+                    failed = writer.flush().await.is_err();
+                }
+                let _ = done.send(());
+            }
+        }
+    }
+    let _ = writer.shutdown().await;
+}
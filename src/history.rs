@@ -0,0 +1,366 @@
+//! Bounded history of recently processed messages
+//!
+//! Keeps the last N messages seen by a processor, with sensitive fields redacted
+//! and large payloads truncated (messages that are not JSON keep only their
+//! size and digest), so "what did the client actually send?" can be
+//! answered in production without a full capture. Records can be looked up by
+//! request id or exported as JSON lines, either as raw records or in the
+//! [capture format](crate::capture).
+
+use crate::capture::{CaptureRecord, CaptureWriter};
+use crate::context::{labels, ConnectionLabels};
+use crate::protocol::JsonRpcId;
+use mcp_error::Result as McpResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default number of records kept
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// Default maximum serialized size of a single stored payload
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 4096;
+
+/// Field names redacted by default (matched case-insensitively)
+pub const DEFAULT_REDACTED_FIELDS: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "authorization",
+    "api_key",
+    "apikey",
+];
+
+/// Replacement value for redacted fields
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// Direction of a recorded message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Received from the peer
+    Inbound,
+    /// Sent to the peer
+    Outbound,
+}
+
+/// A single recorded message
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageRecord {
+    /// Milliseconds since the Unix epoch when the message was recorded
+    pub timestamp_ms: u64,
+    /// Whether the message was received or sent
+    pub direction: Direction,
+    /// Request id, when the message carries one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<JsonRpcId>,
+    /// Method name, for requests and notifications
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Labels of the connection the message was exchanged on
+    #[serde(skip_serializing_if = "ConnectionLabels::is_empty")]
+    pub labels: ConnectionLabels,
+    /// Redacted (and possibly truncated) payload, or the size and digest of a
+    /// message that is not JSON
+    pub payload: Value,
+}
+
+#[derive(Clone)]
+struct HistoryConfig {
+    capacity: usize,
+    max_payload_bytes: usize,
+    redacted_fields: Vec<String>,
+}
+
+/// Shared ring buffer of recent messages
+///
+/// Cloning is cheap and all clones share the same buffer, so one clone can be
+/// handed to a processor while another is kept for inspection. Settings are
+/// those of the history when it was cloned.
+#[derive(Clone)]
+pub struct MessageHistory {
+    config: Arc<HistoryConfig>,
+    records: Arc<Mutex<VecDeque<MessageRecord>>>,
+}
+
+impl MessageHistory {
+    /// Create a history keeping at most `capacity` records
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            config: Arc::new(HistoryConfig {
+                capacity,
+                max_payload_bytes: DEFAULT_MAX_PAYLOAD_BYTES,
+                redacted_fields: DEFAULT_REDACTED_FIELDS
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect(),
+            }),
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Set the maximum serialized size of a stored payload
+    pub fn with_max_payload_bytes(mut self, max_payload_bytes: usize) -> Self {
+        Arc::make_mut(&mut self.config).max_payload_bytes = max_payload_bytes;
+        self
+    }
+
+    /// Replace the list of field names whose values are redacted
+    pub fn with_redacted_fields(mut self, fields: &[&str]) -> Self {
+        Arc::make_mut(&mut self.config).redacted_fields =
+            fields.iter().map(|f| f.to_lowercase()).collect();
+        self
+    }
+
+    /// Record a raw message
+    pub fn record(&self, direction: Direction, message: &str) {
+        self.record_labeled(direction, message, &ConnectionLabels::default());
+    }
+
+    /// Record a raw message exchanged on a labeled connection
+    pub fn record_labeled(&self, direction: Direction, message: &str, labels: &ConnectionLabels) {
+        self.record_with_secrets(direction, message, labels, &[]);
+    }
+
+    /// Record a raw message, also redacting the given secret fields
+    ///
+    /// A message that is not JSON cannot be redacted, so only its size and
+    /// digest are kept.
+    pub(crate) fn record_with_secrets(
+        &self,
+        direction: Direction,
+        message: &str,
+        labels: &ConnectionLabels,
+        secrets: &[String],
+    ) {
+        let config = &self.config;
+        if config.capacity == 0 {
+            return;
+        }
+
+        let (id, method, payload) = match serde_json::from_str::<Value>(message) {
+            Ok(mut value) => {
+                let id = value
+                    .get("id")
+                    .and_then(|id| serde_json::from_value(id.clone()).ok());
+                let method = value
+                    .get("method")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                redact(&mut value, &config.redacted_fields);
+                redact(&mut value, secrets);
+                (id, method, truncate(value, config.max_payload_bytes))
+            }
+            Err(_) => (None, None, unparsed(message)),
+        };
+
+        let record = MessageRecord {
+            timestamp_ms: now_ms(),
+            direction,
+            id,
+            method,
+            labels: labels.clone(),
+            payload,
+        };
+
+        let mut records = self.records.lock().unwrap();
+        if records.len() == config.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// Snapshot of the recorded messages, oldest first
+    pub fn recent(&self) -> Vec<MessageRecord> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Records carrying the given request id, oldest first
+    pub fn find_by_id(&self, id: &JsonRpcId) -> Vec<MessageRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|record| record.id.as_ref() == Some(id))
+            .cloned()
+            .collect()
+    }
+
+    /// Write all records as JSON lines
+    pub fn export<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for record in self.recent() {
+            serde_json::to_writer(&mut writer, &record)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()
+    }
+
+    /// Write all records in the capture format, oldest first
+    ///
+    /// The connection is taken from the `peer` label. Payloads are written as
+    /// recorded, so redacted and truncated payloads stay that way.
+    pub fn export_capture<W: Write>(&self, writer: W) -> McpResult<()> {
+        let mut capture = CaptureWriter::new(writer);
+        for record in self.recent() {
+            capture.write_record(&CaptureRecord {
+                timestamp_ms: record.timestamp_ms,
+                direction: record.direction,
+                connection: record.labels.get(labels::PEER).map(str::to_string),
+                message: record.payload,
+            })?;
+        }
+        capture.flush()
+    }
+
+    /// Drop all records
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear();
+    }
+}
+
+impl Default for MessageHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+/// Replace the values of sensitive fields, recursively
+fn redact(value: &mut Value, fields: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                if fields.iter().any(|f| f.eq_ignore_ascii_case(key)) {
+                    *child = Value::String(REDACTED.to_string());
+                } else {
+                    redact(child, fields);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, fields)),
+        _ => {}
+    }
+}
+
+/// Stand-in for a message that is not JSON, without any of its content
+///
+/// The digest tells repeated messages apart without keeping what they carry.
+fn unparsed(message: &str) -> Value {
+    let mut hasher = DefaultHasher::new();
+    message.hash(&mut hasher);
+    json!({
+        "unparsed": true,
+        "size": message.len(),
+        "digest": format!("{:016x}", hasher.finish()),
+    })
+}
+
+/// Replace payloads larger than `max_bytes` with a truncated preview
+fn truncate(payload: Value, max_bytes: usize) -> Value {
+    let serialized = match &payload {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if serialized.len() <= max_bytes {
+        return payload;
+    }
+
+    let mut end = max_bytes;
+    while !serialized.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let mut preview = Map::new();
+    preview.insert("truncated".to_string(), json!(true));
+    preview.insert("size".to_string(), json!(serialized.len()));
+    preview.insert("preview".to_string(), json!(&serialized[..end]));
+    Value::Object(preview)
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(history: &MessageHistory) -> Value {
+        history.recent().pop().unwrap().payload
+    }
+
+    #[test]
+    fn redacts_default_fields() {
+        let history = MessageHistory::new(4);
+        history.record(
+            Direction::Inbound,
+            r#"{"jsonrpc":"2.0","method":"login","params":{"user":"a","Password":"hunter2"},"id":1}"#,
+        );
+
+        let record = history.recent().pop().unwrap();
+        assert_eq!(record.method.as_deref(), Some("login"));
+        assert_eq!(record.id, Some(JsonRpcId::from(1)));
+        assert_eq!(record.payload["params"]["user"], "a");
+        assert_eq!(record.payload["params"]["Password"], REDACTED);
+    }
+
+    #[test]
+    fn redacts_secret_fields() {
+        let history = MessageHistory::new(4);
+        history.record_with_secrets(
+            Direction::Inbound,
+            r#"{"jsonrpc":"2.0","method":"connect","params":{"dsn":"postgres://u:p@db"},"id":1}"#,
+            &ConnectionLabels::default(),
+            &["dsn".to_string()],
+        );
+        assert_eq!(payload(&history)["params"]["dsn"], REDACTED);
+    }
+
+    #[test]
+    fn keeps_no_content_of_unparseable_messages() {
+        let history = MessageHistory::new(4);
+        let message = r#"{"jsonrpc":"2.0","params":{"token":"abc123"#;
+        history.record(Direction::Inbound, message);
+        history.record(Direction::Inbound, message);
+
+        let records = history.recent();
+        let payload = &records[0].payload;
+        assert!(!payload.to_string().contains("abc123"));
+        assert_eq!(payload["unparsed"], true);
+        assert_eq!(payload["size"], message.len());
+        assert_eq!(records[1].payload["digest"], payload["digest"]);
+        assert!(records[0].id.is_none());
+    }
+
+    #[test]
+    fn truncates_large_payloads() {
+        let history = MessageHistory::new(4).with_max_payload_bytes(16);
+        history.record(
+            Direction::Outbound,
+            r#"{"jsonrpc":"2.0","result":"a long enough result","id":1}"#,
+        );
+        let payload = payload(&history);
+        assert_eq!(payload["truncated"], true);
+        assert_eq!(payload["preview"].as_str().unwrap().len(), 16);
+    }
+
+    #[test]
+    fn drops_oldest_records() {
+        let history = MessageHistory::new(2);
+        for id in 1..=3 {
+            history.record(
+                Direction::Inbound,
+                &format!(r#"{{"jsonrpc":"2.0","method":"ping","id":{}}}"#, id),
+            );
+        }
+        let ids: Vec<_> = history.recent().into_iter().filter_map(|r| r.id).collect();
+        assert_eq!(ids, vec![JsonRpcId::from(2), JsonRpcId::from(3)]);
+    }
+}
@@ -48,14 +48,52 @@
 //! ```
 
 // Publicly expose the core JSON-RPC protocol structures
+pub mod affinity;
+pub mod auth;
+pub mod capture;
+pub mod client;
+pub mod context;
 pub mod conversion;
 pub mod error;
+pub mod event_log;
+pub mod history;
+pub mod lint;
+pub mod mcp;
 pub mod protocol;
+pub mod replay;
+pub mod rewrite;
+pub mod server;
+pub mod sizes;
+pub mod slo;
+pub mod typed;
+pub mod upload;
+pub mod visit;
+
+// Experimental arena allocation of serialized responses
+#[cfg(feature = "arena")]
+pub mod arena;
+
+// OpenTelemetry spans and metrics for processed messages
+#[cfg(feature = "otel")]
+pub mod otel;
+
+// Test utilities for downstream integration suites
+#[cfg(feature = "testing")]
+pub mod testing;
 
 // Re-export core types for convenience
 pub use protocol::{
-    JsonRpcBatch, JsonRpcError, JsonRpcId, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+    IdField, JsonRpcBatch, JsonRpcError, JsonRpcId, JsonRpcMessage, JsonRpcNotification,
+    JsonRpcRequest, JsonRpcResponse, MessageLimits, ValidationPolicy,
 };
+pub use client::{
+    BatchLimits, ClientMiddleware, ContractViolation, IdCorrelation, JsonRpcClient, ManagedClient,
+    McpClient, RetryPolicy,
+};
+pub use context::{ConnectionLabels, RequestContext, SessionStore};
+pub use mcp::ToolInfo;
+pub use server::{JsonRpcServer, JsonRpcServerBuilder};
+pub use typed::{Secret, TypedTool};
 
 // Re-export error types
 pub use mcp_error::{EphErrorExt, Error as McpError, OrExit, Result, Severity, Result as McpResult};
@@ -69,7 +107,10 @@ pub mod transport;
 
 // Re-export for backward compatibility (to be removed in future)
 #[doc(hidden)]
-pub use processor::{JsonRpcProcessor, Tool, ToolRegistry};
+pub use processor::{
+    BatchExecution, ExecutionMode, JsonRpcProcessor, PendingLimitBehavior, ProcessorConfig,
+    ProcessorHandle, ProcessorStats, ResponseOrdering, Tool, ToolRegistry,
+};
 #[doc(hidden)]
 pub use transport::base::{JsonRpcTransport, Transport};
 #[doc(hidden)]
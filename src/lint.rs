@@ -0,0 +1,282 @@
+//! Lint pass for outgoing messages
+//!
+//! Checks messages produced by this side of the connection against the JSON-RPC
+//! validation rules and common MCP conventions, so malformed server behavior is
+//! caught during development rather than by clients. Linting is off by default;
+//! enable it in debug builds or tests with [`LintMode::Log`] or [`LintMode::Panic`].
+
+use crate::context::ConnectionLabels;
+use crate::protocol::{JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+use serde_json::Value;
+
+/// Content block types defined by MCP
+const CONTENT_BLOCK_TYPES: &[&str] = &["text", "image", "audio", "resource", "resource_link"];
+
+/// What to do when an outgoing message has lint findings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LintMode {
+    /// Skip linting entirely
+    #[default]
+    Off,
+    /// Report findings on stderr
+    Log,
+    /// Panic on the first message with findings
+    Panic,
+}
+
+/// A single lint finding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// Short identifier of the violated rule
+    pub rule: &'static str,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl Lint {
+    fn new(rule: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            rule,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.rule, self.message)
+    }
+}
+
+/// Lint an outgoing request
+pub fn lint_request(request: &JsonRpcRequest) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    if let Err(e) = request.validate() {
+        lints.push(Lint::new("spec", e.to_string()));
+    }
+    if let Some(params) = &request.params {
+        lint_params(params, &mut lints);
+    }
+    lints
+}
+
+/// Lint an outgoing notification
+pub fn lint_notification(notification: &JsonRpcNotification) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    if let Err(e) = notification.validate() {
+        lints.push(Lint::new("spec", e.to_string()));
+    }
+    if let Some(params) = &notification.params {
+        lint_params(params, &mut lints);
+    }
+    lints
+}
+
+/// Lint an outgoing response
+pub fn lint_response(response: &JsonRpcResponse) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    if let Err(e) = response.validate() {
+        lints.push(Lint::new("spec", e.to_string()));
+    }
+    if let Some(result) = &response.result {
+        lint_meta(result, &mut lints);
+        lint_content(result, &mut lints);
+    }
+    lints
+}
+
+/// Params must be structured and carry a well-formed `_meta`
+fn lint_params(params: &Value, lints: &mut Vec<Lint>) {
+    if !params.is_object() && !params.is_array() {
+        lints.push(Lint::new(
+            "params-shape",
+            "params must be an object or an array",
+        ));
+    }
+    lint_meta(params, lints);
+}
+
+/// `_meta`, when present, must be an object
+fn lint_meta(value: &Value, lints: &mut Vec<Lint>) {
+    if let Some(meta) = value.get("_meta") {
+        if !meta.is_object() {
+            lints.push(Lint::new("meta-shape", "_meta must be an object"));
+        }
+    }
+}
+
+/// Tool results must carry an array of typed content blocks
+fn lint_content(result: &Value, lints: &mut Vec<Lint>) {
+    if let Some(is_error) = result.get("isError") {
+        if !is_error.is_boolean() {
+            lints.push(Lint::new("content-shape", "isError must be a boolean"));
+        }
+    }
+
+    let content = match result.get("content") {
+        Some(content) => content,
+        None => return,
+    };
+
+    let blocks = match content.as_array() {
+        Some(blocks) => blocks,
+        None => {
+            lints.push(Lint::new("content-shape", "content must be an array"));
+            return;
+        }
+    };
+
+    for (index, block) in blocks.iter().enumerate() {
+        match block.get("type").and_then(Value::as_str) {
+            Some(kind) if CONTENT_BLOCK_TYPES.contains(&kind) => {
+                if kind == "text" && !block.get("text").is_some_and(Value::is_string) {
+                    lints.push(Lint::new(
+                        "content-block",
+                        format!("content[{}]: text block must have a string 'text'", index),
+                    ));
+                }
+            }
+            Some(kind) => lints.push(Lint::new(
+                "content-block",
+                format!("content[{}]: unknown content block type '{}'", index, kind),
+            )),
+            None => lints.push(Lint::new(
+                "content-block",
+                format!("content[{}]: content block must have a string 'type'", index),
+            )),
+        }
+    }
+}
+
+/// Applies the lint pass to outgoing messages according to a [`LintMode`]
+#[derive(Debug, Clone, Default)]
+pub struct OutgoingLinter {
+    mode: LintMode,
+    labels: ConnectionLabels,
+}
+
+impl OutgoingLinter {
+    /// Create a linter with the given mode
+    pub fn new(mode: LintMode) -> Self {
+        Self {
+            mode,
+            labels: ConnectionLabels::default(),
+        }
+    }
+
+    /// Include the connection labels in reported findings
+    pub fn with_labels(mut self, labels: ConnectionLabels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Mode this linter runs in
+    pub fn mode(&self) -> LintMode {
+        self.mode
+    }
+
+    /// Lint an outgoing request
+    pub fn check_request(&self, request: &JsonRpcRequest) {
+        if self.mode != LintMode::Off {
+            self.report("request", &lint_request(request));
+        }
+    }
+
+    /// Lint an outgoing notification
+    pub fn check_notification(&self, notification: &JsonRpcNotification) {
+        if self.mode != LintMode::Off {
+            self.report("notification", &lint_notification(notification));
+        }
+    }
+
+    /// Lint an outgoing response
+    pub fn check_response(&self, response: &JsonRpcResponse) {
+        if self.mode != LintMode::Off {
+            self.report("response", &lint_response(response));
+        }
+    }
+
+    fn report(&self, kind: &str, lints: &[Lint]) {
+        if lints.is_empty() {
+            return;
+        }
+
+        let mut summary = lints
+            .iter()
+            .map(Lint::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        if !self.labels.is_empty() {
+            summary = format!("{} [{}]", summary, self.labels);
+        }
+
+        match self.mode {
+            LintMode::Off => {}
+            LintMode::Log => eprintln!("mcp-jsonrpc lint: outgoing {}: {}", kind, summary),
+            LintMode::Panic => panic!("mcp-jsonrpc lint: outgoing {}: {}", kind, summary),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::JsonRpcId;
+    use serde_json::json;
+
+    fn rules(lints: &[Lint]) -> Vec<&'static str> {
+        lints.iter().map(|lint| lint.rule).collect()
+    }
+
+    #[test]
+    fn off_by_default() {
+        assert_eq!(LintMode::default(), LintMode::Off);
+        assert_eq!(OutgoingLinter::default().mode(), LintMode::Off);
+    }
+
+    #[test]
+    fn well_formed_result_has_no_findings() {
+        let response = JsonRpcResponse::success(
+            JsonRpcId::from(1),
+            json!({"content": [{"type": "text", "text": "hi"}], "isError": false}),
+        );
+        assert!(lint_response(&response).is_empty());
+    }
+
+    #[test]
+    fn reports_content_findings() {
+        let response = JsonRpcResponse::success(
+            JsonRpcId::from(1),
+            json!({
+                "content": [{"type": "text"}, {"type": "video"}, {}],
+                "isError": "no",
+                "_meta": 1,
+            }),
+        );
+        assert_eq!(
+            rules(&lint_response(&response)),
+            vec![
+                "meta-shape",
+                "content-shape",
+                "content-block",
+                "content-block",
+                "content-block"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_scalar_params() {
+        let request = JsonRpcRequest::new("m", Some(json!(1)), JsonRpcId::from(1));
+        assert_eq!(rules(&lint_request(&request)), vec!["params-shape"]);
+        let notification = JsonRpcNotification::new("m", Some(json!({"_meta": []})));
+        assert_eq!(rules(&lint_notification(&notification)), vec!["meta-shape"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "mcp-jsonrpc lint: outgoing response")]
+    fn panic_mode_panics_on_findings() {
+        let response = JsonRpcResponse::success(JsonRpcId::from(1), json!({"content": 1}));
+        OutgoingLinter::new(LintMode::Panic).check_response(&response);
+    }
+}
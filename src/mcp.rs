@@ -0,0 +1,414 @@
+//! MCP-specific structures layered on top of JSON-RPC
+//!
+//! These types describe the standard MCP methods and payloads that the adapter
+//! answers or produces itself, as opposed to the tools registered by the embedder.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// Standard MCP method names
+pub mod methods {
+    /// Open a session and negotiate capabilities
+    pub const INITIALIZE: &str = "initialize";
+    /// Notification sent by the client once initialization is complete
+    pub const INITIALIZED: &str = "notifications/initialized";
+    /// Log message sent by the server (notification)
+    pub const LOGGING_MESSAGE: &str = "notifications/message";
+    /// Check that the peer is still responsive
+    pub const PING: &str = "ping";
+    /// List the tools exposed by the server
+    pub const TOOLS_LIST: &str = "tools/list";
+    /// Call a tool
+    pub const TOOLS_CALL: &str = "tools/call";
+    /// Notification sent by the server when the list of tools changed
+    pub const TOOLS_LIST_CHANGED: &str = "notifications/tools/list_changed";
+    /// List the resources exposed by the server
+    pub const RESOURCES_LIST: &str = "resources/list";
+    /// Read a resource by URI
+    pub const RESOURCES_READ: &str = "resources/read";
+    /// Ask to be notified when a resource changes
+    pub const RESOURCES_SUBSCRIBE: &str = "resources/subscribe";
+    /// Stop change notifications for a resource
+    pub const RESOURCES_UNSUBSCRIBE: &str = "resources/unsubscribe";
+    /// List the prompts exposed by the server
+    pub const PROMPTS_LIST: &str = "prompts/list";
+    /// Get a prompt by name
+    pub const PROMPTS_GET: &str = "prompts/get";
+    /// Set the minimum level of log messages sent by the server
+    pub const LOGGING_SET_LEVEL: &str = "logging/setLevel";
+}
+
+/// MCP protocol version requested by this crate
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Description of a registered tool as advertised by `tools/list`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolInfo {
+    /// Name the tool is registered under
+    pub name: String,
+
+    /// Human-readable description of the tool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// JSON Schema describing the tool's params
+    pub input_schema: Value,
+}
+
+/// Schema advertised for tools that do not describe their params
+pub fn default_input_schema() -> Value {
+    json!({ "type": "object" })
+}
+
+/// Name and version of an MCP client or server
+///
+/// [`implementation!`](crate::implementation) fills both in from the calling
+/// crate's manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Implementation {
+    /// Implementation name
+    pub name: String,
+    /// Implementation version
+    pub version: String,
+    /// Build metadata (non-standard)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildInfo>,
+}
+
+impl Implementation {
+    /// Create an implementation description
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            build: None,
+        }
+    }
+
+    /// Attach build metadata
+    pub fn with_build(mut self, build: BuildInfo) -> Self {
+        self.build = Some(build);
+        self
+    }
+}
+
+impl std::fmt::Display for Implementation {
+    /// Formats as a startup banner, e.g. `weather 1.2.0 (git 3f2a9c1, built 2026-01-05T10:00:00Z, features: cache)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.version)?;
+        let build = match &self.build {
+            Some(build) => build,
+            None => return Ok(()),
+        };
+
+        let mut details = Vec::new();
+        if let Some(git_hash) = &build.git_hash {
+            details.push(format!("git {}", git_hash));
+        }
+        if let Some(build_time) = &build.build_time {
+            details.push(format!("built {}", build_time));
+        }
+        if !build.features.is_empty() {
+            details.push(format!("features: {}", build.features.join(", ")));
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// How a binary was built
+///
+/// [`build_info!`](crate::build_info) captures it at compile time.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    /// Commit the binary was built from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_hash: Option<String>,
+    /// When the binary was built
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_time: Option<String>,
+    /// Cargo features enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+}
+
+impl BuildInfo {
+    /// Create empty build metadata
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the commit the binary was built from
+    pub fn with_git_hash(mut self, git_hash: impl Into<String>) -> Self {
+        self.git_hash = Some(git_hash.into());
+        self
+    }
+
+    /// Set when the binary was built
+    pub fn with_build_time(mut self, build_time: impl Into<String>) -> Self {
+        self.build_time = Some(build_time.into());
+        self
+    }
+
+    /// Set the enabled features
+    pub fn with_features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.features = features.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Features this crate was built with
+    pub fn adapter() -> Self {
+        Self::captured(
+            None,
+            None,
+            &[
+                ("schemars", cfg!(feature = "schemars")),
+                ("validator", cfg!(feature = "validator")),
+                ("arena", cfg!(feature = "arena")),
+                ("testing", cfg!(feature = "testing")),
+                ("msgpack", cfg!(feature = "msgpack")),
+            ],
+        )
+    }
+
+    #[doc(hidden)]
+    pub fn captured(
+        git_hash: Option<&str>,
+        build_time: Option<&str>,
+        features: &[(&str, bool)],
+    ) -> Self {
+        Self {
+            git_hash: git_hash.map(str::to_string),
+            build_time: build_time.map(str::to_string),
+            features: features
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(feature, _)| feature.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Name and version of the calling crate, from its manifest
+///
+/// ```rust,ignore
+/// let server = JsonRpcServer::builder(implementation!().with_build(build_info!()));
+/// ```
+#[macro_export]
+macro_rules! implementation {
+    () => {
+        $crate::mcp::Implementation::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+    };
+}
+
+/// Build metadata of the calling crate, captured at compile time
+///
+/// The commit and build time are read from the `MCP_GIT_HASH` and
+/// `MCP_BUILD_TIME` environment variables when the calling crate is compiled,
+/// typically set by its build script:
+///
+/// ```rust,ignore
+/// // build.rs
+/// let hash = std::process::Command::new("git").args(["rev-parse", "--short", "HEAD"]).output()?;
+/// println!("cargo:rustc-env=MCP_GIT_HASH={}", String::from_utf8(hash.stdout)?.trim());
+/// ```
+///
+/// Cargo does not tell a crate which of its features are enabled, so the
+/// features to report are listed and only the enabled ones are kept:
+///
+/// ```rust,ignore
+/// let build = build_info!(features = ["sqlite", "tls"]);
+/// ```
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::build_info!(features = [])
+    };
+    (features = [$($feature:literal),* $(,)?]) => {
+        $crate::mcp::BuildInfo::captured(
+            option_env!("MCP_GIT_HASH"),
+            option_env!("MCP_BUILD_TIME"),
+            &[$(($feature, cfg!(feature = $feature))),*],
+        )
+    };
+}
+
+/// Capabilities advertised by a server in its `initialize` result
+///
+/// Each capability is kept as raw JSON; its presence is what matters when
+/// deciding whether a method may be called.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    /// Tool support (`tools/list`, `tools/call`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Value>,
+    /// Resource support (`resources/read`, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Value>,
+    /// Prompt support (`prompts/get`, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<Value>,
+    /// Log message support (`logging/setLevel`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Value>,
+    /// Non-standard capabilities
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<Map<String, Value>>,
+}
+
+/// Result of `initialize`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeResult {
+    /// Protocol version chosen by the server
+    pub protocol_version: String,
+    /// Capabilities offered by the server
+    #[serde(default)]
+    pub capabilities: ServerCapabilities,
+    /// Server name and version
+    pub server_info: Implementation,
+    /// Usage hints for the client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+}
+
+/// One page of `tools/list` results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListToolsResult {
+    /// Tools on this page
+    pub tools: Vec<ToolInfo>,
+    /// Cursor for the next page, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Result of `tools/call`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    /// Content blocks produced by the tool
+    #[serde(default)]
+    pub content: Vec<Value>,
+    /// Structured output, for tools declaring an output schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    /// Whether the tool reported a failure
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+/// Description of a resource as advertised by `resources/list`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceInfo {
+    /// URI of the resource
+    pub uri: String,
+    /// Human-readable name
+    pub name: String,
+    /// Description of the resource
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// MIME type, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Contents of a resource
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    /// URI of the resource
+    pub uri: String,
+    /// MIME type, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Text contents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Base64-encoded binary contents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+/// Result of `resources/read`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadResourceResult {
+    /// Contents of the resource (several for composite resources)
+    pub contents: Vec<ResourceContents>,
+}
+
+/// Argument accepted by a prompt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptArgument {
+    /// Argument name
+    pub name: String,
+    /// Description of the argument
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Whether the argument must be provided
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Description of a prompt as advertised by `prompts/list`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptInfo {
+    /// Name the prompt is registered under
+    pub name: String,
+    /// Description of the prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Arguments accepted by the prompt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<PromptArgument>,
+}
+
+/// A message of a prompt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptMessage {
+    /// `user` or `assistant`
+    pub role: String,
+    /// Content block
+    pub content: Value,
+}
+
+/// Result of `prompts/get`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetPromptResult {
+    /// Description of the prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Messages making up the prompt
+    pub messages: Vec<PromptMessage>,
+}
+
+/// Severity of log messages, as used by `logging/setLevel`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoggingLevel {
+    /// Detailed debugging information
+    Debug,
+    /// General informational messages
+    Info,
+    /// Normal but significant events
+    Notice,
+    /// Warning conditions
+    Warning,
+    /// Error conditions
+    Error,
+    /// Critical conditions
+    Critical,
+    /// Action must be taken immediately
+    Alert,
+    /// System is unusable
+    Emergency,
+}
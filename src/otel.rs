@@ -0,0 +1,119 @@
+//! OpenTelemetry instrumentation
+//!
+//! [`OtelInstrumentation`], given to a processor with
+//! [`with_opentelemetry`](crate::processor::JsonRpcProcessor::with_opentelemetry),
+//! records a server span and a duration measurement for every request and
+//! notification it processes, through the global tracer and meter providers.
+//! Attributes follow the OpenTelemetry semantic conventions for JSON-RPC:
+//!
+//! | Attribute                  | Span | `rpc.server.duration` |
+//! |----------------------------|------|-----------------------|
+//! | `rpc.system` (`jsonrpc`)   | yes  | yes                   |
+//! | `rpc.method`               | yes  | yes                   |
+//! | `rpc.jsonrpc.version`      | yes  | yes                   |
+//! | `rpc.jsonrpc.request_id`   | yes  | no                    |
+//! | `rpc.jsonrpc.error_code`   | yes  | yes                   |
+//! | `rpc.jsonrpc.error_message`| yes  | no                    |
+//!
+//! Methods the processor does not serve are reported as `_OTHER`, so peers
+//! cannot inflate the cardinality of spans and metrics. The span is current
+//! while the method executes, so spans started by tools are nested under it,
+//! and it joins the trace of the context current when the processor runs.
+
+use crate::protocol::JsonRpcId;
+use opentelemetry::global::{self, BoxedTracer};
+use opentelemetry::metrics::Histogram;
+use opentelemetry::trace::{FutureExt, SpanKind, Status, TraceContextExt, Tracer};
+use opentelemetry::{Context, InstrumentationScope, KeyValue};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Name of the instrumentation scope of the spans and metrics
+pub const INSTRUMENTATION_NAME: &str = "mcp-jsonrpc";
+
+/// Method name reported for methods the processor does not serve
+pub const OTHER_METHOD: &str = "_OTHER";
+
+/// OpenTelemetry spans and metrics for processed messages
+///
+/// Clones share the same tracer and instruments.
+#[derive(Clone)]
+pub struct OtelInstrumentation {
+    tracer: Arc<BoxedTracer>,
+    duration: Histogram<f64>,
+}
+
+impl Default for OtelInstrumentation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OtelInstrumentation {
+    /// Instrument with the global tracer and meter providers
+    ///
+    /// Set the providers first: instruments created before are not exported.
+    pub fn new() -> Self {
+        let scope = InstrumentationScope::builder(INSTRUMENTATION_NAME)
+            .with_version(env!("CARGO_PKG_VERSION"))
+            .build();
+        let duration = global::meter_with_scope(scope.clone())
+            .f64_histogram("rpc.server.duration")
+            .with_unit("ms")
+            .with_description("Duration of JSON-RPC calls handled by the server")
+            .build();
+        Self {
+            tracer: Arc::new(global::tracer_with_scope(scope)),
+            duration,
+        }
+    }
+
+    /// Run a call within its span, recording its duration
+    ///
+    /// `error_of` extracts the JSON-RPC error code and message of a failed call.
+    pub(crate) async fn instrument<F, E>(
+        &self,
+        method: &str,
+        id: Option<&JsonRpcId>,
+        call: F,
+        error_of: E,
+    ) -> F::Output
+    where
+        F: Future,
+        E: FnOnce(&F::Output) -> Option<(i32, String)>,
+    {
+        let mut attributes = vec![
+            KeyValue::new("rpc.system", "jsonrpc"),
+            KeyValue::new("rpc.method", method.to_string()),
+            KeyValue::new("rpc.jsonrpc.version", "2.0"),
+        ];
+        let mut span_attributes = attributes.clone();
+        if let Some(id) = id {
+            span_attributes.push(KeyValue::new("rpc.jsonrpc.request_id", id.to_string()));
+        }
+        let span = self
+            .tracer
+            .span_builder(method.to_string())
+            .with_kind(SpanKind::Server)
+            .with_attributes(span_attributes)
+            .start(self.tracer.as_ref());
+        let cx = Context::current_with_span(span);
+
+        let started = Instant::now();
+        let output = call.with_context(cx.clone()).await;
+        let elapsed = started.elapsed();
+
+        let span = cx.span();
+        if let Some((code, message)) = error_of(&output) {
+            span.set_attribute(KeyValue::new("rpc.jsonrpc.error_code", code as i64));
+            span.set_attribute(KeyValue::new("rpc.jsonrpc.error_message", message.clone()));
+            span.set_status(Status::error(message));
+            attributes.push(KeyValue::new("rpc.jsonrpc.error_code", code as i64));
+        }
+        span.end();
+        self.duration
+            .record(elapsed.as_secs_f64() * 1000.0, &attributes);
+        output
+    }
+}
@@ -0,0 +1,120 @@
+//! Request coalescing
+//!
+//! Chatty clients often ask the same question several times in a row. With
+//! [`Coalescing`], a call to a [read-only](super::Tool::read_only) tool
+//! arriving within the window of an identical call (same method and params)
+//! is not executed again: it gets the result of the first call, waiting for
+//! it if needed, and is answered with its own id. Unlike single flight (see
+//! [`concurrency`](super::concurrency)), the result is reused after the first
+//! call completes, until the window has passed since it arrived.
+//!
+//! Failures are only shared with the calls waiting for them; the next
+//! identical call executes again. Clones share the cached results, so share
+//! one instance between connections only when results do not depend on the
+//! caller.
+
+use super::concurrency::{copy_error, Shared};
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Default coalescing window
+pub const DEFAULT_COALESCING_WINDOW: Duration = Duration::from_millis(500);
+
+struct Entry {
+    arrived: Instant,
+    outcome: Arc<OnceCell<Shared>>,
+}
+
+/// Shared cache of recent read-only calls
+#[derive(Clone)]
+pub struct Coalescing {
+    window: Duration,
+    entries: Arc<Mutex<HashMap<(String, String), Entry>>>,
+}
+
+impl Default for Coalescing {
+    fn default() -> Self {
+        Self::new(DEFAULT_COALESCING_WINDOW)
+    }
+}
+
+impl Coalescing {
+    /// Reuse results for identical calls arriving within `window` of each other
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Window within which identical calls share a result
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Number of calls whose result can currently be reused
+    pub fn len(&self) -> usize {
+        let now = Instant::now();
+        self.lock()
+            .values()
+            .filter(|entry| now.duration_since(entry.arrived) < self.window)
+            .count()
+    }
+
+    /// Whether no result can currently be reused
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Execute a call unless an identical one arrived within the window
+    pub(crate) async fn run<F, Fut>(
+        &self,
+        method: &str,
+        params: Value,
+        execute: F,
+    ) -> McpResult<Value>
+    where
+        F: FnOnce(Value) -> Fut,
+        Fut: Future<Output = McpResult<Value>>,
+    {
+        // serde_json sorts object members, so equal params give equal keys
+        let key = (method.to_string(), params.to_string());
+        let outcome = {
+            let mut entries = self.lock();
+            let now = Instant::now();
+            entries.retain(|_, entry| now.duration_since(entry.arrived) < self.window);
+            entries
+                .entry(key.clone())
+                .or_insert_with(|| Entry {
+                    arrived: now,
+                    outcome: Arc::default(),
+                })
+                .outcome
+                .clone()
+        };
+
+        let shared = outcome
+            .get_or_init(|| async { execute(params).await.map_err(Arc::new) })
+            .await
+            .clone();
+        if shared.is_err() {
+            let mut entries = self.lock();
+            if entries
+                .get(&key)
+                .is_some_and(|entry| Arc::ptr_eq(&entry.outcome, &outcome))
+            {
+                entries.remove(&key);
+            }
+        }
+        shared.map_err(|e| copy_error(&e))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,201 @@
+//! Per-method concurrency
+//!
+//! [`ConcurrencyLimits`] caps how many calls of a method execute at once, e.g.
+//! a single `index/rebuild` at a time. Clones share the same slots, so one
+//! instance given to every processor limits the method across connections.
+//! Calls over the limit either wait for a slot or fail right away with an
+//! overloaded error (-32001), as selected by [`WhenBusy`].
+//!
+//! With single flight, a call identical to one already executing (same method
+//! and params) is not executed again: it waits for the running call and gets
+//! a copy of its result. Only requests are limited; notifications execute
+//! right away.
+
+use crate::error::{error_details, helpers};
+use mcp_error::{Error as McpError, Result as McpResult};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OnceCell, Semaphore};
+
+/// What a call does when its method is at its concurrency limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhenBusy {
+    /// Wait for a running call to finish
+    #[default]
+    Queue,
+    /// Fail right away with an overloaded error
+    Reject,
+}
+
+/// Concurrency settings of a method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MethodConcurrency {
+    /// Maximum number of calls executing at once, unlimited if `None`
+    pub max_concurrent: Option<usize>,
+    /// What calls over the limit do
+    pub when_busy: WhenBusy,
+    /// Whether identical concurrent calls share one execution
+    pub single_flight: bool,
+}
+
+impl MethodConcurrency {
+    /// Allow at most `max` calls at once, queuing the others
+    pub fn limited(max: usize) -> Self {
+        Self {
+            max_concurrent: Some(max.max(1)),
+            ..Self::default()
+        }
+    }
+
+    /// Share one execution between identical concurrent calls, without limit
+    pub fn single_flight() -> Self {
+        Self {
+            single_flight: true,
+            ..Self::default()
+        }
+    }
+
+    /// Reject calls over the limit instead of queuing them
+    pub fn rejecting_when_busy(mut self) -> Self {
+        self.when_busy = WhenBusy::Reject;
+        self
+    }
+
+    /// Also share one execution between identical concurrent calls
+    pub fn with_single_flight(mut self) -> Self {
+        self.single_flight = true;
+        self
+    }
+}
+
+/// Outcome of an execution, shared with the identical calls waiting on it
+pub(super) type Shared = Result<Value, Arc<McpError>>;
+
+struct MethodState {
+    settings: MethodConcurrency,
+    slots: Option<Semaphore>,
+    /// Executions in progress, by params
+    flights: Mutex<HashMap<String, Arc<OnceCell<Shared>>>>,
+}
+
+impl MethodState {
+    fn new(settings: MethodConcurrency) -> Self {
+        Self {
+            settings,
+            slots: settings.max_concurrent.map(Semaphore::new),
+            flights: Mutex::default(),
+        }
+    }
+
+    /// Run a call in a slot, waiting for one or failing as configured
+    async fn execute<F, Fut>(&self, method: &str, params: Value, execute: F) -> McpResult<Value>
+    where
+        F: FnOnce(Value) -> Fut,
+        Fut: Future<Output = McpResult<Value>>,
+    {
+        let slots = match &self.slots {
+            Some(slots) => slots,
+            None => return execute(params).await,
+        };
+        // The semaphore is never closed, so only `try_acquire` can fail
+        let _slot = match self.settings.when_busy {
+            WhenBusy::Queue => slots.acquire().await.ok(),
+            WhenBusy::Reject => Some(slots.try_acquire().map_err(|_| {
+                helpers::overloaded(&format!(
+                    "Method '{}' is busy: {} call(s) already executing",
+                    method,
+                    self.settings.max_concurrent.unwrap_or_default()
+                ))
+            })?),
+        };
+        execute(params).await
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<OnceCell<Shared>>>> {
+        self.flights.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Shared per-method concurrency limits
+#[derive(Clone, Default)]
+pub struct ConcurrencyLimits {
+    methods: Arc<HashMap<String, Arc<MethodState>>>,
+}
+
+impl ConcurrencyLimits {
+    /// Create limits leaving every method unrestricted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict a method with the given settings
+    pub fn with_method(mut self, method: &str, settings: MethodConcurrency) -> Self {
+        Arc::make_mut(&mut self.methods)
+            .insert(method.to_string(), Arc::new(MethodState::new(settings)));
+        self
+    }
+
+    /// Settings of a method, if it is restricted
+    pub fn settings(&self, method: &str) -> Option<MethodConcurrency> {
+        self.methods.get(method).map(|state| state.settings)
+    }
+
+    /// Execute a call of a method within its limits
+    pub(crate) async fn run<F, Fut>(
+        &self,
+        method: &str,
+        params: Value,
+        execute: F,
+    ) -> McpResult<Value>
+    where
+        F: FnOnce(Value) -> Fut,
+        Fut: Future<Output = McpResult<Value>>,
+    {
+        let state = match self.methods.get(method) {
+            Some(state) => state,
+            None => return execute(params).await,
+        };
+        if !state.settings.single_flight {
+            return state.execute(method, params, execute).await;
+        }
+
+        // serde_json sorts object members, so equal params give equal keys
+        let key = params.to_string();
+        let flight = state.lock().entry(key.clone()).or_default().clone();
+        // Callers wait for the execution in progress; should it be cancelled,
+        // one of them executes instead
+        let shared = flight
+            .get_or_init(|| async {
+                state
+                    .execute(method, params, execute)
+                    .await
+                    .map_err(Arc::new)
+            })
+            .await
+            .clone();
+
+        let mut flights = state.lock();
+        if flights
+            .get(&key)
+            .is_some_and(|current| Arc::ptr_eq(current, &flight))
+        {
+            flights.remove(&key);
+        }
+        drop(flights);
+        shared.map_err(|e| copy_error(&e))
+    }
+}
+
+/// Copy of a shared error, keeping its reference, severity and details
+pub(super) fn copy_error(err: &McpError) -> McpError {
+    let text = err.to_string();
+    let prefix = format!("[{}] ", err.reference);
+    let message = text.strip_prefix(&prefix).unwrap_or(&text);
+    let copy = McpError::new(err.severity, err.reference.clone(), message);
+    match error_details(err) {
+        Some(details) => helpers::with_details(copy, details.clone()),
+        None => copy,
+    }
+}
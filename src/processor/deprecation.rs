@@ -0,0 +1,179 @@
+//! Method deprecation
+//!
+//! Methods marked deprecated with [`ToolRegistry::deprecate`](super::ToolRegistry::deprecate)
+//! keep being served, but every successful object result carries a warning in
+//! its `_meta`:
+//!
+//! ```json
+//! {"_meta":{"deprecation":{"method":"old/method","replacement":"new/method","sunset":"2026-01-01T00:00:00Z","message":"Method 'old/method' is deprecated; use 'new/method' instead"}}}
+//! ```
+//!
+//! Calls are counted per client (the [`PEER`](crate::context::labels::PEER)
+//! label of the connection) so server authors can see who still has to
+//! migrate. A deprecation can also retire the method at its sunset date, after
+//! which calls fail with "method not found" and the replacement in the error
+//! details.
+
+use super::shaping::rfc3339;
+use crate::error::{domain_reference_codes, helpers};
+use mcp_error::{Error as McpError, Severity};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Client name used when the connection has no peer label
+pub const UNKNOWN_CLIENT: &str = "unknown";
+
+/// Deprecation of a method
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deprecation {
+    /// Method to call instead
+    pub replacement: Option<String>,
+    /// When the method is planned to be removed
+    pub sunset: Option<SystemTime>,
+    /// Whether calls are rejected once the sunset date has passed
+    pub reject_after_sunset: bool,
+}
+
+impl Deprecation {
+    /// Deprecate without replacement or sunset date
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Point callers at the method to use instead
+    pub fn with_replacement(mut self, method: impl Into<String>) -> Self {
+        self.replacement = Some(method.into());
+        self
+    }
+
+    /// Announce when the method will be removed
+    pub fn with_sunset(mut self, sunset: SystemTime) -> Self {
+        self.sunset = Some(sunset);
+        self
+    }
+
+    /// Reject calls once the sunset date has passed
+    pub fn rejecting_after_sunset(mut self) -> Self {
+        self.reject_after_sunset = true;
+        self
+    }
+
+    /// Whether calls made at the given time are rejected
+    pub fn is_retired(&self, now: SystemTime) -> bool {
+        self.reject_after_sunset && self.sunset.is_some_and(|sunset| now >= sunset)
+    }
+
+    fn sunset_rfc3339(&self) -> Option<String> {
+        self.sunset.map(|sunset| {
+            let seconds = sunset
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64);
+            rfc3339(seconds)
+        })
+    }
+
+    fn hint(&self) -> String {
+        match &self.replacement {
+            Some(replacement) => format!("; use '{}' instead", replacement),
+            None => String::new(),
+        }
+    }
+
+    /// Warning attached to the results of the method
+    pub fn warning(&self, method: &str) -> Value {
+        let mut warning = json!({
+            "method": method,
+            "message": format!("Method '{}' is deprecated{}", method, self.hint()),
+        });
+        if let Some(replacement) = &self.replacement {
+            warning["replacement"] = json!(replacement);
+        }
+        if let Some(sunset) = self.sunset_rfc3339() {
+            warning["sunset"] = json!(sunset);
+        }
+        warning
+    }
+
+    /// Error returned for calls made after the method was retired
+    pub fn retired_error(&self, method: &str) -> McpError {
+        let sunset = self.sunset_rfc3339().unwrap_or_default();
+        let err = McpError::new(
+            Severity::Error,
+            domain_reference_codes::TOOL_NOT_FOUND,
+            format!(
+                "Method '{}' was retired on {}{}",
+                method,
+                sunset,
+                self.hint()
+            ),
+        );
+        helpers::with_details(
+            err,
+            json!({ "replacement": self.replacement, "sunset": sunset }),
+        )
+    }
+}
+
+/// Calls made to a deprecated method
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeprecationUsage {
+    /// Deprecated method
+    pub method: String,
+    /// Calls in total
+    pub calls: u64,
+    /// Calls per client
+    pub by_client: BTreeMap<String, u64>,
+}
+
+/// Deprecation registered for a method, with the calls counted so far
+///
+/// Clones share the counts, so registries cloned for each connection report
+/// the usage of all of them.
+#[derive(Clone)]
+pub(crate) struct DeprecatedMethod {
+    pub(crate) deprecation: Deprecation,
+    usage: Arc<Mutex<BTreeMap<String, u64>>>,
+}
+
+impl DeprecatedMethod {
+    pub(crate) fn new(deprecation: Deprecation) -> Self {
+        Self {
+            deprecation,
+            usage: Arc::default(),
+        }
+    }
+
+    /// Count a call from the given client
+    pub(crate) fn record_call(&self, client: &str) {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        *usage.entry(client.to_string()).or_default() += 1;
+    }
+
+    pub(crate) fn usage(&self, method: &str) -> DeprecationUsage {
+        let by_client = self.usage.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        DeprecationUsage {
+            method: method.to_string(),
+            calls: by_client.values().sum(),
+            by_client,
+        }
+    }
+
+    /// Attach the deprecation warning to an object result
+    ///
+    /// Other results are returned unchanged since they have no `_meta`.
+    pub(crate) fn annotate(&self, method: &str, mut result: Value) -> Value {
+        if let Value::Object(object) = &mut result {
+            let meta = object
+                .entry("_meta")
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(meta) = meta {
+                meta.insert("deprecation".to_string(), self.deprecation.warning(method));
+            }
+        }
+        result
+    }
+}
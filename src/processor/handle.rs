@@ -0,0 +1,284 @@
+//! Runtime controls for a spawned processor
+//!
+//! [`JsonRpcProcessor::spawn`](super::JsonRpcProcessor::spawn) runs the
+//! processor in its own task and returns a [`ProcessorHandle`], through which an
+//! admin surface can pause and resume reading, read statistics, set the
+//! connection's log level, push notifications to the peer and close the
+//! connection.
+
+use crate::error::helpers;
+use crate::mcp::{methods, LoggingLevel};
+use crate::protocol::{JsonRpcNotification, ValidationPolicy};
+use crate::transport::{BandwidthMeter, BandwidthStats};
+use mcp_error::Result as McpResult;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Message counters of a processor
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    received: AtomicU64,
+    sent: AtomicU64,
+    injected: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn received(&self) {
+        self.received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn sent(&self) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn injected(&self) {
+        self.injected.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Snapshot of a processor's activity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessorStats {
+    /// Messages read from the peer
+    pub messages_received: u64,
+    /// Responses written to the peer (a batch counts once)
+    pub responses_sent: u64,
+    /// Notifications written through [`ProcessorHandle::inject_notification`]
+    pub notifications_injected: u64,
+    /// Whether reading is paused
+    pub paused: bool,
+    /// Traffic of the connection, if a meter was attached with
+    /// [`with_bandwidth_meter`](super::JsonRpcProcessor::with_bandwidth_meter)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<BandwidthStats>,
+}
+
+/// Control channels held by the running processor
+pub(crate) struct Controls {
+    paused: Option<watch::Receiver<bool>>,
+    outbox: Option<mpsc::UnboundedReceiver<Control>>,
+}
+
+impl Controls {
+    /// Controls of a processor run without a handle
+    pub(crate) fn detached() -> Self {
+        Self {
+            paused: None,
+            outbox: None,
+        }
+    }
+
+    /// Whether reading from the peer is paused
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.as_ref().is_some_and(|paused| *paused.borrow())
+    }
+
+    /// Wait for the next command from the handle
+    ///
+    /// Never completes once the handle is dropped; dropping the handle also
+    /// resumes reading for good.
+    pub(crate) async fn next(&mut self) -> Control {
+        tokio::select! {
+            changed = changed(&mut self.paused) => {
+                if !changed {
+                    self.paused = None;
+                }
+                Control::PauseChanged
+            }
+            control = recv(&mut self.outbox) => match control {
+                Some(control) => control,
+                None => {
+                    self.outbox = None;
+                    Control::PauseChanged
+                }
+            },
+        }
+    }
+}
+
+/// Command received by a running processor
+pub(crate) enum Control {
+    /// Reading was paused or resumed
+    PauseChanged,
+    /// Send this notification to the peer
+    Inject(String),
+    /// Stop reading and end the run once pending messages are answered
+    Close,
+}
+
+/// Wait for the pause flag to change; false once the sender is gone
+async fn changed(paused: &mut Option<watch::Receiver<bool>>) -> bool {
+    match paused {
+        Some(paused) => paused.changed().await.is_ok(),
+        None => std::future::pending().await,
+    }
+}
+
+/// Wait for the next command sent through the outbox; None once the sender is gone
+async fn recv(outbox: &mut Option<mpsc::UnboundedReceiver<Control>>) -> Option<Control> {
+    match outbox {
+        Some(outbox) => outbox.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Handle to a processor running in its own task
+pub struct ProcessorHandle {
+    paused: watch::Sender<bool>,
+    outbox: mpsc::UnboundedSender<Control>,
+    counters: Arc<Counters>,
+    bandwidth: Option<BandwidthMeter>,
+    log_level: Arc<Mutex<Option<LoggingLevel>>>,
+    validation: ValidationPolicy,
+    task: JoinHandle<McpResult<()>>,
+}
+
+impl ProcessorHandle {
+    /// Spawn the given run future with a fresh set of controls
+    pub(crate) fn spawn<F, Fut>(
+        counters: Arc<Counters>,
+        bandwidth: Option<BandwidthMeter>,
+        validation: ValidationPolicy,
+        run: F,
+    ) -> Self
+    where
+        F: FnOnce(Controls) -> Fut,
+        Fut: std::future::Future<Output = McpResult<()>> + Send + 'static,
+    {
+        let (paused, paused_rx) = watch::channel(false);
+        let (outbox, outbox_rx) = mpsc::unbounded_channel();
+        let controls = Controls {
+            paused: Some(paused_rx),
+            outbox: Some(outbox_rx),
+        };
+
+        Self {
+            paused,
+            outbox,
+            counters,
+            bandwidth,
+            log_level: Arc::new(Mutex::new(None)),
+            validation,
+            task: tokio::spawn(run(controls)),
+        }
+    }
+
+    /// Stop reading new messages; messages already read are still answered
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    /// Resume reading messages
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    /// Whether reading is paused
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Current statistics
+    pub fn stats(&self) -> ProcessorStats {
+        ProcessorStats {
+            messages_received: self.counters.received.load(Ordering::Relaxed),
+            responses_sent: self.counters.sent.load(Ordering::Relaxed),
+            notifications_injected: self.counters.injected.load(Ordering::Relaxed),
+            paused: self.is_paused(),
+            bandwidth: self.bandwidth.as_ref().map(BandwidthMeter::stats),
+        }
+    }
+
+    /// Set the minimum level of log messages injected on this connection
+    pub fn set_log_level(&self, level: LoggingLevel) {
+        *self.log_level.lock().unwrap_or_else(|e| e.into_inner()) = Some(level);
+    }
+
+    /// Minimum level of log messages injected on this connection, if set
+    pub fn log_level(&self) -> Option<LoggingLevel> {
+        *self.log_level.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Send a notification to the peer
+    ///
+    /// Log messages (`notifications/message`) below the level set with
+    /// [`set_log_level`](Self::set_log_level) are dropped. Notifications are
+    /// checked as configured by `ProcessorConfig::send_validation`. Fails once
+    /// the processor has stopped.
+    pub fn inject_notification(&self, notification: JsonRpcNotification) -> McpResult<()> {
+        self.validation.check_notification(&notification)?;
+        if self.filtered_out(&notification) {
+            return Ok(());
+        }
+
+        let message = serde_json::to_string(&notification).map_err(helpers::json_error)?;
+        self.command(Control::Inject(message))
+    }
+
+    /// Close the connection gracefully
+    ///
+    /// The processor stops reading, answers the messages already read, then
+    /// closes the transport; [`join`](Self::join) returns once it is done.
+    pub fn close(&self) -> McpResult<()> {
+        self.command(Control::Close)
+    }
+
+    /// Close the connection gracefully, aborting the processor if it has not
+    /// finished within `timeout`
+    ///
+    /// Returns whether the processor finished in time.
+    pub async fn shutdown(mut self, timeout: Duration) -> bool {
+        // Fails only when the processor already stopped
+        let _ = self.close();
+        if tokio::time::timeout(timeout, &mut self.task).await.is_ok() {
+            return true;
+        }
+        self.task.abort();
+        false
+    }
+
+    /// Whether the processor task has finished
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stop the processor without waiting for in-flight messages
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+
+    /// Wait for the processor to finish and return the outcome of its run
+    pub async fn join(self) -> McpResult<()> {
+        self.task
+            .await
+            .map_err(|e| helpers::internal_error(&format!("Processor task failed: {}", e)))?
+    }
+
+    fn command(&self, control: Control) -> McpResult<()> {
+        self.outbox
+            .send(control)
+            .map_err(|_| helpers::transport_error("Processor is not running"))
+    }
+
+    /// Whether a log message notification is below the connection's log level
+    fn filtered_out(&self, notification: &JsonRpcNotification) -> bool {
+        if notification.method != methods::LOGGING_MESSAGE {
+            return false;
+        }
+        let minimum = match self.log_level() {
+            Some(level) => level,
+            None => return false,
+        };
+        notification
+            .params
+            .as_ref()
+            .and_then(|params| params.get("level"))
+            .and_then(|level| serde_json::from_value::<LoggingLevel>(level.clone()).ok())
+            .is_some_and(|level| level < minimum)
+    }
+}
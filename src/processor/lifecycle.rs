@@ -0,0 +1,65 @@
+//! Tool lifecycle
+//!
+//! Tools holding expensive resources (database pools, model handles) acquire
+//! them in [`Tool::initialize`] rather than in their first call. The registry
+//! runs it once per tool, either eagerly with
+//! [`ToolRegistry::initialize_all`](super::ToolRegistry::initialize_all)
+//! before connections are served, or lazily right before the first call.
+//! On shutdown, [`Tool::shutdown`] releases them again, in reverse
+//! registration order so tools registered later, which may depend on earlier
+//! ones, go first:
+//!
+//! ```rust,ignore
+//! let server = JsonRpcServer::builder(implementation!())
+//!     .with_tool("search", SearchTool::new(config))
+//!     .build()?;
+//! server.warm_up().await?; // omit to initialize on first call
+//! // ... serve connections until asked to stop, then close them
+//! server.shutdown(Duration::from_secs(5)).await?;
+//! ```
+//!
+//! Concurrent first calls share a single initialization. A failed
+//! initialization fails the call that triggered it; the next call, including
+//! one already waiting, tries again.
+
+use super::Tool;
+use mcp_error::Result as McpResult;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Initialization state of the tools of a registry
+///
+/// Clones share the state, so registries cloned for each connection
+/// initialize each tool once.
+#[derive(Clone, Default)]
+pub(crate) struct Initialized {
+    cells: Arc<Mutex<HashMap<String, Arc<OnceCell<()>>>>>,
+}
+
+impl Initialized {
+    fn cell(&self, name: &str) -> Arc<OnceCell<()>> {
+        self.lock().entry(name.to_string()).or_default().clone()
+    }
+
+    /// Initialize the tool unless done already, waiting for any initialization in progress
+    pub(crate) async fn ensure(&self, name: &str, tool: &Arc<dyn Tool>) -> McpResult<()> {
+        self.cell(name)
+            .get_or_try_init(|| tool.initialize())
+            .await
+            .map(|_| ())
+    }
+
+    pub(crate) fn is_initialized(&self, name: &str) -> bool {
+        self.lock().get(name).is_some_and(|cell| cell.initialized())
+    }
+
+    /// Forget the state of a tool, e.g. once it is replaced
+    pub(crate) fn reset(&self, name: &str) {
+        self.lock().remove(name);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<OnceCell<()>>>> {
+        self.cells.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,1942 @@
+use crate::affinity::Affinity;
+use crate::auth::BearerAuthenticator;
+use crate::context::{labels, ConnectionLabels, RequestContext, SessionStore};
+use crate::conversion::{
+    domain_to_json_rpc_response_with_context, json_rpc_to_domain_request, DomainRequest,
+    DomainResponse,
+};
+use crate::error::{
+    domain_reference_codes, error_codes, error_to_json_rpc_with_context, helpers, DefaultErrorData,
+    ErrorContext, ErrorDataFormatter, ErrorPhase,
+};
+use crate::event_log::EventLog;
+use crate::history::{Direction, MessageHistory};
+use crate::lint::{LintMode, OutgoingLinter};
+use crate::mcp::{default_input_schema, methods, ToolInfo};
+use crate::protocol::{
+    IdField, JsonRpcError, JsonRpcId, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcResponse, MessageLimits, ValidationPolicy,
+};
+use crate::sizes::PayloadSizes;
+use crate::slo::SloTracker;
+use crate::transport::{
+    BandwidthMeter, BatchStreamWriter, BoxedTransport, SplitTransport, Transport, TransportSender,
+};
+use crate::typed::secret::secret_fields;
+use crate::upload::Uploads;
+use async_trait::async_trait;
+use mcp_error::{Error as McpError, Result as McpResult, Severity};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::task::JoinSet;
+
+#[cfg(feature = "arena")]
+use crate::arena::MessageArena;
+#[cfg(feature = "otel")]
+use crate::otel::{OtelInstrumentation, OTHER_METHOD};
+#[cfg(feature = "schemars")]
+use crate::typed::TypedTool;
+
+pub mod coalescing;
+pub mod concurrency;
+pub mod deprecation;
+pub mod handle;
+pub mod lifecycle;
+pub mod ordering;
+pub mod service;
+pub mod shadow;
+pub mod shaping;
+pub mod slow_lane;
+#[cfg(test)]
+mod tests;
+
+pub use coalescing::Coalescing;
+pub use concurrency::{ConcurrencyLimits, MethodConcurrency, WhenBusy};
+use deprecation::{DeprecatedMethod, UNKNOWN_CLIENT};
+pub use deprecation::{Deprecation, DeprecationUsage};
+use handle::{Control, Controls, Counters};
+pub use handle::{ProcessorHandle, ProcessorStats};
+use lifecycle::Initialized;
+pub use ordering::ResponseOrdering;
+use ordering::{Lanes, Reorder};
+pub use service::{MethodService, ServiceFuture};
+pub use shadow::ShadowValidation;
+pub use shaping::ResultTransformer;
+pub use slow_lane::SlowLane;
+
+/// Tool trait representing a service that can be invoked by name
+/// In a real implementation, this would be imported from mcp-core
+#[async_trait]
+pub trait Tool: Send + Sync {
+    async fn execute(&self, params: Value) -> McpResult<Value>;
+
+    /// Execute with the context of the request being served
+    ///
+    /// Defaults to [`execute`](Self::execute); override to use the context.
+    async fn execute_with_context(
+        &self,
+        params: Value,
+        _context: &RequestContext,
+    ) -> McpResult<Value> {
+        self.execute(params).await
+    }
+
+    /// Whether the tool can be called with [`RequestContext::dry_run`] set
+    ///
+    /// Tools opting in must not have side effects during a dry run; they are
+    /// exercised by [`JsonRpcServer::self_test`](crate::server::JsonRpcServer::self_test).
+    fn supports_dry_run(&self) -> bool {
+        false
+    }
+
+    /// Human-readable description advertised in `tools/list`
+    fn description(&self) -> Option<String> {
+        None
+    }
+
+    /// JSON Schema for the params, advertised in `tools/list`
+    fn input_schema(&self) -> Option<Value> {
+        None
+    }
+
+    /// Whether calls have no side effects, so identical calls may share a result
+    ///
+    /// See [`coalescing`].
+    fn read_only(&self) -> bool {
+        false
+    }
+
+    /// Acquire the resources the tool needs before its first call
+    ///
+    /// Run once by the registry; see [`lifecycle`] for when.
+    async fn initialize(&self) -> McpResult<()> {
+        Ok(())
+    }
+
+    /// Flush state and release resources when the server shuts down
+    ///
+    /// See [`ToolRegistry::shutdown_all`].
+    async fn shutdown(&self) -> McpResult<()> {
+        Ok(())
+    }
+}
+
+/// Registry for storing and retrieving tools
+/// In a real implementation, this would be imported from mcp-core
+#[derive(Clone)]
+pub struct ToolRegistry {
+    tools: Arc<HashMap<String, Arc<dyn Tool>>>,
+    /// Tool names in registration order
+    order: Arc<Vec<String>>,
+    deprecations: Arc<HashMap<String, DeprecatedMethod>>,
+    initialized: Initialized,
+}
+
+impl ToolRegistry {
+    /// Create a new empty tool registry
+    pub fn new() -> Self {
+        Self {
+            tools: Arc::new(HashMap::new()),
+            order: Arc::new(Vec::new()),
+            deprecations: Arc::new(HashMap::new()),
+            initialized: Initialized::default(),
+        }
+    }
+
+    /// Create a new registry builder for fluent tool registration
+    pub fn builder() -> ToolRegistryBuilder {
+        ToolRegistryBuilder::new()
+    }
+
+    /// Get a tool by name
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Tool>> {
+        self.tools.get(name)
+    }
+
+    /// Register a new tool with the given name
+    pub fn insert<T: Tool + 'static>(&mut self, name: &str, tool: T) {
+        let tools = Arc::make_mut(&mut self.tools);
+        tools.insert(name.to_string(), Arc::new(tool));
+        register(Arc::make_mut(&mut self.order), name);
+        self.initialized.reset(name);
+    }
+
+    /// Initialize a tool unless done already
+    ///
+    /// Called before the first call of each tool; concurrent callers wait for
+    /// the same initialization.
+    pub async fn initialize(&self, name: &str) -> McpResult<()> {
+        let tool = self.tools.get(name).ok_or_else(|| {
+            McpError::new(
+                Severity::Error,
+                domain_reference_codes::TOOL_NOT_FOUND,
+                format!("Tool '{}' not found", name),
+            )
+        })?;
+        self.initialized.ensure(name, tool).await
+    }
+
+    /// Initialize every tool not initialized yet, in registration order
+    ///
+    /// Stops at the first failure; tools left uninitialized are initialized
+    /// on their first call.
+    pub async fn initialize_all(&self) -> McpResult<()> {
+        for name in self.order.iter() {
+            self.initialize(name).await.map_err(|e| {
+                let msg = format!("Failed to initialize tool '{}': {}", name, e);
+                helpers::domain_error(e, &msg)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Shut every tool down, in reverse registration order
+    ///
+    /// Each tool gets `timeout` to finish. Tools are shut down one after the
+    /// other, even when an earlier one fails or times out; failures are
+    /// reported together once all tools are done. A tool called afterwards is
+    /// initialized again.
+    pub async fn shutdown_all(&self, timeout: Duration) -> McpResult<()> {
+        let mut failures = Vec::new();
+        for name in self.order.iter().rev() {
+            let tool = match self.tools.get(name) {
+                Some(tool) => tool,
+                None => continue,
+            };
+            match tokio::time::timeout(timeout, tool.shutdown()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => failures.push(format!("{}: {}", name, e)),
+                Err(_) => failures.push(format!("{}: timed out after {:?}", name, timeout)),
+            }
+            self.initialized.reset(name);
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(helpers::internal_error(&format!(
+                "Shutdown failed for {} tool(s): {}",
+                failures.len(),
+                failures.join("; ")
+            )))
+        }
+    }
+
+    /// Whether a tool has been initialized
+    pub fn is_initialized(&self, name: &str) -> bool {
+        self.initialized.is_initialized(name)
+    }
+
+    /// Keep serving a method while warning its callers that it is deprecated
+    ///
+    /// See [`deprecation`] for the warning and the per-client usage counts;
+    /// use [`deprecate_with`](Self::deprecate_with) to retire the method at its
+    /// sunset date.
+    pub fn deprecate(
+        &mut self,
+        method: &str,
+        replacement: Option<&str>,
+        sunset: Option<SystemTime>,
+    ) {
+        self.deprecate_with(
+            method,
+            Deprecation {
+                replacement: replacement.map(str::to_string),
+                sunset,
+                reject_after_sunset: false,
+            },
+        );
+    }
+
+    /// Deprecate a method with the given settings
+    pub fn deprecate_with(&mut self, method: &str, deprecation: Deprecation) {
+        let deprecations = Arc::make_mut(&mut self.deprecations);
+        deprecations.insert(method.to_string(), DeprecatedMethod::new(deprecation));
+    }
+
+    /// Deprecation of a method, if it is deprecated
+    pub fn deprecation(&self, method: &str) -> Option<&Deprecation> {
+        self.deprecations
+            .get(method)
+            .map(|deprecated| &deprecated.deprecation)
+    }
+
+    /// Calls made to the deprecated methods, sorted by method
+    pub fn deprecation_usage(&self) -> Vec<DeprecationUsage> {
+        let mut usage: Vec<DeprecationUsage> = self
+            .deprecations
+            .iter()
+            .map(|(method, deprecated)| deprecated.usage(method))
+            .collect();
+        usage.sort_by(|a, b| a.method.cmp(&b.method));
+        usage
+    }
+
+    /// Number of registered tools
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    /// Whether no tool is registered
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Register a typed handler whose input schema is derived from `P`
+    #[cfg(feature = "schemars")]
+    pub fn register_typed<P, F, Fut>(&mut self, name: &str, handler: F)
+    where
+        P: serde::de::DeserializeOwned + schemars::JsonSchema + Send + 'static,
+        F: Fn(P) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = McpResult<Value>> + Send + 'static,
+    {
+        self.insert(name, TypedTool::with_derived_schema(handler));
+    }
+
+    /// Names of the params fields declared secret in the tools' input schemas
+    ///
+    /// See [`Secret`](crate::typed::Secret).
+    pub fn secret_fields(&self) -> BTreeSet<String> {
+        self.tools
+            .values()
+            .filter_map(|tool| tool.input_schema())
+            .flat_map(|schema| secret_fields(&schema))
+            .collect()
+    }
+
+    /// Describe all registered tools, sorted by name
+    pub fn list(&self) -> Vec<ToolInfo> {
+        let mut tools: Vec<ToolInfo> = self
+            .tools
+            .iter()
+            .map(|(name, tool)| ToolInfo {
+                name: name.clone(),
+                description: tool.description(),
+                input_schema: tool.input_schema().unwrap_or_else(default_input_schema),
+            })
+            .collect();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+    }
+}
+
+/// Move a tool name to the end of the registration order
+fn register(order: &mut Vec<String>, name: &str) {
+    order.retain(|registered| registered != name);
+    order.push(name.to_string());
+}
+
+/// Builder for creating ToolRegistry instances
+pub struct ToolRegistryBuilder {
+    tools: HashMap<String, Arc<dyn Tool>>,
+    order: Vec<String>,
+    deprecations: HashMap<String, DeprecatedMethod>,
+}
+
+impl ToolRegistryBuilder {
+    /// Create a new empty builder
+    pub fn new() -> Self {
+        Self {
+            tools: HashMap::new(),
+            order: Vec::new(),
+            deprecations: HashMap::new(),
+        }
+    }
+
+    /// Register a tool with the given name
+    pub fn with_tool<T: Tool + 'static>(mut self, name: &str, tool: T) -> Self {
+        self.tools.insert(name.to_string(), Arc::new(tool));
+        register(&mut self.order, name);
+        self
+    }
+
+    /// Register a typed handler whose input schema is derived from `P`
+    #[cfg(feature = "schemars")]
+    pub fn with_typed_tool<P, F, Fut>(self, name: &str, handler: F) -> Self
+    where
+        P: serde::de::DeserializeOwned + schemars::JsonSchema + Send + 'static,
+        F: Fn(P) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = McpResult<Value>> + Send + 'static,
+    {
+        self.with_tool(name, TypedTool::with_derived_schema(handler))
+    }
+
+    /// Deprecate a method; see [`ToolRegistry::deprecate`]
+    pub fn with_deprecation(mut self, method: &str, deprecation: Deprecation) -> Self {
+        self.deprecations
+            .insert(method.to_string(), DeprecatedMethod::new(deprecation));
+        self
+    }
+
+    /// Build the final ToolRegistry
+    pub fn build(self) -> ToolRegistry {
+        ToolRegistry {
+            tools: Arc::new(self.tools),
+            order: Arc::new(self.order),
+            deprecations: Arc::new(self.deprecations),
+            initialized: Initialized::default(),
+        }
+    }
+}
+
+/// Simple domain response implementation
+struct SimpleDomainResponse {
+    id: String,
+    result: McpResult<Value>,
+}
+
+impl DomainResponse for SimpleDomainResponse {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn result(&self) -> &McpResult<Value> {
+        &self.result
+    }
+}
+
+/// How a processor executes the messages received on its connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionMode {
+    /// Handle one message at a time; responses are written in request order
+    #[default]
+    Sequential,
+    /// Keep reading while earlier messages execute; responses are written in
+    /// the order selected by [`ProcessorConfig::ordering`]
+    Pipelined,
+}
+
+/// What a pipelined processor does once the pending-message limit is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PendingLimitBehavior {
+    /// Stop reading from the peer until pending responses have been written,
+    /// letting transport backpressure (e.g. TCP flow control) slow the peer down
+    #[default]
+    PauseReading,
+    /// Keep reading, rejecting new requests with an overloaded error (-32001)
+    Reject,
+}
+
+/// How the requests of a batch are executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchExecution {
+    /// Execute every element on its own; one failing does not affect the others
+    #[default]
+    Independent,
+    /// Check every element first: validation, method resolution and retired
+    /// methods. If any element fails, none is executed; failed elements get
+    /// their own error and the others a batch-aborted error (-32004), so
+    /// clients can rely on all-or-nothing dispatch for setup sequences
+    ///
+    /// Errors raised by tools while executing are not rolled back.
+    Atomic,
+}
+
+/// What a processor does with application-defined error codes returned by
+/// methods, i.e. codes outside the range reserved by JSON-RPC
+///
+/// Methods return such codes with
+/// [`helpers::coded_error`](crate::error::helpers::coded_error). Codes of the
+/// reserved range are never affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorCodePolicy {
+    /// Send the code as is
+    #[default]
+    PassThrough,
+    /// Send a generic server error (-32000) instead, keeping the message and
+    /// data, whose details hold the original code
+    Clamp,
+    /// Send an internal error (-32603) instead, for servers whose clients
+    /// only expect the codes of the specification
+    Reject,
+}
+
+/// Default maximum number of pending messages per connection
+pub const DEFAULT_MAX_PENDING: usize = 64;
+
+/// Processor configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessorConfig {
+    /// How messages are executed
+    pub execution: ExecutionMode,
+    /// Maximum number of messages read but not yet answered
+    ///
+    /// Only relevant in pipelined mode; a sequential processor never has more
+    /// than one pending message.
+    pub max_pending: usize,
+    /// What to do once `max_pending` is reached
+    pub on_pending_limit: PendingLimitBehavior,
+    /// Order in which responses are written
+    ///
+    /// Only relevant in pipelined mode; see [`ordering`] for the guarantees.
+    pub ordering: ResponseOrdering,
+    /// Limits on method names and ids of incoming messages
+    pub limits: MessageLimits,
+    /// How the requests of a batch are executed
+    pub batches: BatchExecution,
+    /// Checks applied to notifications injected through a [`ProcessorHandle`]
+    pub send_validation: ValidationPolicy,
+    /// What to do with application-defined error codes returned by methods
+    pub error_codes: ErrorCodePolicy,
+}
+
+impl Default for ProcessorConfig {
+    fn default() -> Self {
+        Self {
+            execution: ExecutionMode::default(),
+            max_pending: DEFAULT_MAX_PENDING,
+            on_pending_limit: PendingLimitBehavior::default(),
+            ordering: ResponseOrdering::default(),
+            limits: MessageLimits::default(),
+            batches: BatchExecution::default(),
+            send_validation: ValidationPolicy::default(),
+            error_codes: ErrorCodePolicy::default(),
+        }
+    }
+}
+
+/// Response produced for an incoming message
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Outgoing {
+    Response(JsonRpcResponse),
+    Batch(Vec<JsonRpcResponse>),
+}
+
+/// A received object, or element of a batch, classified by its `id` member
+enum Incoming {
+    Request(JsonRpcRequest),
+    Notification(JsonRpcNotification),
+    /// Not a valid request, answered with the given id (null unless it was valid)
+    Invalid(JsonRpcId, McpError),
+}
+
+impl Incoming {
+    fn classify(value: Value) -> Self {
+        let id = match &value {
+            Value::Object(object) => IdField::of(object),
+            _ => {
+                return Incoming::Invalid(
+                    JsonRpcId::Null,
+                    helpers::protocol_error("Invalid Request: expected an object"),
+                )
+            }
+        };
+        let invalid =
+            |e: serde_json::Error| helpers::protocol_error(&format!("Invalid Request: {}", e));
+        match id {
+            IdField::Invalid(id) => Incoming::Invalid(
+                JsonRpcId::Null,
+                helpers::protocol_error(&format!(
+                    "Invalid Request: id must be a string, an integer or null, not {}",
+                    id
+                )),
+            ),
+            IdField::Absent => match serde_json::from_value(value) {
+                Ok(notification) => Incoming::Notification(notification),
+                Err(e) => Incoming::Invalid(JsonRpcId::Null, invalid(e)),
+            },
+            id => match serde_json::from_value(value) {
+                Ok(request) => Incoming::Request(request),
+                Err(e) => Incoming::Invalid(id.response_id(), invalid(e)),
+            },
+        }
+    }
+}
+
+/// Elements of a batch, if the message is a non-empty array
+fn parse_batch(message: &str) -> Option<Vec<Incoming>> {
+    match serde_json::from_str::<Value>(message) {
+        Ok(Value::Array(elements)) if !elements.is_empty() => {
+            Some(elements.into_iter().map(Incoming::classify).collect())
+        }
+        _ => None,
+    }
+}
+
+/// Everything needed to handle a message, independent of the transport
+#[derive(Clone)]
+struct Dispatcher {
+    tool_registry: ToolRegistry,
+    methods: Arc<HashMap<String, Arc<dyn Tool>>>,
+    linter: OutgoingLinter,
+    labels: ConnectionLabels,
+    session: SessionStore,
+    history: Option<MessageHistory>,
+    slo: Option<SloTracker>,
+    concurrency: Option<ConcurrencyLimits>,
+    slow_lane: Option<SlowLane>,
+    coalescing: Option<Coalescing>,
+    error_data: Arc<dyn ErrorDataFormatter>,
+    limits: MessageLimits,
+    batches: BatchExecution,
+    error_codes: ErrorCodePolicy,
+    transformers: Arc<HashMap<String, Vec<Arc<dyn ResultTransformer>>>>,
+    shadow: Option<ShadowValidation>,
+    secrets: Arc<Vec<String>>,
+    sizes: Option<PayloadSizes>,
+    event_log: Option<EventLog>,
+    authenticator: Option<BearerAuthenticator>,
+    affinity: Option<Affinity>,
+    /// Set when a message upgraded the session; see [`crate::auth`]
+    list_changed: Arc<AtomicBool>,
+    #[cfg(feature = "otel")]
+    otel: Option<OtelInstrumentation>,
+}
+
+/// JSON-RPC processor
+#[derive(Clone)]
+pub struct JsonRpcProcessor<T: Transport> {
+    transport: T,
+    dispatcher: Dispatcher,
+    config: ProcessorConfig,
+    counters: Arc<Counters>,
+    bandwidth: Option<BandwidthMeter>,
+    #[cfg(feature = "arena")]
+    arena: Option<MessageArena>,
+}
+
+impl JsonRpcProcessor<BoxedTransport> {
+    /// Create a processor over a type-erased transport
+    ///
+    /// Processors created this way share one type whatever the transport, so
+    /// connections of different kinds can be kept in one collection.
+    pub fn new_boxed<T: Transport + 'static>(transport: T, tool_registry: ToolRegistry) -> Self {
+        Self::new(transport.boxed(), tool_registry)
+    }
+}
+
+impl JsonRpcProcessor<SplitTransport> {
+    /// Create a processor over a transport split with [`Transport::into_split`]
+    ///
+    /// The processor receives and answers as usual, while clones of its
+    /// [`sender`](Self::sender) write to the peer concurrently, even while
+    /// the processor waits for the next message.
+    pub fn new_split<T: Transport + 'static>(transport: T, tool_registry: ToolRegistry) -> Self {
+        let (sender, receiver) = transport.into_split();
+        Self::new(SplitTransport::new(sender, receiver), tool_registry)
+    }
+
+    /// Sender writing to the peer alongside the processor, e.g. for
+    /// server-initiated notifications
+    ///
+    /// Messages sent through it are not recorded in the history nor counted in
+    /// the statistics of the processor.
+    pub fn sender(&self) -> TransportSender {
+        self.transport.sender().clone()
+    }
+}
+
+impl<T: Transport> JsonRpcProcessor<T> {
+    /// Create a new JSON-RPC processor with the given transport and tool registry
+    pub fn new(transport: T, tool_registry: ToolRegistry) -> Self {
+        let secrets = Arc::new(tool_registry.secret_fields().into_iter().collect());
+        Self {
+            transport,
+            dispatcher: Dispatcher {
+                tool_registry,
+                methods: Arc::new(HashMap::new()),
+                linter: OutgoingLinter::default(),
+                labels: ConnectionLabels::default(),
+                session: SessionStore::default(),
+                history: None,
+                slo: None,
+                concurrency: None,
+                slow_lane: None,
+                coalescing: None,
+                error_data: Arc::new(DefaultErrorData::new()),
+                limits: MessageLimits::default(),
+                batches: BatchExecution::default(),
+                error_codes: ErrorCodePolicy::default(),
+                transformers: Arc::new(HashMap::new()),
+                shadow: None,
+                secrets,
+                sizes: None,
+                event_log: None,
+                authenticator: None,
+                affinity: None,
+                list_changed: Arc::new(AtomicBool::new(false)),
+                #[cfg(feature = "otel")]
+                otel: None,
+            },
+            config: ProcessorConfig::default(),
+            counters: Arc::default(),
+            bandwidth: None,
+            #[cfg(feature = "arena")]
+            arena: None,
+        }
+    }
+
+    /// Serialize responses into a per-connection arena reset after each message
+    ///
+    /// Experimental, and for [`ExecutionMode::Sequential`] only: a pipelined
+    /// processor with an arena fails to run with a configuration error. See
+    /// [`crate::arena`].
+    #[cfg(feature = "arena")]
+    pub fn with_arena(mut self, capacity: usize) -> Self {
+        self.arena = Some(MessageArena::with_capacity(capacity));
+        self
+    }
+
+    /// Set the execution mode, pending-message limits and message limits
+    pub fn with_config(mut self, config: ProcessorConfig) -> Self {
+        self.dispatcher.limits = config.limits;
+        self.dispatcher.batches = config.batches;
+        self.dispatcher.error_codes = config.error_codes;
+        self.config = config;
+        self
+    }
+
+    /// Handle a protocol method outside the tool registry
+    ///
+    /// Unlike tools, such methods are not advertised in `tools/list`. A tool
+    /// registered under the same name takes precedence.
+    pub fn with_method<H: Tool + 'static>(mut self, method: &str, handler: H) -> Self {
+        Arc::make_mut(&mut self.dispatcher.methods).insert(method.to_string(), Arc::new(handler));
+        self
+    }
+
+    /// Report the traffic counted by a meter in the handle's statistics
+    ///
+    /// The meter is taken from the
+    /// [`BandwidthTransport`](crate::transport::BandwidthTransport) the
+    /// processor runs on.
+    pub fn with_bandwidth_meter(mut self, meter: BandwidthMeter) -> Self {
+        self.bandwidth = Some(meter);
+        self
+    }
+
+    /// Record processed messages (redacted and bounded) in the given history
+    pub fn with_history(mut self, history: MessageHistory) -> Self {
+        self.dispatcher.history = Some(history);
+        self
+    }
+
+    /// Set how outgoing responses are linted (off by default)
+    pub fn with_lint_mode(mut self, mode: LintMode) -> Self {
+        self.dispatcher.linter =
+            OutgoingLinter::new(mode).with_labels(self.dispatcher.labels.clone());
+        self
+    }
+
+    /// Tag the connection; labels appear in request contexts, history records
+    /// and lint reports for every message on it
+    pub fn with_labels(mut self, labels: ConnectionLabels) -> Self {
+        self.dispatcher.linter = self.dispatcher.linter.with_labels(labels.clone());
+        self.dispatcher.labels = labels;
+        self
+    }
+
+    /// Labels of the connection
+    pub fn labels(&self) -> &ConnectionLabels {
+        &self.dispatcher.labels
+    }
+
+    /// Session store handed to the tools called on this connection
+    pub fn session(&self) -> &SessionStore {
+        &self.dispatcher.session
+    }
+
+    /// Customize the `error.data` member of error responses
+    pub fn with_error_data_formatter<F: ErrorDataFormatter + 'static>(
+        mut self,
+        formatter: F,
+    ) -> Self {
+        self.dispatcher.error_data = Arc::new(formatter);
+        self
+    }
+
+    /// Record OpenTelemetry spans and metrics for processed messages; see [`crate::otel`]
+    #[cfg(feature = "otel")]
+    pub fn with_opentelemetry(mut self, otel: OtelInstrumentation) -> Self {
+        self.dispatcher.otel = Some(otel);
+        self
+    }
+
+    /// Track error budgets per method, optionally shedding load when exhausted
+    pub fn with_slo(mut self, slo: SloTracker) -> Self {
+        self.dispatcher.slo = Some(slo);
+        self
+    }
+
+    /// Limit the concurrent calls of methods; see [`concurrency`]
+    ///
+    /// Share one instance between processors to limit calls across connections.
+    pub fn with_concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.dispatcher.concurrency = Some(limits);
+        self
+    }
+
+    /// Execute heavy messages on a bounded pool of workers; see [`slow_lane`]
+    pub fn with_slow_lane(mut self, slow_lane: SlowLane) -> Self {
+        self.dispatcher.slow_lane = Some(slow_lane);
+        self
+    }
+
+    /// Share results between identical calls to read-only tools; see [`coalescing`]
+    pub fn with_coalescing(mut self, coalescing: Coalescing) -> Self {
+        self.dispatcher.coalescing = Some(coalescing);
+        self
+    }
+
+    /// Shape the results of a method before they are sent
+    ///
+    /// Several transformers registered for the same method run in registration
+    /// order. See [`shaping`] for the built-in transformers.
+    pub fn with_result_transformer<R: ResultTransformer + 'static>(
+        mut self,
+        method: &str,
+        transformer: R,
+    ) -> Self {
+        Arc::make_mut(&mut self.dispatcher.transformers)
+            .entry(method.to_string())
+            .or_default()
+            .push(Arc::new(transformer));
+        self
+    }
+
+    /// Evaluate a stricter validation policy on incoming messages without enforcing it
+    pub fn with_shadow_validation(mut self, shadow: ShadowValidation) -> Self {
+        self.dispatcher.shadow = Some(shadow);
+        self
+    }
+
+    /// Log every processed message as a JSON line; see [`crate::event_log`]
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.dispatcher.event_log = Some(event_log);
+        self
+    }
+
+    /// Require a bearer token in the `_meta` of incoming messages; see [`crate::auth`]
+    pub fn with_authenticator(mut self, authenticator: BearerAuthenticator) -> Self {
+        self.dispatcher.authenticator = Some(authenticator);
+        self
+    }
+
+    /// Issue affinity keys at `initialize` and redirect sessions owned by
+    /// other processes; see [`crate::affinity`]
+    pub fn with_affinity(mut self, affinity: Affinity) -> Self {
+        self.dispatcher.affinity = Some(affinity);
+        self
+    }
+
+    /// Accept chunked uploads; see [`crate::upload`]
+    pub fn with_uploads(mut self, uploads: Uploads) -> Self {
+        let methods = Arc::make_mut(&mut self.dispatcher.methods);
+        for (method, handler) in uploads.methods() {
+            methods.insert(method.to_string(), handler);
+        }
+        self
+    }
+
+    /// Track request and response sizes per method
+    pub fn with_payload_sizes(mut self, sizes: PayloadSizes) -> Self {
+        self.dispatcher.sizes = Some(sizes);
+        self
+    }
+
+    /// Expose the method router as a service function; see [`service`]
+    pub fn service(&self) -> MethodService {
+        MethodService::new(self.dispatcher.clone(), self.counters.clone())
+    }
+
+    /// The transport, e.g. to poll a [`PollingTransport`](crate::transport::PollingTransport)
+    /// from a custom event loop instead of calling [`run`](Self::run)
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Answer a message read outside [`run`](Self::run)
+    ///
+    /// The message is recorded, counted and dispatched like one read by the
+    /// processor; the serialized response, if any, is returned for the caller
+    /// to send.
+    pub async fn handle_message(&self, message: &str) -> McpResult<Option<String>> {
+        self.dispatcher.record(Direction::Inbound, message);
+        self.counters.received();
+        let response = self.dispatcher.handle_message(message).await?;
+        if let Some(response) = &response {
+            self.dispatcher.record(Direction::Outbound, response);
+            self.counters.sent();
+        }
+        Ok(response)
+    }
+
+    /// Record a message in the history and send it
+    async fn send_response(&mut self, response: &str) -> McpResult<()> {
+        self.dispatcher.record(Direction::Outbound, response);
+        self.counters.sent();
+        self.transport
+            .send(response)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to send response: {}", e)))?;
+        self.announce_list_changed().await
+    }
+
+    /// Answer a message the transport skipped for its size with a parse error
+    async fn reject_oversized(&mut self, err: &McpError) -> McpResult<()> {
+        let context = ErrorContext::new(ErrorPhase::Parse);
+        let response = self.dispatcher.error_response(JsonRpcId::Null, err, context);
+        let response = serde_json::to_string(&response).map_err(helpers::json_error)?;
+        self.send_response(&response).await
+    }
+
+    /// Tell the peer to list the tools again once the session was upgraded
+    async fn announce_list_changed(&mut self) -> McpResult<()> {
+        if !self.dispatcher.list_changed.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let notification = JsonRpcNotification::new(methods::TOOLS_LIST_CHANGED, None);
+        let message = serde_json::to_string(&notification).map_err(helpers::json_error)?;
+        self.dispatcher.record(Direction::Outbound, &message);
+        self.transport
+            .send(&message)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to send notification: {}", e)))
+    }
+
+    /// Send a notification injected through the handle
+    async fn send_injected(&mut self, notification: &str) -> McpResult<()> {
+        self.dispatcher.record(Direction::Outbound, notification);
+        self.counters.injected();
+        self.transport
+            .send(notification)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to send notification: {}", e)))
+    }
+
+    /// Serialize and send the response to a message
+    async fn send_outgoing(&mut self, outgoing: &Outgoing) -> McpResult<()> {
+        #[cfg(feature = "arena")]
+        if let Some(arena) = &mut self.arena {
+            let response = arena.to_json(outgoing)?;
+            self.dispatcher.record(Direction::Outbound, response);
+            self.counters.sent();
+            let sent = self.transport.send(response).await;
+            arena.reset();
+            sent.map_err(|e| helpers::transport_error(&format!("Failed to send response: {}", e)))?;
+            return self.announce_list_changed().await;
+        }
+
+        let response = serde_json::to_string(outgoing).map_err(helpers::json_error)?;
+        self.send_response(&response).await
+    }
+
+    /// Process a batch, writing each response as soon as it is ready
+    ///
+    /// A failed write leaves the peer with a truncated array, so it aborts the run.
+    async fn stream_batch(&mut self, elements: Vec<Incoming>) -> McpResult<()> {
+        if let Some(responses) = self.dispatcher.reject_atomic_batch(&elements) {
+            for response in &responses {
+                self.dispatcher.linter.check_response(response);
+            }
+            if responses.is_empty() {
+                return self.announce_list_changed().await;
+            }
+            return self.send_outgoing(&Outgoing::Batch(responses)).await;
+        }
+
+        let mut writer = BatchStreamWriter::new();
+        for element in elements {
+            let response = match self.dispatcher.process_element(element).await {
+                Some(response) => response,
+                None => continue,
+            };
+            self.dispatcher.linter.check_response(&response);
+
+            let element = serde_json::to_string(&response).map_err(helpers::json_error)?;
+            self.dispatcher.record(Direction::Outbound, &element);
+
+            writer
+                .write_element(&mut self.transport, &element)
+                .await
+                .map_err(|e| {
+                    helpers::transport_error(&format!("Failed to send response: {}", e))
+                })?;
+        }
+
+        if writer.written() > 0 {
+            self.counters.sent();
+        }
+        writer
+            .finish(&mut self.transport)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to send response: {}", e)))?;
+        self.announce_list_changed().await
+    }
+
+    /// Run the processor in a loop, handling incoming messages
+    ///
+    /// Once the input ends, the transport is closed so responses already written
+    /// are flushed and the peer sees the end of the stream.
+    pub async fn run(&mut self) -> McpResult<()> {
+        self.run_with(&mut Controls::detached()).await
+    }
+
+    /// Run the processor in its own task, returning a handle to control it
+    pub fn spawn(self) -> ProcessorHandle
+    where
+        T: 'static,
+    {
+        let counters = self.counters.clone();
+        let bandwidth = self.bandwidth.clone();
+        let validation = self.config.send_validation;
+        ProcessorHandle::spawn(
+            counters,
+            bandwidth,
+            validation,
+            move |mut controls| async move {
+                let mut processor = self;
+                processor.run_with(&mut controls).await
+            },
+        )
+    }
+
+    async fn run_with(&mut self, controls: &mut Controls) -> McpResult<()> {
+        #[cfg(feature = "arena")]
+        if self.arena.is_some() && self.config.execution == ExecutionMode::Pipelined {
+            return Err(helpers::config_error(
+                "The response arena is only supported in sequential mode",
+            ));
+        }
+
+        let result = match self.config.execution {
+            ExecutionMode::Sequential => self.run_sequential(controls).await,
+            ExecutionMode::Pipelined => self.run_pipelined(controls).await,
+        };
+
+        // The outcome of the run takes precedence over a failure to close
+        let closed = self.transport.close().await;
+        result.and(closed)
+    }
+
+    async fn run_sequential(&mut self, controls: &mut Controls) -> McpResult<()> {
+        loop {
+            let paused = controls.is_paused();
+            let message = tokio::select! {
+                received = self.transport.receive(), if !paused => match received {
+                    Ok(msg) => msg,
+                    Err(e) if crate::error::is_message_too_large(&e) => {
+                        self.reject_oversized(&e).await?;
+                        continue;
+                    }
+                    Err(e) => return connection_ended(e),
+                },
+                control = controls.next() => {
+                    match control {
+                        Control::Inject(notification) => self.send_injected(&notification).await?,
+                        Control::Close => return Ok(()),
+                        Control::PauseChanged => {}
+                    }
+                    continue;
+                }
+            };
+
+            self.dispatcher.record(Direction::Inbound, &message);
+            self.counters.received();
+            let _worker = if self.dispatcher.is_heavy(&message) {
+                self.dispatcher.slow_lane_worker().await
+            } else {
+                None
+            };
+
+            // Stream batch responses element by element when the transport allows it
+            if self.transport.supports_partial_send() && message.trim_start().starts_with('[') {
+                if let Some(elements) = parse_batch(&message) {
+                    self.stream_batch(elements).await?;
+                    continue;
+                }
+            }
+
+            match self.dispatcher.dispatch(&message).await {
+                Some(outgoing) => self.send_outgoing(&outgoing).await?,
+                None => self.announce_list_changed().await?,
+            }
+        }
+    }
+
+    /// Read and execute messages concurrently, bounded by `max_pending`
+    ///
+    /// Responses are written in the order selected by `config.ordering`.
+    async fn run_pipelined(&mut self, controls: &mut Controls) -> McpResult<()> {
+        let dispatcher = Arc::new(self.dispatcher.clone());
+        let max_pending = self.config.max_pending.max(1);
+        let ordering = self.config.ordering;
+        let mut in_flight: JoinSet<Executed> = JoinSet::new();
+        let mut reorder = Reorder::default();
+        let mut lanes = Lanes::default();
+        let mut next_seq: u64 = 0;
+        let mut ended: Option<McpResult<()>> = None;
+        // Heavy messages executing or waiting for a worker of the slow lane
+        let mut slow_in_flight: usize = 0;
+        let slow_capacity = self
+            .dispatcher
+            .slow_lane
+            .as_ref()
+            .map_or(0, SlowLane::capacity);
+
+        let spawn = |in_flight: &mut JoinSet<_>,
+                     slow_in_flight: &mut usize,
+                     seq: u64,
+                     lane: Option<String>,
+                     message: String| {
+            let dispatcher = dispatcher.clone();
+            let slow = dispatcher.is_heavy(&message);
+            *slow_in_flight += usize::from(slow);
+            in_flight.spawn(async move {
+                let _worker = if slow {
+                    dispatcher.slow_lane_worker().await
+                } else {
+                    None
+                };
+                let response = dispatcher.handle_message(&message).await;
+                (seq, lane, slow, response)
+            });
+        };
+
+        loop {
+            // Once the input has ended, stop after the last response is written
+            if in_flight.is_empty() {
+                if let Some(result) = ended {
+                    return result;
+                }
+            }
+
+            let pending = in_flight.len() - slow_in_flight + reorder.held() + lanes.queued();
+            let below_limit = pending < max_pending;
+            let can_read = ended.is_none()
+                && !controls.is_paused()
+                && (below_limit || self.config.on_pending_limit == PendingLimitBehavior::Reject);
+
+            tokio::select! {
+                received = self.transport.receive(), if can_read => match received {
+                    Ok(message) => {
+                        self.dispatcher.record(Direction::Inbound, &message);
+                        self.counters.received();
+                        let seq = next_seq;
+                        next_seq += 1;
+                        let admitted = if self.dispatcher.is_heavy(&message) {
+                            slow_in_flight < slow_capacity
+                        } else {
+                            below_limit
+                        };
+                        if admitted {
+                            if ordering == ResponseOrdering::PerMethod {
+                                let lane = Lanes::lane_of(&message);
+                                if let Some((seq, message)) = lanes.admit(lane.clone(), seq, message) {
+                                    spawn(&mut in_flight, &mut slow_in_flight, seq, lane, message);
+                                }
+                            } else {
+                                spawn(&mut in_flight, &mut slow_in_flight, seq, None, message);
+                            }
+                        } else {
+                            let rejection = self.dispatcher.reject_message(&message)?;
+                            if ordering == ResponseOrdering::Fifo {
+                                reorder.complete(seq, rejection);
+                                for response in reorder.take_ready() {
+                                    self.send_response(&response).await?;
+                                }
+                            } else if let Some(rejection) = rejection {
+                                self.send_response(&rejection).await?;
+                            }
+                        }
+                    }
+                    Err(e) if crate::error::is_message_too_large(&e) => {
+                        self.reject_oversized(&e).await?;
+                    }
+                    Err(e) => ended = Some(connection_ended(e)),
+                },
+                Some(joined) = in_flight.join_next() => {
+                    let (seq, lane, slow, response) = joined.map_err(|e| {
+                        helpers::internal_error(&format!("Request task failed: {}", e))
+                    })?;
+                    slow_in_flight -= usize::from(slow);
+                    let response = response?;
+                    match ordering {
+                        ResponseOrdering::Fifo => {
+                            reorder.complete(seq, response);
+                            for response in reorder.take_ready() {
+                                self.send_response(&response).await?;
+                            }
+                        }
+                        ResponseOrdering::PerMethod => {
+                            if let Some(response) = response {
+                                self.send_response(&response).await?;
+                            }
+                            if let Some((seq, message)) = lanes.finish(&lane) {
+                                spawn(&mut in_flight, &mut slow_in_flight, seq, lane, message);
+                            }
+                        }
+                        ResponseOrdering::Completion => {
+                            if let Some(response) = response {
+                                self.send_response(&response).await?;
+                            }
+                        }
+                    }
+                    // Messages without a response, e.g. notifications, may upgrade the session
+                    self.announce_list_changed().await?;
+                }
+                control = controls.next(), if ended.is_none() => match control {
+                    Control::Inject(notification) => self.send_injected(&notification).await?,
+                    Control::Close => ended = Some(Ok(())),
+                    Control::PauseChanged => {}
+                },
+            }
+        }
+    }
+}
+
+/// Message executed by a pipelined processor: its sequence number, ordering
+/// lane, whether it went to the slow lane, and its response
+type Executed = (u64, Option<String>, bool, McpResult<Option<String>>);
+
+/// Size of the compact JSON serialization of a message
+fn compact_size<M: Serialize>(message: &M) -> usize {
+    serde_json::to_vec(message).map_or(0, |bytes| bytes.len())
+}
+
+/// Map a receive error to the outcome of the run
+///
+/// A closed connection ends the run normally and an idle or receive timeout
+/// is passed through as is; anything else is reported as a transport error.
+fn connection_ended(e: McpError) -> McpResult<()> {
+    if crate::error::is_connection_closed(&e) {
+        return Ok(());
+    }
+    if crate::error::is_idle_timeout(&e) || crate::error::is_receive_timeout(&e) {
+        return Err(e);
+    }
+    Err(helpers::transport_error(&format!("Transport error: {}", e)))
+}
+
+impl Dispatcher {
+    /// Record a message in the history, if enabled
+    fn record(&self, direction: Direction, message: &str) {
+        if let Some(history) = &self.history {
+            history.record_with_secrets(direction, message, &self.labels, &self.secrets);
+        }
+    }
+
+    /// Whether the method is answered by a tool, a protocol method or a built-in
+    fn serves(&self, method: &str) -> bool {
+        self.tool_registry.get(method).is_some()
+            || self.methods.contains_key(method)
+            || method == methods::PING
+            || method == methods::TOOLS_LIST
+    }
+
+    /// Whether a raw message goes to the slow lane, if any
+    fn is_heavy(&self, message: &str) -> bool {
+        self.slow_lane
+            .as_ref()
+            .is_some_and(|slow_lane| slow_lane.is_heavy(message))
+    }
+
+    /// Wait for a worker of the slow lane, held while a heavy message executes
+    async fn slow_lane_worker(&self) -> Option<tokio::sync::OwnedSemaphorePermit> {
+        self.slow_lane.as_ref()?.acquire().await
+    }
+
+    /// Count a call to a deprecated tool and return its deprecation
+    fn deprecated_call(&self, method: &str) -> Option<&DeprecatedMethod> {
+        let deprecated = self.tool_registry.deprecations.get(method)?;
+        deprecated.record_call(self.labels.get(labels::PEER).unwrap_or(UNKNOWN_CLIENT));
+        Some(deprecated)
+    }
+
+    /// Execute a tool, reusing the result of an identical read-only call if coalescing
+    async fn execute_tool(
+        &self,
+        tool: &Arc<dyn Tool>,
+        params: Value,
+        context: &RequestContext,
+    ) -> McpResult<Value> {
+        match &self.coalescing {
+            Some(coalescing) if tool.read_only() => {
+                let name = context.method.as_str();
+                coalescing
+                    .run(name, params, |params| {
+                        self.execute_limited(tool, params, context)
+                    })
+                    .await
+            }
+            _ => self.execute_limited(tool, params, context).await,
+        }
+    }
+
+    /// Execute a tool within the concurrency limits of its method
+    async fn execute_limited(
+        &self,
+        tool: &Arc<dyn Tool>,
+        params: Value,
+        context: &RequestContext,
+    ) -> McpResult<Value> {
+        let name = context.method.as_str();
+        // Protocol methods have no lifecycle
+        if self.tool_registry.get(name).is_some() {
+            self.tool_registry.initialize(name).await?;
+        }
+        match &self.concurrency {
+            Some(limits) => {
+                limits
+                    .run(name, params, |params| {
+                        self.execute_within_slo(tool, params, context)
+                    })
+                    .await
+            }
+            None => self.execute_within_slo(tool, params, context).await,
+        }
+    }
+
+    /// Execute a tool, applying the SLO shedding policy and recording the outcome
+    async fn execute_within_slo(
+        &self,
+        tool: &Arc<dyn Tool>,
+        params: Value,
+        context: &RequestContext,
+    ) -> McpResult<Value> {
+        let name = context.method.as_str();
+        let slo = match &self.slo {
+            Some(slo) => slo,
+            None => return tool.execute_with_context(params, context).await,
+        };
+
+        if slo.should_shed(name) {
+            return Err(helpers::overloaded(&format!(
+                "Method '{}' is temporarily unavailable: error budget exhausted",
+                name
+            )));
+        }
+
+        let started = Instant::now();
+        let result = tool.execute_with_context(params, context).await;
+        slo.record(name, result.is_ok(), started.elapsed());
+        result
+    }
+
+    /// Run the result transformers registered for the method
+    fn shape_result(&self, result: Value, context: &RequestContext) -> McpResult<Value> {
+        match self.transformers.get(&context.method) {
+            Some(transformers) => transformers.iter().try_fold(result, |result, transformer| {
+                transformer.transform(result, context)
+            }),
+            None => Ok(result),
+        }
+    }
+
+    /// Build an error response for the given error, shaping `error.data` with the formatter
+    fn error_response(
+        &self,
+        id: JsonRpcId,
+        err: &McpError,
+        context: ErrorContext,
+    ) -> JsonRpcResponse {
+        let context = context.with_id(id.clone());
+        let (code, message) = error_to_json_rpc_with_context(err, &context);
+        JsonRpcResponse::failure(
+            id,
+            JsonRpcError::new(
+                code,
+                message,
+                Some(self.error_data.format_with_context(err, &context)),
+            ),
+        )
+    }
+
+    /// Response to a request failing validation, if it does
+    fn validation_failure(&self, request: &JsonRpcRequest) -> Option<JsonRpcResponse> {
+        let e = request.validate_with_limits(&self.limits).err()?;
+        // Never echo an oversized id back to the peer
+        let id = match self.limits.check_id(&request.id) {
+            Ok(()) => request.id.clone(),
+            Err(_) => JsonRpcId::Null,
+        };
+        // The method is left out since it may be the oversized part
+        let context = ErrorContext::new(ErrorPhase::Validation);
+        if crate::error::is_limit_exceeded(&e) {
+            return Some(self.error_response(id, &e, context));
+        }
+        let data = self
+            .error_data
+            .format_with_context(&e, &context.with_id(id.clone()));
+        Some(JsonRpcResponse::failure(
+            id,
+            JsonRpcError::new(
+                crate::error::error_codes::INVALID_REQUEST,
+                "Invalid request",
+                Some(data),
+            ),
+        ))
+    }
+
+    /// Error of a method that would be rejected before dispatch, if it would
+    ///
+    /// Calls to deprecated methods are not counted.
+    fn resolution_error(&self, method: &str) -> Option<McpError> {
+        if !self.serves(method) {
+            return Some(McpError::new(
+                Severity::Error,
+                domain_reference_codes::TOOL_NOT_FOUND,
+                format!("Method '{}' not found", method),
+            ));
+        }
+        self.tool_registry
+            .deprecations
+            .get(method)
+            .filter(|deprecated| deprecated.deprecation.is_retired(SystemTime::now()))
+            .map(|deprecated| deprecated.deprecation.retired_error(method))
+    }
+
+    /// Response to a request failing the checks made before dispatch, if it does
+    fn precheck(&self, request: &JsonRpcRequest) -> Option<JsonRpcResponse> {
+        if let Some(response) = self.validation_failure(request) {
+            return Some(response);
+        }
+        let err = self.resolution_error(&request.method)?;
+        let context = ErrorContext::new(ErrorPhase::Dispatch).with_method(&request.method);
+        Some(self.error_response(request.id.clone(), &err, context))
+    }
+
+    /// Responses rejecting a whole batch, if atomic and any element fails its checks
+    ///
+    /// Notifications get no response either way, and invalid elements their
+    /// own error.
+    fn reject_atomic_batch(&self, elements: &[Incoming]) -> Option<Vec<JsonRpcResponse>> {
+        if self.batches != BatchExecution::Atomic {
+            return None;
+        }
+        // Some(response) for failed elements, the response being None for notifications
+        let failures: Vec<Option<Option<JsonRpcResponse>>> = elements
+            .iter()
+            .map(|element| match element {
+                Incoming::Request(request) => self.precheck(request).map(Some),
+                Incoming::Notification(notification) => {
+                    (notification.validate_with_limits(&self.limits).is_err()
+                        || self.resolution_error(&notification.method).is_some())
+                    .then_some(None)
+                }
+                Incoming::Invalid(id, err) => Some(Some(self.error_response(
+                    id.clone(),
+                    err,
+                    ErrorContext::new(ErrorPhase::Validation),
+                ))),
+            })
+            .collect();
+        let failed: Vec<usize> = failures
+            .iter()
+            .enumerate()
+            .filter_map(|(index, failure)| failure.as_ref().map(|_| index))
+            .collect();
+        if failed.is_empty() {
+            return None;
+        }
+
+        let err = helpers::with_details(
+            helpers::batch_aborted(&format!(
+                "Not executed: {} of the {} messages of the batch failed their checks",
+                failed.len(),
+                elements.len()
+            )),
+            json!({ "failed": failed }),
+        );
+        let responses = elements
+            .iter()
+            .zip(failures)
+            .filter_map(|(element, failure)| match (element, failure) {
+                (_, Some(response)) => response,
+                (Incoming::Request(request), None) => {
+                    let context =
+                        ErrorContext::new(ErrorPhase::Dispatch).with_method(&request.method);
+                    Some(self.error_response(request.id.clone(), &err, context))
+                }
+                _ => None,
+            })
+            .collect();
+        Some(responses)
+    }
+
+    /// Process a single JSON-RPC request, logging it when an event log is set
+    async fn process_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let pending = self.event_log.as_ref().map(|log| {
+            log.request(
+                &request.method,
+                &request.id,
+                compact_size(&request),
+                &self.labels,
+            )
+        });
+        let response = self.police_error_code(self.traced_request(request).await);
+        if let Some(pending) = pending {
+            let code = response.error.as_ref().map(|error| error.code);
+            pending.answered(code, compact_size(&response));
+        }
+        response
+    }
+
+    /// Apply the error code policy to the response to a request
+    fn police_error_code(&self, mut response: JsonRpcResponse) -> JsonRpcResponse {
+        let code = match &response.error {
+            Some(error) if error_codes::is_application_defined(error.code) => error.code,
+            _ => return response,
+        };
+        match self.error_codes {
+            ErrorCodePolicy::PassThrough => response,
+            ErrorCodePolicy::Clamp => {
+                if let Some(error) = &mut response.error {
+                    error.code = error_codes::SERVER_ERROR_START;
+                }
+                response
+            }
+            ErrorCodePolicy::Reject => {
+                let err = helpers::internal_error(&format!(
+                    "Method returned the application-defined error code {}",
+                    code
+                ));
+                self.error_response(response.id, &err, ErrorContext::new(ErrorPhase::Response))
+            }
+        }
+    }
+
+    /// Process a notification, logging it when an event log is set
+    async fn process_notification(&self, notification: JsonRpcNotification) -> McpResult<()> {
+        let log = match &self.event_log {
+            Some(log) => log,
+            None => return self.traced_notification(notification).await,
+        };
+        let method = notification.method.clone();
+        let bytes = compact_size(&notification);
+        let started = Instant::now();
+        let result = self.traced_notification(notification).await;
+        let code = result
+            .as_ref()
+            .err()
+            .map(|e| crate::error::error_to_json_rpc(e).0);
+        log.notification(&method, code, started.elapsed(), bytes, &self.labels);
+        result
+    }
+
+    /// Process a request, within its span when instrumented
+    async fn traced_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        #[cfg(feature = "otel")]
+        if let Some(otel) = &self.otel {
+            let method = request.method.clone();
+            let id = request.id.clone();
+            let error_of = |response: &JsonRpcResponse| {
+                response
+                    .error
+                    .as_ref()
+                    .map(|error| (error.code, error.message.clone()))
+            };
+            return otel
+                .instrument(
+                    self.reported_method(&method),
+                    Some(&id),
+                    self.answer_request(request),
+                    error_of,
+                )
+                .await;
+        }
+        self.answer_request(request).await
+    }
+
+    /// Process a notification, within its span when instrumented
+    async fn traced_notification(&self, notification: JsonRpcNotification) -> McpResult<()> {
+        #[cfg(feature = "otel")]
+        if let Some(otel) = &self.otel {
+            let method = notification.method.clone();
+            let error_of =
+                |result: &McpResult<()>| result.as_ref().err().map(crate::error::error_to_json_rpc);
+            return otel
+                .instrument(
+                    self.reported_method(&method),
+                    None,
+                    self.apply_notification(notification),
+                    error_of,
+                )
+                .await;
+        }
+        self.apply_notification(notification).await
+    }
+
+    /// Method name to report in telemetry: unserved methods are grouped, so
+    /// peers cannot inflate its cardinality
+    #[cfg(feature = "otel")]
+    fn reported_method<'a>(&self, method: &'a str) -> &'a str {
+        if self.serves(method) {
+            method
+        } else {
+            OTHER_METHOD
+        }
+    }
+
+    async fn answer_request(&self, mut request: JsonRpcRequest) -> JsonRpcResponse {
+        // Validate the request
+        if let Some(response) = self.validation_failure(&request) {
+            return response;
+        }
+
+        if let Some(shadow) = &self.shadow {
+            shadow.check_request(&request);
+        }
+
+        // Convert and process request
+        let dispatch = ErrorContext::new(ErrorPhase::Dispatch).with_method(&request.method);
+        let id = Some(request.id.clone());
+        if let Err(e) = self
+            .authenticate(&request.method, id, &mut request.params)
+            .await
+        {
+            return self.error_response(request.id.clone(), &e, dispatch);
+        }
+        if let Err(e) = self.admit(&request.method, &mut request.params) {
+            return self.error_response(request.id.clone(), &e, dispatch);
+        }
+        let domain_request = match json_rpc_to_domain_request(&request) {
+            Ok(req) => req,
+            Err(e) => return self.error_response(request.id.clone(), &e, dispatch),
+        };
+
+        // Get and execute tool, falling back to protocol methods
+        let handler = self
+            .tool_registry
+            .get(domain_request.tool_name())
+            .or_else(|| self.methods.get(domain_request.tool_name()));
+        // Calls to deprecated tools are counted even once they are rejected
+        let deprecated = handler.and_then(|_| self.deprecated_call(domain_request.tool_name()));
+        let retired = deprecated
+            .filter(|deprecated| deprecated.deprecation.is_retired(SystemTime::now()))
+            .map(|deprecated| {
+                deprecated
+                    .deprecation
+                    .retired_error(domain_request.tool_name())
+            });
+        let response = match (handler, retired) {
+            (None, _) if domain_request.tool_name() == methods::PING => {
+                Ok(JsonRpcResponse::success(request.id.clone(), json!({})))
+            }
+            (None, _) if domain_request.tool_name() == methods::TOOLS_LIST => {
+                let tools = json!({ "tools": self.tool_registry.list() });
+                Ok(JsonRpcResponse::success(
+                    request.id.clone(),
+                    self.restrict_result(methods::TOOLS_LIST, tools),
+                ))
+            }
+            (Some(_), Some(err)) => Ok(self.error_response(request.id.clone(), &err, dispatch)),
+            (Some(tool), None) => {
+                let context =
+                    RequestContext::new(domain_request.tool_name(), Some(request.id.clone()))
+                        .with_labels(self.labels.clone())
+                        .with_session(self.session.clone());
+                let result = self
+                    .execute_tool(tool, domain_request.params().clone(), &context)
+                    .await
+                    .and_then(|result| self.shape_result(result, &context))
+                    .map(|result| self.restrict_result(&context.method, result))
+                    .map(|result| self.annotate_affinity(&context.method, result))
+                    .map(|result| match deprecated {
+                        Some(deprecated) => deprecated.annotate(&context.method, result),
+                        None => result,
+                    });
+                let domain_response = SimpleDomainResponse {
+                    id: domain_request.id.to_string(),
+                    result,
+                };
+                domain_to_json_rpc_response_with_context(
+                    &domain_response,
+                    self.error_data.as_ref(),
+                    &ErrorContext::new(ErrorPhase::Execution)
+                        .with_method(&request.method)
+                        .with_id(request.id.clone()),
+                )
+            }
+            (None, _) => {
+                let err = McpError::new(
+                    Severity::Error,
+                    "TOOL-NOTFOUND",
+                    &format!("Method '{}' not found", domain_request.tool_name()),
+                );
+                Ok(self.error_response(request.id.clone(), &err, dispatch))
+            }
+        };
+
+        // Return response with validation
+        let responding = ErrorContext::new(ErrorPhase::Response).with_method(&request.method);
+        let response = match response {
+            Ok(resp) => {
+                if let Err(e) = resp.validate() {
+                    let err = McpError::new(
+                        Severity::Critical,
+                        "INTERNAL",
+                        &format!("Invalid response generated: {}", e),
+                    );
+                    self.error_response(request.id.clone(), &err, responding)
+                } else {
+                    resp
+                }
+            }
+            Err(e) => self.error_response(request.id.clone(), &e, responding),
+        };
+
+        if let Some(sizes) = &self.sizes {
+            if self.serves(&request.method) {
+                let id = Some(&request.id);
+                sizes.record(
+                    &request.method,
+                    Direction::Inbound,
+                    compact_size(&request),
+                    id,
+                );
+                sizes.record(
+                    &request.method,
+                    Direction::Outbound,
+                    compact_size(&response),
+                    id,
+                );
+            }
+        }
+        response
+    }
+
+    /// Check the bearer token of a message, if an authenticator is set
+    ///
+    /// A message upgrading the session after `initialize` flags the tools as
+    /// changed, to be announced once its response is written.
+    async fn authenticate(
+        &self,
+        method: &str,
+        id: Option<JsonRpcId>,
+        params: &mut Option<Value>,
+    ) -> McpResult<()> {
+        let authenticator = match &self.authenticator {
+            Some(authenticator) => authenticator,
+            None => return Ok(()),
+        };
+        let context = RequestContext::new(method, id)
+            .with_labels(self.labels.clone())
+            .with_session(self.session.clone());
+        let upgraded = authenticator.authenticate(params, &context).await?;
+        if upgraded && method != methods::INITIALIZE {
+            self.list_changed.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Narrow what anonymous sessions see of a result; see [`crate::auth`]
+    fn restrict_result(&self, method: &str, result: Value) -> Value {
+        match &self.authenticator {
+            Some(authenticator) => authenticator.restrict_result(method, result, &self.session),
+            None => result,
+        }
+    }
+
+    /// Settle the affinity key of the session at `initialize`; see [`crate::affinity`]
+    fn admit(&self, method: &str, params: &mut Option<Value>) -> McpResult<()> {
+        match &self.affinity {
+            Some(affinity) if method == methods::INITIALIZE => {
+                affinity.admit(params, &self.session)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Hand the affinity key of the session to the client in `initialize` results
+    fn annotate_affinity(&self, method: &str, result: Value) -> Value {
+        match &self.affinity {
+            Some(affinity) if method == methods::INITIALIZE => {
+                affinity.annotate(result, &self.session)
+            }
+            _ => result,
+        }
+    }
+
+    /// Process a notification (no response required)
+    async fn apply_notification(&self, mut notification: JsonRpcNotification) -> McpResult<()> {
+        // Validate the notification
+        if let Err(e) = notification.validate_with_limits(&self.limits) {
+            return Err(helpers::protocol_error(&format!(
+                "Invalid notification: {}",
+                e
+            )));
+        }
+
+        if let Some(shadow) = &self.shadow {
+            shadow.check_notification(&notification);
+        }
+        self.authenticate(&notification.method, None, &mut notification.params)
+            .await?;
+        if let Some(sizes) = &self.sizes {
+            if self.serves(&notification.method) {
+                let bytes = compact_size(&notification);
+                sizes.record(&notification.method, Direction::Inbound, bytes, None);
+            }
+        }
+
+        // Convert to domain request (reusing existing conversion)
+        let request = JsonRpcRequest {
+            jsonrpc: notification.jsonrpc,
+            method: notification.method,
+            params: notification.params,
+            id: crate::protocol::JsonRpcId::Null,
+        };
+
+        let domain_request = json_rpc_to_domain_request(&request)?;
+
+        if let Some(deprecated) = self.deprecated_call(domain_request.tool_name()) {
+            if deprecated.deprecation.is_retired(SystemTime::now()) {
+                return Err(deprecated
+                    .deprecation
+                    .retired_error(domain_request.tool_name()));
+            }
+        }
+
+        // Execute the tool or protocol method if it exists (ignore result
+        // since it's a notification); protocol methods have no lifecycle
+        let name = domain_request.tool_name();
+        let handler = match self.tool_registry.get(name) {
+            Some(tool) => Some((tool, true)),
+            None => self.methods.get(name).map(|method| (method, false)),
+        };
+        if let Some((tool, is_tool)) = handler {
+            let context = RequestContext::new(name, None)
+                .with_labels(self.labels.clone())
+                .with_session(self.session.clone());
+            let initialized = if is_tool {
+                self.tool_registry.initialize(&context.method).await
+            } else {
+                Ok(())
+            };
+            let executed = match initialized {
+                Ok(()) => {
+                    tool.execute_with_context(domain_request.params().clone(), &context)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = executed {
+                return Err(helpers::domain_error(
+                    McpError::new(Severity::Error, "TOOL-ERROR", &e.to_string()),
+                    "Tool execution failed",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Process a batch of requests and/or notifications
+    async fn process_batch(&self, elements: Vec<Incoming>) -> Vec<JsonRpcResponse> {
+        if let Some(responses) = self.reject_atomic_batch(&elements) {
+            return responses;
+        }
+        let mut responses = Vec::with_capacity(elements.len());
+        for element in elements {
+            if let Some(response) = self.process_element(element).await {
+                responses.push(response);
+            }
+        }
+        responses
+    }
+
+    /// Process a request or notification, returning the response if one is due
+    async fn process_element(&self, element: Incoming) -> Option<JsonRpcResponse> {
+        match element {
+            Incoming::Request(request) => Some(self.process_request(request).await),
+            Incoming::Notification(notification) => {
+                let _ = self.process_notification(notification).await;
+                None
+            }
+            Incoming::Invalid(id, err) => {
+                Some(self.error_response(id, &err, ErrorContext::new(ErrorPhase::Validation)))
+            }
+        }
+    }
+
+    /// Handle a raw message, returning the response if one is due
+    async fn dispatch(&self, message: &str) -> Option<Outgoing> {
+        let value = match serde_json::from_str::<Value>(message) {
+            Ok(value) => value,
+            Err(e) => {
+                // Invalid JSON
+                let err = helpers::json_error(e);
+                return Some(Outgoing::Response(self.error_response(
+                    JsonRpcId::Null,
+                    &err,
+                    ErrorContext::new(ErrorPhase::Parse),
+                )));
+            }
+        };
+
+        let outgoing = match value {
+            Value::Array(elements) if elements.is_empty() => {
+                let err = helpers::protocol_error("Invalid Request: empty batch");
+                Outgoing::Response(self.error_response(
+                    JsonRpcId::Null,
+                    &err,
+                    ErrorContext::new(ErrorPhase::Validation),
+                ))
+            }
+            Value::Array(elements) => {
+                let elements = elements.into_iter().map(Incoming::classify).collect();
+                let responses = self.process_batch(elements).await;
+                for response in &responses {
+                    self.linter.check_response(response);
+                }
+                if responses.is_empty() {
+                    return None; // No response needed for notification-only batches
+                }
+                Outgoing::Batch(responses)
+            }
+            value => {
+                // No response needed for notifications
+                let response = self.process_element(Incoming::classify(value)).await?;
+                self.linter.check_response(&response);
+                Outgoing::Response(response)
+            }
+        };
+
+        Some(outgoing)
+    }
+
+    /// Handle a raw message, returning the serialized response if one is due
+    async fn handle_message(&self, message: &str) -> McpResult<Option<String>> {
+        match self.dispatch(message).await {
+            Some(outgoing) => serde_json::to_string(&outgoing)
+                .map(Some)
+                .map_err(helpers::json_error),
+            None => Ok(None),
+        }
+    }
+
+    /// Answer a message with overloaded errors without executing it
+    fn reject_message(&self, message: &str) -> McpResult<Option<String>> {
+        // The connection is overloaded, not the method, so the method is left out
+        let err = helpers::overloaded("Too many pending requests on this connection");
+        let context = ErrorContext::new(ErrorPhase::Dispatch);
+        let response = match JsonRpcMessage::parse(message) {
+            Ok(JsonRpcMessage::Request(request)) => {
+                serde_json::to_string(&self.error_response(request.id, &err, context))
+            }
+            Ok(JsonRpcMessage::Batch(messages)) => {
+                let responses: Vec<JsonRpcResponse> = messages
+                    .into_iter()
+                    .filter_map(|message| match message {
+                        JsonRpcMessage::Request(request) => {
+                            Some(self.error_response(request.id, &err, context.clone()))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                if responses.is_empty() {
+                    return Ok(None);
+                }
+                serde_json::to_string(&responses)
+            }
+            _ => return Ok(None),
+        };
+        response.map(Some).map_err(helpers::json_error)
+    }
+}
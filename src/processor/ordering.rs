@@ -0,0 +1,127 @@
+//! Response ordering
+//!
+//! A sequential processor answers messages one at a time, so responses always
+//! follow the request order. A pipelined processor executes several messages
+//! at once and, by default, writes each response as soon as it is ready: a
+//! slow request is overtaken by faster ones sent after it. JSON-RPC allows
+//! this since responses carry the id of their request, but many clients assume
+//! first-in first-out answers anyway. [`ResponseOrdering`] makes the guarantee
+//! explicit:
+//!
+//! | Mode           | Execution                        | Responses written           |
+//! |----------------|----------------------------------|-----------------------------|
+//! | [`Completion`] | all messages concurrently        | as each request completes   |
+//! | [`Fifo`]       | all messages concurrently        | in request order            |
+//! | [`PerMethod`]  | one message at a time per method | in request order per method |
+//!
+//! [`Completion`]: ResponseOrdering::Completion
+//! [`Fifo`]: ResponseOrdering::Fifo
+//! [`PerMethod`]: ResponseOrdering::PerMethod
+//!
+//! With `Fifo`, a completed response waits for the responses of all earlier
+//! requests and counts against the pending-message limit until it is written.
+//! Batches form a lane of their own in `PerMethod` mode.
+
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Order in which a pipelined processor writes responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseOrdering {
+    /// Write each response as soon as its request completes
+    #[default]
+    Completion,
+    /// Write responses in the order the requests were received
+    Fifo,
+    /// Execute the messages of each method one at a time, in the order they
+    /// were received; messages of different methods still run concurrently
+    PerMethod,
+}
+
+/// Holds completed responses until all earlier ones are written
+#[derive(Default)]
+pub(crate) struct Reorder {
+    next: u64,
+    ready: BTreeMap<u64, Option<String>>,
+}
+
+impl Reorder {
+    /// Record the outcome of the message with the given sequence number
+    pub(crate) fn complete(&mut self, seq: u64, response: Option<String>) {
+        self.ready.insert(seq, response);
+    }
+
+    /// Take the responses that can be written now, in order
+    pub(crate) fn take_ready(&mut self) -> Vec<String> {
+        let mut responses = Vec::new();
+        while let Some(response) = self.ready.remove(&self.next) {
+            responses.extend(response);
+            self.next += 1;
+        }
+        responses
+    }
+
+    /// Number of completed messages waiting for earlier ones
+    pub(crate) fn held(&self) -> usize {
+        self.ready.len()
+    }
+}
+
+/// Queues the messages of each method behind the one being executed
+#[derive(Default)]
+pub(crate) struct Lanes {
+    busy: HashSet<Option<String>>,
+    queued: HashMap<Option<String>, VecDeque<(u64, String)>>,
+    len: usize,
+}
+
+impl Lanes {
+    /// Lane of a raw message: its method, or `None` for batches and messages
+    /// that cannot be parsed
+    pub(crate) fn lane_of(message: &str) -> Option<String> {
+        serde_json::from_str::<Value>(message)
+            .ok()?
+            .get("method")?
+            .as_str()
+            .map(str::to_string)
+    }
+
+    /// Admit a message, returning it if its lane is free to execute it now
+    pub(crate) fn admit(
+        &mut self,
+        lane: Option<String>,
+        seq: u64,
+        message: String,
+    ) -> Option<(u64, String)> {
+        if self.busy.insert(lane.clone()) {
+            return Some((seq, message));
+        }
+        self.queued
+            .entry(lane)
+            .or_default()
+            .push_back((seq, message));
+        self.len += 1;
+        None
+    }
+
+    /// Mark the lane's message as done, returning the next one to execute
+    pub(crate) fn finish(&mut self, lane: &Option<String>) -> Option<(u64, String)> {
+        let next = self.queued.get_mut(lane).and_then(VecDeque::pop_front);
+        match next {
+            Some(next) => {
+                self.len -= 1;
+                Some(next)
+            }
+            None => {
+                self.queued.remove(lane);
+                self.busy.remove(lane);
+                None
+            }
+        }
+    }
+
+    /// Number of messages waiting for their lane
+    pub(crate) fn queued(&self) -> usize {
+        self.len
+    }
+}
@@ -0,0 +1,132 @@
+//! Service adapter
+//!
+//! Frameworks such as jsonrpsee or tower-lsp route each call to a service
+//! function taking a method and its params and returning a future of the
+//! result. [`MethodService`] exposes the method router of a processor in that
+//! shape, so an existing server can mount the MCP-aware stack (tools,
+//! protocol methods, limits, shaping, history) under its own transport
+//! handling and move to [`run`](super::JsonRpcProcessor::run) later:
+//!
+//! ```rust,ignore
+//! // The processor's transport is not used
+//! let service = JsonRpcProcessor::new(transport, registry)
+//!     .with_method("initialize", Initialize)
+//!     .service();
+//!
+//! // jsonrpsee
+//! let mut module = RpcModule::new(());
+//! module.register_async_method("tools/call", move |params, _, _| {
+//!     let service = service.clone();
+//!     async move {
+//!         service
+//!             .call("tools/call", params.parse::<Option<Value>>()?)
+//!             .await
+//!             .map_err(|e| ErrorObject::owned(e.code, e.message, e.data))
+//!     }
+//! })?;
+//! ```
+//!
+//! Calls go through the same path as requests read by the processor, under
+//! ids of their own, and share its session, so methods that change the
+//! session (authentication, `initialize`) affect later calls. Messages the
+//! processor would push on its own, such as list changed notifications, are
+//! not sent.
+
+use super::handle::Counters;
+use super::Dispatcher;
+use crate::history::Direction;
+use crate::protocol::{JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+use mcp_error::Result as McpResult;
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+/// Future returned by the service function of [`MethodService::into_fn`]
+pub type ServiceFuture = Pin<Box<dyn Future<Output = Result<Value, JsonRpcError>> + Send>>;
+
+/// The method router of a processor as a `(method, params) -> future<result>` service
+///
+/// Created with [`JsonRpcProcessor::service`](super::JsonRpcProcessor::service).
+/// Clones share the processor's session and counters.
+#[derive(Clone)]
+pub struct MethodService {
+    dispatcher: Dispatcher,
+    counters: Arc<Counters>,
+    next_id: Arc<AtomicI64>,
+}
+
+impl MethodService {
+    pub(super) fn new(dispatcher: Dispatcher, counters: Arc<Counters>) -> Self {
+        Self {
+            dispatcher,
+            counters,
+            next_id: Arc::new(AtomicI64::new(1)),
+        }
+    }
+
+    /// Call a method, returning its result or the JSON-RPC error a peer would get
+    pub async fn call(
+        &self,
+        method: impl Into<String>,
+        params: Option<Value>,
+    ) -> Result<Value, JsonRpcError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = JsonRpcRequest::new(method, params, id);
+        self.record(Direction::Inbound, &request);
+        self.counters.received();
+
+        let response = self.dispatcher.process_request(request).await;
+        self.record(Direction::Outbound, &response);
+        self.counters.sent();
+        into_result(response)
+    }
+
+    /// Deliver a notification
+    pub async fn notify(&self, method: impl Into<String>, params: Option<Value>) -> McpResult<()> {
+        let notification = JsonRpcNotification::new(method, params);
+        self.record(Direction::Inbound, &notification);
+        self.counters.received();
+        self.dispatcher.process_notification(notification).await
+    }
+
+    /// Answer a raw message, for frameworks handing over whole frames
+    ///
+    /// Returns the serialized response, if one is due.
+    pub async fn handle(&self, message: &str) -> McpResult<Option<String>> {
+        self.dispatcher.record(Direction::Inbound, message);
+        self.counters.received();
+        let response = self.dispatcher.handle_message(message).await?;
+        if let Some(response) = &response {
+            self.dispatcher.record(Direction::Outbound, response);
+            self.counters.sent();
+        }
+        Ok(response)
+    }
+
+    /// Turn the service into a plain function, for routers taking closures
+    pub fn into_fn(self) -> impl Fn(String, Option<Value>) -> ServiceFuture + Clone + Send + Sync {
+        move |method, params| {
+            let service = self.clone();
+            Box::pin(async move { service.call(method, params).await })
+        }
+    }
+
+    fn record<M: Serialize>(&self, direction: Direction, message: &M) {
+        if self.dispatcher.history.is_none() {
+            return;
+        }
+        if let Ok(message) = serde_json::to_string(message) {
+            self.dispatcher.record(direction, &message);
+        }
+    }
+}
+
+fn into_result(response: JsonRpcResponse) -> Result<Value, JsonRpcError> {
+    match (response.result, response.error) {
+        (_, Some(error)) => Err(error),
+        (result, None) => Ok(result.unwrap_or(Value::Null)),
+    }
+}
@@ -0,0 +1,120 @@
+//! Shadow validation
+//!
+//! Tightening validation can break clients that were relying on lenient
+//! parsing. [`ShadowValidation`] evaluates a candidate [`ValidationPolicy`] on
+//! every incoming request and notification that the active rules accepted,
+//! without enforcing it: messages are still served, and the ones the candidate
+//! would have rejected are counted per method and reported to an optional
+//! handler. Once the counts stay at zero, the candidate can be enforced.
+
+use crate::protocol::{JsonRpcId, JsonRpcNotification, JsonRpcRequest, ValidationPolicy};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Message the candidate policy would have rejected
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowRejection {
+    /// Method of the message
+    pub method: String,
+    /// Id of the request; `None` for notifications
+    pub id: Option<JsonRpcId>,
+    /// Why the candidate policy rejected it
+    pub reason: String,
+}
+
+/// Callback receiving would-be rejections
+pub type ShadowRejectionHandler = Arc<dyn Fn(&ShadowRejection) + Send + Sync>;
+
+/// Counts of the messages evaluated against the candidate policy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowReport {
+    /// Messages evaluated
+    pub checked: u64,
+    /// Messages the candidate policy would have rejected
+    pub would_reject: u64,
+    /// Would-be rejections per method
+    pub by_method: BTreeMap<String, u64>,
+}
+
+/// Candidate validation policy evaluated without being enforced
+///
+/// Clones share the same counts, so one clone can be given to every processor
+/// and another kept to read the report.
+#[derive(Clone)]
+pub struct ShadowValidation {
+    policy: ValidationPolicy,
+    report: Arc<Mutex<ShadowReport>>,
+    handler: Option<ShadowRejectionHandler>,
+}
+
+impl ShadowValidation {
+    /// Evaluate the given policy in the shadow of the active rules
+    pub fn new(policy: ValidationPolicy) -> Self {
+        Self {
+            policy,
+            report: Arc::default(),
+            handler: None,
+        }
+    }
+
+    /// Call the given function for each would-be rejection
+    pub fn with_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&ShadowRejection) + Send + Sync + 'static,
+    {
+        self.handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Candidate policy
+    pub fn policy(&self) -> ValidationPolicy {
+        self.policy
+    }
+
+    /// Counts so far
+    pub fn report(&self) -> ShadowReport {
+        self.lock().clone()
+    }
+
+    /// Reset the counts, e.g. after changing the clients
+    pub fn reset(&self) {
+        *self.lock() = ShadowReport::default();
+    }
+
+    /// Evaluate a request accepted by the active rules
+    pub(crate) fn check_request(&self, request: &JsonRpcRequest) {
+        let outcome = self.policy.check_request(request);
+        self.record(&request.method, Some(&request.id), outcome);
+    }
+
+    /// Evaluate a notification accepted by the active rules
+    pub(crate) fn check_notification(&self, notification: &JsonRpcNotification) {
+        let outcome = self.policy.check_notification(notification);
+        self.record(&notification.method, None, outcome);
+    }
+
+    fn record(&self, method: &str, id: Option<&JsonRpcId>, outcome: mcp_error::Result<()>) {
+        {
+            let mut report = self.lock();
+            report.checked += 1;
+            if outcome.is_err() {
+                report.would_reject += 1;
+                *report.by_method.entry(method.to_string()).or_default() += 1;
+            }
+        }
+
+        if let (Err(e), Some(handler)) = (outcome, &self.handler) {
+            handler(&ShadowRejection {
+                method: method.to_string(),
+                id: id.cloned(),
+                reason: e.to_string(),
+            });
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ShadowReport> {
+        self.report.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,169 @@
+//! Result shaping
+//!
+//! Result transformers registered on a processor with
+//! [`with_result_transformer`](super::JsonRpcProcessor::with_result_transformer)
+//! rewrite what a method returns before it is sent, so output policies such as
+//! size caps or field hygiene are enforced in one place instead of in every
+//! tool. Transformers run in registration order, after the tool succeeded;
+//! errors are not shaped.
+
+use crate::context::RequestContext;
+use crate::visit::{walk_value, Payload, ValuePath, VisitMut, Walk};
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// Rewrites the result of a method before it is sent to the peer
+pub trait ResultTransformer: Send + Sync {
+    /// Shape the result; failing turns the response into an error response
+    fn transform(&self, result: Value, context: &RequestContext) -> McpResult<Value>;
+}
+
+impl<F> ResultTransformer for F
+where
+    F: Fn(Value) -> Value + Send + Sync,
+{
+    fn transform(&self, result: Value, _context: &RequestContext) -> McpResult<Value> {
+        Ok(self(result))
+    }
+}
+
+/// Apply a visitor to a whole result
+fn shape<V: VisitMut>(mut visitor: V, mut result: Value) -> Value {
+    walk_value(
+        &mut visitor,
+        &mut ValuePath::new(Payload::Result),
+        &mut result,
+    );
+    result
+}
+
+/// Keep at most `max_items` items in every array of the result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TruncateArrays {
+    /// Maximum number of items kept per array
+    pub max_items: usize,
+}
+
+impl TruncateArrays {
+    /// Truncate arrays to the given length
+    pub fn new(max_items: usize) -> Self {
+        Self { max_items }
+    }
+}
+
+impl ResultTransformer for TruncateArrays {
+    fn transform(&self, result: Value, _context: &RequestContext) -> McpResult<Value> {
+        Ok(shape(*self, result))
+    }
+}
+
+impl VisitMut for TruncateArrays {
+    fn visit_value(&mut self, _path: &ValuePath, value: &mut Value) -> Walk {
+        if let Value::Array(items) = value {
+            items.truncate(self.max_items);
+        }
+        Walk::Continue
+    }
+}
+
+/// Remove the named members from every object of the result
+#[derive(Debug, Clone, Default)]
+pub struct StripFields {
+    fields: BTreeSet<String>,
+}
+
+impl StripFields {
+    /// Strip the given member names
+    pub fn new<I, S>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl ResultTransformer for StripFields {
+    fn transform(&self, result: Value, _context: &RequestContext) -> McpResult<Value> {
+        Ok(shape(self, result))
+    }
+}
+
+impl VisitMut for &StripFields {
+    fn visit_value(&mut self, _path: &ValuePath, value: &mut Value) -> Walk {
+        if let Value::Object(object) = value {
+            object.retain(|key, _| !self.fields.contains(key));
+        }
+        Walk::Continue
+    }
+}
+
+/// Convert Unix timestamps (seconds) in the named members to RFC 3339 strings
+///
+/// Members holding anything other than an integer are left untouched.
+#[derive(Debug, Clone, Default)]
+pub struct UnixToRfc3339 {
+    fields: BTreeSet<String>,
+}
+
+impl UnixToRfc3339 {
+    /// Convert the given member names
+    pub fn new<I, S>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl ResultTransformer for UnixToRfc3339 {
+    fn transform(&self, result: Value, _context: &RequestContext) -> McpResult<Value> {
+        Ok(shape(self, result))
+    }
+}
+
+impl VisitMut for &UnixToRfc3339 {
+    fn visit_value(&mut self, path: &ValuePath, value: &mut Value) -> Walk {
+        let named = path.last_key().is_some_and(|key| self.fields.contains(key));
+        match value.as_i64() {
+            Some(seconds) if named => {
+                *value = Value::String(rfc3339(seconds));
+                Walk::Skip
+            }
+            _ => Walk::Continue,
+        }
+    }
+}
+
+/// Format a Unix timestamp as an RFC 3339 UTC date-time
+pub(super) fn rfc3339(seconds: i64) -> String {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
@@ -0,0 +1,120 @@
+//! Slow lane
+//!
+//! Bulk calls (large uploads, exports, reindexing) can take every execution
+//! slot of a pipelined processor and leave `ping` or `tools/list` waiting
+//! behind them. A [`SlowLane`] set with
+//! [`with_slow_lane`](super::JsonRpcProcessor::with_slow_lane) routes heavy
+//! messages, those over a size or calling a known-heavy method, to a bounded
+//! pool of workers of their own:
+//!
+//! - at most `workers` heavy messages execute at once; the others wait for a
+//!   worker, up to `max_queued` of them;
+//! - heavy messages beyond that are answered with an overloaded error
+//!   (-32001) right away, whatever the pending-limit behavior, so reading
+//!   never stops on their account;
+//! - heavy messages do not count towards `max_pending`, which is left to the
+//!   fast lane.
+//!
+//! Clones share the workers, so one instance given to every processor bounds
+//! bulk work across connections; the queue bound applies per connection.
+//! The size of a message stands for the size of its params, and a batch is
+//! heavy if any of its elements is. In sequential mode, heavy messages only
+//! wait for a worker.
+//!
+//! With [`ResponseOrdering::Fifo`](super::ResponseOrdering::Fifo), responses
+//! to fast messages are still held back until the heavy messages read before
+//! them are answered; use
+//! [`Completion`](super::ResponseOrdering::Completion) or
+//! [`PerMethod`](super::ResponseOrdering::PerMethod) ordering to let them
+//! through.
+
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Default number of heavy messages waiting for a worker, per connection
+pub const DEFAULT_SLOW_LANE_QUEUE: usize = 16;
+
+/// Routing of heavy messages to a bounded pool of workers
+#[derive(Debug, Clone)]
+pub struct SlowLane {
+    workers: Arc<Semaphore>,
+    worker_count: usize,
+    max_queued: usize,
+    max_message_bytes: Option<usize>,
+    methods: Arc<HashSet<String>>,
+}
+
+impl SlowLane {
+    /// Create a slow lane with the given number of workers, routing nothing
+    /// until a size or methods are set
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        Self {
+            workers: Arc::new(Semaphore::new(workers)),
+            worker_count: workers,
+            max_queued: DEFAULT_SLOW_LANE_QUEUE,
+            max_message_bytes: None,
+            methods: Arc::new(HashSet::new()),
+        }
+    }
+
+    /// Route messages larger than `bytes`
+    pub fn with_messages_over(mut self, bytes: usize) -> Self {
+        self.max_message_bytes = Some(bytes);
+        self
+    }
+
+    /// Route calls of a known-heavy method
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.methods).insert(method.into());
+        self
+    }
+
+    /// Set how many heavy messages may wait for a worker, per connection
+    pub fn with_max_queued(mut self, max: usize) -> Self {
+        self.max_queued = max;
+        self
+    }
+
+    /// Number of workers
+    pub fn workers(&self) -> usize {
+        self.worker_count
+    }
+
+    /// Heavy messages a connection may have pending, executing or waiting
+    pub(crate) fn capacity(&self) -> usize {
+        self.worker_count + self.max_queued
+    }
+
+    /// Whether a raw message goes to the slow lane
+    pub fn is_heavy(&self, message: &str) -> bool {
+        if self
+            .max_message_bytes
+            .is_some_and(|max| message.len() > max)
+        {
+            return true;
+        }
+        if self.methods.is_empty() {
+            return false;
+        }
+        let calls_heavy = |element: &Value| {
+            element
+                .get("method")
+                .and_then(Value::as_str)
+                .is_some_and(|method| self.methods.contains(method))
+        };
+        match serde_json::from_str::<Value>(message) {
+            Ok(Value::Array(elements)) => elements.iter().any(calls_heavy),
+            Ok(message) => calls_heavy(&message),
+            Err(_) => false,
+        }
+    }
+
+    /// Wait for a worker; the message executes while the permit is held
+    pub(crate) async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        // The semaphore is never closed
+        self.workers.clone().acquire_owned().await.ok()
+    }
+}
@@ -0,0 +1,137 @@
+//! Batch streaming against transports failing in the middle of a batch
+
+use super::{JsonRpcProcessor, Tool, ToolRegistry};
+use crate::error::helpers;
+use crate::transport::{BatchStreamWriter, Transport};
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+struct Echo;
+
+#[async_trait]
+impl Tool for Echo {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        Ok(params)
+    }
+}
+
+/// Transport writing parts until its budget is spent, then failing every write
+struct BrokenPipe {
+    incoming: VecDeque<String>,
+    written: Arc<Mutex<String>>,
+    parts_left: usize,
+}
+
+impl BrokenPipe {
+    fn new(incoming: &[&str], parts: usize) -> (Self, Arc<Mutex<String>>) {
+        let written = Arc::new(Mutex::new(String::new()));
+        let transport = Self {
+            incoming: incoming.iter().map(|message| message.to_string()).collect(),
+            written: written.clone(),
+            parts_left: parts,
+        };
+        (transport, written)
+    }
+
+    fn write(&mut self, part: &str) -> McpResult<()> {
+        if self.parts_left == 0 {
+            return Err(helpers::transport_error("Failed to write: broken pipe"));
+        }
+        self.parts_left -= 1;
+        self.written.lock().unwrap().push_str(part);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Transport for BrokenPipe {
+    async fn receive(&mut self) -> McpResult<String> {
+        self.incoming
+            .pop_front()
+            .ok_or_else(helpers::connection_closed)
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.write(message)
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        true
+    }
+
+    async fn send_part(&mut self, part: &str, _end: bool) -> McpResult<()> {
+        self.write(part)
+    }
+}
+
+const BATCH: &str = r#"[
+    {"jsonrpc":"2.0","method":"echo","params":{"n":1},"id":1},
+    {"jsonrpc":"2.0","method":"echo","params":{"n":2},"id":2},
+    {"jsonrpc":"2.0","method":"echo","params":{"n":3},"id":3}
+]"#;
+
+const NEXT: &str = r#"{"jsonrpc":"2.0","method":"echo","params":{"n":4},"id":4}"#;
+
+fn registry() -> ToolRegistry {
+    ToolRegistry::builder().with_tool("echo", Echo).build()
+}
+
+#[tokio::test]
+async fn failed_element_write_aborts_run() {
+    let (transport, written) = BrokenPipe::new(&[BATCH, NEXT], 1);
+    let result = JsonRpcProcessor::new(transport, registry()).run().await;
+    assert!(result.is_err());
+
+    // The first element went out, and nothing after the failed second one
+    let written = written.lock().unwrap().clone();
+    assert!(written.starts_with("[{"));
+    assert!(written.contains(r#""id":1"#));
+    assert!(!written.contains(r#""id":2"#));
+    assert!(!written.contains(r#""id":4"#));
+    assert!(!written.ends_with(']'));
+}
+
+#[tokio::test]
+async fn failed_array_close_aborts_run() {
+    let (transport, written) = BrokenPipe::new(&[BATCH, NEXT], 3);
+    let result = JsonRpcProcessor::new(transport, registry()).run().await;
+    assert!(result.is_err());
+
+    // Every element went out, but not the closing bracket nor the next response
+    let written = written.lock().unwrap().clone();
+    for id in 1..=3 {
+        assert!(written.contains(&format!(r#""id":{}"#, id)));
+    }
+    assert!(!written.contains(r#""id":4"#));
+    assert!(!written.ends_with(']'));
+}
+
+#[tokio::test]
+async fn complete_batch_is_a_valid_array() {
+    let (transport, written) = BrokenPipe::new(&[BATCH, NEXT], usize::MAX);
+    JsonRpcProcessor::new(transport, registry())
+        .run()
+        .await
+        .unwrap();
+
+    let written = written.lock().unwrap().clone();
+    let batch_end = written.find(']').unwrap() + 1;
+    let batch: Value = serde_json::from_str(&written[..batch_end]).unwrap();
+    assert_eq!(batch.as_array().unwrap().len(), 3);
+    let next: Value = serde_json::from_str(&written[batch_end..]).unwrap();
+    assert_eq!(next["id"], 4);
+}
+
+#[tokio::test]
+async fn writer_counts_only_written_elements() {
+    let (mut transport, written) = BrokenPipe::new(&[], 1);
+    let mut writer = BatchStreamWriter::new();
+    writer.write_element(&mut transport, "1").await.unwrap();
+    assert!(writer.write_element(&mut transport, "2").await.is_err());
+    assert_eq!(writer.written(), 1);
+    assert!(writer.finish(&mut transport).await.is_err());
+    assert_eq!(*written.lock().unwrap(), "[1");
+}
@@ -1,8 +1,116 @@
-use crate::error::helpers;
+use crate::error::{error_codes, helpers};
 use mcp_error::Result as McpResult;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// Default maximum length of a method name, in bytes
+pub const DEFAULT_MAX_METHOD_LEN: usize = 256;
+
+/// Default maximum length of a string id, in bytes
+pub const DEFAULT_MAX_ID_LEN: usize = 256;
+
+/// Size limits on the envelope of a message
+///
+/// Ids are echoed back in responses and method names end up in logs, metrics
+/// and history, so both are bounded. Violations are reported with dedicated
+/// error codes ([`METHOD_TOO_LONG`](crate::error::error_codes::METHOD_TOO_LONG)
+/// and [`ID_TOO_LONG`](crate::error::error_codes::ID_TOO_LONG)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLimits {
+    /// Maximum length of a method name, in bytes
+    pub max_method_len: usize,
+    /// Maximum length of a string id, in bytes
+    pub max_id_len: usize,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        Self {
+            max_method_len: DEFAULT_MAX_METHOD_LEN,
+            max_id_len: DEFAULT_MAX_ID_LEN,
+        }
+    }
+}
+
+impl MessageLimits {
+    /// Check a method name against the limit
+    pub fn check_method(&self, method: &str) -> McpResult<()> {
+        if method.len() > self.max_method_len {
+            return Err(helpers::method_too_long(method.len(), self.max_method_len));
+        }
+        Ok(())
+    }
+
+    /// Check an id against the limit; only string ids can exceed it
+    pub fn check_id(&self, id: &JsonRpcId) -> McpResult<()> {
+        match id {
+            JsonRpcId::String(s) if s.len() > self.max_id_len => {
+                Err(helpers::id_too_long(s.len(), self.max_id_len))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How strictly requests and notifications are checked
+///
+/// Applied on the send path, rejecting a message locally gives a descriptive
+/// error instead of a generic one from the peer, and keeps spec-violating
+/// bytes off the wire. A stricter policy can also be evaluated on incoming
+/// messages without enforcing it, see
+/// [`ShadowValidation`](crate::processor::ShadowValidation).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationPolicy {
+    /// Send messages as built
+    Off,
+    /// Check the version and method name, as `validate` does
+    #[default]
+    Basic,
+    /// Also require structured params and enforce the given limits
+    Strict(MessageLimits),
+}
+
+impl ValidationPolicy {
+    /// Check a request
+    pub fn check_request(&self, request: &JsonRpcRequest) -> McpResult<()> {
+        match self {
+            ValidationPolicy::Off => Ok(()),
+            ValidationPolicy::Basic => request.validate(),
+            ValidationPolicy::Strict(limits) => {
+                request.validate_with_limits(limits)?;
+                check_params(&request.method, request.params.as_ref())
+            }
+        }
+    }
+
+    /// Check a notification
+    pub fn check_notification(&self, notification: &JsonRpcNotification) -> McpResult<()> {
+        match self {
+            ValidationPolicy::Off => Ok(()),
+            ValidationPolicy::Basic => notification.validate(),
+            ValidationPolicy::Strict(limits) => {
+                notification.validate_with_limits(limits)?;
+                check_params(&notification.method, notification.params.as_ref())
+            }
+        }
+    }
+}
+
+/// Params must be a structured value when present
+fn check_params(method: &str, params: Option<&Value>) -> McpResult<()> {
+    let kind = match params {
+        None | Some(Value::Object(_)) | Some(Value::Array(_)) => return Ok(()),
+        Some(Value::Null) => "null",
+        Some(Value::Bool(_)) => "a boolean",
+        Some(Value::Number(_)) => "a number",
+        Some(Value::String(_)) => "a string",
+    };
+    Err(helpers::protocol_error(&format!(
+        "Params of '{}' must be an object or an array, not {}",
+        method, kind
+    )))
+}
+
 /// JSON-RPC 2.0 Request object
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcRequest {
@@ -21,6 +129,23 @@ pub struct JsonRpcRequest {
 }
 
 impl JsonRpcRequest {
+    /// Create a new JSON-RPC 2.0 request
+    pub fn new(method: impl Into<String>, params: Option<Value>, id: impl Into<JsonRpcId>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.into(),
+            params,
+            id: id.into(),
+        }
+    }
+
+    /// Validate a request, then check its method and id against the given limits
+    pub fn validate_with_limits(&self, limits: &MessageLimits) -> McpResult<()> {
+        self.validate()?;
+        limits.check_method(&self.method)?;
+        limits.check_id(&self.id)
+    }
+
     /// Validate that a request adheres to the JSON-RPC 2.0 specification
     pub fn validate(&self) -> McpResult<()> {
         // Check protocol version
@@ -65,6 +190,32 @@ pub struct JsonRpcResponse {
 }
 
 impl JsonRpcResponse {
+    /// Create a successful response carrying the given result
+    pub fn success(id: JsonRpcId, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            result: Some(result),
+            error: None,
+            id,
+        }
+    }
+
+    /// Create an error response carrying the given error object
+    pub fn failure(id: JsonRpcId, error: JsonRpcError) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            result: None,
+            error: Some(error),
+            id,
+        }
+    }
+
+    /// Validate a response, then check its id against the given limits
+    pub fn validate_with_limits(&self, limits: &MessageLimits) -> McpResult<()> {
+        self.validate()?;
+        limits.check_id(&self.id)
+    }
+
     /// Validate that a response adheres to the JSON-RPC 2.0 specification
     pub fn validate(&self) -> McpResult<()> {
         // Check protocol version
@@ -113,6 +264,21 @@ pub struct JsonRpcNotification {
 }
 
 impl JsonRpcNotification {
+    /// Create a new JSON-RPC 2.0 notification
+    pub fn new(method: impl Into<String>, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.into(),
+            params,
+        }
+    }
+
+    /// Validate a notification, then check its method against the given limits
+    pub fn validate_with_limits(&self, limits: &MessageLimits) -> McpResult<()> {
+        self.validate()?;
+        limits.check_method(&self.method)
+    }
+
     /// Validate that a notification adheres to the JSON-RPC 2.0 specification
     pub fn validate(&self) -> McpResult<()> {
         // Check protocol version
@@ -153,7 +319,52 @@ pub struct JsonRpcError {
 }
 
 impl JsonRpcError {
+    /// Create a new error object
+    pub fn new(code: i32, message: impl Into<String>, data: Option<Value>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data,
+        }
+    }
+
+    /// Create an error with a code defined by the specification or in the
+    /// server error range (-32099 to -32000)
+    ///
+    /// Fails for any other code, so misused codes are caught where the error
+    /// is built rather than when it is sent; use
+    /// [`application`](Self::application) for application-defined codes.
+    pub fn custom(code: i32, message: impl Into<String>, data: Option<Value>) -> McpResult<Self> {
+        if !error_codes::is_defined(code) {
+            return Err(helpers::protocol_error(&format!(
+                "Error code {} is neither defined by JSON-RPC nor a server error (-32099 to -32000); \
+                 use JsonRpcError::application for application-defined codes",
+                code
+            )));
+        }
+        Ok(Self::new(code, message, data))
+    }
+
+    /// Create an error with an application-defined code, outside the range
+    /// reserved by the specification (-32768 to -32000)
+    pub fn application(
+        code: i32,
+        message: impl Into<String>,
+        data: Option<Value>,
+    ) -> McpResult<Self> {
+        if !error_codes::is_application_defined(code) {
+            return Err(helpers::protocol_error(&format!(
+                "Error code {} is reserved by JSON-RPC (-32768 to -32000)",
+                code
+            )));
+        }
+        Ok(Self::new(code, message, data))
+    }
+
     /// Validate that an error object adheres to the JSON-RPC 2.0 specification
+    ///
+    /// Codes of the reserved range must be defined by the specification or be
+    /// server errors; codes outside it are application-defined and accepted.
     pub fn validate(&self) -> McpResult<()> {
         // Error message must not be empty
         if self.message.is_empty() {
@@ -161,19 +372,11 @@ impl JsonRpcError {
         }
 
         // Validate error code ranges
-        match self.code {
-            -32700 => {}                                    // Parse error
-            -32600 => {}                                    // Invalid request
-            -32601 => {}                                    // Method not found
-            -32602 => {}                                    // Invalid params
-            -32603 => {}                                    // Internal error
-            code if (-32099..=-32000).contains(&code) => {} // Server error
-            _ => {
-                return Err(helpers::protocol_error(&format!(
-                    "Invalid error code: {}",
-                    self.code
-                )));
-            }
+        if !error_codes::is_defined(self.code) && !error_codes::is_application_defined(self.code) {
+            return Err(helpers::protocol_error(&format!(
+                "Invalid error code: {}",
+                self.code
+            )));
         }
 
         Ok(())
@@ -183,7 +386,7 @@ impl JsonRpcError {
 /// JSON-RPC ID (can be string, number, or null)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, Hash)]
 pub enum JsonRpcId {
     Null,
     String(String),
@@ -201,6 +404,110 @@ impl JsonRpcId {
     }
 }
 
+impl From<i64> for JsonRpcId {
+    fn from(n: i64) -> Self {
+        JsonRpcId::Number(n)
+    }
+}
+
+impl From<String> for JsonRpcId {
+    fn from(s: String) -> Self {
+        JsonRpcId::String(s)
+    }
+}
+
+impl From<&str> for JsonRpcId {
+    fn from(s: &str) -> Self {
+        JsonRpcId::String(s.to_string())
+    }
+}
+
+/// The `id` member of an incoming object, telling a missing member from `"id": null`
+///
+/// [`JsonRpcId`] alone cannot: a missing `id` makes a notification, while
+/// `"id": null` is a request (discouraged, but answered, with a null id).
+/// Ids of another type cannot be echoed back, so the message is an invalid
+/// request answered with a null id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum IdField {
+    /// No `id` member: a notification
+    #[default]
+    Absent,
+    /// `"id": null`
+    Null,
+    /// A string or integer id
+    Id(JsonRpcId),
+    /// An id of another type (fractional number, boolean, object or array)
+    Invalid(Value),
+}
+
+impl IdField {
+    /// Read the `id` member of an object
+    pub fn of(object: &serde_json::Map<String, Value>) -> Self {
+        object.get("id").map_or(IdField::Absent, Self::from_value)
+    }
+
+    /// Classify a present `id` value
+    pub fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Null => IdField::Null,
+            Value::String(s) => IdField::Id(JsonRpcId::String(s.clone())),
+            Value::Number(n) => match n.as_i64() {
+                Some(n) => IdField::Id(JsonRpcId::Number(n)),
+                None => IdField::Invalid(value.clone()),
+            },
+            _ => IdField::Invalid(value.clone()),
+        }
+    }
+
+    /// Whether the member is missing
+    pub fn is_absent(&self) -> bool {
+        matches!(self, IdField::Absent)
+    }
+
+    /// Id of a request, `None` for notifications and invalid ids
+    pub fn request_id(&self) -> Option<JsonRpcId> {
+        match self {
+            IdField::Null => Some(JsonRpcId::Null),
+            IdField::Id(id) => Some(id.clone()),
+            IdField::Absent | IdField::Invalid(_) => None,
+        }
+    }
+
+    /// Id to answer an invalid message with: its own if valid, null otherwise
+    pub fn response_id(&self) -> JsonRpcId {
+        self.request_id().unwrap_or(JsonRpcId::Null)
+    }
+}
+
+impl From<JsonRpcId> for IdField {
+    fn from(id: JsonRpcId) -> Self {
+        match id {
+            JsonRpcId::Null => IdField::Null,
+            id => IdField::Id(id),
+        }
+    }
+}
+
+impl Serialize for IdField {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            // Skip absent ids with `skip_serializing_if = "IdField::is_absent"`
+            IdField::Absent | IdField::Null => serializer.serialize_none(),
+            IdField::Id(id) => id.serialize(serializer),
+            IdField::Invalid(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for IdField {
+    /// Deserializes a present member; use `#[serde(default)]` to read a
+    /// missing one as [`IdField::Absent`]
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Value::deserialize(deserializer).map(|value| Self::from_value(&value))
+    }
+}
+
 /// Batch of JSON-RPC requests/notifications
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -211,6 +518,82 @@ pub enum JsonRpcBatch {
     Notifications(Vec<JsonRpcNotification>),
 }
 
+/// Any JSON-RPC 2.0 message
+///
+/// Classification follows the specification: an object with a `method` is a
+/// request when it has an `id` member, even `null`, and a notification
+/// otherwise; an object without a `method` is a response; an array is a
+/// batch. See [`IdField`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum JsonRpcMessage {
+    /// A request expecting a response
+    Request(JsonRpcRequest),
+    /// A notification (no response expected)
+    Notification(JsonRpcNotification),
+    /// A response to a request
+    Response(JsonRpcResponse),
+    /// A batch of messages
+    Batch(Vec<JsonRpcMessage>),
+}
+
+impl JsonRpcMessage {
+    /// Parse a raw frame into a message
+    pub fn parse(message: &str) -> McpResult<Self> {
+        let value: Value = serde_json::from_str(message).map_err(helpers::json_error)?;
+        Self::from_value(value)
+    }
+
+    /// Classify and convert an already parsed JSON value
+    pub fn from_value(value: Value) -> McpResult<Self> {
+        match value {
+            Value::Array(items) => items
+                .into_iter()
+                .map(Self::from_value)
+                .collect::<McpResult<Vec<_>>>()
+                .map(JsonRpcMessage::Batch),
+            Value::Object(ref object) => {
+                let id = IdField::of(object);
+                if let IdField::Invalid(id) = id {
+                    return Err(helpers::protocol_error(&format!(
+                        "Invalid message: id must be a string, an integer or null, not {}",
+                        id
+                    )));
+                }
+                let message = if !object.contains_key("method") {
+                    serde_json::from_value(value).map(JsonRpcMessage::Response)
+                } else if id.is_absent() {
+                    serde_json::from_value(value).map(JsonRpcMessage::Notification)
+                } else {
+                    serde_json::from_value(value).map(JsonRpcMessage::Request)
+                };
+                message.map_err(|e| helpers::protocol_error(&format!("Invalid message: {}", e)))
+            }
+            _ => Err(helpers::protocol_error(
+                "Invalid message: expected an object or an array",
+            )),
+        }
+    }
+
+    /// Method name, for requests and notifications
+    pub fn method(&self) -> Option<&str> {
+        match self {
+            JsonRpcMessage::Request(request) => Some(&request.method),
+            JsonRpcMessage::Notification(notification) => Some(&notification.method),
+            _ => None,
+        }
+    }
+
+    /// Id, for requests and responses
+    pub fn id(&self) -> Option<&JsonRpcId> {
+        match self {
+            JsonRpcMessage::Request(request) => Some(&request.id),
+            JsonRpcMessage::Response(response) => Some(&response.id),
+            _ => None,
+        }
+    }
+}
+
 /// Parse a string ID into a JsonRpcId
 pub fn parse_id(id_str: &str) -> JsonRpcId {
     if id_str == "null" {
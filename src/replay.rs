@@ -0,0 +1,361 @@
+//! Replay of a captured session against a live server
+//!
+//! [`Replay`] takes the records of a [capture](crate::capture), sends the
+//! messages of the client side again over a transport connected to a server,
+//! and compares each response with the one recorded. The result is a
+//! [`ReplayReport`] listing every divergence, so a server upgrade can be
+//! checked against real traffic before it is rolled out:
+//!
+//! ```rust,ignore
+//! let records = CaptureReader::new(BufReader::new(File::open("session.jsonl")?))
+//!     .with_connection("10.0.0.7:5123");
+//! let report = Replay::from_reader(records)?
+//!     .with_speed(0.0)
+//!     .with_ignored_field("/result/generatedAt")
+//!     .run(&mut TcpTransport::connect("127.0.0.1:9000").await?)
+//!     .await?;
+//! assert!(report.is_clean(), "{}", serde_json::to_string_pretty(&report)?);
+//! ```
+//!
+//! Captures written by a server record client messages as inbound, which is
+//! the default; use [`with_client_direction`](Replay::with_client_direction)
+//! for captures written by a client. Messages are sent one at a time, each
+//! after the responses to the previous one, so the replay is deterministic
+//! even if the session was pipelined. Requests keep their original ids, and
+//! responses are matched to requests by id in the order they were sent, so
+//! ids reused during the session are still told apart. Notifications are sent
+//! but not compared, and responses the client sent to server requests are not
+//! sent at all. Filter the capture by connection rather than by method, since
+//! responses carry no method.
+
+use crate::capture::{CaptureReader, CaptureRecord};
+use crate::history::Direction;
+use crate::transport::Transport;
+use mcp_error::Result as McpResult;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::io::BufRead;
+use std::time::{Duration, Instant};
+
+/// Default time to wait for the response to a replayed request
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How a replayed response departs from the recorded one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DivergenceKind {
+    /// Both responses exist but differ
+    Mismatch,
+    /// The server did not answer in time, or the connection ended
+    Missing,
+    /// The capture holds no response to compare with
+    Unrecorded,
+}
+
+/// A replayed request whose response differs from the recorded one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Divergence {
+    /// Position of the request among the replayed requests, from 0
+    pub position: usize,
+    /// Method called
+    pub method: String,
+    /// Id of the request
+    pub id: Value,
+    /// How the responses differ
+    pub kind: DivergenceKind,
+    /// JSON Pointers of the differing values, within the response
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Recorded response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Value>,
+    /// Response of the live server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<Value>,
+}
+
+/// Outcome of a replay
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    /// Messages sent, a batch counting once
+    pub messages_sent: usize,
+    /// Requests replayed
+    pub requests: usize,
+    /// Requests answered as recorded
+    pub matched: usize,
+    /// Requests answered differently, or not at all
+    pub divergences: Vec<Divergence>,
+}
+
+impl ReplayReport {
+    /// Whether every request was answered as recorded
+    pub fn is_clean(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+/// Replays the client side of a captured session
+#[derive(Debug, Clone)]
+pub struct Replay {
+    records: Vec<CaptureRecord>,
+    client: Direction,
+    speed: f64,
+    response_timeout: Duration,
+    ignored: Vec<String>,
+}
+
+/// A request sent, waiting for its response
+struct Outstanding {
+    position: usize,
+    method: String,
+    id: Value,
+}
+
+impl Replay {
+    /// Replay the given records, in order
+    pub fn from_records(records: impl IntoIterator<Item = CaptureRecord>) -> Self {
+        Self {
+            records: records.into_iter().collect(),
+            client: Direction::Inbound,
+            speed: 1.0,
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+            ignored: Vec::new(),
+        }
+    }
+
+    /// Replay the records of a capture, failing on the first malformed line
+    pub fn from_reader<R: BufRead>(reader: CaptureReader<R>) -> McpResult<Self> {
+        Ok(Self::from_records(reader.collect::<McpResult<Vec<_>>>()?))
+    }
+
+    /// Set the direction of the messages sent by the client
+    pub fn with_client_direction(mut self, direction: Direction) -> Self {
+        self.client = direction;
+        self
+    }
+
+    /// Divide the original gaps between messages by `speed`
+    ///
+    /// As with [`CaptureReader::replay`], 0 or an infinite speed sends each
+    /// message as soon as the previous one is answered.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Set how long to wait for each response
+    pub fn with_response_timeout(mut self, timeout: Duration) -> Self {
+        self.response_timeout = timeout;
+        self
+    }
+
+    /// Ignore a value expected to change between runs, such as a timestamp,
+    /// given as a JSON Pointer within the response (e.g. `/result/generatedAt`)
+    pub fn with_ignored_field(mut self, pointer: impl Into<String>) -> Self {
+        self.ignored.push(pointer.into());
+        self
+    }
+
+    /// Replay the session over a transport connected to the server
+    ///
+    /// Fails only if a message cannot be sent; a connection ending while
+    /// responses are awaited reports them as missing.
+    pub async fn run<T: Transport>(&self, transport: &mut T) -> McpResult<ReplayReport> {
+        let mut recorded = self.recorded_responses();
+        let mut report = ReplayReport::default();
+        let mut start: Option<(u64, Instant)> = None;
+        let mut connected = true;
+
+        let sent = self
+            .records
+            .iter()
+            .filter(|record| record.direction == self.client);
+        for record in sent {
+            let requests = match messages(&record.message) {
+                Some(elements) if elements.iter().any(|e| e.get("method").is_some()) => {
+                    requests_of(&elements)
+                }
+                // Answers to server requests, which the live server never sent
+                _ => continue,
+            };
+
+            // Keep each message at its original offset from the first one, so
+            // the time spent waiting for responses does not add up
+            let (first, started) = *start.get_or_insert((record.timestamp_ms, Instant::now()));
+            if self.speed > 0.0 && self.speed.is_finite() {
+                let offset = record.timestamp_ms.saturating_sub(first) as f64 / 1000.0;
+                let at = started + Duration::from_secs_f64(offset / self.speed);
+                tokio::time::sleep_until(at.into()).await;
+            }
+
+            let mut outstanding = Vec::with_capacity(requests.len());
+            for (method, id) in requests {
+                outstanding.push(Outstanding {
+                    position: report.requests,
+                    method,
+                    id,
+                });
+                report.requests += 1;
+            }
+            if connected {
+                transport.send(&record.raw()).await?;
+                report.messages_sent += 1;
+            }
+
+            while connected && !outstanding.is_empty() {
+                let message =
+                    match tokio::time::timeout(self.response_timeout, transport.receive()).await {
+                        Ok(Ok(message)) => message,
+                        Ok(Err(_)) => {
+                            connected = false;
+                            break;
+                        }
+                        Err(_) => break,
+                    };
+                let received = serde_json::from_str::<Value>(&message)
+                    .ok()
+                    .and_then(|value| messages(&value))
+                    .unwrap_or_default();
+                for response in received {
+                    let id = response.get("id").cloned().unwrap_or(Value::Null);
+                    if let Some(index) = outstanding.iter().position(|o| o.id == id) {
+                        let request = outstanding.remove(index);
+                        let expected = take_recorded(&mut recorded, &request.id);
+                        self.compare(request, expected, Some(response), &mut report);
+                    }
+                }
+            }
+            for request in outstanding {
+                let expected = take_recorded(&mut recorded, &request.id);
+                self.compare(request, expected, None, &mut report);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Recorded responses by id, in capture order
+    fn recorded_responses(&self) -> HashMap<String, VecDeque<Value>> {
+        let mut responses: HashMap<String, VecDeque<Value>> = HashMap::new();
+        let received = self
+            .records
+            .iter()
+            .filter(|record| record.direction != self.client)
+            .filter_map(|record| messages(&record.message));
+        for response in received.flatten().filter(is_response) {
+            let id = response.get("id").cloned().unwrap_or(Value::Null);
+            responses
+                .entry(id.to_string())
+                .or_default()
+                .push_back(response);
+        }
+        responses
+    }
+
+    fn compare(
+        &self,
+        request: Outstanding,
+        expected: Option<Value>,
+        actual: Option<Value>,
+        report: &mut ReplayReport,
+    ) {
+        let (kind, paths) = match (&expected, &actual) {
+            (_, None) => (DivergenceKind::Missing, Vec::new()),
+            (None, Some(_)) => (DivergenceKind::Unrecorded, Vec::new()),
+            (Some(expected), Some(actual)) => {
+                let mut paths = Vec::new();
+                self.diff(&mut String::new(), expected, actual, &mut paths);
+                if paths.is_empty() {
+                    report.matched += 1;
+                    return;
+                }
+                (DivergenceKind::Mismatch, paths)
+            }
+        };
+        report.divergences.push(Divergence {
+            position: request.position,
+            method: request.method,
+            id: request.id,
+            kind,
+            paths,
+            expected,
+            actual,
+        });
+    }
+
+    /// Collect the pointers at which two values differ
+    fn diff(&self, path: &mut String, expected: &Value, actual: &Value, paths: &mut Vec<String>) {
+        if self.ignored.iter().any(|ignored| ignored == path) {
+            return;
+        }
+        match (expected, actual) {
+            (Value::Object(expected), Value::Object(actual)) => {
+                let mut keys: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                for key in keys {
+                    // The envelope is the same for every response
+                    if path.is_empty() && (key == "jsonrpc" || key == "id") {
+                        continue;
+                    }
+                    let len = path.len();
+                    path.push('/');
+                    path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                    match (expected.get(key), actual.get(key)) {
+                        (Some(expected), Some(actual)) => self.diff(path, expected, actual, paths),
+                        _ if self.ignored.iter().any(|ignored| ignored == path) => {}
+                        _ => paths.push(path.clone()),
+                    }
+                    path.truncate(len);
+                }
+            }
+            (Value::Array(expected), Value::Array(actual)) => {
+                for index in 0..expected.len().max(actual.len()) {
+                    let len = path.len();
+                    path.push_str(&format!("/{}", index));
+                    match (expected.get(index), actual.get(index)) {
+                        (Some(expected), Some(actual)) => self.diff(path, expected, actual, paths),
+                        _ if self.ignored.iter().any(|ignored| ignored == path) => {}
+                        _ => paths.push(path.clone()),
+                    }
+                    path.truncate(len);
+                }
+            }
+            (expected, actual) if expected != actual => paths.push(path.clone()),
+            _ => {}
+        }
+    }
+}
+
+/// Elements of a message or batch, `None` if it is not a JSON-RPC message
+fn messages(message: &Value) -> Option<Vec<Value>> {
+    let elements = match message {
+        Value::Array(elements) => elements.clone(),
+        Value::Object(_) => vec![message.clone()],
+        _ => return None,
+    };
+    Some(elements)
+}
+
+/// Method and id of the requests among the elements of a message
+fn requests_of(elements: &[Value]) -> Vec<(String, Value)> {
+    elements
+        .iter()
+        .filter_map(|element| {
+            let method = element.get("method")?.as_str()?;
+            let id = element.get("id")?;
+            Some((method.to_string(), id.clone()))
+        })
+        .collect()
+}
+
+fn is_response(message: &Value) -> bool {
+    message.get("method").is_none()
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+fn take_recorded(recorded: &mut HashMap<String, VecDeque<Value>>, id: &Value) -> Option<Value> {
+    recorded.get_mut(&id.to_string())?.pop_front()
+}
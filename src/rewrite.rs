@@ -0,0 +1,321 @@
+//! Declarative message rewrite rules
+//!
+//! Gateways often need small changes to the messages they pass on: tagging
+//! requests with a tenant, dropping a debug flag, renaming a field or a method
+//! that moved. [`RewriteRules`] describes such changes as data, loadable from a
+//! JSON config file, and [`RewritingTransport`](crate::transport::RewritingTransport)
+//! applies them to every message received and sent on a connection.
+//!
+//! ```json
+//! {"rules": [
+//!   {"match": {"direction": "inbound", "method": "tools/call"},
+//!    "actions": [
+//!      {"op": "set", "path": "/params/_meta/tenant", "value": "acme"},
+//!      {"op": "remove", "path": "/params/arguments/debug"},
+//!      {"op": "rename", "from": "/params/arguments/q", "to": "/params/arguments/query"}]},
+//!   {"match": {"method": "legacy/*"},
+//!    "actions": [{"op": "mapMethod", "to": "v2/*"}]}
+//! ]}
+//! ```
+//!
+//! - Paths are JSON Pointers from the root of the message, e.g. `/params/x`,
+//!   `/result/items/0` or `/error/data`.
+//! - `set` creates missing objects along its path. `remove` and `rename` do
+//!   nothing when the source is missing.
+//! - A method ending with `*` matches every method starting with the part
+//!   before it. In `mapMethod`, a `to` ending with `*` keeps the part the `*`
+//!   of the match stood for, so `legacy/*` to `v2/*` turns `legacy/search`
+//!   into `v2/search`.
+//! - Responses have no method of their own. They match the method of the
+//!   request they answer, as it was before any rewrite, when the rules are
+//!   applied by a transport that saw the request.
+//! - Every matching rule applies, in order, each seeing the changes of the
+//!   previous ones. The elements of a batch are rewritten one by one.
+
+use crate::error::helpers;
+use crate::history::Direction;
+use mcp_error::Result as McpResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// Messages a rule applies to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleMatch {
+    /// Method, or method prefix followed by `*`; any method if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Direction of the message; both if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<Direction>,
+}
+
+impl RuleMatch {
+    /// Whether a message with the given method going in the given direction matches
+    pub fn matches(&self, direction: Direction, method: Option<&str>) -> bool {
+        let method_matches = match (&self.method, method) {
+            (None, _) => true,
+            (Some(pattern), Some(method)) => match pattern.strip_suffix('*') {
+                Some(prefix) => method.starts_with(prefix),
+                None => method == pattern,
+            },
+            (Some(_), None) => false,
+        };
+        method_matches && self.direction.is_none_or(|d| d == direction)
+    }
+}
+
+/// Change made to a matching message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase", deny_unknown_fields)]
+pub enum RewriteAction {
+    /// Set the value at a path, creating missing objects along it
+    Set {
+        /// Where to set the value
+        path: String,
+        /// The value
+        value: Value,
+    },
+    /// Remove the value at a path
+    Remove {
+        /// What to remove
+        path: String,
+    },
+    /// Move the value at a path to another
+    Rename {
+        /// Current path of the value
+        from: String,
+        /// New path of the value
+        to: String,
+    },
+    /// Change the method of requests and notifications
+    MapMethod {
+        /// New method; see the module documentation for `*`
+        to: String,
+    },
+}
+
+/// A condition and the changes made to the messages meeting it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteRule {
+    /// Messages the rule applies to
+    #[serde(rename = "match", default)]
+    pub matcher: RuleMatch,
+    /// Changes, made in order
+    pub actions: Vec<RewriteAction>,
+}
+
+impl RewriteRule {
+    /// Create a rule for the messages meeting the given condition
+    pub fn new(matcher: RuleMatch) -> Self {
+        Self {
+            matcher,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Add a change
+    pub fn with_action(mut self, action: RewriteAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+}
+
+/// Ordered set of rewrite rules
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteRules {
+    /// The rules, applied in order
+    pub rules: Vec<RewriteRule>,
+}
+
+impl RewriteRules {
+    /// Create an empty set of rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule
+    pub fn with_rule(mut self, rule: RewriteRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Parse and check rules in the JSON format of the module documentation
+    pub fn from_json(json: &str) -> McpResult<Self> {
+        let rules: Self = serde_json::from_str(json)
+            .map_err(|e| helpers::config_error(&format!("Invalid rewrite rules: {}", e)))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Read rules from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            helpers::config_error(&format!(
+                "Cannot read rewrite rules {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Check that every path is a JSON Pointer below the root of the message
+    pub fn validate(&self) -> McpResult<()> {
+        for (index, rule) in self.rules.iter().enumerate() {
+            for action in &rule.actions {
+                let paths = match action {
+                    RewriteAction::Set { path, .. } | RewriteAction::Remove { path } => {
+                        vec![path]
+                    }
+                    RewriteAction::Rename { from, to } => vec![from, to],
+                    RewriteAction::MapMethod { .. } => Vec::new(),
+                };
+                if let Some(path) = paths.into_iter().find(|path| !path.starts_with('/')) {
+                    return Err(helpers::config_error(&format!(
+                        "Rewrite rule {}: '{}' is not a JSON Pointer below the message root",
+                        index, path
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether there is no rule
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rewrite a message or batch in place, returning the number of rules applied
+    ///
+    /// `method` is used for responses, which carry none, and is ignored for
+    /// requests and notifications.
+    pub fn apply(&self, direction: Direction, method: Option<&str>, message: &mut Value) -> usize {
+        match message {
+            Value::Array(elements) => elements
+                .iter_mut()
+                .map(|element| self.apply_one(direction, method, element))
+                .sum(),
+            message => self.apply_one(direction, method, message),
+        }
+    }
+
+    fn apply_one(&self, direction: Direction, method: Option<&str>, message: &mut Value) -> usize {
+        let method = match message.get("method").and_then(Value::as_str) {
+            Some(own) => Some(own.to_string()),
+            None => method.map(str::to_string),
+        };
+        let mut applied = 0;
+        for rule in &self.rules {
+            if !rule.matcher.matches(direction, method.as_deref()) {
+                continue;
+            }
+            applied += 1;
+            for action in &rule.actions {
+                apply_action(action, rule.matcher.method.as_deref(), message);
+            }
+        }
+        applied
+    }
+}
+
+fn apply_action(action: &RewriteAction, pattern: Option<&str>, message: &mut Value) {
+    match action {
+        RewriteAction::Set { path, value } => set(message, path, value.clone()),
+        RewriteAction::Remove { path } => {
+            remove(message, path);
+        }
+        RewriteAction::Rename { from, to } => {
+            if let Some(value) = remove(message, from) {
+                set(message, to, value);
+            }
+        }
+        RewriteAction::MapMethod { to } => {
+            if let Some(Value::String(method)) = message.get_mut("method") {
+                *method = map_method(pattern, to, method);
+            }
+        }
+    }
+}
+
+/// New name of a method matched by `pattern`
+fn map_method(pattern: Option<&str>, to: &str, method: &str) -> String {
+    let prefix = pattern.and_then(|pattern| pattern.strip_suffix('*'));
+    match (prefix, to.strip_suffix('*')) {
+        (Some(prefix), Some(to)) => {
+            format!("{}{}", to, method.strip_prefix(prefix).unwrap_or(method))
+        }
+        _ => to.to_string(),
+    }
+}
+
+/// Reference tokens of a JSON Pointer, unescaped
+fn tokens(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+/// Set a value, creating missing objects; does nothing if a parent is a scalar
+fn set(root: &mut Value, pointer: &str, value: Value) {
+    let tokens = tokens(pointer);
+    let (last, parents) = match tokens.split_last() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut current = root;
+    for token in parents {
+        current = match current {
+            Value::Object(object) => object
+                .entry(token.clone())
+                .or_insert_with(|| Value::Object(Map::new())),
+            Value::Array(items) => match token.parse::<usize>().ok().and_then(|i| items.get_mut(i))
+            {
+                Some(item) => item,
+                None => return,
+            },
+            _ => return,
+        };
+    }
+    match current {
+        Value::Object(object) => {
+            object.insert(last.clone(), value);
+        }
+        Value::Array(items) if last == "-" => items.push(value),
+        Value::Array(items) => {
+            if let Some(item) = last.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                *item = value;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Remove and return a value, if present
+fn remove(root: &mut Value, pointer: &str) -> Option<Value> {
+    let tokens = tokens(pointer);
+    let (last, parents) = tokens.split_last()?;
+    let mut current = root;
+    for token in parents {
+        current = match current {
+            Value::Object(object) => object.get_mut(token)?,
+            Value::Array(items) => items.get_mut(token.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    match current {
+        Value::Object(object) => object.remove(last),
+        Value::Array(items) => {
+            let index = last.parse::<usize>().ok()?;
+            (index < items.len()).then(|| items.remove(index))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,363 @@
+//! Admin control socket
+//!
+//! Connections spawned through a [`SessionRegistry`] can be managed at runtime
+//! by operators. [`AdminServer`] answers the management methods in
+//! [`admin_methods`] with its own registry, separate from the tools served to
+//! clients, so the admin surface cannot be reached from a client connection and
+//! client tools cannot be reached from the admin socket. On Unix it listens on
+//! a local socket with [`AdminServer::serve`]; the registry can also be served
+//! over any other transport with [`AdminServer::processor`].
+
+use super::parse_params;
+use crate::context::{labels, ConnectionLabels};
+use crate::error::helpers;
+use crate::mcp::{BuildInfo, Implementation};
+use crate::processor::{
+    JsonRpcProcessor, ProcessorHandle, ProcessorStats, ShadowValidation, Tool, ToolRegistry,
+};
+use crate::sizes::PayloadSizes;
+use crate::slo::SloTracker;
+use crate::transport::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// Management methods answered by the admin socket
+pub mod admin_methods {
+    /// List the running sessions with their labels and statistics
+    pub const LIST_SESSIONS: &str = "admin/listSessions";
+    /// Close a session gracefully: `{"id": 3}`
+    pub const CLOSE_SESSION: &str = "admin/closeSession";
+    /// Run the configured reload handler
+    pub const RELOAD_CONFIG: &str = "admin/reloadConfig";
+    /// Session statistics and, when configured, SLO status, shadow validation
+    /// counts and payload sizes
+    pub const DUMP_METRICS: &str = "admin/dumpMetrics";
+    /// Server name, version and build metadata, and the adapter version
+    pub const SERVER_INFO: &str = "admin/serverInfo";
+}
+
+/// Callback reloading the embedder's configuration, returning a summary for the operator
+pub type ReloadHandler = Arc<dyn Fn() -> McpResult<Value> + Send + Sync>;
+
+/// Description of a running session
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    /// Session id, assigned when the session was registered
+    pub id: u64,
+    /// Labels of the connection
+    pub labels: ConnectionLabels,
+    /// Activity of the connection
+    pub stats: ProcessorStats,
+}
+
+struct Session {
+    handle: ProcessorHandle,
+    labels: ConnectionLabels,
+}
+
+/// Running sessions visible to the admin socket
+///
+/// Clones share the same sessions. Finished sessions are dropped from the
+/// registry the next time it is listed.
+#[derive(Clone, Default)]
+pub struct SessionRegistry {
+    sessions: Arc<Mutex<BTreeMap<u64, Session>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl SessionRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn a processor and register it, returning the session id
+    pub fn spawn<T: Transport + 'static>(&self, processor: JsonRpcProcessor<T>) -> u64 {
+        let labels = processor.labels().clone();
+        self.insert(processor.spawn(), labels)
+    }
+
+    /// Register an already spawned processor, returning the session id
+    pub fn insert(&self, handle: ProcessorHandle, labels: ConnectionLabels) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.lock().insert(id, Session { handle, labels });
+        id
+    }
+
+    /// Describe the running sessions, ordered by id
+    pub fn list(&self) -> Vec<SessionInfo> {
+        self.prune();
+        self.lock()
+            .iter()
+            .map(|(id, session)| SessionInfo {
+                id: *id,
+                labels: session.labels.clone(),
+                stats: session.handle.stats(),
+            })
+            .collect()
+    }
+
+    /// Close a session gracefully, returning whether it was found
+    pub fn close(&self, id: u64) -> McpResult<bool> {
+        match self.lock().remove(&id) {
+            Some(session) if !session.handle.is_finished() => {
+                session.handle.close()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Close every session gracefully, waiting up to `timeout` for them to
+    /// finish
+    ///
+    /// Sessions still running after `timeout` are aborted; returns how many
+    /// were. The registry is empty afterwards, but sessions registered
+    /// meanwhile are left running.
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        let sessions = std::mem::take(&mut *self.lock());
+        let mut closing = JoinSet::new();
+        for session in sessions.into_values() {
+            closing.spawn(session.handle.shutdown(timeout));
+        }
+        let mut aborted = 0;
+        while let Some(finished) = closing.join_next().await {
+            if !matches!(finished, Ok(true)) {
+                aborted += 1;
+            }
+        }
+        aborted
+    }
+
+    /// Drop finished sessions from the registry
+    pub(crate) fn prune(&self) {
+        self.lock()
+            .retain(|_, session| !session.handle.is_finished());
+    }
+
+    /// Number of registered sessions, including finished ones not yet pruned
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no session is registered
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<u64, Session>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Server for the management methods
+#[derive(Clone)]
+pub struct AdminServer {
+    sessions: SessionRegistry,
+    slo: Option<SloTracker>,
+    shadow: Option<ShadowValidation>,
+    sizes: Option<PayloadSizes>,
+    reload: Option<ReloadHandler>,
+    server_info: Option<Implementation>,
+}
+
+impl AdminServer {
+    /// Create an admin server managing the given sessions
+    pub fn new(sessions: SessionRegistry) -> Self {
+        Self {
+            sessions,
+            slo: None,
+            shadow: None,
+            sizes: None,
+            reload: None,
+            server_info: None,
+        }
+    }
+
+    /// Include the SLO status of each method in `admin/dumpMetrics`
+    pub fn with_slo(mut self, slo: SloTracker) -> Self {
+        self.slo = Some(slo);
+        self
+    }
+
+    /// Include the shadow validation report in `admin/dumpMetrics`
+    pub fn with_shadow_validation(mut self, shadow: ShadowValidation) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    /// Include the payload size report in `admin/dumpMetrics`
+    pub fn with_payload_sizes(mut self, sizes: PayloadSizes) -> Self {
+        self.sizes = Some(sizes);
+        self
+    }
+
+    /// Report the given server description in `admin/serverInfo`
+    pub fn with_server_info(mut self, server_info: Implementation) -> Self {
+        self.server_info = Some(server_info);
+        self
+    }
+
+    /// Run the given function on `admin/reloadConfig`
+    pub fn with_reload_handler<F>(mut self, reload: F) -> Self
+    where
+        F: Fn() -> McpResult<Value> + Send + Sync + 'static,
+    {
+        self.reload = Some(Arc::new(reload));
+        self
+    }
+
+    /// Registry of the management methods
+    pub fn registry(&self) -> ToolRegistry {
+        ToolRegistry::builder()
+            .with_tool(
+                admin_methods::LIST_SESSIONS,
+                ListSessions {
+                    sessions: self.sessions.clone(),
+                },
+            )
+            .with_tool(
+                admin_methods::CLOSE_SESSION,
+                CloseSession {
+                    sessions: self.sessions.clone(),
+                },
+            )
+            .with_tool(
+                admin_methods::RELOAD_CONFIG,
+                ReloadConfig {
+                    reload: self.reload.clone(),
+                },
+            )
+            .with_tool(
+                admin_methods::DUMP_METRICS,
+                DumpMetrics {
+                    sessions: self.sessions.clone(),
+                    slo: self.slo.clone(),
+                    shadow: self.shadow.clone(),
+                    sizes: self.sizes.clone(),
+                },
+            )
+            .with_tool(
+                admin_methods::SERVER_INFO,
+                ServerInfo {
+                    server_info: self.server_info.clone(),
+                },
+            )
+            .build()
+    }
+
+    /// Create a processor serving the management methods over the given transport
+    pub fn processor<T: Transport>(&self, transport: T) -> JsonRpcProcessor<T> {
+        JsonRpcProcessor::new(transport, self.registry())
+            .with_labels(ConnectionLabels::new().with(labels::LISTENER, "admin"))
+    }
+
+    /// Listen on a Unix socket and serve each admin connection in its own task
+    ///
+    /// Access control relies on the socket file permissions, so the socket
+    /// should be created in a directory only operators can reach.
+    #[cfg(unix)]
+    pub async fn serve(&self, path: impl AsRef<std::path::Path>) -> McpResult<()> {
+        use crate::transport::UnixTransport;
+
+        let listener = UnixTransport::bind(path).await?;
+        loop {
+            let transport = UnixTransport::accept(&listener).await?;
+            let mut processor = self.processor(transport);
+            tokio::spawn(async move { processor.run().await });
+        }
+    }
+}
+
+/// Answers `admin/listSessions`
+struct ListSessions {
+    sessions: SessionRegistry,
+}
+
+#[async_trait]
+impl Tool for ListSessions {
+    async fn execute(&self, _params: Value) -> McpResult<Value> {
+        Ok(json!({ "sessions": self.sessions.list() }))
+    }
+}
+
+#[derive(Deserialize)]
+struct CloseSessionParams {
+    id: u64,
+}
+
+/// Answers `admin/closeSession`
+struct CloseSession {
+    sessions: SessionRegistry,
+}
+
+#[async_trait]
+impl Tool for CloseSession {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let params: CloseSessionParams = parse_params(params)?;
+        let closed = self.sessions.close(params.id)?;
+        Ok(json!({ "closed": closed }))
+    }
+}
+
+/// Answers `admin/reloadConfig`
+struct ReloadConfig {
+    reload: Option<ReloadHandler>,
+}
+
+#[async_trait]
+impl Tool for ReloadConfig {
+    async fn execute(&self, _params: Value) -> McpResult<Value> {
+        match &self.reload {
+            Some(reload) => reload(),
+            None => Err(helpers::config_error("No reload handler is configured")),
+        }
+    }
+}
+
+/// Answers `admin/dumpMetrics`
+struct DumpMetrics {
+    sessions: SessionRegistry,
+    slo: Option<SloTracker>,
+    shadow: Option<ShadowValidation>,
+    sizes: Option<PayloadSizes>,
+}
+
+#[async_trait]
+impl Tool for DumpMetrics {
+    async fn execute(&self, _params: Value) -> McpResult<Value> {
+        let mut metrics = json!({ "sessions": self.sessions.list() });
+        if let Some(slo) = &self.slo {
+            metrics["slo"] = json!(slo.report());
+        }
+        if let Some(shadow) = &self.shadow {
+            metrics["shadowValidation"] = json!(shadow.report());
+        }
+        if let Some(sizes) = &self.sizes {
+            metrics["payloadSizes"] = json!(sizes.report());
+        }
+        Ok(metrics)
+    }
+}
+
+/// Answers `admin/serverInfo`
+struct ServerInfo {
+    server_info: Option<Implementation>,
+}
+
+#[async_trait]
+impl Tool for ServerInfo {
+    async fn execute(&self, _params: Value) -> McpResult<Value> {
+        let adapter = Implementation::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+            .with_build(BuildInfo::adapter());
+        Ok(json!({ "serverInfo": self.server_info, "adapter": adapter }))
+    }
+}
@@ -0,0 +1,164 @@
+//! Forwarding tools
+//!
+//! A [`ForwardingTool`] answers a local method by calling a method on another
+//! JSON-RPC server and returning its result, so servers can be composed from
+//! upstream servers without writing the glue by hand. Requests get fresh ids on
+//! the upstream connection, so clients of the local server can never collide
+//! with the ids used upstream.
+
+use crate::client::managed::{Connector, ManagedClient};
+use crate::client::JsonRpcClient;
+use crate::error::{self, helpers};
+use crate::processor::Tool;
+use crate::transport::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Connection to the server calls are forwarded to
+#[async_trait]
+pub trait Upstream: Send + Sync {
+    /// Call a method upstream and return its result
+    async fn call(&self, method: &str, params: Option<Value>) -> McpResult<Value>;
+}
+
+#[async_trait]
+impl<T: Transport> Upstream for Mutex<JsonRpcClient<T>> {
+    async fn call(&self, method: &str, params: Option<Value>) -> McpResult<Value> {
+        self.lock().await.call(method, params).await
+    }
+}
+
+#[async_trait]
+impl<C: Connector + 'static> Upstream for ManagedClient<C> {
+    async fn call(&self, method: &str, params: Option<Value>) -> McpResult<Value> {
+        ManagedClient::call(self, method, params).await
+    }
+}
+
+/// Timeout and retry policy of a forwarding tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForwardPolicy {
+    /// Maximum time to wait for each upstream attempt (`None` waits forever)
+    pub timeout: Option<Duration>,
+    /// Additional attempts after a timeout or a connection error
+    ///
+    /// Errors returned by the upstream server are never retried. Only enable
+    /// retries for methods that are safe to execute more than once.
+    pub max_retries: u32,
+    /// Delay between attempts
+    pub retry_delay: Duration,
+}
+
+impl Default for ForwardPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            max_retries: 0,
+            retry_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Tool proxying calls to a method of an upstream server
+///
+/// Upstream errors are lifted with
+/// [`json_rpc_to_error`](crate::error::json_rpc_to_error), so they keep their
+/// meaning: invalid params are reported to the client as invalid params and an
+/// overloaded upstream as overloaded, anything else as a tool failure. The
+/// details hold the upstream error object.
+pub struct ForwardingTool {
+    upstream: Arc<dyn Upstream>,
+    method: String,
+    policy: ForwardPolicy,
+    description: Option<String>,
+    input_schema: Option<Value>,
+}
+
+impl ForwardingTool {
+    /// Forward to `method` over a client connection used by this tool only
+    pub fn new<T: Transport + 'static>(
+        client: JsonRpcClient<T>,
+        method: impl Into<String>,
+    ) -> Self {
+        Self::with_upstream(Arc::new(Mutex::new(client)), method)
+    }
+
+    /// Forward to `method` over a shared upstream connection
+    ///
+    /// Share one `Arc<Mutex<JsonRpcClient<_>>>` or [`ManagedClient`] between
+    /// several tools to forward several methods to the same server.
+    pub fn with_upstream(upstream: Arc<dyn Upstream>, method: impl Into<String>) -> Self {
+        Self {
+            upstream,
+            method: method.into(),
+            policy: ForwardPolicy::default(),
+            description: None,
+            input_schema: None,
+        }
+    }
+
+    /// Set the timeout and retry policy
+    pub fn with_policy(mut self, policy: ForwardPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the description advertised for this tool
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the JSON Schema advertised for this tool's params
+    pub fn with_input_schema(mut self, schema: Value) -> Self {
+        self.input_schema = Some(schema);
+        self
+    }
+
+    /// Make one upstream call, bounded by the policy timeout
+    async fn attempt(&self, params: Option<Value>) -> McpResult<Value> {
+        let call = self.upstream.call(&self.method, params);
+        match self.policy.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, call).await.map_err(|_| {
+                helpers::transport_error(&format!(
+                    "Upstream method '{}' did not answer within {:?}",
+                    self.method, timeout
+                ))
+            })?,
+            None => call.await,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for ForwardingTool {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let params = match params {
+            Value::Null => None,
+            params => Some(params),
+        };
+
+        let mut retries = 0;
+        loop {
+            match self.attempt(params.clone()).await {
+                Err(e) if error::is_connection_error(&e) && retries < self.policy.max_retries => {
+                    retries += 1;
+                    tokio::time::sleep(self.policy.retry_delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    fn input_schema(&self) -> Option<Value> {
+        self.input_schema.clone()
+    }
+}
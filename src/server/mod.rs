@@ -0,0 +1,513 @@
+//! MCP server assembly
+//!
+//! [`JsonRpcServerBuilder`] collects tools, resource providers and prompts along
+//! with the capabilities advertised in the `initialize` result, and cross-checks
+//! them when built: registering a handler enables the matching capability, while
+//! declaring a capability that nothing implements is a configuration error. This
+//! keeps the advertisement and the methods actually answered consistent.
+//!
+//! The resulting [`JsonRpcServer`] is cheap to clone and creates one
+//! [`JsonRpcProcessor`] per connection; a [`ServerRuntime`] accepts the
+//! connections of a listener and serves them. [`JsonRpcServer::self_test`] exercises
+//! the registered tools before connections are served, and an [`AdminServer`]
+//! lets operators manage the connections registered in a [`SessionRegistry`].
+//! A [`ForwardingTool`] serves a method by proxying it to an upstream server.
+
+use crate::context::RequestContext;
+use crate::error::{has_dedicated_mapping, helpers};
+use crate::mcp::{
+    methods, BuildInfo, GetPromptResult, Implementation, InitializeResult, LoggingLevel,
+    PromptArgument, PromptInfo, ReadResourceResult, ResourceInfo, ServerCapabilities,
+    PROTOCOL_VERSION,
+};
+use crate::processor::{JsonRpcProcessor, Tool, ToolRegistry};
+use crate::transport::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod admin;
+pub mod forward;
+pub mod runtime;
+pub mod self_test;
+
+pub use admin::{AdminServer, SessionRegistry};
+pub use forward::{ForwardPolicy, ForwardingTool, Upstream};
+pub use runtime::{Listener, ServerRuntime};
+pub use self_test::{SelfTestOutcome, SelfTestReport, SelfTestResult};
+
+/// Source of resources served through `resources/list` and `resources/read`
+#[async_trait]
+pub trait ResourceProvider: Send + Sync {
+    /// Resources advertised in `resources/list`
+    async fn list(&self) -> McpResult<Vec<ResourceInfo>> {
+        Ok(Vec::new())
+    }
+
+    /// Read a resource, returning `None` if the URI is not served by this provider
+    async fn read(&self, uri: &str) -> McpResult<Option<ReadResourceResult>>;
+}
+
+/// Prompt served through `prompts/list` and `prompts/get`
+#[async_trait]
+pub trait Prompt: Send + Sync {
+    /// Description advertised in `prompts/list`
+    fn description(&self) -> Option<String> {
+        None
+    }
+
+    /// Arguments advertised in `prompts/list`
+    fn arguments(&self) -> Vec<PromptArgument> {
+        Vec::new()
+    }
+
+    /// Render the prompt with the given arguments
+    async fn get(&self, arguments: Map<String, Value>) -> McpResult<GetPromptResult>;
+}
+
+/// Callback invoked when the client changes the log level
+pub type LogLevelHandler = Arc<dyn Fn(LoggingLevel) + Send + Sync>;
+
+/// Builder for [`JsonRpcServer`]
+pub struct JsonRpcServerBuilder {
+    server_info: Implementation,
+    instructions: Option<String>,
+    capabilities: ServerCapabilities,
+    tools: ToolRegistry,
+    resources: Vec<Arc<dyn ResourceProvider>>,
+    prompts: HashMap<String, Arc<dyn Prompt>>,
+    log_level: Option<LogLevelHandler>,
+}
+
+impl JsonRpcServerBuilder {
+    /// Create a builder for a server with the given name and version
+    pub fn new(server_info: Implementation) -> Self {
+        Self {
+            server_info,
+            instructions: None,
+            capabilities: ServerCapabilities::default(),
+            tools: ToolRegistry::new(),
+            resources: Vec::new(),
+            prompts: HashMap::new(),
+            log_level: None,
+        }
+    }
+
+    /// Report build metadata in the `serverInfo` of the `initialize` result
+    pub fn with_build_info(mut self, build: BuildInfo) -> Self {
+        self.server_info.build = Some(build);
+        self
+    }
+
+    /// Set usage hints returned to clients in the `initialize` result
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Declare capabilities explicitly
+    ///
+    /// Declared capabilities must be backed by a registered handler; capabilities
+    /// left undeclared are enabled automatically when a handler is registered.
+    pub fn with_capabilities(mut self, capabilities: ServerCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Register a tool with the given name
+    pub fn with_tool<T: Tool + 'static>(mut self, name: &str, tool: T) -> Self {
+        self.tools.insert(name, tool);
+        self
+    }
+
+    /// Use the given registry for tools (replaces previously registered tools)
+    pub fn with_tool_registry(mut self, tools: ToolRegistry) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Register a resource provider (providers are queried in registration order)
+    pub fn with_resource_provider<P: ResourceProvider + 'static>(mut self, provider: P) -> Self {
+        self.resources.push(Arc::new(provider));
+        self
+    }
+
+    /// Register a prompt with the given name
+    pub fn with_prompt<P: Prompt + 'static>(mut self, name: &str, prompt: P) -> Self {
+        self.prompts.insert(name.to_string(), Arc::new(prompt));
+        self
+    }
+
+    /// Handle `logging/setLevel` with the given callback
+    pub fn with_log_level_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(LoggingLevel) + Send + Sync + 'static,
+    {
+        self.log_level = Some(Arc::new(handler));
+        self
+    }
+
+    /// Reconcile the declared capabilities with the registered handlers
+    pub fn build(self) -> McpResult<JsonRpcServer> {
+        let mut capabilities = self.capabilities;
+        reconcile(&mut capabilities.tools, !self.tools.is_empty(), "tools")?;
+        reconcile(
+            &mut capabilities.resources,
+            !self.resources.is_empty(),
+            "resources",
+        )?;
+        reconcile(
+            &mut capabilities.prompts,
+            !self.prompts.is_empty(),
+            "prompts",
+        )?;
+        reconcile(
+            &mut capabilities.logging,
+            self.log_level.is_some(),
+            "logging",
+        )?;
+
+        Ok(JsonRpcServer {
+            initialize: Arc::new(InitializeResult {
+                protocol_version: PROTOCOL_VERSION.to_string(),
+                capabilities,
+                server_info: self.server_info,
+                instructions: self.instructions,
+            }),
+            tools: self.tools,
+            resources: Arc::new(self.resources),
+            prompts: Arc::new(self.prompts),
+            log_level: self.log_level,
+        })
+    }
+}
+
+/// Enable a capability backed by a handler, or reject a declared one that is not
+fn reconcile(capability: &mut Option<Value>, implemented: bool, name: &str) -> McpResult<()> {
+    match (capability.is_some(), implemented) {
+        (true, false) => Err(helpers::config_error(&format!(
+            "The '{}' capability is declared but no handler is registered for it",
+            name
+        ))),
+        (false, true) => {
+            *capability = Some(json!({}));
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// MCP server answering `initialize` and the methods of its capabilities
+#[derive(Clone)]
+pub struct JsonRpcServer {
+    initialize: Arc<InitializeResult>,
+    tools: ToolRegistry,
+    resources: Arc<Vec<Arc<dyn ResourceProvider>>>,
+    prompts: Arc<HashMap<String, Arc<dyn Prompt>>>,
+    log_level: Option<LogLevelHandler>,
+}
+
+impl JsonRpcServer {
+    /// Create a builder for a server with the given name and version
+    pub fn builder(server_info: Implementation) -> JsonRpcServerBuilder {
+        JsonRpcServerBuilder::new(server_info)
+    }
+
+    /// Capabilities advertised in the `initialize` result
+    pub fn capabilities(&self) -> &ServerCapabilities {
+        &self.initialize.capabilities
+    }
+
+    /// Server name and version
+    pub fn server_info(&self) -> &Implementation {
+        &self.initialize.server_info
+    }
+
+    /// Registered tools
+    pub fn tools(&self) -> &ToolRegistry {
+        &self.tools
+    }
+
+    /// Exercise every tool supporting dry runs with params derived from its schema
+    ///
+    /// Run this before serving connections; [`SelfTestReport::ensure_passed`]
+    /// turns failures into an error.
+    pub async fn self_test(&self) -> SelfTestReport {
+        self_test::run(&self.tools).await
+    }
+
+    /// Initialize every tool now instead of on its first call
+    ///
+    /// Run this before serving connections; see [`lifecycle`](crate::processor::lifecycle).
+    pub async fn warm_up(&self) -> McpResult<()> {
+        self.tools.initialize_all().await
+    }
+
+    /// Shut every tool down, giving each `timeout` to finish
+    ///
+    /// Run this once connections are closed, e.g. after closing the sessions
+    /// of a [`SessionRegistry`](admin::SessionRegistry); a [`ServerRuntime`]
+    /// does both when shutting down. Tools are shut down
+    /// in reverse registration order; see [`ToolRegistry::shutdown_all`].
+    pub async fn shutdown(&self, timeout: Duration) -> McpResult<()> {
+        self.tools.shutdown_all(timeout).await
+    }
+
+    /// Create a processor serving this server over the given transport
+    pub fn processor<T: Transport>(&self, transport: T) -> JsonRpcProcessor<T> {
+        let mut processor = JsonRpcProcessor::new(transport, self.tools.clone()).with_method(
+            methods::INITIALIZE,
+            Initialize {
+                result: self.initialize.clone(),
+            },
+        );
+
+        let capabilities = self.capabilities();
+        if capabilities.tools.is_some() {
+            processor = processor.with_method(
+                methods::TOOLS_CALL,
+                CallTool {
+                    tools: self.tools.clone(),
+                },
+            );
+        }
+        if capabilities.resources.is_some() {
+            processor = processor
+                .with_method(
+                    methods::RESOURCES_LIST,
+                    ListResources {
+                        providers: self.resources.clone(),
+                    },
+                )
+                .with_method(
+                    methods::RESOURCES_READ,
+                    ReadResource {
+                        providers: self.resources.clone(),
+                    },
+                );
+        }
+        if capabilities.prompts.is_some() {
+            processor = processor
+                .with_method(
+                    methods::PROMPTS_LIST,
+                    ListPrompts {
+                        prompts: self.prompts.clone(),
+                    },
+                )
+                .with_method(
+                    methods::PROMPTS_GET,
+                    GetPrompt {
+                        prompts: self.prompts.clone(),
+                    },
+                );
+        }
+        if let Some(handler) = &self.log_level {
+            processor = processor.with_method(
+                methods::LOGGING_SET_LEVEL,
+                SetLogLevel {
+                    handler: handler.clone(),
+                },
+            );
+        }
+        processor
+    }
+}
+
+fn parse_params<P: DeserializeOwned>(params: Value) -> McpResult<P> {
+    serde_json::from_value(params)
+        .map_err(|e| helpers::invalid_params(&format!("Invalid params: {}", e)))
+}
+
+/// Answers `initialize` with the server's fixed result
+struct Initialize {
+    result: Arc<InitializeResult>,
+}
+
+#[async_trait]
+impl Tool for Initialize {
+    async fn execute(&self, _params: Value) -> McpResult<Value> {
+        serde_json::to_value(self.result.as_ref()).map_err(helpers::json_error)
+    }
+}
+
+#[derive(Deserialize)]
+struct CallToolParams {
+    name: String,
+    #[serde(default)]
+    arguments: Option<Value>,
+}
+
+/// Answers `tools/call` by dispatching to the tool registry
+///
+/// Tool failures are reported in the result with `isError` set, except errors
+/// with a dedicated JSON-RPC code (such as invalid params), which stay errors.
+struct CallTool {
+    tools: ToolRegistry,
+}
+
+#[async_trait]
+impl Tool for CallTool {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        self.execute_with_context(params, &RequestContext::default())
+            .await
+    }
+
+    async fn execute_with_context(
+        &self,
+        params: Value,
+        context: &RequestContext,
+    ) -> McpResult<Value> {
+        let params: CallToolParams = parse_params(params)?;
+        let tool = self
+            .tools
+            .get(&params.name)
+            .ok_or_else(|| helpers::invalid_params(&format!("Unknown tool '{}'", params.name)))?;
+
+        let arguments = params.arguments.unwrap_or_else(|| json!({}));
+        let executed = match self.tools.initialize(&params.name).await {
+            Ok(()) => tool.execute_with_context(arguments, context).await,
+            Err(e) => Err(e),
+        };
+        match executed {
+            Ok(value) => Ok(call_tool_result(value)),
+            Err(e) if has_dedicated_mapping(&e) => Err(e),
+            Err(e) => Ok(json!({
+                "content": [{ "type": "text", "text": e.to_string() }],
+                "isError": true,
+            })),
+        }
+    }
+}
+
+/// Shape a tool's return value as a `tools/call` result
+///
+/// Values already carrying `content` are passed through; anything else is
+/// rendered as text, with objects also returned as structured content.
+fn call_tool_result(value: Value) -> Value {
+    if value.get("content").is_some() {
+        return value;
+    }
+
+    let text = match &value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let mut result = json!({ "content": [{ "type": "text", "text": text }] });
+    if value.is_object() {
+        result["structuredContent"] = value;
+    }
+    result
+}
+
+/// Answers `resources/list` with the resources of every provider
+struct ListResources {
+    providers: Arc<Vec<Arc<dyn ResourceProvider>>>,
+}
+
+#[async_trait]
+impl Tool for ListResources {
+    async fn execute(&self, _params: Value) -> McpResult<Value> {
+        let mut resources = Vec::new();
+        for provider in self.providers.iter() {
+            resources.extend(provider.list().await?);
+        }
+        Ok(json!({ "resources": resources }))
+    }
+}
+
+#[derive(Deserialize)]
+struct ReadResourceParams {
+    uri: String,
+}
+
+/// Answers `resources/read` with the first provider serving the URI
+struct ReadResource {
+    providers: Arc<Vec<Arc<dyn ResourceProvider>>>,
+}
+
+#[async_trait]
+impl Tool for ReadResource {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let params: ReadResourceParams = parse_params(params)?;
+        for provider in self.providers.iter() {
+            if let Some(result) = provider.read(&params.uri).await? {
+                return serde_json::to_value(result).map_err(helpers::json_error);
+            }
+        }
+        Err(helpers::invalid_params(&format!(
+            "Unknown resource '{}'",
+            params.uri
+        )))
+    }
+}
+
+/// Answers `prompts/list`, sorted by name
+struct ListPrompts {
+    prompts: Arc<HashMap<String, Arc<dyn Prompt>>>,
+}
+
+#[async_trait]
+impl Tool for ListPrompts {
+    async fn execute(&self, _params: Value) -> McpResult<Value> {
+        let mut prompts: Vec<PromptInfo> = self
+            .prompts
+            .iter()
+            .map(|(name, prompt)| PromptInfo {
+                name: name.clone(),
+                description: prompt.description(),
+                arguments: prompt.arguments(),
+            })
+            .collect();
+        prompts.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(json!({ "prompts": prompts }))
+    }
+}
+
+#[derive(Deserialize)]
+struct GetPromptParams {
+    name: String,
+    #[serde(default)]
+    arguments: Map<String, Value>,
+}
+
+/// Answers `prompts/get`
+struct GetPrompt {
+    prompts: Arc<HashMap<String, Arc<dyn Prompt>>>,
+}
+
+#[async_trait]
+impl Tool for GetPrompt {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let params: GetPromptParams = parse_params(params)?;
+        let prompt = self
+            .prompts
+            .get(&params.name)
+            .ok_or_else(|| helpers::invalid_params(&format!("Unknown prompt '{}'", params.name)))?;
+        let result = prompt.get(params.arguments).await?;
+        serde_json::to_value(result).map_err(helpers::json_error)
+    }
+}
+
+#[derive(Deserialize)]
+struct SetLogLevelParams {
+    level: LoggingLevel,
+}
+
+/// Answers `logging/setLevel`
+struct SetLogLevel {
+    handler: LogLevelHandler,
+}
+
+#[async_trait]
+impl Tool for SetLogLevel {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let params: SetLogLevelParams = parse_params(params)?;
+        (self.handler)(params.level);
+        Ok(json!({}))
+    }
+}
@@ -0,0 +1,260 @@
+//! Serving connections
+//!
+//! [`TcpTransport::bind`](crate::transport::TcpTransport::bind) only returns a
+//! listener. A [`ServerRuntime`] accepts connections from it and serves each
+//! with a processor of its own, every processor sharing the tools of one
+//! [`JsonRpcServer`]:
+//!
+//! ```rust,ignore
+//! let listener = TcpTransport::bind(([0, 0, 0, 0], 9000)).await?;
+//! server
+//!     .runtime(listener)
+//!     .with_name("public")
+//!     .with_drain_timeout(Duration::from_secs(10))
+//!     .run_until(async {
+//!         let _ = tokio::signal::ctrl_c().await;
+//!     })
+//!     .await?;
+//! ```
+//!
+//! Connections are registered in a [`SessionRegistry`], which can be shared
+//! with an [`AdminServer`](super::AdminServer) so operators see and close them.
+//! Once the shutdown future completes, the runtime stops accepting, closes
+//! every connection gracefully, and shuts the tools down.
+
+use super::{JsonRpcServer, SessionRegistry};
+use crate::context::{labels, ConnectionLabels};
+use crate::error::helpers;
+use crate::processor::JsonRpcProcessor;
+use crate::transport::{BoxedTransport, JsonRpcTransport, TcpListeners, Transport};
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Time given to connections and tools to finish once shutting down, by default
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Delay before accepting again after an accept failed, e.g. for lack of
+/// file descriptors
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Transport a runtime creates for each accepted stream
+pub type StreamTransport<S> = JsonRpcTransport<ReadHalf<S>, WriteHalf<S>>;
+
+type TransportSetup<S> = Arc<dyn Fn(StreamTransport<S>) -> BoxedTransport + Send + Sync>;
+
+type ProcessorSetup =
+    Arc<dyn Fn(JsonRpcProcessor<BoxedTransport>) -> JsonRpcProcessor<BoxedTransport> + Send + Sync>;
+
+/// Source of the connections served by a [`ServerRuntime`]
+///
+/// Accepting must be cancel safe, as it is raced against the shutdown future.
+#[async_trait]
+pub trait Listener: Send + Sync {
+    /// Stream of an accepted connection
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// Accept the next connection, with labels describing the peer
+    async fn accept(&self) -> McpResult<(Self::Stream, ConnectionLabels)>;
+}
+
+#[async_trait]
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> McpResult<(TcpStream, ConnectionLabels)> {
+        let (stream, peer) = TcpListener::accept(self)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to accept: {}", e)))?;
+        Ok((stream, peer_labels(peer)))
+    }
+}
+
+#[async_trait]
+impl Listener for TcpListeners {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> McpResult<(TcpStream, ConnectionLabels)> {
+        let (stream, peer) = TcpListeners::accept(self).await?;
+        Ok((stream, peer_labels(peer)))
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl Listener for tokio::net::UnixListener {
+    type Stream = tokio::net::UnixStream;
+
+    async fn accept(&self) -> McpResult<(Self::Stream, ConnectionLabels)> {
+        let (stream, _) = tokio::net::UnixListener::accept(self)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to accept: {}", e)))?;
+        // Clients rarely bind their end, so their credentials tell more than
+        // their address
+        let connection = match crate::transport::PeerCredentials::of(&stream) {
+            Some(peer) => peer.connection_labels(),
+            None => ConnectionLabels::new(),
+        };
+        Ok((stream, connection))
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "vsock"))]
+#[async_trait]
+impl Listener for crate::transport::VsockListener {
+    type Stream = crate::transport::VsockStream;
+
+    async fn accept(&self) -> McpResult<(Self::Stream, ConnectionLabels)> {
+        let (stream, peer) = crate::transport::VsockListener::accept(self)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to accept: {}", e)))?;
+        Ok((
+            stream,
+            ConnectionLabels::new().with(labels::PEER, peer.to_string()),
+        ))
+    }
+}
+
+fn peer_labels(peer: SocketAddr) -> ConnectionLabels {
+    ConnectionLabels::new().with(labels::PEER, peer.to_string())
+}
+
+/// Accept loop serving each connection of a listener with a processor
+///
+/// Each processor is created by [`JsonRpcServer::processor`] and labelled with
+/// the labels of its listener, such as the peer address or credentials, and
+/// the listener name if set. Connections are served until the
+/// peer closes them, an operator closes them through the session registry, or
+/// the runtime shuts down.
+pub struct ServerRuntime<L: Listener> {
+    server: JsonRpcServer,
+    listener: L,
+    sessions: SessionRegistry,
+    name: Option<String>,
+    drain_timeout: Duration,
+    transport: Option<TransportSetup<L::Stream>>,
+    processor: Option<ProcessorSetup>,
+}
+
+impl JsonRpcServer {
+    /// Create a runtime serving the connections accepted by `listener`
+    pub fn runtime<L: Listener>(&self, listener: L) -> ServerRuntime<L> {
+        ServerRuntime {
+            server: self.clone(),
+            listener,
+            sessions: SessionRegistry::new(),
+            name: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            transport: None,
+            processor: None,
+        }
+    }
+}
+
+impl<L: Listener> ServerRuntime<L> {
+    /// Label connections with the given [`LISTENER`](labels::LISTENER) name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Register connections in the given registry, e.g. one shared with an
+    /// [`AdminServer`](super::AdminServer)
+    pub fn with_sessions(mut self, sessions: SessionRegistry) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// Give connections, then tools, up to `timeout` each to finish when
+    /// shutting down
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Configure or wrap the transport of each connection, e.g. to set its
+    /// framing or add heartbeats
+    pub fn with_transport<F, T>(mut self, setup: F) -> Self
+    where
+        F: Fn(StreamTransport<L::Stream>) -> T + Send + Sync + 'static,
+        T: Transport + 'static,
+    {
+        self.transport = Some(Arc::new(move |transport| setup(transport).boxed()));
+        self
+    }
+
+    /// Configure the processor of each connection
+    pub fn with_processor<F>(mut self, setup: F) -> Self
+    where
+        F: Fn(JsonRpcProcessor<BoxedTransport>) -> JsonRpcProcessor<BoxedTransport>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.processor = Some(Arc::new(setup));
+        self
+    }
+
+    /// Registry of the connections being served
+    pub fn sessions(&self) -> &SessionRegistry {
+        &self.sessions
+    }
+
+    /// Serve connections until the process ends
+    pub async fn run(self) -> McpResult<()> {
+        self.run_until(std::future::pending()).await
+    }
+
+    /// Serve connections until `shutdown` completes, then shut down
+    ///
+    /// Shutting down stops accepting, closes every registered connection
+    /// gracefully and waits for them up to the drain timeout, aborting those
+    /// still running. The server's tools are shut down last; their shutdown
+    /// error, if any, is returned.
+    pub async fn run_until<F>(self, shutdown: F) -> McpResult<()>
+    where
+        F: Future<Output = ()> + Send,
+    {
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                accepted = self.listener.accept() => match accepted {
+                    Ok((stream, connection)) => self.start(stream, connection),
+                    Err(_) => tokio::select! {
+                        _ = &mut shutdown => break,
+                        _ = tokio::time::sleep(ACCEPT_BACKOFF) => {}
+                    },
+                },
+            }
+        }
+
+        self.sessions.shutdown(self.drain_timeout).await;
+        self.server.shutdown(self.drain_timeout).await
+    }
+
+    /// Serve an accepted connection in a task of its own
+    fn start(&self, stream: L::Stream, mut connection: ConnectionLabels) {
+        let transport = JsonRpcTransport::new(stream);
+        let transport = match &self.transport {
+            Some(setup) => setup(transport),
+            None => transport.boxed(),
+        };
+
+        if let Some(name) = &self.name {
+            connection = connection.with(labels::LISTENER, name.clone());
+        }
+        let mut processor = self.server.processor(transport).with_labels(connection);
+        if let Some(setup) = &self.processor {
+            processor = setup(processor);
+        }
+
+        self.sessions.prune();
+        self.sessions.spawn(processor);
+    }
+}
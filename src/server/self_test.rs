@@ -0,0 +1,223 @@
+//! Startup self-test
+//!
+//! Exercises registered tools with params derived from their input schemas, in
+//! a dry-run [`RequestContext`], so broken tools are reported before the server
+//! starts accepting connections. Only tools opting in through
+//! [`Tool::supports_dry_run`] are called; the others are reported as skipped.
+
+use crate::context::RequestContext;
+use crate::error::helpers;
+use crate::processor::{Tool, ToolRegistry};
+use mcp_error::Result as McpResult;
+use serde_json::{json, Map, Value};
+
+/// Maximum nesting followed when deriving sample params from a schema
+const MAX_SAMPLE_DEPTH: usize = 16;
+
+/// Outcome of exercising a single tool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelfTestOutcome {
+    /// The tool succeeded with the sample params
+    Passed,
+    /// The tool does not support dry runs and was not called
+    Skipped,
+    /// The tool failed with the given error
+    Failed(String),
+}
+
+/// Self-test result for a single tool
+#[derive(Debug, Clone)]
+pub struct SelfTestResult {
+    /// Name the tool is registered under
+    pub tool: String,
+    /// Sample params the tool was called with
+    pub params: Value,
+    /// What happened
+    pub outcome: SelfTestOutcome,
+}
+
+/// Results of a self-test run, sorted by tool name
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+    /// One result per registered tool
+    pub results: Vec<SelfTestResult>,
+}
+
+impl SelfTestReport {
+    /// Whether no tool failed
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Results of the tools that failed
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestResult> {
+        self.results
+            .iter()
+            .filter(|result| matches!(result.outcome, SelfTestOutcome::Failed(_)))
+    }
+
+    /// Turn failures into a configuration error listing every failing tool
+    pub fn ensure_passed(&self) -> McpResult<()> {
+        let failures: Vec<String> = self
+            .failures()
+            .map(|result| match &result.outcome {
+                SelfTestOutcome::Failed(error) => format!("{}: {}", result.tool, error),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(helpers::config_error(&format!(
+                "Self-test failed for {} tool(s): {}",
+                failures.len(),
+                failures.join("; ")
+            )))
+        }
+    }
+}
+
+/// Exercise every tool of the registry that supports dry runs
+pub(crate) async fn run(tools: &ToolRegistry) -> SelfTestReport {
+    let mut results = Vec::new();
+    for info in tools.list() {
+        let tool = match tools.get(&info.name) {
+            Some(tool) => tool,
+            None => continue,
+        };
+        let params = sample_params(&info.input_schema);
+        let outcome = exercise(tools, tool.as_ref(), &info.name, params.clone()).await;
+        results.push(SelfTestResult {
+            tool: info.name,
+            params,
+            outcome,
+        });
+    }
+    SelfTestReport { results }
+}
+
+async fn exercise(
+    tools: &ToolRegistry,
+    tool: &dyn Tool,
+    name: &str,
+    params: Value,
+) -> SelfTestOutcome {
+    if !tool.supports_dry_run() {
+        return SelfTestOutcome::Skipped;
+    }
+    if let Err(e) = tools.initialize(name).await {
+        return SelfTestOutcome::Failed(format!("Initialization failed: {}", e));
+    }
+
+    let context = RequestContext::new(name, None).with_dry_run(true);
+    match tool.execute_with_context(params, &context).await {
+        Ok(_) => SelfTestOutcome::Passed,
+        Err(e) => SelfTestOutcome::Failed(e.to_string()),
+    }
+}
+
+/// Derive sample params from a JSON Schema
+///
+/// Uses `default`, `examples`, `const` and `enum` values when present and falls
+/// back to the smallest value of the declared type. Only required properties
+/// (and those with a default) are filled in.
+pub fn sample_params(schema: &Value) -> Value {
+    sample_value(schema, schema, 0)
+}
+
+fn sample_value(schema: &Value, root: &Value, depth: usize) -> Value {
+    if depth > MAX_SAMPLE_DEPTH {
+        return Value::Null;
+    }
+
+    for key in ["default", "const"] {
+        if let Some(value) = schema.get(key) {
+            return value.clone();
+        }
+    }
+    for key in ["examples", "enum"] {
+        if let Some(value) = schema.get(key).and_then(|values| values.get(0)) {
+            return value.clone();
+        }
+    }
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return match reference
+            .strip_prefix('#')
+            .and_then(|path| root.pointer(path))
+        {
+            Some(target) => sample_value(target, root, depth + 1),
+            None => Value::Null,
+        };
+    }
+    for key in ["allOf", "anyOf", "oneOf"] {
+        if let Some(first) = schema.get(key).and_then(|schemas| schemas.get(0)) {
+            return sample_value(first, root, depth + 1);
+        }
+    }
+
+    let kind = match schema.get("type") {
+        Some(Value::String(kind)) => kind.as_str(),
+        Some(Value::Array(kinds)) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|kind| *kind != "null")
+            .unwrap_or("null"),
+        _ if schema.get("properties").is_some() => "object",
+        _ => "null",
+    };
+
+    match kind {
+        "object" => sample_object(schema, root, depth),
+        "array" => sample_array(schema, root, depth),
+        "string" => {
+            let min_length = schema.get("minLength").and_then(Value::as_u64).unwrap_or(0);
+            json!("x".repeat(min_length as usize))
+        }
+        "integer" => json!(schema.get("minimum").and_then(Value::as_i64).unwrap_or(0)),
+        "number" => json!(schema.get("minimum").and_then(Value::as_f64).unwrap_or(0.0)),
+        "boolean" => json!(false),
+        _ => Value::Null,
+    }
+}
+
+fn sample_object(schema: &Value, root: &Value, depth: usize) -> Value {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let mut object = Map::new();
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (name, property) in properties {
+            if required.contains(&name.as_str()) || property.get("default").is_some() {
+                object.insert(name.clone(), sample_value(property, root, depth + 1));
+            }
+        }
+    }
+    Value::Object(object)
+}
+
+fn sample_array(schema: &Value, root: &Value, depth: usize) -> Value {
+    let mut items: Vec<Value> = schema
+        .get("prefixItems")
+        .and_then(Value::as_array)
+        .map(|prefix| {
+            prefix
+                .iter()
+                .map(|item| sample_value(item, root, depth + 1))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let min_items = schema.get("minItems").and_then(Value::as_u64).unwrap_or(0) as usize;
+    while items.len() < min_items {
+        let item = schema
+            .get("items")
+            .map(|item| sample_value(item, root, depth + 1))
+            .unwrap_or(Value::Null);
+        items.push(item);
+    }
+    Value::Array(items)
+}
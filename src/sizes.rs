@@ -0,0 +1,193 @@
+//! Per-method payload sizes
+//!
+//! [`PayloadSizes`] keeps a size histogram of the requests and responses of
+//! each method, and optionally the N largest payloads seen, so operators can
+//! find which methods dominate bandwidth and memory. Sizes are those of the
+//! compact JSON serialization of each request and response; a batch counts as
+//! its individual elements. Only methods the processor serves are tracked, so
+//! clients cannot grow the report by calling made-up methods.
+
+use crate::history::Direction;
+use crate::protocol::JsonRpcId;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Upper bounds of the histogram buckets, in bytes
+///
+/// A last, unbounded bucket counts larger payloads.
+pub const SIZE_BUCKETS: &[usize] = &[256, 1024, 4096, 16384, 65536, 262_144, 1_048_576];
+
+/// Distribution of payload sizes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeHistogram {
+    /// Payloads observed
+    pub count: u64,
+    /// Total size of the payloads, in bytes
+    pub total_bytes: u64,
+    /// Largest payload, in bytes
+    pub max_bytes: usize,
+    /// Payloads per bucket: `buckets[i]` counts sizes up to `SIZE_BUCKETS[i]`
+    /// (and above the previous bound); the last entry counts larger sizes
+    pub buckets: Vec<u64>,
+}
+
+impl Default for SizeHistogram {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            total_bytes: 0,
+            max_bytes: 0,
+            buckets: vec![0; SIZE_BUCKETS.len() + 1],
+        }
+    }
+}
+
+impl SizeHistogram {
+    fn observe(&mut self, bytes: usize) {
+        self.count += 1;
+        self.total_bytes += bytes as u64;
+        self.max_bytes = self.max_bytes.max(bytes);
+        let bucket = SIZE_BUCKETS
+            .iter()
+            .position(|bound| bytes <= *bound)
+            .unwrap_or(SIZE_BUCKETS.len());
+        self.buckets[bucket] += 1;
+    }
+}
+
+/// Request and response sizes of a method
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodSizes {
+    /// Method name
+    pub method: String,
+    /// Sizes of the incoming requests and notifications
+    pub requests: SizeHistogram,
+    /// Sizes of the responses
+    pub responses: SizeHistogram,
+}
+
+/// One of the largest payloads seen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LargePayload {
+    /// Method the payload belongs to
+    pub method: String,
+    /// Whether the payload was a request or a response
+    pub direction: Direction,
+    /// Size, in bytes
+    pub bytes: usize,
+    /// Id of the request, when it had one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<JsonRpcId>,
+    /// Milliseconds since the Unix epoch when the payload was seen
+    pub timestamp_ms: u64,
+}
+
+/// Snapshot of the tracked sizes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeReport {
+    /// Sizes per method, sorted by method name
+    pub methods: Vec<MethodSizes>,
+    /// Largest payloads, largest first
+    pub largest: Vec<LargePayload>,
+}
+
+#[derive(Default)]
+struct SizesInner {
+    methods: BTreeMap<String, MethodSizes>,
+    largest: Vec<LargePayload>,
+}
+
+/// Shared tracker of payload sizes
+///
+/// Clones share the same data, so one tracker can be given to every processor
+/// and another kept for introspection.
+#[derive(Clone, Default)]
+pub struct PayloadSizes {
+    inner: Arc<Mutex<SizesInner>>,
+    top_n: usize,
+}
+
+impl PayloadSizes {
+    /// Create a tracker keeping histograms only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also keep the `n` largest payloads
+    pub fn with_top_n(mut self, n: usize) -> Self {
+        self.top_n = n;
+        self
+    }
+
+    /// Record the size of a payload of the given method
+    pub fn record(&self, method: &str, direction: Direction, bytes: usize, id: Option<&JsonRpcId>) {
+        let mut inner = self.lock();
+        let sizes = inner
+            .methods
+            .entry(method.to_string())
+            .or_insert_with(|| MethodSizes {
+                method: method.to_string(),
+                ..MethodSizes::default()
+            });
+        match direction {
+            Direction::Inbound => sizes.requests.observe(bytes),
+            Direction::Outbound => sizes.responses.observe(bytes),
+        }
+
+        if self.top_n == 0 {
+            return;
+        }
+        let full = inner.largest.len() >= self.top_n;
+        if full
+            && inner
+                .largest
+                .last()
+                .is_some_and(|smallest| smallest.bytes >= bytes)
+        {
+            return;
+        }
+        let position = inner.largest.partition_point(|large| large.bytes >= bytes);
+        inner.largest.insert(
+            position,
+            LargePayload {
+                method: method.to_string(),
+                direction,
+                bytes,
+                id: id.cloned(),
+                timestamp_ms: now_ms(),
+            },
+        );
+        inner.largest.truncate(self.top_n);
+    }
+
+    /// Snapshot of the sizes recorded so far
+    pub fn report(&self) -> SizeReport {
+        let inner = self.lock();
+        SizeReport {
+            methods: inner.methods.values().cloned().collect(),
+            largest: inner.largest.clone(),
+        }
+    }
+
+    /// Forget everything recorded so far
+    pub fn reset(&self) {
+        *self.lock() = SizesInner::default();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SizesInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,480 @@
+//! Per-method service level objectives and error budgets
+//!
+//! A call counts against the error budget when it fails or takes longer than the
+//! method's latency target. The budget burn is the observed bad-call ratio over a
+//! sliding window divided by the allowed ratio (`1 - success_rate`): a burn of 1.0
+//! means the budget is exactly used up. When a [`SheddingPolicy`] is configured,
+//! methods with an exhausted budget are rejected until the window recovers.
+//!
+//! Each method keeps fixed-size time buckets (call counts and a latency
+//! histogram), so memory does not grow with the call rate, and only a bounded
+//! number of method names is tracked apart.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Target a method is expected to meet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SloTarget {
+    /// Fraction of calls that must be good, e.g. `0.99`
+    pub success_rate: f64,
+    /// Calls slower than this count as bad
+    pub latency: Duration,
+    /// Sliding window over which the budget is computed
+    pub window: Duration,
+}
+
+impl Default for SloTarget {
+    fn default() -> Self {
+        Self {
+            success_rate: 0.99,
+            latency: Duration::from_secs(1),
+            window: Duration::from_secs(300),
+        }
+    }
+}
+
+/// What to do when a method's error budget is exhausted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SheddingPolicy {
+    /// Only report budget burn
+    #[default]
+    Never,
+    /// Reject calls to the method while its budget is exhausted, once at least
+    /// `min_samples` calls have been observed in the window
+    RejectWhenExhausted { min_samples: usize },
+}
+
+/// Current SLO status of a method
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SloStatus {
+    /// Method name, or [`OTHER_METHOD`] for methods past the tracking limit
+    pub method: String,
+    /// Calls observed in the window
+    pub total: usize,
+    /// Good calls observed in the window
+    pub good: usize,
+    /// Fraction of bad calls in the window
+    pub error_ratio: f64,
+    /// Fraction of the error budget consumed (1.0 = exhausted)
+    pub budget_burn: f64,
+    /// Whether the budget is exhausted
+    pub exhausted: bool,
+    /// Upper bound of the 99th percentile latency in the window, in
+    /// milliseconds, from the bounds of [`LATENCY_BOUNDS_MS`]; `None` without
+    /// calls
+    pub latency_p99_ms: Option<u64>,
+}
+
+/// Number of time buckets a window is divided into
+///
+/// Calls leave the window one bucket (`window / 60`) at a time.
+pub const WINDOW_BUCKETS: usize = 60;
+
+/// Upper bounds, in milliseconds, of the latency histogram kept per bucket
+///
+/// Slower calls fall into a last, unbounded slot, reported with the slowest
+/// latency observed.
+pub const LATENCY_BOUNDS_MS: &[u64] = &[
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 30_000, 60_000,
+];
+
+const LATENCY_SLOTS: usize = LATENCY_BOUNDS_MS.len() + 1;
+
+/// Default number of methods tracked besides those with a target of their own
+pub const DEFAULT_MAX_METHODS: usize = 256;
+
+/// Name under which calls to methods past the tracking limit are grouped
+pub const OTHER_METHOD: &str = "_OTHER";
+
+/// Calls recorded during one slice of the window
+#[derive(Clone, Copy, Default)]
+struct Bucket {
+    /// Slice of time the counts belong to, starting at 1 (0 is unused)
+    epoch: u64,
+    total: u64,
+    good: u64,
+    latencies: [u64; LATENCY_SLOTS],
+    max_latency_ms: u64,
+}
+
+/// Ring of buckets covering the window of a method
+struct MethodWindow {
+    buckets: Vec<Bucket>,
+}
+
+impl MethodWindow {
+    fn new() -> Self {
+        Self {
+            buckets: vec![Bucket::default(); WINDOW_BUCKETS],
+        }
+    }
+
+    fn record(&mut self, epoch: u64, good: bool, latency: Duration) {
+        let bucket = &mut self.buckets[(epoch % WINDOW_BUCKETS as u64) as usize];
+        if bucket.epoch != epoch {
+            *bucket = Bucket {
+                epoch,
+                ..Bucket::default()
+            };
+        }
+
+        let latency_ms = latency.as_millis().min(u64::MAX as u128) as u64;
+        let slot = LATENCY_BOUNDS_MS
+            .iter()
+            .position(|&bound| latency_ms <= bound)
+            .unwrap_or(LATENCY_BOUNDS_MS.len());
+        bucket.total += 1;
+        bucket.good += good as u64;
+        bucket.latencies[slot] += 1;
+        bucket.max_latency_ms = bucket.max_latency_ms.max(latency_ms);
+    }
+
+    fn status(&self, method: &str, target: &SloTarget, epoch: u64) -> SloStatus {
+        let mut total = 0;
+        let mut good = 0;
+        let mut latencies = [0; LATENCY_SLOTS];
+        let mut max_latency_ms = 0;
+        for bucket in self
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.epoch + WINDOW_BUCKETS as u64 > epoch)
+        {
+            total += bucket.total;
+            good += bucket.good;
+            for (sum, count) in latencies.iter_mut().zip(bucket.latencies) {
+                *sum += count;
+            }
+            max_latency_ms = max_latency_ms.max(bucket.max_latency_ms);
+        }
+
+        let error_ratio = if total == 0 {
+            0.0
+        } else {
+            (total - good) as f64 / total as f64
+        };
+        let allowed = (1.0 - target.success_rate).max(f64::EPSILON);
+        let budget_burn = error_ratio / allowed;
+
+        SloStatus {
+            method: method.to_string(),
+            total: total as usize,
+            good: good as usize,
+            error_ratio,
+            budget_burn,
+            exhausted: budget_burn >= 1.0,
+            latency_p99_ms: percentile(&latencies, total, 0.99, max_latency_ms),
+        }
+    }
+}
+
+/// Upper bound of the histogram slot holding the given percentile
+fn percentile(latencies: &[u64], total: u64, quantile: f64, max_latency_ms: u64) -> Option<u64> {
+    if total == 0 {
+        return None;
+    }
+    let rank = ((total as f64 * quantile).ceil() as u64).max(1);
+    let mut seen = 0;
+    for (slot, count) in latencies.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return Some(
+                LATENCY_BOUNDS_MS
+                    .get(slot)
+                    .copied()
+                    .unwrap_or(max_latency_ms),
+            );
+        }
+    }
+    Some(max_latency_ms)
+}
+
+struct SloInner {
+    targets: HashMap<String, SloTarget>,
+    default_target: Option<SloTarget>,
+    policy: SheddingPolicy,
+    max_methods: usize,
+    methods: HashMap<String, MethodWindow>,
+}
+
+impl SloInner {
+    fn target(&self, method: &str) -> Option<SloTarget> {
+        self.targets.get(method).copied().or(self.default_target)
+    }
+
+    /// Name the calls of `method` are tracked under
+    ///
+    /// Method names come from the peer, so only the first `max_methods`
+    /// without a target of their own are tracked apart.
+    fn key<'a>(&self, method: &'a str) -> &'a str {
+        if self.targets.contains_key(method)
+            || self.methods.contains_key(method)
+            || self.methods.len() < self.max_methods
+        {
+            method
+        } else {
+            OTHER_METHOD
+        }
+    }
+}
+
+/// Shared tracker of per-method SLOs
+///
+/// Clones share state, so a clone kept by the embedder sees the same numbers the
+/// processor records. Memory is bounded: each method keeps [`WINDOW_BUCKETS`]
+/// counters whatever the call rate, and methods past the tracking limit (see
+/// [`with_max_methods`](Self::with_max_methods)) share the [`OTHER_METHOD`]
+/// counters.
+#[derive(Clone)]
+pub struct SloTracker {
+    inner: Arc<Mutex<SloInner>>,
+    origin: Instant,
+}
+
+impl SloTracker {
+    /// Create a tracker with no targets (nothing is tracked until targets are set)
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(SloInner {
+                targets: HashMap::new(),
+                default_target: None,
+                policy: SheddingPolicy::Never,
+                max_methods: DEFAULT_MAX_METHODS,
+                methods: HashMap::new(),
+            })),
+            origin: Instant::now(),
+        }
+    }
+
+    /// Set the target for a specific method
+    pub fn with_target(self, method: &str, target: SloTarget) -> Self {
+        self.inner
+            .lock()
+            .unwrap()
+            .targets
+            .insert(method.to_string(), target);
+        self
+    }
+
+    /// Set the target applied to methods without a specific one
+    pub fn with_default_target(self, target: SloTarget) -> Self {
+        self.inner.lock().unwrap().default_target = Some(target);
+        self
+    }
+
+    /// Set the load-shedding policy
+    pub fn with_policy(self, policy: SheddingPolicy) -> Self {
+        self.inner.lock().unwrap().policy = policy;
+        self
+    }
+
+    /// Set how many methods without a target of their own are tracked apart
+    /// (default [`DEFAULT_MAX_METHODS`]); calls to further methods are grouped
+    /// under [`OTHER_METHOD`]
+    pub fn with_max_methods(self, max_methods: usize) -> Self {
+        self.inner.lock().unwrap().max_methods = max_methods;
+        self
+    }
+
+    /// Record the outcome of a call
+    pub fn record(&self, method: &str, success: bool, latency: Duration) {
+        self.record_at(method, success, latency, Instant::now());
+    }
+
+    fn record_at(&self, method: &str, success: bool, latency: Duration, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        let target = match inner.target(method) {
+            Some(target) => target,
+            None => return,
+        };
+
+        let epoch = self.epoch(&target, now);
+        let key = inner.key(method).to_string();
+        inner
+            .methods
+            .entry(key)
+            .or_insert_with(MethodWindow::new)
+            .record(epoch, success && latency <= target.latency, latency);
+    }
+
+    /// Current status of a tracked method
+    ///
+    /// A method past the tracking limit reports the [`OTHER_METHOD`] status.
+    pub fn status(&self, method: &str) -> Option<SloStatus> {
+        self.status_at(method, Instant::now())
+    }
+
+    fn status_at(&self, method: &str, now: Instant) -> Option<SloStatus> {
+        let inner = self.inner.lock().unwrap();
+        let target = inner.target(method)?;
+        let key = inner.key(method);
+        let epoch = self.epoch(&target, now);
+        Some(match inner.methods.get(key) {
+            Some(window) => window.status(key, &target, epoch),
+            None => MethodWindow::new().status(key, &target, epoch),
+        })
+    }
+
+    /// Status of every method observed so far, sorted by method name
+    pub fn report(&self) -> Vec<SloStatus> {
+        let methods: Vec<String> = {
+            let inner = self.inner.lock().unwrap();
+            inner.methods.keys().cloned().collect()
+        };
+        let mut report: Vec<SloStatus> = methods.iter().filter_map(|m| self.status(m)).collect();
+        report.sort_by(|a, b| a.method.cmp(&b.method));
+        report
+    }
+
+    /// Whether calls to the method should be rejected under the shedding policy
+    pub fn should_shed(&self, method: &str) -> bool {
+        self.should_shed_at(method, Instant::now())
+    }
+
+    fn should_shed_at(&self, method: &str, now: Instant) -> bool {
+        let min_samples = match self.inner.lock().unwrap().policy {
+            SheddingPolicy::Never => return false,
+            SheddingPolicy::RejectWhenExhausted { min_samples } => min_samples,
+        };
+
+        self.status_at(method, now)
+            .map(|status| status.exhausted && status.total >= min_samples)
+            .unwrap_or(false)
+    }
+
+    /// Slice of the target's window `now` falls in
+    fn epoch(&self, target: &SloTarget, now: Instant) -> u64 {
+        let width = (target.window / WINDOW_BUCKETS as u32).max(Duration::from_millis(1));
+        (now.saturating_duration_since(self.origin).as_nanos() / width.as_nanos()) as u64 + 1
+    }
+}
+
+impl Default for SloTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    fn tracker(policy: SheddingPolicy) -> SloTracker {
+        SloTracker::new()
+            .with_default_target(SloTarget {
+                success_rate: 0.9,
+                latency: Duration::from_millis(100),
+                window: WINDOW,
+            })
+            .with_policy(policy)
+    }
+
+    fn record(tracker: &SloTracker, method: &str, good: usize, bad: usize, now: Instant) {
+        let fast = Duration::from_millis(5);
+        for _ in 0..good {
+            tracker.record_at(method, true, fast, now);
+        }
+        for _ in 0..bad {
+            tracker.record_at(method, false, fast, now);
+        }
+    }
+
+    #[test]
+    fn calls_leave_the_window() {
+        let tracker = tracker(SheddingPolicy::Never);
+        let start = tracker.origin;
+        record(&tracker, "m", 3, 1, start);
+        record(&tracker, "m", 2, 0, start + WINDOW / 2);
+
+        let status = tracker.status_at("m", start + WINDOW / 2).unwrap();
+        assert_eq!((status.total, status.good), (6, 5));
+
+        // The first calls expire once a full window has passed
+        let status = tracker.status_at("m", start + WINDOW + WINDOW / 4).unwrap();
+        assert_eq!((status.total, status.good), (2, 2));
+
+        let status = tracker.status_at("m", start + 3 * WINDOW).unwrap();
+        assert_eq!(status.total, 0);
+        assert_eq!(status.latency_p99_ms, None);
+    }
+
+    #[test]
+    fn slow_calls_are_bad() {
+        let tracker = tracker(SheddingPolicy::Never);
+        let now = tracker.origin;
+        tracker.record_at("m", true, Duration::from_millis(150), now);
+        tracker.record_at("m", true, Duration::from_millis(50), now);
+
+        let status = tracker.status_at("m", now).unwrap();
+        assert_eq!((status.total, status.good), (2, 1));
+        assert_eq!(status.latency_p99_ms, Some(200));
+    }
+
+    #[test]
+    fn sheds_once_budget_exhausted_with_enough_samples() {
+        let tracker = tracker(SheddingPolicy::RejectWhenExhausted { min_samples: 10 });
+        let now = tracker.origin;
+
+        // Exhausted, but too few calls to act on
+        record(&tracker, "m", 0, 9, now);
+        assert!(!tracker.should_shed_at("m", now));
+        record(&tracker, "m", 0, 1, now);
+        assert!(tracker.should_shed_at("m", now));
+
+        // Recovers with the window
+        assert!(!tracker.should_shed_at("m", now + 2 * WINDOW));
+    }
+
+    #[test]
+    fn burn_below_one_does_not_shed() {
+        let tracker = tracker(SheddingPolicy::RejectWhenExhausted { min_samples: 10 });
+        let now = tracker.origin;
+        record(&tracker, "m", 95, 5, now);
+
+        let status = tracker.status_at("m", now).unwrap();
+        assert!((status.budget_burn - 0.5).abs() < 1e-9);
+        assert!(!status.exhausted);
+        assert!(!tracker.should_shed_at("m", now));
+
+        record(&tracker, "m", 0, 6, now);
+        assert!(tracker.should_shed_at("m", now));
+    }
+
+    #[test]
+    fn never_policy_does_not_shed() {
+        let tracker = tracker(SheddingPolicy::Never);
+        let now = tracker.origin;
+        record(&tracker, "m", 0, 100, now);
+        assert!(tracker.status_at("m", now).unwrap().exhausted);
+        assert!(!tracker.should_shed_at("m", now));
+    }
+
+    #[test]
+    fn methods_past_the_limit_are_grouped() {
+        let tracker = tracker(SheddingPolicy::Never)
+            .with_target("targeted", SloTarget::default())
+            .with_max_methods(2);
+        let now = tracker.origin;
+        for method in ["a", "b", "c", "d", "targeted"] {
+            record(&tracker, method, 1, 0, now);
+        }
+
+        let methods: Vec<_> = tracker.report().into_iter().map(|s| s.method).collect();
+        assert_eq!(methods, vec![OTHER_METHOD, "a", "b", "targeted"]);
+        let other = tracker.status_at("d", now).unwrap();
+        assert_eq!((other.method.as_str(), other.total), (OTHER_METHOD, 2));
+    }
+
+    #[test]
+    fn untargeted_methods_are_not_tracked() {
+        let tracker = SloTracker::new();
+        tracker.record("m", false, Duration::ZERO);
+        assert!(tracker.status("m").is_none());
+        assert!(tracker.report().is_empty());
+    }
+}
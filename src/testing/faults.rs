@@ -0,0 +1,237 @@
+//! Fault injection for chaos testing
+//!
+//! [`FaultyTransport`] wraps any transport and breaks it on purpose,
+//! following a [`FaultPolicy`]: each message received or sent may fail,
+//! arrive truncated, arrive twice or, when sent, silently vanish. Faults are
+//! drawn from a [`SeededRng`], so a failing run replays with its seed.
+//!
+//! ```rust,ignore
+//! let policy = FaultPolicy::new(seed)
+//!     .with_truncation(0.05)
+//!     .with_duplicates(0.05)
+//!     .with_dropped_writes(0.01);
+//! let transport = FaultyTransport::new(transport, policy);
+//! let stats = transport.stats();
+//! JsonRpcProcessor::new(transport, registry).run().await?;
+//! println!("{:?}", stats.snapshot());
+//! ```
+
+use crate::error::helpers;
+use crate::testing::scheduler::SeededRng;
+use crate::transport::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Probability of each fault, per message
+///
+/// Every probability defaults to 0, so only the faults set are injected.
+#[derive(Debug, Clone)]
+pub struct FaultPolicy {
+    seed: u64,
+    receive_errors: f64,
+    send_errors: f64,
+    truncation: f64,
+    duplicates: f64,
+    dropped_writes: f64,
+}
+
+impl FaultPolicy {
+    /// Inject no fault until some are set, drawing them from `seed`
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            receive_errors: 0.0,
+            send_errors: 0.0,
+            truncation: 0.0,
+            duplicates: 0.0,
+            dropped_writes: 0.0,
+        }
+    }
+
+    /// Fail receives with a transport error, the message being kept for the
+    /// next receive
+    pub fn with_receive_errors(mut self, probability: f64) -> Self {
+        self.receive_errors = probability;
+        self
+    }
+
+    /// Fail sends with a transport error, the message not being written
+    pub fn with_send_errors(mut self, probability: f64) -> Self {
+        self.send_errors = probability;
+        self
+    }
+
+    /// Cut messages received or sent short, at a random length
+    pub fn with_truncation(mut self, probability: f64) -> Self {
+        self.truncation = probability;
+        self
+    }
+
+    /// Deliver messages received, or write messages sent, twice
+    pub fn with_duplicates(mut self, probability: f64) -> Self {
+        self.duplicates = probability;
+        self
+    }
+
+    /// Report sends as successful without writing them
+    pub fn with_dropped_writes(mut self, probability: f64) -> Self {
+        self.dropped_writes = probability;
+        self
+    }
+
+    /// Seed the faults are drawn from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+/// Number of faults injected, by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultCounts {
+    /// Receives failed
+    pub receive_errors: u64,
+    /// Sends failed
+    pub send_errors: u64,
+    /// Messages truncated, either way
+    pub truncated: u64,
+    /// Messages duplicated, either way
+    pub duplicated: u64,
+    /// Sends reported successful but not written
+    pub dropped_writes: u64,
+}
+
+/// Live counters of the faults injected by a [`FaultyTransport`]
+#[derive(Debug, Clone, Default)]
+pub struct FaultStats(Arc<[AtomicU64; 5]>);
+
+impl FaultStats {
+    fn record(&self, fault: Fault) {
+        self.0[fault as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Faults injected so far
+    pub fn snapshot(&self) -> FaultCounts {
+        let count = |fault: Fault| self.0[fault as usize].load(Ordering::Relaxed);
+        FaultCounts {
+            receive_errors: count(Fault::ReceiveError),
+            send_errors: count(Fault::SendError),
+            truncated: count(Fault::Truncated),
+            duplicated: count(Fault::Duplicated),
+            dropped_writes: count(Fault::DroppedWrite),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Fault {
+    ReceiveError,
+    SendError,
+    Truncated,
+    Duplicated,
+    DroppedWrite,
+}
+
+/// Transport wrapper injecting faults into the messages of another
+///
+/// Streamed writes are turned off, so every message sent goes through the
+/// policy whole.
+pub struct FaultyTransport<T> {
+    inner: T,
+    policy: FaultPolicy,
+    rng: SeededRng,
+    stats: FaultStats,
+    /// Message received but not delivered yet, after an injected error or
+    /// for a duplicate
+    held: Option<String>,
+}
+
+impl<T: Transport> FaultyTransport<T> {
+    /// Wrap a transport, injecting the faults of `policy`
+    pub fn new(inner: T, policy: FaultPolicy) -> Self {
+        Self {
+            inner,
+            rng: SeededRng::new(policy.seed),
+            policy,
+            stats: FaultStats::default(),
+            held: None,
+        }
+    }
+
+    /// Counters of the faults injected, shared with the transport
+    pub fn stats(&self) -> FaultStats {
+        self.stats.clone()
+    }
+
+    /// Access the wrapped transport
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn roll(&mut self, probability: f64, fault: Fault) -> bool {
+        let hit = probability > 0.0 && self.rng.next_f64() < probability;
+        if hit {
+            self.stats.record(fault);
+        }
+        hit
+    }
+
+    fn maybe_truncate(&mut self, message: &str) -> String {
+        if message.len() > 1 && self.roll(self.policy.truncation, Fault::Truncated) {
+            let mut end = 1 + self.rng.next_below(message.len() - 1);
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            message[..end].to_string()
+        } else {
+            message.to_string()
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for FaultyTransport<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        let message = match self.held.take() {
+            Some(message) => message,
+            None => self.inner.receive().await?,
+        };
+        if self.roll(self.policy.receive_errors, Fault::ReceiveError) {
+            self.held = Some(message);
+            return Err(helpers::transport_error("Injected receive failure"));
+        }
+        if self.roll(self.policy.duplicates, Fault::Duplicated) {
+            self.held = Some(message.clone());
+        }
+        Ok(self.maybe_truncate(&message))
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        if self.roll(self.policy.send_errors, Fault::SendError) {
+            return Err(helpers::transport_error("Failed to send: injected failure"));
+        }
+        if self.roll(self.policy.dropped_writes, Fault::DroppedWrite) {
+            return Ok(());
+        }
+        let message = self.maybe_truncate(message);
+        self.inner.send(&message).await?;
+        if self.roll(self.policy.duplicates, Fault::Duplicated) {
+            self.inner.send(&message).await?;
+        }
+        Ok(())
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        false
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.inner.close().await
+    }
+}
@@ -0,0 +1,89 @@
+use crate::protocol::{JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Fill in `"jsonrpc": "2.0"` when the literal omits it
+fn with_version(mut value: Value) -> Value {
+    if let Value::Object(map) = &mut value {
+        map.entry("jsonrpc")
+            .or_insert_with(|| Value::String("2.0".to_string()));
+    }
+    value
+}
+
+#[track_caller]
+fn parse<T: DeserializeOwned>(kind: &str, value: Value) -> T {
+    match serde_json::from_value(with_version(value.clone())) {
+        Ok(parsed) => parsed,
+        Err(e) => panic!("invalid JSON-RPC {} literal {}: {}", kind, value, e),
+    }
+}
+
+/// Build a validated request from a JSON literal (used by [`jsonrpc_request!`](crate::jsonrpc_request))
+#[track_caller]
+pub fn request_from_value(value: Value) -> JsonRpcRequest {
+    let request: JsonRpcRequest = parse("request", value);
+    if let Err(e) = request.validate() {
+        panic!("invalid JSON-RPC request literal: {}", e);
+    }
+    request
+}
+
+/// Build a validated notification from a JSON literal (used by [`jsonrpc_notification!`](crate::jsonrpc_notification))
+#[track_caller]
+pub fn notification_from_value(value: Value) -> JsonRpcNotification {
+    let notification: JsonRpcNotification = parse("notification", value);
+    if let Err(e) = notification.validate() {
+        panic!("invalid JSON-RPC notification literal: {}", e);
+    }
+    notification
+}
+
+/// Build a validated response from a JSON literal (used by [`jsonrpc_response!`](crate::jsonrpc_response))
+#[track_caller]
+pub fn response_from_value(value: Value) -> JsonRpcResponse {
+    let response: JsonRpcResponse = parse("response", value);
+    if let Err(e) = response.validate() {
+        panic!("invalid JSON-RPC response literal: {}", e);
+    }
+    response
+}
+
+/// Build a validated [`JsonRpcRequest`] from an inline JSON literal
+///
+/// The `jsonrpc` member may be omitted and defaults to `"2.0"`. Panics if the
+/// literal does not describe a valid request.
+///
+/// ```rust,ignore
+/// let request = jsonrpc_request!({"method": "tools/call", "params": {"name": "echo"}, "id": 1});
+/// ```
+#[macro_export]
+macro_rules! jsonrpc_request {
+    ($($json:tt)+) => {
+        $crate::testing::fixtures::request_from_value($crate::testing::serde_json::json!($($json)+))
+    };
+}
+
+/// Build a validated [`JsonRpcNotification`] from an inline JSON literal
+///
+/// ```rust,ignore
+/// let notification = jsonrpc_notification!({"method": "notifications/initialized"});
+/// ```
+#[macro_export]
+macro_rules! jsonrpc_notification {
+    ($($json:tt)+) => {
+        $crate::testing::fixtures::notification_from_value($crate::testing::serde_json::json!($($json)+))
+    };
+}
+
+/// Build a validated [`JsonRpcResponse`] from an inline JSON literal
+///
+/// ```rust,ignore
+/// let response = jsonrpc_response!({"result": {"ok": true}, "id": 1});
+/// ```
+#[macro_export]
+macro_rules! jsonrpc_response {
+    ($($json:tt)+) => {
+        $crate::testing::fixtures::response_from_value($crate::testing::serde_json::json!($($json)+))
+    };
+}
@@ -0,0 +1,125 @@
+//! Simulated network latency
+//!
+//! [`LatencyTransport`] delays the messages of another transport by a base
+//! latency plus random jitter, to check timeouts and cancellation under
+//! conditions closer to a real network than an in-memory pipe. Jitter is
+//! drawn from a [`SeededRng`], so runs replay with their seed.
+//!
+//! ```rust,ignore
+//! let transport = LatencyTransport::new(transport, seed)
+//!     .with_receive_delay(Duration::from_millis(40), Duration::from_millis(20))
+//!     .with_send_delay(Duration::from_millis(40), Duration::from_millis(20));
+//! ```
+
+use crate::testing::scheduler::SeededRng;
+use crate::transport::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Delay of a direction: a base latency plus up to `jitter` more
+#[derive(Debug, Clone, Copy, Default)]
+struct Delay {
+    base: Duration,
+    jitter: Duration,
+}
+
+/// Transport wrapper delaying each message received and sent
+///
+/// A received message is held until its delay elapsed; receiving stays cancel
+/// safe, as a cancelled receive leaves it held for the next. The next message
+/// is only read once it was delivered, so messages arriving back to back are
+/// spaced by the delay rather than delayed together. A send waits for
+/// its delay before writing, slowing the sender down like a congested link.
+pub struct LatencyTransport<T> {
+    inner: T,
+    rng: SeededRng,
+    receive_delay: Delay,
+    send_delay: Delay,
+    /// Message received and the time it is delivered at
+    held: Option<(String, Instant)>,
+    /// Whether parts of a message were sent but not its end
+    mid_message: bool,
+}
+
+impl<T: Transport> LatencyTransport<T> {
+    /// Wrap a transport, without delays until they are set
+    pub fn new(inner: T, seed: u64) -> Self {
+        Self {
+            inner,
+            rng: SeededRng::new(seed),
+            receive_delay: Delay::default(),
+            send_delay: Delay::default(),
+            held: None,
+            mid_message: false,
+        }
+    }
+
+    /// Deliver messages received `base` plus up to `jitter` late
+    pub fn with_receive_delay(mut self, base: Duration, jitter: Duration) -> Self {
+        self.receive_delay = Delay { base, jitter };
+        self
+    }
+
+    /// Write messages sent `base` plus up to `jitter` late
+    pub fn with_send_delay(mut self, base: Duration, jitter: Duration) -> Self {
+        self.send_delay = Delay { base, jitter };
+        self
+    }
+
+    /// Access the wrapped transport
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn draw(&mut self, delay: Delay) -> Duration {
+        delay.base + delay.jitter.mul_f64(self.rng.next_f64())
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for LatencyTransport<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        if self.held.is_none() {
+            let message = self.inner.receive().await?;
+            let delay = self.draw(self.receive_delay);
+            self.held = Some((message, Instant::now() + delay));
+        }
+        if let Some((_, deliver_at)) = &self.held {
+            tokio::time::sleep_until(*deliver_at).await;
+        }
+        let (message, _) = self.held.take().expect("message held");
+        Ok(message)
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        let delay = self.draw(self.send_delay);
+        tokio::time::sleep(delay).await;
+        self.inner.send(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.inner.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        // Delayed once per message, before its first part
+        if !self.mid_message {
+            let delay = self.draw(self.send_delay);
+            tokio::time::sleep(delay).await;
+        }
+        self.inner.send_part(part, end).await?;
+        self.mid_message = !end;
+        Ok(())
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.inner.close().await
+    }
+}
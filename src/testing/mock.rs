@@ -0,0 +1,261 @@
+//! Scripted in-memory transport
+//!
+//! [`MockTransport`] stands in for a connection in processor tests: the test
+//! queues the messages the peer sends, and reads back what the processor
+//! wrote, through a [`MockHandle`] that stays usable once the transport was
+//! moved into the processor.
+//!
+//! ```rust,ignore
+//! let transport = MockTransport::scripted([
+//!     r#"{"jsonrpc":"2.0","method":"echo","params":{"a":1},"id":1}"#,
+//! ]);
+//! let handle = transport.handle();
+//! JsonRpcProcessor::new(transport, registry).run().await?;
+//! assert_eq!(handle.sent_values()[0]["result"], json!({"a": 1}));
+//! ```
+//!
+//! Connection loss is scripted like a message, with
+//! [`push_close`](MockHandle::push_close) or
+//! [`push_error`](MockHandle::push_error), or tied to the processor's
+//! output with [`close_after_sent`](MockHandle::close_after_sent).
+
+use crate::error::helpers;
+use crate::transport::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// What the peer does next
+#[derive(Debug, Clone)]
+enum Step {
+    Message(String),
+    Error(String),
+    Close,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    script: VecDeque<Step>,
+    /// Set once the connection is lost, by the script or the transport
+    closed: bool,
+    /// Whether the transport was closed by its owner
+    closed_locally: bool,
+    sent: Vec<String>,
+    unread: VecDeque<String>,
+    close_after_sent: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    script_changed: Notify,
+    message_sent: Notify,
+}
+
+/// In-memory transport replaying a script of incoming messages
+#[derive(Debug)]
+pub struct MockTransport {
+    shared: Arc<Shared>,
+}
+
+impl MockTransport {
+    /// A transport waiting for messages pushed through its handle
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::default(),
+        }
+    }
+
+    /// A transport receiving the given messages, then a closed connection
+    pub fn scripted<I, S>(messages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let transport = Self::new();
+        let handle = transport.handle();
+        for message in messages {
+            handle.push(message);
+        }
+        handle.push_close();
+        transport
+    }
+
+    /// Handle scripting the peer and inspecting what was sent
+    pub fn handle(&self) -> MockHandle {
+        MockHandle {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn receive(&mut self) -> McpResult<String> {
+        loop {
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                if state.closed {
+                    return Err(helpers::connection_closed());
+                }
+                match state.script.pop_front() {
+                    Some(Step::Message(message)) => return Ok(message),
+                    Some(Step::Error(message)) => {
+                        return Err(helpers::transport_error(&message));
+                    }
+                    Some(Step::Close) => {
+                        state.closed = true;
+                        self.shared.message_sent.notify_one();
+                        return Err(helpers::connection_closed());
+                    }
+                    None => {}
+                }
+            }
+            // A push made since the lock was released left a permit
+            self.shared.script_changed.notified().await;
+        }
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.closed {
+            return Err(helpers::transport_error(
+                "Failed to send: connection closed",
+            ));
+        }
+        state.sent.push(message.to_string());
+        state.unread.push_back(message.to_string());
+        if state.close_after_sent == Some(state.sent.len()) {
+            state.closed = true;
+        }
+        drop(state);
+        self.shared.message_sent.notify_one();
+        self.shared.script_changed.notify_one();
+        Ok(())
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.closed = true;
+        state.closed_locally = true;
+        drop(state);
+        self.shared.message_sent.notify_one();
+        Ok(())
+    }
+}
+
+/// Scripts the peer of a [`MockTransport`] and records what it was sent
+///
+/// Cheap to clone; clones drive the same transport.
+#[derive(Debug, Clone)]
+pub struct MockHandle {
+    shared: Arc<Shared>,
+}
+
+impl MockHandle {
+    fn push_step(&self, step: Step) {
+        self.shared.state.lock().unwrap().script.push_back(step);
+        self.shared.script_changed.notify_one();
+    }
+
+    /// Queue a message from the peer
+    pub fn push(&self, message: impl Into<String>) {
+        self.push_step(Step::Message(message.into()));
+    }
+
+    /// Queue a message from the peer, serialized
+    pub fn push_value<T: Serialize>(&self, message: &T) {
+        let message = serde_json::to_string(message).expect("message serializes");
+        self.push(message);
+    }
+
+    /// Lose the connection once the messages queued so far were received
+    pub fn push_close(&self) {
+        self.push_step(Step::Close);
+    }
+
+    /// Fail the receive following the messages queued so far, with a
+    /// transport error; the connection stays usable afterwards
+    pub fn push_error(&self, message: impl Into<String>) {
+        self.push_step(Step::Error(message.into()));
+    }
+
+    /// Lose the connection right away, dropping the messages still queued
+    pub fn close(&self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.script_changed.notify_one();
+        self.shared.message_sent.notify_one();
+    }
+
+    /// Lose the connection once `count` messages were sent in total, e.g. in
+    /// the middle of the responses to a batch
+    pub fn close_after_sent(&self, count: usize) {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.sent.len() >= count {
+            state.closed = true;
+        } else {
+            state.close_after_sent = Some(count);
+        }
+        drop(state);
+        self.shared.script_changed.notify_one();
+        self.shared.message_sent.notify_one();
+    }
+
+    /// Messages sent so far, in order
+    pub fn sent(&self) -> Vec<String> {
+        self.shared.state.lock().unwrap().sent.clone()
+    }
+
+    /// Messages sent so far, parsed; panics on invalid JSON
+    #[track_caller]
+    pub fn sent_values(&self) -> Vec<Value> {
+        self.sent()
+            .iter()
+            .map(|message| match serde_json::from_str(message) {
+                Ok(value) => value,
+                Err(e) => panic!("sent message is not JSON ({}): {}", e, message),
+            })
+            .collect()
+    }
+
+    /// Wait for the next message sent, `None` once the transport is closed
+    /// and every message was read
+    ///
+    /// Each message is returned once, in order; [`sent`](Self::sent) keeps
+    /// all of them.
+    pub async fn next_sent(&self) -> Option<String> {
+        loop {
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                if let Some(message) = state.unread.pop_front() {
+                    return Some(message);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.shared.message_sent.notified().await;
+        }
+    }
+
+    /// Whether the connection was lost or closed
+    pub fn is_closed(&self) -> bool {
+        self.shared.state.lock().unwrap().closed
+    }
+
+    /// Whether the transport was closed by its owner, e.g. at the end of
+    /// [`JsonRpcProcessor::run`](crate::processor::JsonRpcProcessor::run)
+    pub fn was_closed_locally(&self) -> bool {
+        self.shared.state.lock().unwrap().closed_locally
+    }
+}
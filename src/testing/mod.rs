@@ -0,0 +1,21 @@
+//! Test utilities for exercising the JSON-RPC adapter
+//!
+//! These helpers are only compiled with the `testing` feature and are meant for
+//! downstream integration suites that need reproducible behavior in CI.
+
+pub mod faults;
+pub mod fixtures;
+pub mod latency;
+pub mod mock;
+pub mod scheduler;
+pub mod tools;
+
+pub use faults::{FaultCounts, FaultPolicy, FaultStats, FaultyTransport};
+pub use latency::LatencyTransport;
+pub use mock::{MockHandle, MockTransport};
+pub use scheduler::{yield_point, DeterministicScheduler, ScheduleTrace, SeededRng, YieldPoint};
+pub use tools::{with_test_tools, DelayTool, EchoTool, FailTool, PanicTool, SlowStreamTool};
+
+// Used by the fixture macros so callers don't need their own serde_json import
+#[doc(hidden)]
+pub use serde_json;
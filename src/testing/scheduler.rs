@@ -0,0 +1,372 @@
+use crate::error::helpers;
+use mcp_error::Result as McpResult;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Duration;
+
+/// Environment variable used to override the scheduler seed in CI
+pub const SEED_ENV_VAR: &str = "MCP_JSONRPC_SCHED_SEED";
+
+/// Default number of polls before the scheduler gives up on a run
+const DEFAULT_MAX_POLLS: usize = 100_000;
+
+/// Default paused time every task may wait before a run is reported as stalled
+const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Small deterministic pseudo-random generator (SplitMix64)
+///
+/// Not suitable for anything but reproducible test scheduling.
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    /// Create a new generator from the given seed
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Produce the next 64-bit value
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Produce a value in `0..bound` (returns 0 when `bound` is 0)
+    pub fn next_below(&mut self, bound: usize) -> usize {
+        if bound == 0 {
+            return 0;
+        }
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Produce a float in `0.0..1.0`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Order in which tasks were polled during a scheduler run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleTrace {
+    /// Seed the run was started with
+    pub seed: u64,
+    /// Task ids in the order they were polled
+    pub polls: Vec<usize>,
+}
+
+/// Run queue state shared with the task wakers
+struct RunQueue {
+    ready: Vec<bool>,
+    /// Waker of the scheduler while it waits for a task to become ready
+    scheduler: Option<Waker>,
+}
+
+/// Waker that marks its task as ready in the shared run queue
+struct TaskWaker {
+    id: usize,
+    queue: Arc<Mutex<RunQueue>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if let Ok(mut queue) = self.queue.lock() {
+            if let Some(flag) = queue.ready.get_mut(self.id) {
+                *flag = true;
+            }
+            if let Some(scheduler) = queue.scheduler.take() {
+                scheduler.wake();
+            }
+        }
+    }
+}
+
+type BoxedTask = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Executor that interleaves tasks in a seed-determined order
+///
+/// Every time more than one task is ready, the next one to poll is picked by a
+/// [`SeededRng`]. Running the same set of tasks with the same seed always yields
+/// the same interleaving, so an ordering bug found in CI can be replayed locally
+/// by reusing the seed reported in the failure.
+///
+/// Tasks run on a current-thread Tokio runtime with paused time, so they can
+/// use timers, in-memory I/O (`tokio::io::duplex`, [`MockTransport`]) and
+/// `tokio::spawn`. Time only advances when every task is waiting, which keeps
+/// runs with timers reproducible. Tasks spawned through Tokio, such as the
+/// handlers of a pipelined [`JsonRpcProcessor`], run between two polls of the
+/// scheduler's tasks in Tokio's own (deterministic) order: the seed picks
+/// among the tasks given to [`spawn`](Self::spawn). Real sockets and
+/// `spawn_blocking` make a run depend on the outside world again.
+///
+/// [`run`](Self::run) starts its own runtime, so it must be called outside
+/// of one, e.g. from a plain `#[test]`.
+///
+/// [`MockTransport`]: crate::testing::MockTransport
+/// [`JsonRpcProcessor`]: crate::processor::JsonRpcProcessor
+pub struct DeterministicScheduler {
+    seed: u64,
+    rng: SeededRng,
+    tasks: Vec<Option<BoxedTask>>,
+    queue: Arc<Mutex<RunQueue>>,
+    max_polls: usize,
+    stall_timeout: Duration,
+}
+
+impl DeterministicScheduler {
+    /// Create a scheduler using the given seed
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: SeededRng::new(seed),
+            tasks: Vec::new(),
+            queue: Arc::new(Mutex::new(RunQueue {
+                ready: Vec::new(),
+                scheduler: None,
+            })),
+            max_polls: DEFAULT_MAX_POLLS,
+            stall_timeout: DEFAULT_STALL_TIMEOUT,
+        }
+    }
+
+    /// Create a scheduler seeded from `MCP_JSONRPC_SCHED_SEED`, or `default_seed` if unset
+    pub fn from_env(default_seed: u64) -> Self {
+        let seed = std::env::var(SEED_ENV_VAR)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default_seed);
+        Self::new(seed)
+    }
+
+    /// Limit the total number of polls performed by [`run`](Self::run)
+    pub fn with_max_polls(mut self, max_polls: usize) -> Self {
+        self.max_polls = max_polls;
+        self
+    }
+
+    /// Set how long, in paused time, every task may wait before the run is
+    /// reported as stalled
+    pub fn with_stall_timeout(mut self, stall_timeout: Duration) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// Seed used by this scheduler
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Add a task to the scheduler, returning its id
+    pub fn spawn<F>(&mut self, future: F) -> usize
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let id = self.tasks.len();
+        self.tasks.push(Some(Box::pin(future)));
+        self.queue.lock().unwrap().ready.push(true);
+        id
+    }
+
+    /// Run all tasks to completion
+    ///
+    /// Fails if the remaining tasks all wait longer than the stall timeout
+    /// (nothing wakes them) or if the poll budget is exhausted. The error
+    /// message contains the seed.
+    pub fn run(&mut self) -> McpResult<ScheduleTrace> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .map_err(|e| {
+                helpers::internal_error(&format!("Failed to start scheduler runtime: {}", e))
+            })?;
+        runtime.block_on(self.drive())
+    }
+
+    async fn drive(&mut self) -> McpResult<ScheduleTrace> {
+        let mut polls = Vec::new();
+
+        loop {
+            let candidates = self.candidates(None);
+
+            if candidates.is_empty() {
+                if self.tasks.iter().all(Option::is_none) {
+                    return Ok(ScheduleTrace {
+                        seed: self.seed,
+                        polls,
+                    });
+                }
+                let woken = std::future::poll_fn(|cx| {
+                    if self.candidates(Some(cx.waker())).is_empty() {
+                        Poll::Pending
+                    } else {
+                        Poll::Ready(())
+                    }
+                });
+                if tokio::time::timeout(self.stall_timeout, woken)
+                    .await
+                    .is_err()
+                {
+                    return Err(helpers::internal_error(&format!(
+                        "Deterministic scheduler stalled with pending tasks (seed {})",
+                        self.seed
+                    )));
+                }
+                continue;
+            }
+
+            if polls.len() >= self.max_polls {
+                return Err(helpers::internal_error(&format!(
+                    "Deterministic scheduler exceeded {} polls (seed {})",
+                    self.max_polls, self.seed
+                )));
+            }
+
+            let id = candidates[self.rng.next_below(candidates.len())];
+            self.queue.lock().unwrap().ready[id] = false;
+            polls.push(id);
+
+            let waker = Waker::from(Arc::new(TaskWaker {
+                id,
+                queue: self.queue.clone(),
+            }));
+            let mut cx = Context::from_waker(&waker);
+
+            if let Some(task) = self.tasks[id].as_mut() {
+                if task.as_mut().poll(&mut cx).is_ready() {
+                    self.tasks[id] = None;
+                }
+            }
+
+            // Let tasks spawned through Tokio, timers and I/O make progress
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// Ids of the unfinished tasks that are ready, registering `scheduler` to
+    /// be woken by the next task wake-up
+    fn candidates(&self, scheduler: Option<&Waker>) -> Vec<usize> {
+        let mut queue = self.queue.lock().unwrap();
+        if let Some(scheduler) = scheduler {
+            queue.scheduler = Some(scheduler.clone());
+        }
+        (0..self.tasks.len())
+            .filter(|&id| queue.ready[id] && self.tasks[id].is_some())
+            .collect()
+    }
+}
+
+/// Future that yields control back to the scheduler exactly once
+///
+/// Insert it between steps of a task to create an explicit interleaving point.
+pub fn yield_point() -> YieldPoint {
+    YieldPoint { yielded: false }
+}
+
+/// Future returned by [`yield_point`]
+pub struct YieldPoint {
+    yielded: bool,
+}
+
+impl Future for YieldPoint {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::{ExecutionMode, JsonRpcProcessor, ProcessorConfig, ToolRegistry};
+    use crate::testing::{with_test_tools, MockTransport};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Order in which three tasks of three steps each ran under `seed`
+    fn interleaving(seed: u64) -> (Vec<usize>, ScheduleTrace) {
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let mut scheduler = DeterministicScheduler::new(seed);
+        for task in 0..3 {
+            let order = order.clone();
+            scheduler.spawn(async move {
+                for _ in 0..3 {
+                    order.borrow_mut().push(task);
+                    yield_point().await;
+                }
+            });
+        }
+        let trace = scheduler.run().unwrap();
+        let order = order.borrow().clone();
+        (order, trace)
+    }
+
+    #[test]
+    fn same_seed_same_interleaving() {
+        let (order, trace) = interleaving(7);
+        assert_eq!(interleaving(7), (order.clone(), trace));
+        assert_eq!(order.len(), 9);
+    }
+
+    #[test]
+    fn different_seeds_different_interleavings() {
+        assert_ne!(interleaving(1).0, interleaving(2).0);
+    }
+
+    #[test]
+    fn drives_timers_and_pipelined_processor() {
+        let transport = MockTransport::new();
+        let handle = transport.handle();
+        handle.push(r#"{"jsonrpc":"2.0","method":"delay","params":{"ms":50},"id":1}"#);
+        handle.push(r#"{"jsonrpc":"2.0","method":"delay","params":{"ms":10},"id":2}"#);
+        handle.close_after_sent(2);
+        let mut processor =
+            JsonRpcProcessor::new(transport, with_test_tools(ToolRegistry::builder()).build())
+                .with_config(ProcessorConfig {
+                    execution: ExecutionMode::Pipelined,
+                    ..ProcessorConfig::default()
+                });
+
+        let mut scheduler = DeterministicScheduler::new(3);
+        scheduler.spawn(async move {
+            processor.run().await.unwrap();
+        });
+        scheduler.run().unwrap();
+
+        // Completion order: the shorter delay answers first
+        let ids: Vec<_> = handle
+            .sent_values()
+            .iter()
+            .map(|r| r["id"].clone())
+            .collect();
+        assert_eq!(ids, vec![2, 1]);
+    }
+
+    #[test]
+    fn reports_stalled_run_with_seed() {
+        let (sender, receiver) = tokio::sync::oneshot::channel::<()>();
+        let mut scheduler = DeterministicScheduler::new(11);
+        scheduler.spawn(async move {
+            let _ = receiver.await;
+        });
+        let error = scheduler.run().unwrap_err();
+        assert!(error.to_string().contains("seed 11"));
+        drop(sender);
+    }
+}
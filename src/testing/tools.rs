@@ -0,0 +1,270 @@
+//! Test-double tools
+//!
+//! Standard tools for integration suites and load tests, so each suite does
+//! not grow its own copy of an echo or a sleeping tool. Every tool takes its
+//! defaults from its constructor, and the params of a call may override them:
+//!
+//! | Tool               | Default name  | Params                                        |
+//! |--------------------|---------------|-----------------------------------------------|
+//! | [`EchoTool`]       | `echo`        | anything, returned as is                      |
+//! | [`DelayTool`]      | `delay`       | `{"ms": 250}`                                 |
+//! | [`FailTool`]       | `fail`        | `{"code": -32000, "message": "...", "data": ...}` |
+//! | [`PanicTool`]      | `panic`       | `{"message": "..."}`                          |
+//! | [`SlowStreamTool`] | `slow_stream` | `{"chunks": 10, "chunkBytes": 1024, "intervalMs": 50}` |
+//!
+//! ```rust,ignore
+//! let registry = with_test_tools(ToolRegistry::builder())
+//!     .with_tool("search", Search)
+//!     .build();
+//! ```
+
+use crate::error::helpers;
+use crate::processor::{Tool, ToolRegistryBuilder};
+use crate::protocol::JsonRpcError;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Names the tools are registered under by [`with_test_tools`]
+pub mod tool_names {
+    /// [`EchoTool`](super::EchoTool)
+    pub const ECHO: &str = "echo";
+    /// [`DelayTool`](super::DelayTool)
+    pub const DELAY: &str = "delay";
+    /// [`FailTool`](super::FailTool)
+    pub const FAIL: &str = "fail";
+    /// [`PanicTool`](super::PanicTool)
+    pub const PANIC: &str = "panic";
+    /// [`SlowStreamTool`](super::SlowStreamTool)
+    pub const SLOW_STREAM: &str = "slow_stream";
+}
+
+/// Register every test tool under its default name
+pub fn with_test_tools(builder: ToolRegistryBuilder) -> ToolRegistryBuilder {
+    builder
+        .with_tool(tool_names::ECHO, EchoTool)
+        .with_tool(tool_names::DELAY, DelayTool::default())
+        .with_tool(tool_names::FAIL, FailTool::default())
+        .with_tool(tool_names::PANIC, PanicTool::default())
+        .with_tool(tool_names::SLOW_STREAM, SlowStreamTool::default())
+}
+
+fn param_u64(params: &Value, name: &str) -> Option<u64> {
+    params.get(name).and_then(Value::as_u64)
+}
+
+/// Returns its params
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EchoTool;
+
+#[async_trait]
+impl Tool for EchoTool {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        Ok(params)
+    }
+
+    fn supports_dry_run(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> Option<String> {
+        Some("Return the params".to_string())
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+}
+
+/// Sleeps before answering `{"delayedMs": ...}`
+#[derive(Debug, Clone, Copy)]
+pub struct DelayTool {
+    delay: Duration,
+}
+
+impl DelayTool {
+    /// Sleep for `delay` unless the params give `ms`
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl Default for DelayTool {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(100))
+    }
+}
+
+#[async_trait]
+impl Tool for DelayTool {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let delay = param_u64(&params, "ms")
+            .map(Duration::from_millis)
+            .unwrap_or(self.delay);
+        tokio::time::sleep(delay).await;
+        Ok(json!({ "delayedMs": delay.as_millis() as u64 }))
+    }
+
+    fn supports_dry_run(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> Option<String> {
+        Some("Sleep, then answer".to_string())
+    }
+}
+
+/// Always fails, answered with the chosen JSON-RPC error
+///
+/// The code is sent as is, so application-defined codes reach the peer
+/// unless the processor's [`ErrorCodePolicy`](crate::processor::ErrorCodePolicy)
+/// changes them.
+#[derive(Debug, Clone)]
+pub struct FailTool {
+    error: JsonRpcError,
+}
+
+impl FailTool {
+    /// Fail with the given code and message unless the params give others
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            error: JsonRpcError::new(code, message, None),
+        }
+    }
+
+    /// Attach `data` to the error
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.error.data = Some(data);
+        self
+    }
+}
+
+impl Default for FailTool {
+    fn default() -> Self {
+        Self::new(
+            crate::error::error_codes::SERVER_ERROR_START,
+            "Failed on purpose",
+        )
+    }
+}
+
+#[async_trait]
+impl Tool for FailTool {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let mut error = self.error.clone();
+        if let Some(code) = params
+            .get("code")
+            .and_then(Value::as_i64)
+            .and_then(|code| i32::try_from(code).ok())
+        {
+            error.code = code;
+        }
+        if let Some(message) = params.get("message").and_then(Value::as_str) {
+            error.message = message.to_string();
+        }
+        if let Some(data) = params.get("data") {
+            error.data = Some(data.clone());
+        }
+        Err(helpers::coded_error(error))
+    }
+
+    fn description(&self) -> Option<String> {
+        Some("Fail with the chosen error".to_string())
+    }
+}
+
+/// Panics when called, to check how a server copes with a crashing tool
+#[derive(Debug, Clone)]
+pub struct PanicTool {
+    message: String,
+}
+
+impl PanicTool {
+    /// Panic with the given message unless the params give another
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl Default for PanicTool {
+    fn default() -> Self {
+        Self::new("panicked on purpose")
+    }
+}
+
+#[async_trait]
+impl Tool for PanicTool {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let message = params
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or(&self.message);
+        panic!("{}", message)
+    }
+
+    fn description(&self) -> Option<String> {
+        Some("Panic".to_string())
+    }
+}
+
+/// Produces a large result slowly: `chunks` strings of `chunkBytes` each,
+/// one every `intervalMs`
+///
+/// The result is sent whole once complete, so the tool holds an execution
+/// slot for the whole production, then a large response; suited to
+/// exercising timeouts, the slow lane and bandwidth limits. Answers
+/// `{"chunks": ["...", ...]}`.
+#[derive(Debug, Clone, Copy)]
+pub struct SlowStreamTool {
+    chunks: u64,
+    chunk_bytes: u64,
+    interval: Duration,
+}
+
+impl SlowStreamTool {
+    /// Produce `chunks` chunks of `chunk_bytes` bytes, one every `interval`,
+    /// unless the params give other values
+    pub fn new(chunks: u64, chunk_bytes: u64, interval: Duration) -> Self {
+        Self {
+            chunks,
+            chunk_bytes,
+            interval,
+        }
+    }
+}
+
+impl Default for SlowStreamTool {
+    fn default() -> Self {
+        Self::new(10, 1024, Duration::from_millis(50))
+    }
+}
+
+#[async_trait]
+impl Tool for SlowStreamTool {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let chunks = param_u64(&params, "chunks").unwrap_or(self.chunks);
+        let chunk_bytes = param_u64(&params, "chunkBytes").unwrap_or(self.chunk_bytes);
+        let interval = param_u64(&params, "intervalMs")
+            .map(Duration::from_millis)
+            .unwrap_or(self.interval);
+
+        let mut produced = Vec::with_capacity(chunks.min(1024) as usize);
+        for index in 0..chunks {
+            if index > 0 {
+                tokio::time::sleep(interval).await;
+            }
+            let fill = char::from(b'a' + (index % 26) as u8);
+            produced.push(Value::String(
+                std::iter::repeat_n(fill, chunk_bytes as usize).collect(),
+            ));
+        }
+        Ok(json!({ "chunks": produced }))
+    }
+
+    fn description(&self) -> Option<String> {
+        Some("Produce a large result slowly".to_string())
+    }
+}
@@ -0,0 +1,313 @@
+use crate::transport::base::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default period over which [`BandwidthStats`] rates are averaged
+pub const DEFAULT_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Bandwidth allowed in one direction, enforced as a token bucket on bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BandwidthLimit {
+    /// Sustained rate, in bytes per second
+    pub bytes_per_second: u64,
+    /// Bytes that may pass at once after an idle period
+    pub burst: u64,
+}
+
+impl BandwidthLimit {
+    /// Allow `bytes_per_second`, with a burst of one second's worth
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            burst: bytes_per_second,
+        }
+    }
+
+    /// Set the burst
+    pub fn with_burst(mut self, burst: u64) -> Self {
+        self.burst = burst;
+        self
+    }
+}
+
+/// Snapshot of the traffic of a connection
+///
+/// Bytes are those of the messages, excluding framing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthStats {
+    /// Bytes received since the connection was opened
+    pub bytes_received: u64,
+    /// Bytes sent since the connection was opened
+    pub bytes_sent: u64,
+    /// Bytes received per second, averaged over the rate window
+    pub receive_rate: u64,
+    /// Bytes sent per second, averaged over the rate window
+    pub send_rate: u64,
+    /// Receives and sends delayed by a limit
+    pub throttled: u64,
+}
+
+/// Bytes moved in one direction
+#[derive(Debug, Default)]
+struct Traffic {
+    total: u64,
+    /// Sizes of the recent messages, oldest first
+    recent: VecDeque<(Instant, u64)>,
+}
+
+impl Traffic {
+    fn record(&mut self, bytes: u64, now: Instant, window: Duration) {
+        self.total += bytes;
+        self.recent.push_back((now, bytes));
+        self.prune(now, window);
+    }
+
+    fn prune(&mut self, now: Instant, window: Duration) {
+        while let Some((at, _)) = self.recent.front() {
+            if now.duration_since(*at) < window {
+                break;
+            }
+            self.recent.pop_front();
+        }
+    }
+
+    fn rate(&mut self, now: Instant, window: Duration) -> u64 {
+        self.prune(now, window);
+        let bytes: u64 = self.recent.iter().map(|(_, bytes)| bytes).sum();
+        (bytes as f64 / window.as_secs_f64()) as u64
+    }
+}
+
+#[derive(Debug)]
+struct MeterState {
+    window: Duration,
+    received: Traffic,
+    sent: Traffic,
+    throttled: u64,
+}
+
+/// Traffic counters of a [`BandwidthTransport`]
+///
+/// Clones share the same counters, so the meter can be read while the
+/// transport is owned by a processor; see
+/// [`JsonRpcProcessor::with_bandwidth_meter`](crate::processor::JsonRpcProcessor::with_bandwidth_meter).
+#[derive(Debug, Clone)]
+pub struct BandwidthMeter {
+    state: Arc<Mutex<MeterState>>,
+}
+
+impl BandwidthMeter {
+    fn new(window: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MeterState {
+                window,
+                received: Traffic::default(),
+                sent: Traffic::default(),
+                throttled: 0,
+            })),
+        }
+    }
+
+    /// Current traffic
+    pub fn stats(&self) -> BandwidthStats {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let window = state.window;
+        BandwidthStats {
+            bytes_received: state.received.total,
+            bytes_sent: state.sent.total,
+            receive_rate: state.received.rate(now, window),
+            send_rate: state.sent.rate(now, window),
+            throttled: state.throttled,
+        }
+    }
+
+    fn received(&self, bytes: usize) {
+        let mut state = self.lock();
+        let window = state.window;
+        state.received.record(bytes as u64, Instant::now(), window);
+    }
+
+    fn sent(&self, bytes: usize) {
+        let mut state = self.lock();
+        let window = state.window;
+        state.sent.record(bytes as u64, Instant::now(), window);
+    }
+
+    fn throttled(&self) {
+        self.lock().throttled += 1;
+    }
+
+    fn set_window(&self, window: Duration) {
+        self.lock().window = window;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MeterState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Token bucket on bytes; a message larger than the bucket is let through and
+/// paid for by delaying the following ones
+#[derive(Debug)]
+struct TokenBucket {
+    limit: BandwidthLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: BandwidthLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.bytes_per_second as f64)
+            .min(self.limit.burst as f64);
+        self.updated = now;
+    }
+
+    /// When the bucket is out of debt, if it is in debt now
+    fn ready_at(&mut self) -> Option<Instant> {
+        let now = Instant::now();
+        self.refill(now);
+        if self.tokens >= 0.0 || self.limit.bytes_per_second == 0 {
+            return None;
+        }
+        let wait = -self.tokens / self.limit.bytes_per_second as f64;
+        Some(now + Duration::from_secs_f64(wait))
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        self.refill(Instant::now());
+        self.tokens -= bytes as f64;
+    }
+}
+
+/// Wait until the bucket, if any, allows traffic, counting the waits
+async fn wait_for(bucket: &mut Option<TokenBucket>, meter: &BandwidthMeter) {
+    if let Some(at) = bucket.as_mut().and_then(TokenBucket::ready_at) {
+        meter.throttled();
+        tokio::time::sleep_until(at.into()).await;
+    }
+}
+
+/// Transport wrapper metering the bytes of each connection, and optionally
+/// throttling connections that exceed a bandwidth
+///
+/// Throttling delays the receive or send following the traffic that went over
+/// the limit, rather than splitting messages, so a single large message (a
+/// big resource read, say) passes whole and the connection then pauses until
+/// it is paid for. A delayed receive leaves the bytes in the socket, which
+/// slows the peer down through flow control. Receives stay cancel safe.
+///
+/// ```rust,ignore
+/// let transport = BandwidthTransport::new(TcpTransport::new(stream))
+///     .with_send_limit(BandwidthLimit::new(4 << 20));
+/// let meter = transport.meter();
+/// let handle = JsonRpcProcessor::new(transport, registry)
+///     .with_bandwidth_meter(meter)
+///     .spawn();
+/// ```
+pub struct BandwidthTransport<T> {
+    inner: T,
+    meter: BandwidthMeter,
+    receive_limit: Option<TokenBucket>,
+    send_limit: Option<TokenBucket>,
+}
+
+impl<T: Transport> BandwidthTransport<T> {
+    /// Wrap a transport, metering without limits until they are set
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            meter: BandwidthMeter::new(DEFAULT_RATE_WINDOW),
+            receive_limit: None,
+            send_limit: None,
+        }
+    }
+
+    /// Limit the bandwidth of received messages
+    pub fn with_receive_limit(mut self, limit: BandwidthLimit) -> Self {
+        self.receive_limit = Some(TokenBucket::new(limit));
+        self
+    }
+
+    /// Limit the bandwidth of sent messages
+    pub fn with_send_limit(mut self, limit: BandwidthLimit) -> Self {
+        self.send_limit = Some(TokenBucket::new(limit));
+        self
+    }
+
+    /// Set the period over which rates are averaged
+    pub fn with_rate_window(self, window: Duration) -> Self {
+        self.meter.set_window(window);
+        self
+    }
+
+    /// Counters of the connection, shared with the transport
+    pub fn meter(&self) -> BandwidthMeter {
+        self.meter.clone()
+    }
+
+    /// Access the wrapped transport
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for BandwidthTransport<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        wait_for(&mut self.receive_limit, &self.meter).await;
+        let message = self.inner.receive().await?;
+        self.meter.received(message.len());
+        if let Some(bucket) = &mut self.receive_limit {
+            bucket.consume(message.len());
+        }
+        Ok(message)
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        wait_for(&mut self.send_limit, &self.meter).await;
+        if let Some(bucket) = &mut self.send_limit {
+            bucket.consume(message.len());
+        }
+        self.inner.send(message).await?;
+        self.meter.sent(message.len());
+        Ok(())
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.inner.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        wait_for(&mut self.send_limit, &self.meter).await;
+        if let Some(bucket) = &mut self.send_limit {
+            bucket.consume(part.len());
+        }
+        self.inner.send_part(part, end).await?;
+        self.meter.sent(part.len());
+        Ok(())
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.inner.close().await
+    }
+}
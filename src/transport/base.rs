@@ -1,21 +1,237 @@
 use crate::error::helpers;
+use crate::protocol::JsonRpcMessage;
+use crate::transport::split::{TransportReceiver, TransportSender};
+#[cfg(feature = "compression")]
+use crate::transport::compression::{self, Compression, Negotiation};
 use async_trait::async_trait;
 use mcp_error::Result as McpResult;
+use std::time::Duration;
 use tokio::io::{
     split, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf,
 };
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
 
 /// Transport trait for JSON-RPC communication
+///
+/// The trait is object safe, so connections of different kinds can be stored
+/// together as [`BoxedTransport`]s.
 #[async_trait]
-pub trait Transport {
+pub trait Transport: Send {
+    /// Receive the next message
+    ///
+    /// Implementations should be cancel safe: if the future is dropped before
+    /// completing, no partially received message may be lost, so receiving can
+    /// be raced against a timer (see [`IdleTimeoutTransport`](super::IdleTimeoutTransport)).
     async fn receive(&mut self) -> McpResult<String>;
     async fn send(&mut self, message: &str) -> McpResult<()>;
+
+    /// Receive the next message, parsed and classified as a request,
+    /// notification, response or batch
+    ///
+    /// A message that fails to parse is consumed and its error returned. This
+    /// serves clients, which take a message as a whole: a processor keeps
+    /// receiving text, since it answers each malformed element of a batch on
+    /// its own, with the id it could read, and records messages as received.
+    async fn receive_message(&mut self) -> McpResult<JsonRpcMessage> {
+        let message = self.receive().await?;
+        JsonRpcMessage::parse(&message)
+    }
+
+    /// Whether this transport can write a message in several parts with [`send_part`](Self::send_part)
+    fn supports_partial_send(&self) -> bool {
+        false
+    }
+
+    /// Write part of a message; the message is complete once a part is sent with `end` set
+    async fn send_part(&mut self, _part: &str, _end: bool) -> McpResult<()> {
+        Err(helpers::transport_error(
+            "Partial sends are not supported by this transport",
+        ))
+    }
+    /// Flush pending writes and shut down the sending side of the connection
+    ///
+    /// This is a graceful half-close: the peer sees the end of the stream after
+    /// the last message sent, while messages it still sends can be received
+    /// until it closes its side. Nothing may be sent after closing. The default
+    /// implementation does nothing, leaving cleanup to drop.
+    async fn close(&mut self) -> McpResult<()> {
+        Ok(())
+    }
+
+    /// Box the transport, erasing its type
+    fn boxed(self) -> BoxedTransport
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
+
+    /// Split the transport into a cloneable sender and a receiver usable
+    /// concurrently, see [`split`](super::split)
+    ///
+    /// Must be called within a Tokio runtime.
+    fn into_split(self) -> (TransportSender, TransportReceiver)
+    where
+        Self: Sized + 'static,
+    {
+        super::split::split(self)
+    }
+}
+
+/// Type-erased transport
+pub type BoxedTransport = Box<dyn Transport + Send>;
+
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        (**self).receive().await
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        (**self).send(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        (**self).supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        (**self).send_part(part, end).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        (**self).close().await
+    }
+}
+
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for &mut T {
+    async fn receive(&mut self) -> McpResult<String> {
+        (**self).receive().await
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        (**self).send(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        (**self).supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        (**self).send_part(part, end).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        (**self).close().await
+    }
+}
+
+/// Incremental writer for a JSON array of batch responses
+///
+/// Writes `[`, each element and the separating commas as they become available
+/// instead of serializing the whole batch in memory first. A failed write leaves
+/// the peer with a truncated array, so the connection should be dropped.
+#[derive(Debug, Default)]
+pub struct BatchStreamWriter {
+    written: usize,
+}
+
+impl BatchStreamWriter {
+    /// Create a writer for a new batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of elements written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Write one serialized element of the batch
+    pub async fn write_element<T: Transport + ?Sized>(
+        &mut self,
+        transport: &mut T,
+        element: &str,
+    ) -> McpResult<()> {
+        let separator = if self.written == 0 { "[" } else { "," };
+        transport
+            .send_part(&format!("{}{}", separator, element), false)
+            .await?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Close the array (nothing is written for an empty batch)
+    pub async fn finish<T: Transport + ?Sized>(self, transport: &mut T) -> McpResult<()> {
+        if self.written == 0 {
+            return Ok(());
+        }
+        transport.send_part("]", true).await
+    }
+}
+
+/// How messages are delimited on a byte stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// One message per line
+    #[default]
+    NewlineDelimited,
+    /// Each message preceded by a `Content-Length` header and a blank line, as
+    /// in the Language Server Protocol; other headers are ignored
+    ContentLength,
+    /// Detect the framing from the first message received, and answer with
+    /// the same; messages sent before are newline-delimited
+    Auto,
+}
+
+/// Progress through a `Content-Length` frame, kept across cancelled receives
+#[derive(Debug, Default)]
+struct FrameState {
+    content_length: Option<usize>,
+    /// Headers were read; `pending` holds body bytes
+    in_body: bool,
+    /// Compression of the body, as announced
+    encoding: Option<String>,
+}
+
+/// Bytes to write and whether to flush after them
+type Chunk = (Vec<u8>, bool);
+
+/// Where outgoing bytes go
+enum Writer<W> {
+    /// Written by the sender itself
+    Direct(W),
+    /// Written by a background task, through the send queue
+    Queued {
+        chunks: mpsc::Sender<Chunk>,
+        task: JoinHandle<McpResult<()>>,
+    },
+    /// Stopped writing, for the given reason
+    Stopped(String),
 }
 
 /// JSON-RPC transport implementation
 pub struct JsonRpcTransport<R, W> {
     reader: BufReader<R>,
-    writer: W,
+    writer: Writer<W>,
+    /// Bytes of a line or frame whose end has not been received yet
+    pending: Vec<u8>,
+    framing: Framing,
+    frame: FrameState,
+    /// Parts of a message being sent with `Content-Length` framing
+    outgoing: String,
+    max_message_size: Option<usize>,
+    /// The rest of the current line belongs to a message over the limit
+    discard_line: bool,
+    /// Bytes left of a frame over the limit
+    discard_bytes: usize,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    send_queue: Option<usize>,
+    #[cfg(feature = "compression")]
+    compression: Negotiation,
 }
 
 impl<T> JsonRpcTransport<ReadHalf<T>, WriteHalf<T>>
@@ -26,34 +242,791 @@ where
     pub fn new(io: T) -> Self {
         let (r, w) = split(io);
         let reader = BufReader::new(r);
-        Self { reader, writer: w }
+        Self {
+            reader,
+            writer: Writer::Direct(w),
+            pending: Vec::new(),
+            framing: Framing::default(),
+            frame: FrameState::default(),
+            outgoing: String::new(),
+            max_message_size: None,
+            discard_line: false,
+            discard_bytes: 0,
+            read_timeout: None,
+            write_timeout: None,
+            send_queue: None,
+            #[cfg(feature = "compression")]
+            compression: Negotiation::default(),
+        }
+    }
+}
+
+impl<R, W> JsonRpcTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    /// Delimit messages as given instead of one per line
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Reject incoming messages larger than `bytes`, without buffering them
+    ///
+    /// The receive fails with an error recognized by
+    /// [`is_message_too_large`](crate::error::is_message_too_large), mapped
+    /// to a parse error, and the message is skipped: the next receive returns
+    /// the message after it. Messages are unbounded by default.
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
+    }
+
+    /// Size limit of incoming messages, if any
+    pub fn max_message_size(&self) -> Option<usize> {
+        self.max_message_size
+    }
+
+    /// Fail receives waiting longer than `timeout` for a message
+    ///
+    /// The receive fails with an error recognized by
+    /// [`is_receive_timeout`](crate::error::is_receive_timeout), which ends
+    /// [`JsonRpcProcessor::run`](crate::processor::JsonRpcProcessor::run), so
+    /// a stalled peer does not hold the connection forever. What was read of
+    /// the message is kept, so receiving again resumes it.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of receives, if any
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Fail writes taking longer than `timeout`, e.g. to a peer that stopped
+    /// reading
+    ///
+    /// The send fails with an error recognized by
+    /// [`is_send_timeout`](crate::error::is_send_timeout). Part of the
+    /// message may have been written, so the connection is no longer usable.
+    /// The timeout also bounds closing, which flushes pending writes.
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Write messages from a background task, queueing up to `capacity`
+    /// messages or message parts
+    ///
+    /// Sends return once the message is queued, so a slow reader only holds
+    /// the sender up once the queue is full, at which point sends fail with
+    /// a transport error instead of waiting. Write errors, including the
+    /// [write timeout](Self::with_write_timeout), fail the next send; closing
+    /// waits for the queue to be written.
+    pub fn with_send_queue(mut self, capacity: usize) -> Self {
+        self.send_queue = Some(capacity.max(1));
+        self
+    }
+
+    /// Timeout of writes, if any
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Compress large messages with the first of `algorithms` the peer also
+    /// accepts
+    ///
+    /// The algorithms are offered to the peer, which must enable compression
+    /// too, before the first message; see [`compression`](super::compression).
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, algorithms: &[Compression]) -> Self {
+        self.compression.accepted = algorithms.to_vec();
+        self
+    }
+
+    /// Only compress messages of at least `bytes`, 1 KiB by default
+    #[cfg(feature = "compression")]
+    pub fn with_compression_threshold(mut self, bytes: usize) -> Self {
+        self.compression.threshold = bytes;
+        self
+    }
+
+    /// Algorithm large messages are sent with, once negotiated with the peer
+    #[cfg(feature = "compression")]
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression.negotiated()
+    }
+
+    /// Framing in use; once detected, [`Framing::Auto`] reports the framing
+    /// of the peer
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    async fn receive_framed(&mut self) -> McpResult<String> {
+        #[cfg(feature = "compression")]
+        if self.compression.awaits_offer() {
+            self.receive_offer().await?;
+        }
+        if self.framing == Framing::Auto {
+            self.detect_framing().await?;
+        }
+        match self.framing {
+            Framing::ContentLength => self.receive_frame().await,
+            _ => self.receive_line().await,
+        }
+    }
+
+    /// Settle [`Framing::Auto`] from the first byte of the first message
+    async fn detect_framing(&mut self) -> McpResult<()> {
+        let first = self.peek_first().await?;
+        self.framing = if first.eq_ignore_ascii_case(&b'c') {
+            Framing::ContentLength
+        } else {
+            Framing::NewlineDelimited
+        };
+        Ok(())
+    }
+
+    /// Byte the next message starts with, without consuming it
+    async fn peek_first(&mut self) -> McpResult<u8> {
+        loop {
+            let buffer = self
+                .reader
+                .fill_buf()
+                .await
+                .map_err(|e| helpers::transport_error(&format!("Failed to read: {}", e)))?;
+            if buffer.is_empty() {
+                return Err(helpers::connection_closed());
+            }
+            // Blank lines before the first message are skipped
+            let blank = buffer
+                .iter()
+                .take_while(|byte| byte.is_ascii_whitespace())
+                .count();
+            let first = buffer.get(blank).copied();
+            self.reader.consume(blank);
+            if let Some(first) = first {
+                return Ok(first);
+            }
+        }
+    }
+
+    /// Read the compression algorithms the peer offers, if it starts with
+    /// an offer
+    #[cfg(feature = "compression")]
+    async fn receive_offer(&mut self) -> McpResult<()> {
+        // A dropped receive may have left part of the offer in `pending`
+        if self.pending.is_empty() && self.peek_first().await? != b'~' {
+            return self.compression.receive_offer(None);
+        }
+        if !self.read_line().await? {
+            return Err(helpers::connection_closed());
+        }
+        let line = std::mem::take(&mut self.pending);
+        self.compression
+            .receive_offer(Some(&String::from_utf8_lossy(&line)))
+    }
+
+    /// Append the rest of the current line to `pending`, returning false at
+    /// the end of the stream
+    ///
+    /// Partial lines stay in `pending` if this future is dropped. A line over
+    /// the size limit fails the read and is skipped up to its end, even across
+    /// receives.
+    async fn read_line(&mut self) -> McpResult<bool> {
+        loop {
+            let buffer = self
+                .reader
+                .fill_buf()
+                .await
+                .map_err(|e| helpers::transport_error(&format!("Failed to read: {}", e)))?;
+            if buffer.is_empty() {
+                return Ok(false);
+            }
+            let (used, complete) = match buffer.iter().position(|&byte| byte == b'\n') {
+                Some(end) => (end + 1, true),
+                None => (buffer.len(), false),
+            };
+            if self.discard_line {
+                self.discard_line = !complete;
+                self.reader.consume(used);
+                continue;
+            }
+            self.pending.extend_from_slice(&buffer[..used]);
+            self.reader.consume(used);
+
+            if let Some(max) = self.max_message_size {
+                if self.pending.len() - usize::from(complete) > max {
+                    self.pending.clear();
+                    self.discard_line = !complete;
+                    return Err(helpers::message_too_large(max));
+                }
+            }
+            if complete {
+                return Ok(true);
+            }
+        }
+    }
+
+    async fn receive_line(&mut self) -> McpResult<String> {
+        // A compressed message is a header line followed by its body
+        self.skip_discarded().await?;
+        if self.frame.in_body {
+            return self.receive_body().await;
+        }
+        if !self.read_line().await? && self.pending.is_empty() {
+            return Err(helpers::connection_closed());
+        }
+        #[cfg(feature = "compression")]
+        if self.pending.first() == Some(&b'~') {
+            let line = std::mem::take(&mut self.pending);
+            let line = String::from_utf8_lossy(&line);
+            let (name, length) = compression::parse_header(&line)?;
+            self.frame.encoding = Some(name.to_string());
+            self.start_body(length)?;
+            return self.receive_body().await;
+        }
+        checked_message(std::mem::take(&mut self.pending))
+    }
+
+    async fn receive_frame(&mut self) -> McpResult<String> {
+        // Every step keeps what it read in `pending` and `frame`, so a dropped
+        // future resumes where it stopped
+        self.skip_discarded().await?;
+        while !self.frame.in_body {
+            if !self.read_line().await? {
+                return Err(helpers::connection_closed());
+            }
+            let line = std::mem::take(&mut self.pending);
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if line.is_empty() {
+                let length = match self.frame.content_length {
+                    Some(length) => length,
+                    None => return Err(helpers::protocol_error("Missing Content-Length header")),
+                };
+                self.start_body(length)?;
+            } else if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    let length = value.trim().parse().map_err(|_| {
+                        self.frame = FrameState::default();
+                        helpers::protocol_error("Invalid Content-Length header")
+                    })?;
+                    self.frame.content_length = Some(length);
+                } else if name.trim().eq_ignore_ascii_case("content-encoding") {
+                    self.frame.encoding = Some(value.trim().to_string());
+                }
+            } else {
+                self.frame = FrameState::default();
+                return Err(helpers::protocol_error("Invalid header line"));
+            }
+        }
+
+        self.receive_body().await
+    }
+
+    /// Skip what is left of a message over the size limit
+    async fn skip_discarded(&mut self) -> McpResult<()> {
+        while self.discard_bytes > 0 {
+            let buffer = self
+                .reader
+                .fill_buf()
+                .await
+                .map_err(|e| helpers::transport_error(&format!("Failed to read: {}", e)))?;
+            if buffer.is_empty() {
+                return Err(helpers::connection_closed());
+            }
+            let skipped = buffer.len().min(self.discard_bytes);
+            self.reader.consume(skipped);
+            self.discard_bytes -= skipped;
+        }
+        Ok(())
+    }
+
+    /// Expect a body of `length` bytes, unless over the size limit
+    fn start_body(&mut self, length: usize) -> McpResult<()> {
+        if let Some(max) = self.max_message_size.filter(|&max| length > max) {
+            self.frame = FrameState::default();
+            self.discard_bytes = length;
+            return Err(helpers::message_too_large(max));
+        }
+        self.frame.content_length = Some(length);
+        self.frame.in_body = true;
+        Ok(())
+    }
+
+    async fn receive_body(&mut self) -> McpResult<String> {
+        let length = self.frame.content_length.unwrap_or_default();
+        while self.pending.len() < length {
+            let buffer = self
+                .reader
+                .fill_buf()
+                .await
+                .map_err(|e| helpers::transport_error(&format!("Failed to read: {}", e)))?;
+            if buffer.is_empty() {
+                return Err(helpers::connection_closed());
+            }
+            let take = buffer.len().min(length - self.pending.len());
+            self.pending.extend_from_slice(&buffer[..take]);
+            self.reader.consume(take);
+        }
+        let frame = std::mem::take(&mut self.frame);
+        let body = std::mem::take(&mut self.pending);
+        match frame.encoding {
+            Some(encoding) => checked_message(self.decode(&encoding, &body)?),
+            None => checked_message(body),
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    fn decode(&self, encoding: &str, body: &[u8]) -> McpResult<Vec<u8>> {
+        self.compression
+            .incoming(encoding)?
+            .decode(body, self.max_message_size)
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn decode(&self, encoding: &str, _body: &[u8]) -> McpResult<Vec<u8>> {
+        Err(helpers::protocol_error(&format!(
+            "Unsupported content encoding: {}",
+            encoding
+        )))
+    }
+
+    /// Write a whole message in the framing in use, compressed if large
+    async fn write_message(&mut self, message: &str) -> McpResult<()> {
+        #[cfg(feature = "compression")]
+        if let Some(algorithm) = self.compression.outgoing(message.len()) {
+            let body = algorithm.encode(message.as_bytes())?;
+            let mut bytes = match self.framing {
+                Framing::ContentLength => format!(
+                    "Content-Length: {}\r\nContent-Encoding: {}\r\n\r\n",
+                    body.len(),
+                    algorithm
+                ),
+                _ => format!("~{} {}\n", algorithm, body.len()),
+            }
+            .into_bytes();
+            bytes.extend_from_slice(&body);
+            return self.write(bytes, true).await;
+        }
+        if self.framing == Framing::ContentLength {
+            let frame = format!("Content-Length: {}\r\n\r\n{}", message.len(), message);
+            return self.write(frame.into_bytes(), true).await;
+        }
+        let message_with_newline = format!("{}\n", message);
+        self.write(message_with_newline.into_bytes(), false).await
+    }
+
+    /// Write outgoing bytes, directly or through the send queue
+    async fn write(&mut self, bytes: Vec<u8>, flush: bool) -> McpResult<()> {
+        // The compression offer goes before anything else
+        #[cfg(feature = "compression")]
+        let bytes = match self.compression.take_offer() {
+            Some(offer) => [offer.into_bytes(), bytes].concat(),
+            None => bytes,
+        };
+        if let (Some(capacity), Writer::Direct(_)) = (self.send_queue, &self.writer) {
+            self.start_queue(capacity);
+        }
+        match &mut self.writer {
+            Writer::Direct(writer) => {
+                within(self.write_timeout, write_chunk(writer, &bytes, flush)).await
+            }
+            Writer::Queued { chunks, .. } => match chunks.try_send((bytes, flush)) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => Err(helpers::transport_error(&format!(
+                    "Failed to send: send queue full ({} pending)",
+                    chunks.max_capacity()
+                ))),
+                // The task stopped on a write error
+                Err(TrySendError::Closed(_)) => match self.stop_queue().await {
+                    Err(e) => Err(e),
+                    Ok(()) => Err(helpers::transport_error(
+                        "Failed to send: connection closed",
+                    )),
+                },
+            },
+            Writer::Stopped(reason) => Err(helpers::transport_error(&format!(
+                "Failed to send: {}",
+                reason
+            ))),
+        }
+    }
+
+    /// Hand the writer to a task draining the send queue
+    fn start_queue(&mut self, capacity: usize) {
+        let writer = std::mem::replace(&mut self.writer, Writer::Stopped(String::new()));
+        let Writer::Direct(mut writer) = writer else {
+            self.writer = writer;
+            return;
+        };
+        let (chunks, mut queue) = mpsc::channel::<Chunk>(capacity);
+        let write_timeout = self.write_timeout;
+        let task = tokio::spawn(async move {
+            while let Some((bytes, flush)) = queue.recv().await {
+                within(write_timeout, write_chunk(&mut writer, &bytes, flush)).await?;
+            }
+            within(write_timeout, shutdown(&mut writer)).await
+        });
+        self.writer = Writer::Queued { chunks, task };
+    }
+
+    /// Stop the queue task once it wrote what was queued, returning the
+    /// error it stopped on if any
+    async fn stop_queue(&mut self) -> McpResult<()> {
+        let writer = std::mem::replace(
+            &mut self.writer,
+            Writer::Stopped("connection closed".to_string()),
+        );
+        let Writer::Queued { chunks, task } = writer else {
+            self.writer = writer;
+            return Ok(());
+        };
+        drop(chunks);
+        let result = task.await.unwrap_or_else(|e| {
+            Err(helpers::transport_error(&format!(
+                "Writer task failed: {}",
+                e
+            )))
+        });
+        if let Err(e) = &result {
+            self.writer = Writer::Stopped(e.to_string());
+        }
+        result
+    }
+}
+
+/// Run a write within the timeout, if any
+async fn within<F>(limit: Option<Duration>, write: F) -> McpResult<()>
+where
+    F: std::future::Future<Output = McpResult<()>>,
+{
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, write)
+            .await
+            .unwrap_or_else(|_| Err(helpers::send_timeout(limit))),
+        None => write.await,
     }
 }
 
+async fn write_chunk<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    bytes: &[u8],
+    flush: bool,
+) -> McpResult<()> {
+    writer
+        .write_all(bytes)
+        .await
+        .map_err(|e| helpers::transport_error(&format!("Failed to send: {}", e)))?;
+    if flush {
+        writer
+            .flush()
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to flush: {}", e)))?;
+    }
+    Ok(())
+}
+
+async fn shutdown<W: AsyncWrite + Unpin>(writer: &mut W) -> McpResult<()> {
+    // shutdown flushes buffered data before closing the write half
+    writer
+        .shutdown()
+        .await
+        .map_err(|e| helpers::transport_error(&format!("Failed to close: {}", e)))
+}
+
+/// Decode a received message and check it looks like JSON-RPC 2.0
+pub(crate) fn checked_message(bytes: Vec<u8>) -> McpResult<String> {
+    let message = String::from_utf8(bytes)
+        .map_err(|_| helpers::protocol_error("Invalid UTF-8 in message"))?;
+    if !message.contains("\"jsonrpc\":\"2.0\"") && !message.contains("\"jsonrpc\": \"2.0\"") {
+        return Err(helpers::protocol_error("Invalid JSON-RPC message"));
+    }
+    Ok(message)
+}
+
 #[async_trait]
 impl<T> Transport for JsonRpcTransport<ReadHalf<T>, WriteHalf<T>>
 where
     T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
     async fn receive(&mut self) -> McpResult<String> {
-        let mut line = String::new();
-        match self.reader.read_line(&mut line).await {
-            Ok(0) => Err(helpers::transport_error("Connection closed")),
-            Ok(_) => {
-                if !line.contains("\"jsonrpc\":\"2.0\"") && !line.contains("\"jsonrpc\": \"2.0\"") {
-                    return Err(helpers::protocol_error("Invalid JSON-RPC message"));
-                }
-                Ok(line)
-            }
-            Err(e) => Err(helpers::transport_error(&format!("Failed to read: {}", e))),
+        match self.read_timeout {
+            // Receiving is cancel safe, so timing out loses nothing
+            Some(limit) => tokio::time::timeout(limit, self.receive_framed())
+                .await
+                .unwrap_or_else(|_| Err(helpers::receive_timeout(limit))),
+            None => self.receive_framed().await,
         }
     }
 
     async fn send(&mut self, message: &str) -> McpResult<()> {
-        let message_with_newline = format!("{}\n", message);
-        self.writer
-            .write_all(message_with_newline.as_bytes())
+        self.write_message(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        // The length of a frame is written before its body
+        self.framing != Framing::ContentLength
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        if self.framing == Framing::ContentLength {
+            self.outgoing.push_str(part);
+            if !end {
+                return Ok(());
+            }
+            let message = std::mem::take(&mut self.outgoing);
+            return self.write_message(&message).await;
+        }
+        let mut bytes = part.as_bytes().to_vec();
+        if end {
+            bytes.push(b'\n');
+        }
+        self.write(bytes, end).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        match &mut self.writer {
+            Writer::Direct(writer) => within(self.write_timeout, shutdown(writer)).await,
+            // The task shuts the writer down once the queue is written
+            Writer::Queued { .. } => self.stop_queue().await,
+            Writer::Stopped(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, AsyncReadExt, DuplexStream};
+
+    const PING: &str = r#"{"jsonrpc":"2.0","method":"ping"}"#;
+    const CALL: &str = r#"{"jsonrpc":"2.0","method":"call","params":{"é":1},"id":1}"#;
+
+    fn frame(message: &str) -> String {
+        format!(
+            "Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}",
+            message.len(),
+            message
+        )
+    }
+
+    fn pair(
+        framing: Framing,
+    ) -> (
+        JsonRpcTransport<ReadHalf<DuplexStream>, WriteHalf<DuplexStream>>,
+        DuplexStream,
+    ) {
+        let (local, remote) = duplex(1 << 16);
+        (JsonRpcTransport::new(local).with_framing(framing), remote)
+    }
+
+    #[tokio::test]
+    async fn receives_content_length_frames_split_across_reads() {
+        let (mut transport, mut peer) = pair(Framing::ContentLength);
+        let bytes = frame(CALL) + &frame(PING);
+        tokio::spawn(async move {
+            for chunk in bytes.as_bytes().chunks(7) {
+                peer.write_all(chunk).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+            peer
+        });
+
+        assert_eq!(transport.receive().await.unwrap(), CALL);
+        assert_eq!(transport.receive().await.unwrap(), PING);
+    }
+
+    #[tokio::test]
+    async fn sends_content_length_frames() {
+        let (mut transport, mut peer) = pair(Framing::ContentLength);
+        assert!(!transport.supports_partial_send());
+        transport.send(CALL).await.unwrap();
+        transport.send_part("[", false).await.unwrap();
+        transport.send_part(PING, false).await.unwrap();
+        transport.send_part("]", true).await.unwrap();
+        transport.close().await.unwrap();
+
+        let mut written = String::new();
+        peer.read_to_string(&mut written).await.unwrap();
+        let batch = format!("[{}]", PING);
+        assert_eq!(
+            written,
+            format!(
+                "Content-Length: {}\r\n\r\n{}Content-Length: {}\r\n\r\n{}",
+                CALL.len(),
+                CALL,
+                batch.len(),
+                batch
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn auto_framing_follows_the_first_message() {
+        let (mut transport, mut peer) = pair(Framing::Auto);
+        peer.write_all(frame(PING).as_bytes()).await.unwrap();
+        assert_eq!(transport.receive().await.unwrap(), PING);
+        assert_eq!(transport.framing(), Framing::ContentLength);
+
+        let (mut transport, mut peer) = pair(Framing::Auto);
+        peer.write_all(format!("\n{}\n", PING).as_bytes())
             .await
-            .map_err(|e| helpers::transport_error(&format!("Failed to send: {}", e)))
+            .unwrap();
+        assert_eq!(transport.receive().await.unwrap().trim_end(), PING);
+        assert_eq!(transport.framing(), Framing::NewlineDelimited);
+    }
+
+    #[tokio::test]
+    async fn rejects_frames_without_content_length() {
+        let (mut transport, mut peer) = pair(Framing::ContentLength);
+        peer.write_all(b"X-Foo: 1\r\n\r\n").await.unwrap();
+        let err = transport.receive().await.unwrap_err();
+        assert!(err.to_string().contains("Missing Content-Length header"));
+
+        // The next frame is still read
+        peer.write_all(frame(PING).as_bytes()).await.unwrap();
+        assert_eq!(transport.receive().await.unwrap(), PING);
+    }
+
+    #[tokio::test]
+    async fn cancelled_receive_resumes_the_frame() {
+        let (mut transport, mut peer) = pair(Framing::ContentLength);
+        let bytes = frame(CALL);
+        let (head, tail) = bytes.split_at(bytes.len() - 5);
+        peer.write_all(head.as_bytes()).await.unwrap();
+        let cancelled = tokio::time::timeout(Duration::from_millis(10), transport.receive()).await;
+        assert!(cancelled.is_err());
+
+        peer.write_all(tail.as_bytes()).await.unwrap();
+        assert_eq!(transport.receive().await.unwrap(), CALL);
+    }
+
+    #[tokio::test]
+    async fn skips_lines_over_the_size_limit() {
+        let (transport, mut peer) = pair(Framing::NewlineDelimited);
+        let mut transport = transport.with_max_message_size(100);
+        let big = format!(r#"{{"jsonrpc":"2.0","method":"{}"}}"#, "a".repeat(500));
+        let bytes = format!("{}\n{}\n", big, PING);
+        tokio::spawn(async move {
+            for chunk in bytes.as_bytes().chunks(64) {
+                peer.write_all(chunk).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+            peer
+        });
+
+        let err = transport.receive().await.unwrap_err();
+        assert!(crate::error::is_message_too_large(&err));
+        assert_eq!(transport.receive().await.unwrap().trim_end(), PING);
+    }
+
+    #[tokio::test]
+    async fn skips_frames_over_the_size_limit() {
+        let (transport, mut peer) = pair(Framing::ContentLength);
+        let mut transport = transport.with_max_message_size(100);
+        assert_eq!(transport.max_message_size(), Some(100));
+        let big = format!(r#"{{"jsonrpc":"2.0","method":"{}"}}"#, "a".repeat(500));
+        peer.write_all((frame(&big) + &frame(PING)).as_bytes())
+            .await
+            .unwrap();
+
+        let err = transport.receive().await.unwrap_err();
+        assert!(crate::error::is_message_too_large(&err));
+        assert_eq!(transport.receive().await.unwrap(), PING);
+    }
+
+    #[tokio::test]
+    async fn accepts_messages_at_the_size_limit() {
+        let (transport, mut peer) = pair(Framing::NewlineDelimited);
+        let mut transport = transport.with_max_message_size(PING.len());
+        peer.write_all(format!("{}\n", PING).as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(transport.receive().await.unwrap().trim_end(), PING);
+    }
+
+    #[cfg(feature = "compression")]
+    mod compression {
+        use super::*;
+
+        fn big_message() -> String {
+            format!(r#"{{"jsonrpc":"2.0","method":"{}"}}"#, "x".repeat(5000))
+        }
+
+        #[tokio::test]
+        async fn negotiates_and_round_trips_compressed_messages() {
+            for framing in [Framing::NewlineDelimited, Framing::ContentLength] {
+                let (local, remote) = duplex(1 << 16);
+                let mut client = JsonRpcTransport::new(local)
+                    .with_framing(framing)
+                    .with_compression(&[Compression::Zstd, Compression::Gzip])
+                    .with_compression_threshold(100);
+                let mut server = JsonRpcTransport::new(remote)
+                    .with_framing(Framing::Auto)
+                    .with_compression(&[Compression::Gzip, Compression::Zstd]);
+                assert_eq!(client.compression(), None);
+
+                let big = big_message();
+                client.send(&big).await.unwrap();
+                assert_eq!(server.receive().await.unwrap().trim_end(), big);
+                assert_eq!(server.compression(), Some(Compression::Gzip));
+
+                server.send(&big).await.unwrap();
+                assert_eq!(client.receive().await.unwrap().trim_end(), big);
+                assert_eq!(client.compression(), Some(Compression::Zstd));
+
+                client.send(&big).await.unwrap();
+                assert_eq!(server.receive().await.unwrap().trim_end(), big);
+            }
+        }
+
+        #[tokio::test]
+        async fn compresses_only_messages_over_the_threshold() {
+            let (mut transport, mut peer) = pair(Framing::NewlineDelimited);
+            transport = transport
+                .with_compression(&[Compression::Gzip])
+                .with_compression_threshold(100);
+            peer.write_all(format!("~accept-encoding gzip\n{}\n", PING).as_bytes())
+                .await
+                .unwrap();
+            assert_eq!(transport.receive().await.unwrap().trim_end(), PING);
+
+            let big = big_message();
+            transport.send(PING).await.unwrap();
+            transport.send(&big).await.unwrap();
+            transport.close().await.unwrap();
+
+            let mut written = Vec::new();
+            peer.read_to_end(&mut written).await.unwrap();
+            let body = Compression::Gzip.encode(big.as_bytes()).unwrap();
+            let mut expected =
+                format!("~accept-encoding gzip\n{}\n~gzip {}\n", PING, body.len()).into_bytes();
+            expected.extend_from_slice(&body);
+            assert_eq!(written, expected);
+        }
+
+        #[tokio::test]
+        async fn sends_uncompressed_to_a_peer_without_an_offer() {
+            let (mut transport, mut peer) = pair(Framing::NewlineDelimited);
+            transport = transport
+                .with_compression(&[Compression::Zstd])
+                .with_compression_threshold(1);
+            peer.write_all(format!("{}\n", PING).as_bytes())
+                .await
+                .unwrap();
+            assert_eq!(transport.receive().await.unwrap().trim_end(), PING);
+            assert_eq!(transport.compression(), None);
+
+            transport.send(PING).await.unwrap();
+            transport.close().await.unwrap();
+            let mut written = String::new();
+            peer.read_to_string(&mut written).await.unwrap();
+            assert_eq!(written, format!("~accept-encoding zstd\n{}\n", PING));
+        }
     }
 }
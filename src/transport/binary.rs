@@ -0,0 +1,98 @@
+//! Byte-oriented transports
+//!
+//! [`Transport`] carries messages as text. Encodings that are not text, such
+//! as MessagePack or CBOR, implement [`BytesTransport`] instead, and
+//! [`Utf8Transport`] adapts them for the processor once they produce JSON:
+//!
+//! ```rust,ignore
+//! let transport = Utf8Transport::new(CborToJson::new(stream));
+//! JsonRpcProcessor::new(transport, registry).run().await?;
+//! ```
+//!
+//! Every [`Transport`] is also a [`BytesTransport`], exchanging the UTF-8
+//! bytes of its messages, so code written against bytes takes existing
+//! transports as they are.
+
+use crate::error::helpers;
+use crate::transport::base::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+
+/// Transport exchanging messages as bytes
+///
+/// The methods mirror those of [`Transport`], under other names so both
+/// traits can be in scope together.
+#[async_trait]
+pub trait BytesTransport: Send {
+    /// Receive the next message
+    ///
+    /// Should be cancel safe, like [`Transport::receive`].
+    async fn receive_bytes(&mut self) -> McpResult<Vec<u8>>;
+
+    /// Send a message
+    async fn send_bytes(&mut self, message: &[u8]) -> McpResult<()>;
+
+    /// Flush pending writes and shut down the sending side, see
+    /// [`Transport::close`]
+    async fn close_bytes(&mut self) -> McpResult<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: Transport + ?Sized> BytesTransport for T {
+    async fn receive_bytes(&mut self) -> McpResult<Vec<u8>> {
+        self.receive().await.map(String::into_bytes)
+    }
+
+    async fn send_bytes(&mut self, message: &[u8]) -> McpResult<()> {
+        let message = std::str::from_utf8(message)
+            .map_err(|_| helpers::protocol_error("Invalid UTF-8 in message"))?;
+        self.send(message).await
+    }
+
+    async fn close_bytes(&mut self) -> McpResult<()> {
+        self.close().await
+    }
+}
+
+/// [`Transport`] over a [`BytesTransport`] whose messages are UTF-8 text
+///
+/// A received message that is not valid UTF-8 fails its receive, and the
+/// next message can be received.
+pub struct Utf8Transport<B> {
+    inner: B,
+}
+
+impl<B: BytesTransport> Utf8Transport<B> {
+    /// Exchange the messages of `inner` as text
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+
+    /// The byte transport
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    /// Unwrap the byte transport
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<B: BytesTransport> Transport for Utf8Transport<B> {
+    async fn receive(&mut self) -> McpResult<String> {
+        let message = self.inner.receive_bytes().await?;
+        String::from_utf8(message).map_err(|_| helpers::protocol_error("Invalid UTF-8 in message"))
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.inner.send_bytes(message.as_bytes()).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.inner.close_bytes().await
+    }
+}
@@ -0,0 +1,396 @@
+//! Codec negotiation
+//!
+//! A listener serving both JSON and MessagePack clients on one port wraps each
+//! accepted connection in a [`NegotiatedTransport`], which sniffs the first
+//! byte the client sends and selects the codec for the rest of the session:
+//!
+//! - `{`, `[` or whitespace: newline-delimited JSON, as [`JsonRpcTransport`]
+//! - a MessagePack map or array marker: back-to-back MessagePack values, as
+//!   [`MessagePackTransport`]
+//!
+//! Any other first byte is rejected. Whatever the codec, messages reach the
+//! processor as JSON text, so tools and middleware are unaware of it; use
+//! [`NegotiatedTransport::codec`] to label the connection.
+
+use crate::error::helpers;
+use crate::transport::base::{JsonRpcTransport, Transport};
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::fmt;
+use tokio::io::{
+    split, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+    ReadHalf, WriteHalf,
+};
+
+/// Largest MessagePack message accepted by default, in bytes
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Wire encoding of a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    /// Newline-delimited JSON
+    Json,
+    /// Back-to-back MessagePack values
+    MessagePack,
+}
+
+impl Codec {
+    /// Codec a session starting with the given byte uses, if any
+    pub fn sniff(first: u8) -> Option<Self> {
+        match first {
+            b'{' | b'[' | b' ' | b'\t' | b'\r' | b'\n' => Some(Self::Json),
+            // fixmap, fixarray, array 16/32 and map 16/32
+            0x80..=0x9f | 0xdc..=0xdf => Some(Self::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Name of the codec, e.g. for connection labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::MessagePack => "msgpack",
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Transport exchanging MessagePack-encoded messages
+///
+/// Messages are converted to and from JSON text at the boundary, so a
+/// MessagePack binary value, which has no JSON equivalent, is rejected.
+pub struct MessagePackTransport<T> {
+    reader: ReadHalf<T>,
+    writer: WriteHalf<T>,
+    /// Bytes received but not decoded yet
+    pending: Vec<u8>,
+    /// How far the message at the start of `pending` was scanned
+    scan: Scan,
+    /// Parts of a message sent with `send_part`
+    parts: String,
+    max_message_size: usize,
+}
+
+impl<T> MessagePackTransport<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    /// Create a transport over the given stream
+    pub fn new(io: T) -> Self {
+        let (reader, writer) = split(io);
+        Self {
+            reader,
+            writer,
+            pending: Vec::new(),
+            scan: Scan::default(),
+            parts: String::new(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// Reject incoming messages larger than the given size, in bytes
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Decode the first complete message of `pending`, if any
+    ///
+    /// The message is only decoded once all its bytes are received, so a
+    /// message arriving in many reads is scanned once, not decoded again on
+    /// every read.
+    fn decode_pending(&mut self) -> McpResult<Option<String>> {
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+
+        let length = match self.scan.advance(&self.pending, self.max_message_size) {
+            Ok(Some(length)) => length,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                // The stream cannot be resynchronized after a malformed value
+                self.pending.clear();
+                self.scan = Scan::default();
+                return Err(e);
+            }
+        };
+        self.scan = Scan::default();
+        let decoded = rmp_serde::from_slice::<Value>(&self.pending[..length]);
+        self.pending.drain(..length);
+        match decoded {
+            Ok(value) => Ok(Some(value.to_string())),
+            Err(e) => Err(helpers::protocol_error(&format!(
+                "Invalid MessagePack message: {}",
+                e
+            ))),
+        }
+    }
+
+    async fn write_encoded(&mut self, message: &str) -> McpResult<()> {
+        let value: Value = serde_json::from_str(message).map_err(helpers::json_error)?;
+        let encoded = rmp_serde::to_vec(&value).map_err(|e| {
+            helpers::protocol_error(&format!("Failed to encode MessagePack: {}", e))
+        })?;
+        self.writer
+            .write_all(&encoded)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to send: {}", e)))?;
+        self.writer
+            .flush()
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to flush: {}", e)))
+    }
+}
+
+/// Progress through the message at the start of the received bytes
+///
+/// MessagePack containers announce how many values they hold, so a message
+/// is complete once as many values as announced were skipped. Values already
+/// skipped are not scanned again when more bytes arrive.
+#[derive(Debug)]
+struct Scan {
+    /// Length of the values skipped so far
+    offset: usize,
+    /// Values still to skip before the message is complete
+    left: usize,
+}
+
+impl Default for Scan {
+    fn default() -> Self {
+        Self { offset: 0, left: 1 }
+    }
+}
+
+impl Scan {
+    /// Skip the values available in `buf`, returning the length of the
+    /// message once it is complete
+    ///
+    /// Fails as soon as the message is known to exceed `max` bytes, before
+    /// its bytes arrive.
+    fn advance(&mut self, buf: &[u8], max: usize) -> McpResult<Option<usize>> {
+        while self.left > 0 {
+            let (length, values) = match value_header(&buf[self.offset..])? {
+                Some(header) => header,
+                None => return Ok(None),
+            };
+            let left = self.left - 1 + values;
+            // Every value left takes a byte at least
+            let end = self.offset.saturating_add(length);
+            if end.saturating_add(left) > max {
+                return Err(helpers::protocol_error(&format!(
+                    "MessagePack message exceeds {} bytes",
+                    max
+                )));
+            }
+            if buf.len() < end {
+                return Ok(None);
+            }
+            self.offset = end;
+            self.left = left;
+        }
+        Ok(Some(self.offset))
+    }
+}
+
+/// Length of the value starting `buf`, without the values it contains, and
+/// the number of values it contains; `None` until its header is received
+fn value_header(buf: &[u8]) -> McpResult<Option<(usize, usize)>> {
+    let marker = match buf.first() {
+        Some(&marker) => marker,
+        None => return Ok(None),
+    };
+    // Big-endian length of `size` bytes following the marker
+    let length = |size: usize| -> Option<usize> {
+        let bytes = buf.get(1..1 + size)?;
+        Some(
+            bytes
+                .iter()
+                .fold(0, |length, &byte| (length << 8) | byte as usize),
+        )
+    };
+    let header = match marker {
+        // positive fixint, nil, false, true, negative fixint
+        0x00..=0x7f | 0xc0 | 0xc2 | 0xc3 | 0xe0..=0xff => Some((1, 0)),
+        0x80..=0x8f => Some((1, 2 * (marker & 0x0f) as usize)),
+        0x90..=0x9f => Some((1, (marker & 0x0f) as usize)),
+        0xa0..=0xbf => Some((1 + (marker & 0x1f) as usize, 0)),
+        // bin 8/16/32 and str 8/16/32
+        0xc4 | 0xd9 => length(1).map(|length| (2 + length, 0)),
+        0xc5 | 0xda => length(2).map(|length| (3 + length, 0)),
+        0xc6 | 0xdb => length(4).map(|length| (5 + length, 0)),
+        // ext 8/16/32, whose length is followed by a type byte
+        0xc7 => length(1).map(|length| (3 + length, 0)),
+        0xc8 => length(2).map(|length| (4 + length, 0)),
+        0xc9 => length(4).map(|length| (6 + length, 0)),
+        // uint 8, int 8 and the fixed-size numbers and extensions
+        0xcc | 0xd0 => Some((2, 0)),
+        0xcd | 0xd1 => Some((3, 0)),
+        0xca | 0xce | 0xd2 => Some((5, 0)),
+        0xcb | 0xcf | 0xd3 => Some((9, 0)),
+        0xd4 => Some((3, 0)),
+        0xd5 => Some((4, 0)),
+        0xd6 => Some((6, 0)),
+        0xd7 => Some((10, 0)),
+        0xd8 => Some((18, 0)),
+        // array 16/32 and map 16/32
+        0xdc => length(2).map(|values| (3, values)),
+        0xdd => length(4).map(|values| (5, values)),
+        0xde => length(2).map(|values| (3, 2 * values)),
+        0xdf => length(4).map(|values| (5, 2 * values)),
+        0xc1 => {
+            return Err(helpers::protocol_error(
+                "Invalid MessagePack message: reserved marker 0xc1",
+            ))
+        }
+    };
+    Ok(header)
+}
+
+#[async_trait]
+impl<T> Transport for MessagePackTransport<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    async fn receive(&mut self) -> McpResult<String> {
+        loop {
+            if let Some(message) = self.decode_pending()? {
+                return Ok(message);
+            }
+            // read_buf is cancel safe: received bytes stay in `pending`
+            match self.reader.read_buf(&mut self.pending).await {
+                Ok(0) if self.pending.is_empty() => return Err(helpers::connection_closed()),
+                Ok(0) => {
+                    self.pending.clear();
+                    self.scan = Scan::default();
+                    return Err(helpers::protocol_error(
+                        "Connection closed in the middle of a message",
+                    ));
+                }
+                Ok(_) => {}
+                Err(e) => return Err(helpers::transport_error(&format!("Failed to read: {}", e))),
+            }
+        }
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.write_encoded(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        true
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        // A MessagePack value can only be encoded once the JSON text is complete
+        self.parts.push_str(part);
+        if !end {
+            return Ok(());
+        }
+        let message = std::mem::take(&mut self.parts);
+        self.write_encoded(&message).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.writer
+            .shutdown()
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to close: {}", e)))
+    }
+}
+
+/// Newline-delimited JSON over a sniffed stream
+type JsonTransport<T> = JsonRpcTransport<ReadHalf<BufReader<T>>, WriteHalf<BufReader<T>>>;
+
+enum Negotiated<T> {
+    Json(Box<JsonTransport<T>>),
+    MessagePack(MessagePackTransport<BufReader<T>>),
+}
+
+/// Transport whose codec is selected from the first byte the peer sends
+pub struct NegotiatedTransport<T> {
+    inner: Negotiated<T>,
+    codec: Codec,
+}
+
+impl<T> NegotiatedTransport<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    /// Wait for the peer's first byte and select the codec it implies
+    ///
+    /// The sniffed byte is not consumed. This waits for the peer, so accept
+    /// loops should negotiate in the task serving the connection, possibly
+    /// under a timeout.
+    pub async fn accept(io: T) -> McpResult<Self> {
+        let mut buffered = BufReader::new(io);
+        let first = match buffered.fill_buf().await {
+            Ok([]) => return Err(helpers::connection_closed()),
+            Ok(bytes) => bytes[0],
+            Err(e) => return Err(helpers::transport_error(&format!("Failed to read: {}", e))),
+        };
+
+        let codec = Codec::sniff(first).ok_or_else(|| {
+            helpers::protocol_error(&format!(
+                "Unrecognized encoding (first byte 0x{:02x})",
+                first
+            ))
+        })?;
+        let inner = match codec {
+            Codec::Json => Negotiated::Json(Box::new(JsonRpcTransport::new(buffered))),
+            Codec::MessagePack => Negotiated::MessagePack(MessagePackTransport::new(buffered)),
+        };
+        Ok(Self { inner, codec })
+    }
+
+    /// Codec selected for the session
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+}
+
+#[async_trait]
+impl<T> Transport for NegotiatedTransport<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    async fn receive(&mut self) -> McpResult<String> {
+        match &mut self.inner {
+            Negotiated::Json(transport) => transport.receive().await,
+            Negotiated::MessagePack(transport) => transport.receive().await,
+        }
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        match &mut self.inner {
+            Negotiated::Json(transport) => transport.send(message).await,
+            Negotiated::MessagePack(transport) => transport.send(message).await,
+        }
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        match &self.inner {
+            Negotiated::Json(transport) => transport.supports_partial_send(),
+            Negotiated::MessagePack(transport) => transport.supports_partial_send(),
+        }
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        match &mut self.inner {
+            Negotiated::Json(transport) => transport.send_part(part, end).await,
+            Negotiated::MessagePack(transport) => transport.send_part(part, end).await,
+        }
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        match &mut self.inner {
+            Negotiated::Json(transport) => transport.close().await,
+            Negotiated::MessagePack(transport) => transport.close().await,
+        }
+    }
+}
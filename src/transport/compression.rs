@@ -0,0 +1,201 @@
+//! Per-message compression
+//!
+//! A [`JsonRpcTransport`](super::JsonRpcTransport) built with
+//! [`with_compression`](super::JsonRpcTransport::with_compression) starts the
+//! connection by writing the algorithms it accepts on a line of its own:
+//!
+//! ```text
+//! ~accept-encoding zstd gzip
+//! ```
+//!
+//! Once the peer's line was received, messages of at least the
+//! [threshold](super::JsonRpcTransport::with_compression_threshold) are sent
+//! compressed with the first algorithm of the list both ends accept; smaller
+//! ones, and everything sent before, stay plain text. A compressed message is
+//! announced by a `Content-Encoding` header with `Content-Length` framing, and
+//! by a line giving the algorithm and compressed length otherwise:
+//!
+//! ```text
+//! ~zstd 1834
+//! <1834 bytes>
+//! ```
+//!
+//! Both ends must enable compression: a peer that does not sends no offer, so
+//! nothing is compressed towards it, but it would reject the offer line.
+
+use crate::error::helpers;
+use mcp_error::Result as McpResult;
+use std::fmt;
+use std::io::{Read, Write};
+
+/// Smallest message compressed by default, in bytes
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Prefix of the line offering compression algorithms
+const OFFER: &str = "~accept-encoding";
+
+/// Compression algorithm of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// Zstandard, faster and usually smaller
+    Zstd,
+    /// Gzip, for peers without zstd
+    Gzip,
+}
+
+impl Compression {
+    /// Name of the algorithm on the wire
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zstd",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    /// Algorithm with the given wire name, if supported
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "zstd" => Some(Self::Zstd),
+            "gzip" => Some(Self::Gzip),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn encode(&self, bytes: &[u8]) -> McpResult<Vec<u8>> {
+        let encoded = match self {
+            Self::Zstd => zstd::stream::encode_all(bytes, 0),
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes).and_then(|()| encoder.finish())
+            }
+        };
+        encoded.map_err(|e| helpers::transport_error(&format!("Failed to compress: {}", e)))
+    }
+
+    /// Decompress a message, failing once it exceeds `max` bytes
+    pub(crate) fn decode(&self, bytes: &[u8], max: Option<usize>) -> McpResult<Vec<u8>> {
+        let decoder: Box<dyn Read + '_> = match self {
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(bytes).map_err(|e| {
+                helpers::protocol_error(&format!("Invalid {} message: {}", self, e))
+            })?),
+            Self::Gzip => Box::new(flate2::read::GzDecoder::new(bytes)),
+        };
+        // One byte over the limit tells a message at the limit from a larger one
+        let limit = max.map_or(u64::MAX, |max| max as u64 + 1);
+        let mut decoded = Vec::new();
+        decoder
+            .take(limit)
+            .read_to_end(&mut decoded)
+            .map_err(|e| helpers::protocol_error(&format!("Invalid {} message: {}", self, e)))?;
+        match max {
+            Some(max) if decoded.len() > max => Err(helpers::message_too_large(max)),
+            _ => Ok(decoded),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Compression settings of a transport, and what its peer accepts
+#[derive(Debug, Clone)]
+pub(crate) struct Negotiation {
+    /// Algorithms accepted, by preference; none disables compression
+    pub(crate) accepted: Vec<Compression>,
+    pub(crate) threshold: usize,
+    offer_sent: bool,
+    /// Algorithms the peer accepts, once its offer was received or found
+    /// missing
+    peer: Option<Vec<Compression>>,
+}
+
+impl Default for Negotiation {
+    fn default() -> Self {
+        Self {
+            accepted: Vec::new(),
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            offer_sent: false,
+            peer: None,
+        }
+    }
+}
+
+impl Negotiation {
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.accepted.is_empty()
+    }
+
+    /// Offer line to write before anything else, once
+    pub(crate) fn take_offer(&mut self) -> Option<String> {
+        if !self.is_enabled() || self.offer_sent {
+            return None;
+        }
+        self.offer_sent = true;
+        let names: Vec<_> = self.accepted.iter().map(Compression::as_str).collect();
+        Some(format!("{} {}\n", OFFER, names.join(" ")))
+    }
+
+    /// Whether the peer's offer is still to be read
+    pub(crate) fn awaits_offer(&self) -> bool {
+        self.is_enabled() && self.peer.is_none()
+    }
+
+    /// Record the peer's offer line, or `None` if it sent none
+    pub(crate) fn receive_offer(&mut self, line: Option<&str>) -> McpResult<()> {
+        let Some(line) = line else {
+            self.peer = Some(Vec::new());
+            return Ok(());
+        };
+        let names = line
+            .trim()
+            .strip_prefix(OFFER)
+            .ok_or_else(|| helpers::protocol_error("Invalid compression offer"))?;
+        // Algorithms this build does not know are left out
+        self.peer = Some(
+            names
+                .split_whitespace()
+                .filter_map(Compression::from_name)
+                .collect(),
+        );
+        Ok(())
+    }
+
+    /// Algorithm to send a message of `size` bytes with, if compressed
+    pub(crate) fn outgoing(&self, size: usize) -> Option<Compression> {
+        if size < self.threshold {
+            return None;
+        }
+        self.negotiated()
+    }
+
+    /// Algorithm messages sent are compressed with, once negotiated
+    pub(crate) fn negotiated(&self) -> Option<Compression> {
+        let peer = self.peer.as_ref()?;
+        self.accepted
+            .iter()
+            .copied()
+            .find(|algorithm| peer.contains(algorithm))
+    }
+
+    /// Check a message announced as compressed with `name` can be decoded
+    pub(crate) fn incoming(&self, name: &str) -> McpResult<Compression> {
+        Compression::from_name(name)
+            .filter(|algorithm| self.accepted.contains(algorithm))
+            .ok_or_else(|| {
+                helpers::protocol_error(&format!("Unsupported content encoding: {}", name.trim()))
+            })
+    }
+}
+
+/// Parse the line announcing a compressed message, `~<algorithm> <length>`
+pub(crate) fn parse_header(line: &str) -> McpResult<(&str, usize)> {
+    line.trim()
+        .strip_prefix('~')
+        .and_then(|header| header.split_once(' '))
+        .and_then(|(name, length)| Some((name, length.trim().parse().ok()?)))
+        .ok_or_else(|| helpers::protocol_error("Invalid compressed message header"))
+}
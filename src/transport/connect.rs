@@ -0,0 +1,299 @@
+//! TCP connection options
+//!
+//! [`TcpTransport::connect`](super::TcpTransport::connect) resolves host names
+//! and tries each resolved address in turn, like the standard library.
+//! [`ConnectConfig`] chooses how the addresses are tried, and bounds each
+//! attempt:
+//!
+//! ```rust,ignore
+//! let config = ConnectConfig::new()
+//!     .with_strategy(ConnectStrategy::HappyEyeballs(DEFAULT_CONNECTION_ATTEMPT_DELAY))
+//!     .with_attempt_timeout(Duration::from_secs(5));
+//! let transport = TcpTransport::connect_with("mcp.example.com:9000", &config).await?;
+//! ```
+//!
+//! Connections can also go through a [`Proxy`], which then resolves the host
+//! name itself.
+
+use crate::transport::proxy::Proxy;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::time::Duration;
+use tokio::net::{lookup_host, TcpStream};
+use tokio::task::JoinSet;
+
+/// Delay between attempts recommended by RFC 8305
+pub const DEFAULT_CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Order and pace in which the resolved addresses are tried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectStrategy {
+    /// One at a time, in the order the resolver returned them
+    #[default]
+    Sequential,
+    /// One at a time, IPv4 addresses first
+    PreferIpv4,
+    /// One at a time, IPv6 addresses first
+    PreferIpv6,
+    /// Alternating address families, starting a new attempt whenever the
+    /// previous one failed or has not succeeded within the delay, the first
+    /// connection made winning (RFC 8305)
+    HappyEyeballs(Duration),
+}
+
+/// Address to connect to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetAddr {
+    /// Addresses tried in turn
+    Addresses(Vec<SocketAddr>),
+    /// Host name or IP address, and port, resolved when connecting
+    Host(String, u16),
+}
+
+/// Values usable as the address to connect to: socket addresses, `"host:port"`
+/// strings, and `(host, port)` pairs
+pub trait IntoTargetAddr {
+    /// Convert into a target, failing if a string is not `host:port`
+    fn into_target_addr(self) -> io::Result<TargetAddr>;
+}
+
+impl IntoTargetAddr for TargetAddr {
+    fn into_target_addr(self) -> io::Result<TargetAddr> {
+        Ok(self)
+    }
+}
+
+macro_rules! socket_addr_targets {
+    ($($address:ty),*) => {
+        $(impl IntoTargetAddr for $address {
+            fn into_target_addr(self) -> io::Result<TargetAddr> {
+                Ok(TargetAddr::Addresses(vec![SocketAddr::from(self)]))
+            }
+        })*
+    };
+}
+
+socket_addr_targets!(
+    SocketAddr,
+    SocketAddrV4,
+    SocketAddrV6,
+    (IpAddr, u16),
+    (Ipv4Addr, u16),
+    (Ipv6Addr, u16),
+    ([u8; 4], u16),
+    ([u16; 8], u16)
+);
+
+impl IntoTargetAddr for &[SocketAddr] {
+    fn into_target_addr(self) -> io::Result<TargetAddr> {
+        Ok(TargetAddr::Addresses(self.to_vec()))
+    }
+}
+
+impl IntoTargetAddr for Vec<SocketAddr> {
+    fn into_target_addr(self) -> io::Result<TargetAddr> {
+        Ok(TargetAddr::Addresses(self))
+    }
+}
+
+impl IntoTargetAddr for (&str, u16) {
+    fn into_target_addr(self) -> io::Result<TargetAddr> {
+        Ok(TargetAddr::Host(self.0.to_string(), self.1))
+    }
+}
+
+impl IntoTargetAddr for (String, u16) {
+    fn into_target_addr(self) -> io::Result<TargetAddr> {
+        Ok(TargetAddr::Host(self.0, self.1))
+    }
+}
+
+impl IntoTargetAddr for &str {
+    fn into_target_addr(self) -> io::Result<TargetAddr> {
+        if let Ok(address) = self.parse::<SocketAddr>() {
+            return Ok(TargetAddr::Addresses(vec![address]));
+        }
+        let (host, port) = self
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+            .filter(|(host, _)| !host.is_empty())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid address {}, expected host:port", self),
+                )
+            })?;
+        Ok(TargetAddr::Host(host.to_string(), port))
+    }
+}
+
+impl IntoTargetAddr for String {
+    fn into_target_addr(self) -> io::Result<TargetAddr> {
+        self.as_str().into_target_addr()
+    }
+}
+
+impl IntoTargetAddr for &String {
+    fn into_target_addr(self) -> io::Result<TargetAddr> {
+        self.as_str().into_target_addr()
+    }
+}
+
+/// How [`TcpTransport::connect_with`](super::TcpTransport::connect_with)
+/// tries the resolved addresses
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectConfig {
+    /// Order and pace of the attempts
+    pub strategy: ConnectStrategy,
+    /// Time given to each attempt, the OS timeout if `None`
+    pub attempt_timeout: Option<Duration>,
+    /// Proxy to connect through, directly if `None`
+    pub proxy: Option<Proxy>,
+}
+
+impl ConnectConfig {
+    /// Try the addresses one at a time, in resolution order
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the order and pace of the attempts
+    pub fn with_strategy(mut self, strategy: ConnectStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Give up on an address once connecting to it took `timeout`
+    pub fn with_attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = Some(timeout);
+        self
+    }
+
+    /// Connect through the given proxy
+    ///
+    /// The proxy resolves host names, so the strategy does not apply; the
+    /// attempt timeout bounds the whole exchange with the proxy.
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Resolve `addr` and connect to one of its addresses
+    ///
+    /// Fails with the error of the last attempt when none succeeded.
+    pub(crate) async fn connect(&self, addr: impl IntoTargetAddr) -> io::Result<TcpStream> {
+        let target = addr.into_target_addr()?;
+        if let Some(proxy) = &self.proxy {
+            return self.connect_through(proxy, target).await;
+        }
+        let mut addresses = match target {
+            TargetAddr::Addresses(addresses) => addresses,
+            TargetAddr::Host(host, port) => lookup_host((host.as_str(), port)).await?.collect(),
+        };
+        match self.strategy {
+            ConnectStrategy::Sequential => {}
+            ConnectStrategy::PreferIpv4 => addresses.sort_by_key(SocketAddr::is_ipv6),
+            ConnectStrategy::PreferIpv6 => addresses.sort_by_key(SocketAddr::is_ipv4),
+            ConnectStrategy::HappyEyeballs(delay) => {
+                return self.race(interleave(addresses), delay).await;
+            }
+        }
+
+        let mut last_error = None;
+        for address in addresses {
+            match attempt(address, self.attempt_timeout).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(no_address))
+    }
+
+    async fn connect_through(&self, proxy: &Proxy, target: TargetAddr) -> io::Result<TcpStream> {
+        let hosts = match target {
+            TargetAddr::Addresses(addresses) => addresses
+                .iter()
+                .map(|address| (address.ip().to_string(), address.port()))
+                .collect(),
+            TargetAddr::Host(host, port) => vec![(host, port)],
+        };
+        let mut last_error = None;
+        for (host, port) in hosts {
+            let connecting = proxy.connect(&host, port);
+            let connected = match self.attempt_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, connecting)
+                    .await
+                    .unwrap_or_else(|_| Err(timed_out(&format!("{}:{}", host, port)))),
+                None => connecting.await,
+            };
+            match connected {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(no_address))
+    }
+
+    /// Start an attempt per address, `delay` apart unless the previous one
+    /// failed, until one connects
+    async fn race(&self, addresses: Vec<SocketAddr>, delay: Duration) -> io::Result<TcpStream> {
+        let mut addresses = addresses.into_iter().peekable();
+        // Dropping the set aborts the attempts still running
+        let mut attempts = JoinSet::new();
+        let mut last_error = None;
+        loop {
+            if let Some(address) = addresses.next() {
+                attempts.spawn(attempt(address, self.attempt_timeout));
+            }
+            if attempts.is_empty() {
+                return Err(last_error.unwrap_or_else(no_address));
+            }
+            tokio::select! {
+                Some(finished) = attempts.join_next() => match finished {
+                    Ok(Ok(stream)) => return Ok(stream),
+                    Ok(Err(e)) => last_error = Some(e),
+                    Err(e) => last_error = Some(io::Error::other(e)),
+                },
+                _ = tokio::time::sleep(delay), if addresses.peek().is_some() => {}
+            }
+        }
+    }
+}
+
+async fn attempt(address: SocketAddr, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    let connecting = TcpStream::connect(address);
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, connecting)
+            .await
+            .map_err(|_| timed_out(&address.to_string()))?,
+        None => connecting.await,
+    }
+}
+
+/// Alternate the address families, starting with that of the first address
+fn interleave(addresses: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_is_ipv6 = addresses.first().is_some_and(SocketAddr::is_ipv6);
+    let (mut first, mut second): (Vec<_>, Vec<_>) = addresses
+        .into_iter()
+        .partition(|address| address.is_ipv6() == first_is_ipv6);
+    let mut interleaved = Vec::with_capacity(first.len() + second.len());
+    first.reverse();
+    second.reverse();
+    loop {
+        match (first.pop(), second.pop()) {
+            (None, None) => return interleaved,
+            (a, b) => interleaved.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+fn timed_out(address: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("connecting to {} timed out", address),
+    )
+}
+
+fn no_address() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no address to connect to")
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Notable conditions detected by transport wrappers
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TransportEvent {
+    /// A write did not complete within the write timeout (the peer stopped reading)
+    WriteStalled {
+        /// Configured write timeout
+        timeout: Duration,
+        /// Size of the message being written
+        bytes: usize,
+    },
+    /// A message was not delivered and was moved to the dead-letter queue
+    DeadLettered {
+        /// Size of the message
+        bytes: usize,
+    },
+    /// The connection failed and will be re-established
+    ConnectionLost {
+        /// Error the connection failed with
+        error: String,
+    },
+    /// A connection attempt failed and will be retried
+    ConnectFailed {
+        /// Attempt number, from 1
+        attempt: u32,
+        /// Error the attempt failed with
+        error: String,
+    },
+    /// A lost connection was re-established
+    Reconnected {
+        /// Attempts it took
+        attempts: u32,
+    },
+}
+
+/// Callback receiving transport events
+pub type TransportEventHandler = Arc<dyn Fn(&TransportEvent) + Send + Sync>;
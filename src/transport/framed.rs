@@ -0,0 +1,278 @@
+//! tokio-util codec
+//!
+//! [`JsonRpcCodec`] frames JSON-RPC messages for
+//! [`Framed`](tokio_util::codec::Framed), and a `Framed` using it is a
+//! [`Transport`], so any byte stream carries JSON-RPC without a transport of
+//! its own:
+//!
+//! ```rust,ignore
+//! let stream = connector.connect(domain, tcp).await?;
+//! let transport = Framed::new(stream, JsonRpcCodec::new().with_max_message_size(1 << 20));
+//! JsonRpcProcessor::new(transport, registry).run().await?;
+//! ```
+//!
+//! The codec reads the framings of [`JsonRpcTransport`](super::JsonRpcTransport),
+//! except for compressed messages.
+
+use crate::error::helpers;
+use crate::transport::base::{checked_message, Framing, Transport};
+use async_trait::async_trait;
+use bytes::{Buf, BufMut, BytesMut};
+use futures_util::{SinkExt, StreamExt};
+use mcp_error::{Error as McpError, Result as McpResult};
+use std::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+/// Encoder and decoder of JSON-RPC messages
+///
+/// Decoded items are results: a message failing its checks, e.g. over the
+/// size limit, is skipped and its error yielded, while decoding goes on with
+/// the next message.
+#[derive(Debug, Clone, Default)]
+pub struct JsonRpcCodec {
+    framing: Framing,
+    /// Framing detected from the first message, with [`Framing::Auto`]
+    detected: Option<Framing>,
+    max_message_size: Option<usize>,
+    /// Where to resume looking for the end of a line
+    next_index: usize,
+    /// The rest of the current line belongs to a message over the limit
+    discard_line: bool,
+    /// Bytes left of a frame over the limit
+    discard_bytes: usize,
+    /// `Content-Length` read from the headers of the current frame
+    content_length: Option<usize>,
+    /// The headers are read and the body is expected
+    in_body: bool,
+    encoding: Option<String>,
+}
+
+impl JsonRpcCodec {
+    /// One message per line, without size limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delimit messages as given instead of one per line
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Reject messages over `max` bytes
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = Some(max);
+        self
+    }
+
+    /// Framing in use, `None` while it is still to be detected
+    fn active_framing(&self) -> Option<Framing> {
+        match self.framing {
+            Framing::Auto => self.detected,
+            framing => Some(framing),
+        }
+    }
+
+    fn decode_line(&mut self, buf: &mut BytesMut) -> Option<McpResult<String>> {
+        loop {
+            let end = match buf[self.next_index..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+            {
+                Some(offset) => self.next_index + offset,
+                None if self.discard_line => {
+                    buf.clear();
+                    self.next_index = 0;
+                    return None;
+                }
+                None => {
+                    self.next_index = buf.len();
+                    if let Some(max) = self.max_message_size.filter(|&max| buf.len() > max) {
+                        buf.clear();
+                        self.next_index = 0;
+                        self.discard_line = true;
+                        return Some(Err(helpers::message_too_large(max)));
+                    }
+                    return None;
+                }
+            };
+            let line = buf.split_to(end + 1);
+            self.next_index = 0;
+            if std::mem::take(&mut self.discard_line) || line.trim_ascii().is_empty() {
+                continue;
+            }
+            if let Some(max) = self.max_message_size.filter(|&max| end > max) {
+                return Some(Err(helpers::message_too_large(max)));
+            }
+            return Some(checked_message(line[..end].to_vec()));
+        }
+    }
+
+    fn decode_frame(&mut self, buf: &mut BytesMut) -> Option<McpResult<String>> {
+        while !self.in_body {
+            let end = buf.iter().position(|&byte| byte == b'\n')?;
+            let line = buf.split_to(end + 1);
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if line.is_empty() {
+                let length = match self.content_length {
+                    Some(length) => length,
+                    // Blank lines between frames
+                    None if self.encoding.is_none() => continue,
+                    None => return Some(Err(self.invalid_frame("Missing Content-Length header"))),
+                };
+                if let Some(max) = self.max_message_size.filter(|&max| length > max) {
+                    self.reset_frame();
+                    self.discard_bytes = length;
+                    return Some(Err(helpers::message_too_large(max)));
+                }
+                self.in_body = true;
+            } else if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    match value.trim().parse() {
+                        Ok(length) => self.content_length = Some(length),
+                        Err(_) => {
+                            return Some(Err(self.invalid_frame("Invalid Content-Length header")))
+                        }
+                    }
+                } else if name.trim().eq_ignore_ascii_case("content-encoding") {
+                    self.encoding = Some(value.trim().to_string());
+                }
+            } else {
+                return Some(Err(self.invalid_frame("Invalid header line")));
+            }
+        }
+
+        let length = self.content_length.unwrap_or_default();
+        if buf.len() < length {
+            buf.reserve(length - buf.len());
+            return None;
+        }
+        let body = buf.split_to(length);
+        let encoding = self.encoding.take();
+        self.reset_frame();
+        Some(match encoding {
+            Some(encoding) => Err(helpers::protocol_error(&format!(
+                "Unsupported content encoding: {}",
+                encoding
+            ))),
+            None => checked_message(body.to_vec()),
+        })
+    }
+
+    fn reset_frame(&mut self) {
+        self.content_length = None;
+        self.in_body = false;
+        self.encoding = None;
+    }
+
+    fn invalid_frame(&mut self, message: &str) -> McpError {
+        self.reset_frame();
+        helpers::protocol_error(message)
+    }
+
+    fn decode_message(&mut self, buf: &mut BytesMut) -> Option<McpResult<String>> {
+        let skipped = buf.len().min(self.discard_bytes);
+        buf.advance(skipped);
+        self.discard_bytes -= skipped;
+        if self.discard_bytes > 0 {
+            return None;
+        }
+
+        let framing = match self.active_framing() {
+            Some(framing) => framing,
+            None => {
+                // Blank lines before the first message are skipped
+                let blank = buf
+                    .iter()
+                    .take_while(|byte| byte.is_ascii_whitespace())
+                    .count();
+                buf.advance(blank);
+                let framing = match buf.first() {
+                    Some(b'{') | Some(b'[') => Framing::NewlineDelimited,
+                    Some(_) => Framing::ContentLength,
+                    None => return None,
+                };
+                self.detected = Some(framing);
+                framing
+            }
+        };
+        match framing {
+            Framing::ContentLength => self.decode_frame(buf),
+            _ => self.decode_line(buf),
+        }
+    }
+
+    fn decode_last(&mut self, buf: &mut BytesMut) -> Option<McpResult<String>> {
+        if let Some(decoded) = self.decode_message(buf) {
+            return Some(decoded);
+        }
+        // The last line may lack its newline; a truncated frame is dropped
+        let last = std::mem::take(buf);
+        self.next_index = 0;
+        let newline_delimited = self.active_framing() != Some(Framing::ContentLength);
+        if newline_delimited && !self.discard_line && !last.trim_ascii().is_empty() {
+            return Some(checked_message(last.to_vec()));
+        }
+        None
+    }
+}
+
+impl Decoder for JsonRpcCodec {
+    type Item = McpResult<String>;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        Ok(self.decode_message(buf))
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        Ok(self.decode_last(buf))
+    }
+}
+
+impl Encoder<String> for JsonRpcCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: String, buf: &mut BytesMut) -> io::Result<()> {
+        // Messages sent before one was received are newline-delimited
+        if self.active_framing() == Some(Framing::ContentLength) {
+            buf.put_slice(format!("Content-Length: {}\r\n\r\n", message.len()).as_bytes());
+            buf.put_slice(message.as_bytes());
+        } else {
+            buf.reserve(message.len() + 1);
+            buf.put_slice(message.as_bytes());
+            buf.put_u8(b'\n');
+        }
+        Ok(())
+    }
+}
+
+fn io_error(action: &str, err: io::Error) -> McpError {
+    helpers::transport_error(&format!("Failed to {}: {}", action, err))
+}
+
+#[async_trait]
+impl<S> Transport for Framed<S, JsonRpcCodec>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn receive(&mut self) -> McpResult<String> {
+        match self.next().await {
+            Some(Ok(received)) => received,
+            Some(Err(e)) => Err(io_error("read", e)),
+            None => Err(helpers::connection_closed()),
+        }
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        SinkExt::send(self, message.to_string())
+            .await
+            .map_err(|e| io_error("write", e))
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        SinkExt::close(self).await.map_err(|e| io_error("close", e))
+    }
+}
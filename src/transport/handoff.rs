@@ -0,0 +1,490 @@
+//! Listener handoff between processes
+//!
+//! An upgraded daemon takes over the listening sockets of the one it replaces,
+//! so connection attempts queue in the kernel backlog instead of being refused
+//! while the new binary starts. Listeners travel either over a Unix socket
+//! (`SCM_RIGHTS`), between unrelated processes:
+//!
+//! ```rust,ignore
+//! // Old daemon, asked to hand over on its control socket
+//! let (control, _) = control_listener.accept().await?;
+//! send_listeners(&control, &listeners).await?;
+//! // stop accepting, drain connections in progress, exit
+//!
+//! // New daemon
+//! let control = UnixStream::connect("/run/mcp/handoff.sock").await?;
+//! let listeners = receive_listeners(&control).await?;
+//! ```
+//!
+//! or by inheritance, when the old daemon starts the new one itself: the
+//! descriptors stay open across `exec` and their numbers are passed in
+//! [`LISTEN_FDS_ENV`]:
+//!
+//! ```rust,ignore
+//! let mut command = Command::new(std::env::current_exe()?);
+//! inherit_listeners(&mut command, &listeners);
+//! command.spawn()?;
+//!
+//! // In the new process; empty on a fresh start
+//! let listeners = listeners_from_env()?;
+//! ```
+//!
+//! Both sides keep a working listener until the old one is dropped, so the
+//! old daemon should stop accepting once the new one has taken over.
+//!
+//! Listeners are inherited the same way from systemd when it activates the
+//! service through a socket unit, which lets it bind privileged ports and
+//! start the server on the first connection:
+//!
+//! ```rust,ignore
+//! for (name, listener) in systemd_listeners()? {
+//!     match listener {
+//!         HandoffListener::Tcp(listener) => { /* serve */ }
+//!         HandoffListener::Unix(listener) => { /* serve */ }
+//!     }
+//! }
+//! ```
+
+use crate::error::helpers;
+use mcp_error::Result as McpResult;
+use std::collections::HashSet;
+use std::io;
+use std::mem::{size_of, size_of_val, zeroed};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::ptr;
+use std::sync::OnceLock;
+use tokio::io::Interest;
+use tokio::net::{TcpListener, UnixListener, UnixStream};
+
+/// Environment variable listing the inherited listener descriptors,
+/// comma-separated
+pub const LISTEN_FDS_ENV: &str = "MCP_LISTEN_FDS";
+
+/// First descriptor passed by systemd socket activation
+pub const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// Name systemd gives listeners without a `FileDescriptorName=`
+const SD_DEFAULT_NAME: &str = "unknown";
+
+/// Maximum number of listeners handed over in one message
+pub const MAX_HANDOFF_LISTENERS: usize = 64;
+
+/// Set once the listeners in [`LISTEN_FDS_ENV`] have been taken
+static ENV_LISTENERS_TAKEN: OnceLock<()> = OnceLock::new();
+
+/// Set once the systemd listeners have been taken
+static SYSTEMD_LISTENERS_TAKEN: OnceLock<()> = OnceLock::new();
+
+/// Listener handed over between processes
+#[derive(Debug)]
+pub enum HandoffListener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+impl HandoffListener {
+    /// Wrap a received descriptor, which must be a listening socket
+    ///
+    /// Must be called within a Tokio runtime.
+    fn from_fd(fd: OwnedFd) -> io::Result<Self> {
+        match listener_family(fd.as_fd())? {
+            libc::AF_UNIX => {
+                let listener = std::os::unix::net::UnixListener::from(fd);
+                listener.set_nonblocking(true)?;
+                UnixListener::from_std(listener).map(Self::Unix)
+            }
+            libc::AF_INET | libc::AF_INET6 => {
+                let listener = std::net::TcpListener::from(fd);
+                listener.set_nonblocking(true)?;
+                TcpListener::from_std(listener).map(Self::Tcp)
+            }
+            family => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported socket family {}", family),
+            )),
+        }
+    }
+}
+
+impl From<TcpListener> for HandoffListener {
+    fn from(listener: TcpListener) -> Self {
+        Self::Tcp(listener)
+    }
+}
+
+impl From<UnixListener> for HandoffListener {
+    fn from(listener: UnixListener) -> Self {
+        Self::Unix(listener)
+    }
+}
+
+impl AsRawFd for HandoffListener {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Self::Tcp(listener) => listener.as_raw_fd(),
+            Self::Unix(listener) => listener.as_raw_fd(),
+        }
+    }
+}
+
+impl AsFd for HandoffListener {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        match self {
+            Self::Tcp(listener) => listener.as_fd(),
+            Self::Unix(listener) => listener.as_fd(),
+        }
+    }
+}
+
+/// Send listeners to the process at the other end of a Unix socket
+///
+/// The listeners stay open in this process too; drop them once the peer has
+/// taken over.
+pub async fn send_listeners(stream: &UnixStream, listeners: &[HandoffListener]) -> McpResult<()> {
+    if listeners.len() > MAX_HANDOFF_LISTENERS {
+        return Err(helpers::config_error(&format!(
+            "Cannot hand over {} listeners, at most {} are allowed",
+            listeners.len(),
+            MAX_HANDOFF_LISTENERS
+        )));
+    }
+    let fds: Vec<RawFd> = listeners.iter().map(AsRawFd::as_raw_fd).collect();
+    loop {
+        stream
+            .writable()
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to send listeners: {}", e)))?;
+        match stream.try_io(Interest::WRITABLE, || send_fds(stream.as_raw_fd(), &fds)) {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(e) => {
+                return Err(helpers::transport_error(&format!(
+                    "Failed to send listeners: {}",
+                    e
+                )))
+            }
+        }
+    }
+}
+
+/// Receive the listeners sent by [`send_listeners`] at the other end of a Unix socket
+pub async fn receive_listeners(stream: &UnixStream) -> McpResult<Vec<HandoffListener>> {
+    let fds = loop {
+        stream.readable().await.map_err(|e| {
+            helpers::transport_error(&format!("Failed to receive listeners: {}", e))
+        })?;
+        match stream.try_io(Interest::READABLE, || recv_fds(stream.as_raw_fd())) {
+            Ok(fds) => break fds,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(e) => {
+                return Err(helpers::transport_error(&format!(
+                    "Failed to receive listeners: {}",
+                    e
+                )))
+            }
+        }
+    };
+    fds.into_iter()
+        .map(|fd| {
+            HandoffListener::from_fd(fd).map_err(|e| {
+                helpers::transport_error(&format!("Received an unusable listener: {}", e))
+            })
+        })
+        .collect()
+}
+
+/// Let a child process inherit listeners
+///
+/// The descriptors are kept open across `exec` in the child only, and their
+/// numbers passed in [`LISTEN_FDS_ENV`]. For a `tokio::process::Command`, pass
+/// `command.as_std_mut()`.
+pub fn inherit_listeners(command: &mut Command, listeners: &[HandoffListener]) {
+    let fds: Vec<RawFd> = listeners.iter().map(AsRawFd::as_raw_fd).collect();
+    let value = fds
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    command.env(LISTEN_FDS_ENV, value);
+    // SAFETY: only calls fcntl, which is async-signal-safe, and does not allocate
+    unsafe {
+        command.pre_exec(move || {
+            for &fd in &fds {
+                set_cloexec(fd, false)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Take the listeners inherited through [`inherit_listeners`]
+///
+/// Returns no listener when [`LISTEN_FDS_ENV`] is not set, or when the
+/// listeners were already taken by an earlier call. The descriptors are no
+/// longer inherited by this process's own children, but the variable is left
+/// in place, as changing the environment is unsound once other threads may
+/// read it: remove it with [`Command::env_remove`] on children started
+/// without [`inherit_listeners`]. Must be called within a Tokio runtime.
+pub fn listeners_from_env() -> McpResult<Vec<HandoffListener>> {
+    let value = match std::env::var(LISTEN_FDS_ENV) {
+        Ok(value) => value,
+        Err(_) => return Ok(Vec::new()),
+    };
+    if ENV_LISTENERS_TAKEN.set(()).is_err() {
+        return Ok(Vec::new());
+    }
+
+    let mut seen = HashSet::new();
+    let mut listeners = Vec::new();
+    for part in value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let fd: RawFd = part.parse().map_err(|_| {
+            helpers::config_error(&format!(
+                "Invalid descriptor '{}' in {}",
+                part, LISTEN_FDS_ENV
+            ))
+        })?;
+        if fd < 0 || !seen.insert(fd) {
+            return Err(helpers::config_error(&format!(
+                "Invalid descriptor '{}' in {}",
+                part, LISTEN_FDS_ENV
+            )));
+        }
+        listeners.push(take_inherited(fd)?);
+    }
+    Ok(listeners)
+}
+
+/// Take the listeners passed by systemd socket activation, with their names
+///
+/// Names are set with `FileDescriptorName=` in the socket unit, and are
+/// `"unknown"` otherwise. Returns no listener unless `LISTEN_PID` names this
+/// process, so a child started without clearing the variables does not take
+/// descriptors meant for its parent, and none when the listeners were already
+/// taken by an earlier call. The variables are left in place, and the
+/// descriptors are no longer inherited by this process's own children. Must
+/// be called within a Tokio runtime.
+pub fn systemd_listeners() -> McpResult<Vec<(String, HandoffListener)>> {
+    let pid = match std::env::var("LISTEN_PID") {
+        Ok(pid) => pid,
+        Err(_) => return Ok(Vec::new()),
+    };
+    if pid.trim().parse::<u32>().ok() != Some(std::process::id()) {
+        return Ok(Vec::new());
+    }
+    if SYSTEMD_LISTENERS_TAKEN.set(()).is_err() {
+        return Ok(Vec::new());
+    }
+    let count = std::env::var("LISTEN_FDS").unwrap_or_default();
+    let names = std::env::var("LISTEN_FDNAMES").unwrap_or_default();
+
+    let count: RawFd = count
+        .trim()
+        .parse()
+        .ok()
+        .filter(|count| (0..=RawFd::MAX - SD_LISTEN_FDS_START).contains(count))
+        .ok_or_else(|| {
+            helpers::config_error(&format!(
+                "Invalid descriptor count '{}' in LISTEN_FDS",
+                count
+            ))
+        })?;
+    let mut names = names.split(':');
+    (SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count)
+        .map(|fd| {
+            let name = match names.next() {
+                Some(name) if !name.is_empty() => name.to_string(),
+                _ => SD_DEFAULT_NAME.to_string(),
+            };
+            Ok((name, take_inherited(fd)?))
+        })
+        .collect()
+}
+
+/// Take ownership of an inherited listener descriptor
+fn take_inherited(fd: RawFd) -> McpResult<HandoffListener> {
+    // Also checks the descriptor is open before taking ownership of it
+    set_cloexec(fd, true).map_err(|e| {
+        helpers::transport_error(&format!("Inherited descriptor {} is unusable: {}", fd, e))
+    })?;
+    // SAFETY: the descriptor is open and passed once, so it is owned here only
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    HandoffListener::from_fd(fd)
+        .map_err(|e| helpers::transport_error(&format!("Inherited an unusable listener: {}", e)))
+}
+
+/// Address family of a socket, failing unless it is listening
+fn listener_family(fd: BorrowedFd<'_>) -> io::Result<libc::c_int> {
+    let mut listening: libc::c_int = 0;
+    let mut len = size_of::<libc::c_int>() as libc::socklen_t;
+    // SAFETY: the buffer matches the length passed
+    let result = unsafe {
+        libc::getsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_ACCEPTCONN,
+            &mut listening as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if listening == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "socket is not listening",
+        ));
+    }
+
+    // SAFETY: an all-zero sockaddr_storage is valid
+    let mut address: libc::sockaddr_storage = unsafe { zeroed() };
+    let mut len = size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    // SAFETY: the buffer matches the length passed
+    let result = unsafe {
+        libc::getsockname(
+            fd.as_raw_fd(),
+            &mut address as *mut libc::sockaddr_storage as *mut libc::sockaddr,
+            &mut len,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(address.ss_family as libc::c_int)
+}
+
+fn set_cloexec(fd: RawFd, cloexec: bool) -> io::Result<()> {
+    // SAFETY: fcntl does not touch memory; invalid descriptors fail with EBADF
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        if flags < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let flags = if cloexec {
+            flags | libc::FD_CLOEXEC
+        } else {
+            flags & !libc::FD_CLOEXEC
+        };
+        if libc::fcntl(fd, libc::F_SETFD, flags) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Control buffer large enough for `count` descriptors, aligned for `cmsghdr`
+fn control_buffer(count: usize) -> Vec<u64> {
+    // SAFETY: CMSG_SPACE only computes a size
+    let space = unsafe { libc::CMSG_SPACE((count * size_of::<RawFd>()) as u32) } as usize;
+    vec![0; space.div_ceil(size_of::<u64>())]
+}
+
+/// Send descriptors, with their count as data so truncation can be detected
+fn send_fds(socket: RawFd, fds: &[RawFd]) -> io::Result<()> {
+    let count = (fds.len() as u32).to_le_bytes();
+    let mut iov = libc::iovec {
+        iov_base: count.as_ptr() as *mut libc::c_void,
+        iov_len: count.len(),
+    };
+    let mut control = control_buffer(fds.len());
+    // SAFETY: an all-zero msghdr is valid
+    let mut msg: libc::msghdr = unsafe { zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    if !fds.is_empty() {
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = (control.len() * size_of::<u64>()) as _;
+        // SAFETY: the control buffer has room for one header and the descriptors
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(size_of_val(fds) as u32) as _;
+            ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg) as *mut RawFd, fds.len());
+        }
+    }
+
+    // SAFETY: msg points to buffers that outlive the call
+    let sent = unsafe { libc::sendmsg(socket, &msg, 0) };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if (sent as usize) < count.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::WriteZero,
+            "listener count only partially sent",
+        ));
+    }
+    Ok(())
+}
+
+/// Receive descriptors sent by [`send_fds`]
+fn recv_fds(socket: RawFd) -> io::Result<Vec<OwnedFd>> {
+    let mut count = [0u8; 4];
+    let mut iov = libc::iovec {
+        iov_base: count.as_mut_ptr() as *mut libc::c_void,
+        iov_len: count.len(),
+    };
+    let mut control = control_buffer(MAX_HANDOFF_LISTENERS);
+    // SAFETY: an all-zero msghdr is valid
+    let mut msg: libc::msghdr = unsafe { zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = (control.len() * size_of::<u64>()) as _;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let flags = libc::MSG_CMSG_CLOEXEC;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let flags = 0;
+    // SAFETY: msg points to buffers that outlive the call
+    let received = unsafe { libc::recvmsg(socket, &mut msg, flags) };
+    if received < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // Take ownership first, so the descriptors are closed on any error below
+    let mut fds = Vec::new();
+    // SAFETY: the headers were filled in by recvmsg within the control buffer
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                let len = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                for i in 0..len / size_of::<RawFd>() {
+                    fds.push(OwnedFd::from_raw_fd(ptr::read_unaligned(data.add(i))));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    for fd in &fds {
+        set_cloexec(fd.as_raw_fd(), true)?;
+    }
+
+    if received == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "peer closed the socket before sending listeners",
+        ));
+    }
+    let expected = u32::from_le_bytes(count) as usize;
+    if (received as usize) < count.len()
+        || msg.msg_flags & libc::MSG_CTRUNC != 0
+        || expected != fds.len()
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected {} listeners, received {}", expected, fds.len()),
+        ));
+    }
+    Ok(fds)
+}
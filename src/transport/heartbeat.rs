@@ -0,0 +1,192 @@
+//! Heartbeats keeping idle connections alive
+//!
+//! NAT gateways and stateful firewalls drop connections that carry no traffic
+//! for a few minutes, without telling either end. A [`HeartbeatTransport`]
+//! sends a message whenever nothing was sent for an interval, and can give up
+//! on a peer from which nothing was received for longer:
+//!
+//! ```rust,ignore
+//! let transport = HeartbeatTransport::new(transport, Duration::from_secs(30))
+//!     .with_dead_peer_timeout(Duration::from_secs(90));
+//! JsonRpcProcessor::new(transport, registry).run().await?;
+//! ```
+//!
+//! With [`Heartbeat::Ping`], any MCP peer answers the heartbeats, so they
+//! also prove it alive; `$/heartbeat` notifications only do when the peer
+//! sends its own.
+
+use crate::error::helpers;
+use crate::mcp::methods;
+use crate::protocol::{JsonRpcId, JsonRpcNotification, JsonRpcRequest};
+use crate::transport::base::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::{timeout_at, Instant};
+
+/// Method of heartbeat notifications
+pub const HEARTBEAT_METHOD: &str = "$/heartbeat";
+
+/// Prefix of the ids of heartbeat pings
+const PING_ID_PREFIX: &str = "heartbeat-";
+
+/// Message sent as a heartbeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Heartbeat {
+    /// A `$/heartbeat` notification, which peers ignore
+    #[default]
+    Notification,
+    /// A `ping` request, whose response is consumed by the transport
+    Ping,
+}
+
+/// Transport wrapper sending heartbeats on idle connections
+///
+/// Heartbeats are sent while a receive is pending, as it always is for a
+/// [`JsonRpcProcessor`](crate::processor::JsonRpcProcessor), and never in the
+/// middle of a message sent in parts. Heartbeats received, and responses to
+/// heartbeat pings, are consumed rather than returned.
+///
+/// With a dead-peer timeout, a receive during which nothing was received for
+/// that long fails with a connection closed error, see
+/// [`is_connection_closed`](crate::error::is_connection_closed), which ends a
+/// processor's run like the peer closing the connection.
+pub struct HeartbeatTransport<T> {
+    inner: T,
+    interval: Duration,
+    heartbeat: Heartbeat,
+    dead_peer_timeout: Option<Duration>,
+    last_sent: Instant,
+    last_received: Instant,
+    next_ping: u64,
+    /// Whether parts of a message were sent but not its end
+    mid_message: bool,
+}
+
+impl<T: Transport> HeartbeatTransport<T> {
+    /// Wrap a transport, sending a heartbeat after `interval` without sending
+    pub fn new(inner: T, interval: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            inner,
+            interval,
+            heartbeat: Heartbeat::default(),
+            dead_peer_timeout: None,
+            last_sent: now,
+            last_received: now,
+            next_ping: 1,
+            mid_message: false,
+        }
+    }
+
+    /// Send the given kind of heartbeat
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Consider the peer dead once nothing was received from it for `timeout`
+    pub fn with_dead_peer_timeout(mut self, timeout: Duration) -> Self {
+        self.dead_peer_timeout = Some(timeout);
+        self
+    }
+
+    /// Access the wrapped transport
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    async fn beat(&mut self) -> McpResult<()> {
+        let message = match self.heartbeat {
+            Heartbeat::Notification => {
+                let notification = JsonRpcNotification::new(HEARTBEAT_METHOD, None);
+                serde_json::to_string(&notification)
+            }
+            Heartbeat::Ping => {
+                let id = JsonRpcId::String(format!("{}{}", PING_ID_PREFIX, self.next_ping));
+                self.next_ping += 1;
+                serde_json::to_string(&JsonRpcRequest::new(methods::PING, None, id))
+            }
+        }
+        .map_err(helpers::json_error)?;
+        self.inner.send(&message).await?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+}
+
+/// Whether the message is a heartbeat or answers a heartbeat ping
+fn is_heartbeat(message: &str) -> bool {
+    if !message.contains(HEARTBEAT_METHOD) && !message.contains(PING_ID_PREFIX) {
+        return false;
+    }
+    let Ok(value) = serde_json::from_str::<Value>(message) else {
+        return false;
+    };
+    match (value.get("method"), value.get("id")) {
+        (Some(method), None) => method == HEARTBEAT_METHOD,
+        (None, Some(Value::String(id))) => id.starts_with(PING_ID_PREFIX),
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for HeartbeatTransport<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        loop {
+            let next_beat = self.last_sent + self.interval;
+            let dead_at = self
+                .dead_peer_timeout
+                .map(|timeout| self.last_received + timeout);
+            let wake = dead_at.map_or(next_beat, |dead_at| dead_at.min(next_beat));
+
+            match timeout_at(wake, self.inner.receive()).await {
+                Ok(Ok(message)) => {
+                    self.last_received = Instant::now();
+                    if !is_heartbeat(&message) {
+                        return Ok(message);
+                    }
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(_) if dead_at.is_some_and(|dead_at| Instant::now() >= dead_at) => {
+                    return Err(helpers::connection_closed_because(&format!(
+                        "nothing received for {:?}",
+                        self.dead_peer_timeout.unwrap_or_default()
+                    )));
+                }
+                Err(_) if self.mid_message => {
+                    // Not between two parts; wait for the message to end
+                    self.last_sent = Instant::now();
+                }
+                Err(_) => self.beat().await?,
+            }
+        }
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.inner.send(message).await?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.inner.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        self.inner.send_part(part, end).await?;
+        self.last_sent = Instant::now();
+        self.mid_message = !end;
+        Ok(())
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.inner.close().await
+    }
+}
@@ -0,0 +1,580 @@
+//! HTTP server transport
+//!
+//! [`HttpServerTransport`] serves JSON-RPC over plain HTTP/1.1 without a web
+//! framework: the body of each `POST` is a message, a request or a batch, and
+//! the body of its response is what the processor answers.
+//!
+//! ```rust,ignore
+//! let mut http = HttpServerTransport::bind(([127, 0, 0, 1], 8080))
+//!     .await?
+//!     .with_path("/mcp");
+//! loop {
+//!     let mut processor = server.processor(http.accept().await?);
+//!     tokio::spawn(async move { processor.run().await });
+//! }
+//! ```
+//!
+//! ```text
+//! $ curl -d '{"jsonrpc":"2.0","method":"ping","id":1}' http://127.0.0.1:8080/mcp
+//! {"jsonrpc":"2.0","result":{},"id":1}
+//! ```
+//!
+//! - Every HTTP connection is a session of its own, served through the
+//!   [`HttpConnection`] returned by [`HttpServerTransport::accept`] once it
+//!   posts its first message. A client keeping its connection alive keeps its
+//!   session; one connecting for each request starts a new one every time.
+//! - A connection posts one message at a time, so what the processor answers
+//!   is the response to the message being processed. Responses sent while no
+//!   message awaits one are dropped.
+//! - Notifications and batches of notifications are answered `202 Accepted`
+//!   with an empty body as soon as they are received.
+//! - Requests and notifications the processor sends on its own, such as list
+//!   changed notifications, have no request to answer: they are published on
+//!   the [`EventStream`] set with [`HttpServerTransport::with_event_stream`],
+//!   which clients read with a `GET`, and dropped without one.
+//! - Bodies must come with a `Content-Length`; other methods are answered
+//!   `405`, other paths `404` once a path is set, and bodies over the
+//!   limit `413`. Connections are kept alive unless the client asks otherwise.
+
+use crate::error::helpers;
+use crate::protocol::IdField;
+use crate::transport::base::Transport;
+use crate::transport::sse::EventStream;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Largest request body accepted by default, in bytes
+pub const DEFAULT_MAX_BODY_SIZE: usize = 4 * 1024 * 1024;
+
+/// Largest request line and headers accepted, in bytes
+pub(crate) const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// Connections posted to and not yet accepted
+const QUEUE_SIZE: usize = 64;
+
+/// A `POST` waiting for the processor
+struct Exchange {
+    body: String,
+    /// Response body, `None` when nothing is due
+    reply: oneshot::Sender<Option<String>>,
+}
+
+#[derive(Debug, Clone)]
+struct Options {
+    path: Option<String>,
+    max_body_size: usize,
+    events: Option<EventStream>,
+}
+
+impl Options {
+    fn allow(&self) -> &'static str {
+        if self.events.is_some() {
+            "GET, POST"
+        } else {
+            "POST"
+        }
+    }
+}
+
+/// JSON-RPC over HTTP `POST`, for web clients
+///
+/// Connections are accepted from the first [`accept`](Self::accept) on, so
+/// options set beforehand apply to all of them.
+pub struct HttpServerTransport {
+    listener: Option<TcpListener>,
+    local_addr: SocketAddr,
+    options: Options,
+    incoming: Option<mpsc::Receiver<HttpConnection>>,
+    accept: Option<JoinHandle<()>>,
+}
+
+impl HttpServerTransport {
+    /// Listen on the given address
+    pub async fn bind(addr: impl Into<SocketAddr>) -> McpResult<Self> {
+        let listener = TcpListener::bind(addr.into())
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to bind: {}", e)))?;
+        Self::from_listener(listener)
+    }
+
+    /// Serve the connections of a bound listener
+    pub fn from_listener(listener: TcpListener) -> McpResult<Self> {
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| helpers::transport_error(&format!("Failed to bind: {}", e)))?;
+        Ok(Self {
+            listener: Some(listener),
+            local_addr,
+            options: Options {
+                path: None,
+                max_body_size: DEFAULT_MAX_BODY_SIZE,
+                events: None,
+            },
+            incoming: None,
+            accept: None,
+        })
+    }
+
+    /// Only serve the given path, answering `404` elsewhere
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.options.path = Some(path.into());
+        self
+    }
+
+    /// Set the largest request body accepted
+    pub fn with_max_body_size(mut self, max: usize) -> Self {
+        self.options.max_body_size = max;
+        self
+    }
+
+    /// Serve `GET` requests with the given event stream, and publish on it
+    /// the messages answering no request
+    pub fn with_event_stream(mut self, events: EventStream) -> Self {
+        self.options.events = Some(events);
+        self
+    }
+
+    /// Address the transport listens on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Wait for a connection to post its first message and return its
+    /// transport
+    ///
+    /// Cancel safe: a connection is either returned or left queued.
+    pub async fn accept(&mut self) -> McpResult<HttpConnection> {
+        self.start();
+        let incoming = self
+            .incoming
+            .as_mut()
+            .ok_or_else(helpers::connection_closed)?;
+        incoming.recv().await.ok_or_else(helpers::connection_closed)
+    }
+
+    /// Stop accepting connections
+    ///
+    /// Connections already accepted are served until their transport is
+    /// dropped; those not accepted yet are answered `503`.
+    pub fn close(&mut self) {
+        if let Some(accept) = self.accept.take() {
+            accept.abort();
+        }
+        self.listener = None;
+        self.incoming = None;
+    }
+
+    /// Start accepting connections, unless started already
+    fn start(&mut self) {
+        let listener = match self.listener.take() {
+            Some(listener) => listener,
+            None => return,
+        };
+        let (connections, incoming) = mpsc::channel(QUEUE_SIZE);
+        self.incoming = Some(incoming);
+        let options = Arc::new(self.options.clone());
+        self.accept = Some(tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    // Out of descriptors or a connection reset before being
+                    // accepted; the listener itself is still usable
+                    Err(_) => {
+                        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                        continue;
+                    }
+                };
+                tokio::spawn(serve_connection(
+                    stream,
+                    peer,
+                    options.clone(),
+                    connections.clone(),
+                ));
+            }
+        }));
+    }
+}
+
+impl Drop for HttpServerTransport {
+    fn drop(&mut self) {
+        if let Some(accept) = self.accept.take() {
+            accept.abort();
+        }
+    }
+}
+
+/// One HTTP connection, as the transport of its session
+///
+/// Received messages are the bodies posted on the connection, and the
+/// response sent next answers the one being processed.
+pub struct HttpConnection {
+    peer: SocketAddr,
+    exchanges: mpsc::Receiver<Exchange>,
+    /// Reply of the message being processed, until its response is sent
+    pending: Option<oneshot::Sender<Option<String>>>,
+    events: Option<EventStream>,
+}
+
+impl HttpConnection {
+    /// Address of the client
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+}
+
+#[async_trait]
+impl Transport for HttpConnection {
+    async fn receive(&mut self) -> McpResult<String> {
+        // Cancel safe: an exchange is either returned or left queued
+        let exchange = self
+            .exchanges
+            .recv()
+            .await
+            .ok_or_else(helpers::connection_closed)?;
+        if expects_response(&exchange.body) {
+            self.pending = Some(exchange.reply);
+        } else {
+            self.pending = None;
+            let _ = exchange.reply.send(None);
+        }
+        Ok(exchange.body)
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        if is_response(message) {
+            if let Some(reply) = self.pending.take() {
+                let _ = reply.send(Some(message.to_string()));
+            }
+        } else if let Some(events) = &self.events {
+            events.publish(message);
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        // The client waiting, if any, is answered 503
+        self.exchanges.close();
+        self.pending = None;
+        Ok(())
+    }
+}
+
+/// Whether a message sent by the processor answers one it received, rather
+/// than being a request or notification of its own
+fn is_response(message: &str) -> bool {
+    match serde_json::from_str::<Value>(message) {
+        Ok(Value::Object(object)) => !object.contains_key("method"),
+        _ => true,
+    }
+}
+
+/// Whether the processor answers a message; notifications and batches of
+/// notifications call for no response
+fn expects_response(body: &str) -> bool {
+    let expects = |element: &Value| match element {
+        Value::Object(object) => !IdField::of(object).is_absent() || !object.contains_key("method"),
+        _ => true,
+    };
+    match serde_json::from_str::<Value>(body) {
+        Ok(Value::Array(elements)) if !elements.is_empty() => elements.iter().any(expects),
+        Ok(Value::Array(_)) | Err(_) => true,
+        Ok(message) => expects(&message),
+    }
+}
+
+/// Request line and headers of an HTTP/1.1 message
+#[derive(Debug)]
+pub(crate) struct Head {
+    /// Request line or status line, split on spaces
+    pub start: Vec<String>,
+    /// Headers, names in lowercase
+    pub headers: Vec<(String, String)>,
+}
+
+impl Head {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether a header holds the given token, e.g. `Connection: close`
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        self.header(name).is_some_and(|value| {
+            value
+                .split(',')
+                .any(|part| part.trim().eq_ignore_ascii_case(token))
+        })
+    }
+
+    pub fn content_length(&self) -> Option<Result<usize, ()>> {
+        self.header("content-length")
+            .map(|length| length.trim().parse().map_err(|_| ()))
+    }
+}
+
+/// Read the head of the next message; `None` once the peer closed cleanly
+pub(crate) async fn read_head<R>(reader: &mut R) -> Result<Option<Head>, u16>
+where
+    R: AsyncBufReadExt + Unpin,
+{
+    let mut lines = Vec::new();
+    let mut size = 0;
+    loop {
+        let mut line = Vec::new();
+        let read = (&mut *reader)
+            .take((MAX_HEAD_SIZE - size + 1) as u64)
+            .read_until(b'\n', &mut line)
+            .await
+            .map_err(|_| 400u16)?;
+        if read == 0 {
+            return if lines.is_empty() { Ok(None) } else { Err(400) };
+        }
+        size += read;
+        if size > MAX_HEAD_SIZE {
+            return Err(431);
+        }
+        let line = String::from_utf8(line).map_err(|_| 400u16)?;
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            // Blank lines before the start line are tolerated
+            if lines.is_empty() {
+                continue;
+            }
+            break;
+        }
+        lines.push(line.to_string());
+    }
+
+    let start = lines[0].split(' ').map(str::to_string).collect::<Vec<_>>();
+    let headers = lines[1..]
+        .iter()
+        .map(|line| {
+            let (name, value) = line.split_once(':').ok_or(400u16)?;
+            Ok((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect::<Result<Vec<_>, u16>>()?;
+    Ok(Some(Head { start, headers }))
+}
+
+pub(crate) fn reason(status: u16) -> &'static str {
+    match status {
+        100 => "Continue",
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        505 => "HTTP Version Not Supported",
+        _ => "Error",
+    }
+}
+
+/// Serve the requests of one connection, one at a time, handing the
+/// connection over once it posts its first message
+async fn serve_connection(
+    stream: TcpStream,
+    peer: SocketAddr,
+    options: Arc<Options>,
+    connections: mpsc::Sender<HttpConnection>,
+) {
+    let mut stream = BufReader::new(stream);
+    let mut exchanges = None;
+    loop {
+        let head = match read_head(&mut stream).await {
+            Ok(Some(head)) => head,
+            Ok(None) => return,
+            Err(status) => {
+                let _ = write_response(stream.get_mut(), &options, status, None, true).await;
+                return;
+            }
+        };
+        let version = head.start.get(2).map(String::as_str).unwrap_or_default();
+        let keep_alive = match version {
+            "HTTP/1.1" => !head.has_token("connection", "close"),
+            "HTTP/1.0" => head.has_token("connection", "keep-alive"),
+            _ => {
+                let _ = write_response(stream.get_mut(), &options, 505, None, true).await;
+                return;
+            }
+        };
+
+        if let Some(events) = event_stream(&head, &options) {
+            let _ = serve_events(stream.get_mut(), events, &head).await;
+            return;
+        }
+
+        let status = check_request(&head, &options);
+        let body = match status {
+            Some(status) => Err(status),
+            None => read_body(&mut stream, &head).await,
+        };
+        let body = match body {
+            Ok(body) => body,
+            Err(status) => {
+                // The body is left unread, so the connection cannot be reused
+                let _ = write_response(stream.get_mut(), &options, status, None, true).await;
+                return;
+            }
+        };
+
+        let exchanges = match &mut exchanges {
+            Some(exchanges) => exchanges,
+            None => {
+                // One exchange at a time: the next is posted once this one
+                // is answered
+                let (sender, receiver) = mpsc::channel(1);
+                let connection = HttpConnection {
+                    peer,
+                    exchanges: receiver,
+                    pending: None,
+                    events: options.events.clone(),
+                };
+                if connections.send(connection).await.is_err() {
+                    let _ = write_response(stream.get_mut(), &options, 503, None, true).await;
+                    return;
+                }
+                exchanges.insert(sender)
+            }
+        };
+        let (reply, response) = oneshot::channel();
+        let answered = match exchanges.send(Exchange { body, reply }).await {
+            Ok(()) => response.await.ok(),
+            Err(_) => None,
+        };
+        let written = match answered {
+            Some(Some(response)) => {
+                write_response(
+                    stream.get_mut(),
+                    &options,
+                    200,
+                    Some(&response),
+                    !keep_alive,
+                )
+                .await
+            }
+            Some(None) => write_response(stream.get_mut(), &options, 202, None, !keep_alive).await,
+            // The connection's transport was closed or dropped
+            None => {
+                let _ = write_response(stream.get_mut(), &options, 503, None, true).await;
+                return;
+            }
+        };
+        if written.is_err() || !keep_alive {
+            return;
+        }
+    }
+}
+
+/// The event stream a `GET` asks for
+fn event_stream<'a>(head: &Head, options: &'a Options) -> Option<&'a EventStream> {
+    let events = options.events.as_ref()?;
+    match head.start.as_slice() {
+        [method, target, _] if method == "GET" && path_allowed(target, options) => Some(events),
+        _ => None,
+    }
+}
+
+async fn serve_events(
+    stream: &mut TcpStream,
+    events: &EventStream,
+    head: &Head,
+) -> std::io::Result<()> {
+    // No length: the stream runs until either side closes the connection
+    let mut response = "HTTP/1.1 200 OK\r\n".to_string();
+    for (name, value) in EventStream::HEADERS {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    stream.write_all(response.as_bytes()).await?;
+    events.serve(stream, head.header("last-event-id")).await
+}
+
+fn path_allowed(target: &str, options: &Options) -> bool {
+    let path = target.split('?').next().unwrap_or_default();
+    options
+        .path
+        .as_ref()
+        .is_none_or(|expected| expected == path)
+}
+
+/// Status to reject a request with, before reading its body
+fn check_request(head: &Head, options: &Options) -> Option<u16> {
+    let (method, target) = match head.start.as_slice() {
+        [method, target, _] => (method, target),
+        _ => return Some(400),
+    };
+    if !path_allowed(target, options) {
+        return Some(404);
+    }
+    if method != "POST" {
+        return Some(405);
+    }
+    if head.has_token("transfer-encoding", "chunked") {
+        return Some(411);
+    }
+    match head.content_length() {
+        None => Some(411),
+        Some(Err(())) => Some(400),
+        Some(Ok(length)) if length > options.max_body_size => Some(413),
+        Some(Ok(_)) => None,
+    }
+}
+
+async fn read_body(stream: &mut BufReader<TcpStream>, head: &Head) -> Result<String, u16> {
+    let length = match head.content_length() {
+        Some(Ok(length)) => length,
+        _ => return Err(411),
+    };
+    if head.has_token("expect", "100-continue") {
+        stream
+            .get_mut()
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .await
+            .map_err(|_| 400u16)?;
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).await.map_err(|_| 400u16)?;
+    String::from_utf8(body).map_err(|_| 400)
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    options: &Options,
+    status: u16,
+    body: Option<&str>,
+    close: bool,
+) -> std::io::Result<()> {
+    let body = body.unwrap_or_default();
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\n",
+        status,
+        reason(status),
+        body.len()
+    );
+    if !body.is_empty() {
+        response.push_str("Content-Type: application/json\r\n");
+    }
+    if status == 405 {
+        response.push_str(&format!("Allow: {}\r\n", options.allow()));
+    }
+    if close {
+        response.push_str("Connection: close\r\n");
+    }
+    response.push_str("\r\n");
+    response.push_str(body);
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await
+}
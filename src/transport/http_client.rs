@@ -0,0 +1,400 @@
+//! HTTP client transport
+//!
+//! [`HttpClientTransport`] talks to servers that expose JSON-RPC over HTTP
+//! `POST`, such as [`HttpServerTransport`](super::HttpServerTransport): each
+//! message sent is the body of a `POST`, and the body of each response is
+//! yielded by [`receive`](Transport::receive).
+//!
+//! ```rust,ignore
+//! let transport = HttpClientTransport::new("http://127.0.0.1:8080/mcp")?
+//!     .with_header("Authorization", "Bearer ...");
+//! let mut client = JsonRpcClient::new(transport);
+//! ```
+//!
+//! Messages are posted concurrently, each on an idle connection when there
+//! is one and on a new connection otherwise; connections are kept alive and
+//! reused unless the server closes them. Responses are therefore yielded in
+//! the order they arrive, not the order their requests were sent. A request
+//! is only sent again when writing it to a reused connection fails; once
+//! written, the server may have executed it, so a connection closed before
+//! the response is reported by [`receive`](Transport::receive). Only
+//! `http://` URLs are supported. Connections can go through a SOCKS5 or
+//! HTTP [`Proxy`], set with [`with_proxy`](HttpClientTransport::with_proxy).
+
+use crate::error::helpers;
+use crate::transport::base::Transport;
+use crate::transport::http::{read_head, Head};
+use crate::transport::proxy::Proxy;
+use async_trait::async_trait;
+use mcp_error::{Error as McpError, Result as McpResult};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+/// Idle connections kept open by default
+pub const DEFAULT_MAX_IDLE_CONNECTIONS: usize = 8;
+
+/// Largest response body accepted by default, in bytes
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+type Connection = BufReader<TcpStream>;
+
+/// Server address and options, shared with the tasks reading responses
+#[derive(Clone)]
+struct Endpoint {
+    host: String,
+    port: u16,
+    path: String,
+    headers: Vec<(String, String)>,
+    max_idle: usize,
+    max_response_size: usize,
+    proxy: Option<Proxy>,
+    idle: Arc<Mutex<Vec<Connection>>>,
+}
+
+impl Endpoint {
+    /// Take an idle connection, if any
+    fn checkout(&self) -> Option<Connection> {
+        self.idle.lock().unwrap().pop()
+    }
+
+    fn checkin(&self, connection: Connection) {
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < self.max_idle {
+            idle.push(connection);
+        }
+    }
+
+    async fn connect(&self) -> McpResult<Connection> {
+        let stream = match &self.proxy {
+            Some(proxy) => proxy.connect(&self.host, self.port).await,
+            None => TcpStream::connect((self.host.as_str(), self.port)).await,
+        }
+        .map_err(|e| helpers::transport_error(&format!("Failed to connect: {}", e)))?;
+        let _ = stream.set_nodelay(true);
+        Ok(BufReader::new(stream))
+    }
+
+    fn request(&self, body: &str) -> Vec<u8> {
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\n\
+             Accept: application/json\r\nContent-Length: {}\r\n",
+            self.path,
+            self.host,
+            self.port,
+            body.len()
+        );
+        for (name, value) in &self.headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        request.into_bytes()
+    }
+}
+
+/// JSON-RPC over HTTP `POST`, with keep-alive connections reused
+pub struct HttpClientTransport {
+    endpoint: Arc<Endpoint>,
+    responses: mpsc::UnboundedSender<McpResult<String>>,
+    incoming: Option<mpsc::UnboundedReceiver<McpResult<String>>>,
+}
+
+impl HttpClientTransport {
+    /// Post to the given `http://host[:port][/path]` URL
+    ///
+    /// No connection is opened until the first message is sent.
+    pub fn new(url: &str) -> McpResult<Self> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| helpers::transport_error(&format!("Unsupported URL: {}", url)))?;
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) if rest[index..].starts_with('/') => rest.split_at(index),
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let path = if path.starts_with('?') {
+            format!("/{}", path)
+        } else {
+            path.to_string()
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            // Bracketed IPv6 addresses hold colons of their own
+            Some((host, port)) if !port.contains(']') => {
+                let port = port
+                    .parse()
+                    .map_err(|_| helpers::transport_error(&format!("Invalid URL: {}", url)))?;
+                (host, port)
+            }
+            _ => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(helpers::transport_error(&format!("Invalid URL: {}", url)));
+        }
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+
+        let (responses, incoming) = mpsc::unbounded_channel();
+        Ok(Self {
+            endpoint: Arc::new(Endpoint {
+                host: host.to_string(),
+                port,
+                path,
+                headers: Vec::new(),
+                max_idle: DEFAULT_MAX_IDLE_CONNECTIONS,
+                max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+                proxy: None,
+                idle: Arc::new(Mutex::new(Vec::new())),
+            }),
+            responses,
+            incoming: Some(incoming),
+        })
+    }
+
+    /// Add a header to every request, e.g. `Authorization`
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.endpoint_mut()
+            .headers
+            .push((name.into(), value.into()));
+        self
+    }
+
+    /// Set how many idle connections are kept open for reuse
+    pub fn with_max_idle_connections(mut self, max: usize) -> Self {
+        self.endpoint_mut().max_idle = max;
+        self
+    }
+
+    /// Set the largest response body accepted
+    ///
+    /// A larger response fails the receive it was due to, and its connection
+    /// is closed.
+    pub fn with_max_response_size(mut self, max: usize) -> Self {
+        self.endpoint_mut().max_response_size = max;
+        self
+    }
+
+    /// Connect to the server through the given proxy
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.endpoint_mut().proxy = Some(proxy);
+        self
+    }
+
+    /// Number of connections currently idle
+    pub fn idle_connections(&self) -> usize {
+        self.endpoint.idle.lock().unwrap().len()
+    }
+
+    fn endpoint_mut(&mut self) -> &mut Endpoint {
+        // Responses still being read keep the options they were sent with
+        Arc::make_mut(&mut self.endpoint)
+    }
+}
+
+#[async_trait]
+impl Transport for HttpClientTransport {
+    async fn receive(&mut self) -> McpResult<String> {
+        // Cancel safe: a response is either returned or left queued
+        self.incoming
+            .as_mut()
+            .ok_or_else(helpers::connection_closed)?
+            .recv()
+            .await
+            .ok_or_else(helpers::connection_closed)?
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        if self.incoming.is_none() {
+            return Err(helpers::connection_closed());
+        }
+        let request = self.endpoint.request(message);
+
+        // Failures to connect or to write are reported here; failures to
+        // read the response are reported by `receive`
+        let (mut connection, reused) = match self.endpoint.checkout() {
+            Some(connection) => (connection, true),
+            None => (self.endpoint.connect().await?, false),
+        };
+        let written = connection.get_mut().write_all(&request).await;
+        let connection = match written {
+            Ok(()) => connection,
+            // The server closed the idle connection in the meantime
+            Err(_) if reused => {
+                let mut connection = self.endpoint.connect().await?;
+                write_request(&mut connection, &request).await?;
+                connection
+            }
+            Err(e) => {
+                return Err(helpers::transport_error(&format!("Failed to send: {}", e)));
+            }
+        };
+
+        let endpoint = self.endpoint.clone();
+        let responses = self.responses.clone();
+        tokio::spawn(async move {
+            // Not retried when closed before answering: the server may have
+            // executed the request already
+            let response = match read_response(&endpoint, connection).await {
+                Ok(Some(body)) => Ok(body),
+                // Accepted, nothing to yield
+                Ok(None) => return,
+                Err(Some(e)) => Err(e),
+                Err(None) => Err(helpers::connection_closed()),
+            };
+            let _ = responses.send(response);
+        });
+        Ok(())
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.incoming = None;
+        self.endpoint.idle.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+async fn write_request(connection: &mut Connection, request: &[u8]) -> McpResult<()> {
+    connection
+        .get_mut()
+        .write_all(request)
+        .await
+        .map_err(|e| helpers::transport_error(&format!("Failed to send: {}", e)))
+}
+
+/// Read one response and give the connection back when it can be reused
+///
+/// Yields `Ok(None)` for a response without body, and `Err(None)` when the
+/// connection was closed before the response started.
+async fn read_response(
+    endpoint: &Endpoint,
+    mut connection: Connection,
+) -> Result<Option<String>, Option<McpError>> {
+    let read_error = |e: String| Some(helpers::transport_error(&format!("Failed to read: {}", e)));
+    let head = loop {
+        let head = match read_head(&mut connection).await {
+            Ok(Some(head)) => head,
+            Ok(None) => return Err(None),
+            Err(status) => return Err(read_error(format!("malformed response ({})", status))),
+        };
+        // Interim responses, e.g. `100 Continue`, precede the final one
+        if !head
+            .start
+            .get(1)
+            .is_some_and(|status| status.starts_with('1'))
+        {
+            break head;
+        }
+    };
+    let status: u16 = head
+        .start
+        .get(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| read_error("malformed status line".to_string()))?;
+
+    let (body, reusable) = read_body(&mut connection, &head, status, endpoint.max_response_size)
+        .await
+        .map_err(|e| match e {
+            BodyError::TooLarge => Some(helpers::message_too_large(endpoint.max_response_size)),
+            BodyError::Io(e) => read_error(e.to_string()),
+        })?;
+    let keep_alive = match head.start.first().map(String::as_str) {
+        Some("HTTP/1.1") => !head.has_token("connection", "close"),
+        _ => head.has_token("connection", "keep-alive"),
+    };
+    if reusable && keep_alive {
+        endpoint.checkin(connection);
+    }
+
+    match status {
+        200..=299 => {
+            let body = String::from_utf8(body)
+                .map_err(|_| Some(helpers::protocol_error("Invalid UTF-8 in message")))?;
+            Ok(Some(body).filter(|body| !body.trim().is_empty()))
+        }
+        _ => Err(Some(helpers::transport_error(&format!(
+            "HTTP {} {}",
+            status,
+            head.start.get(2..).unwrap_or_default().join(" ")
+        )))),
+    }
+}
+
+enum BodyError {
+    /// Over the largest response size accepted
+    TooLarge,
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for BodyError {
+    fn from(e: std::io::Error) -> Self {
+        BodyError::Io(e)
+    }
+}
+
+fn invalid_body(message: &str) -> BodyError {
+    BodyError::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message.to_string(),
+    ))
+}
+
+/// Read the body of a response, up to `max` bytes, telling whether the
+/// connection can be reused
+async fn read_body(
+    connection: &mut Connection,
+    head: &Head,
+    status: u16,
+    max: usize,
+) -> Result<(Vec<u8>, bool), BodyError> {
+    if status == 204 || status == 304 {
+        return Ok((Vec::new(), true));
+    }
+    if head.has_token("transfer-encoding", "chunked") {
+        let mut body = Vec::new();
+        loop {
+            let mut line = String::new();
+            connection.read_line(&mut line).await?;
+            let size = line.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16).map_err(|_| invalid_body("bad chunk"))?;
+            if size == 0 {
+                // Trailers, up to the blank line
+                loop {
+                    line.clear();
+                    connection.read_line(&mut line).await?;
+                    if line.trim().is_empty() {
+                        return Ok((body, true));
+                    }
+                }
+            }
+            let start = body.len();
+            if size > max - start {
+                return Err(BodyError::TooLarge);
+            }
+            body.resize(start + size, 0);
+            connection.read_exact(&mut body[start..]).await?;
+            line.clear();
+            connection.read_line(&mut line).await?;
+        }
+    }
+    match head.content_length() {
+        Some(Ok(length)) if length > max => Err(BodyError::TooLarge),
+        Some(Ok(length)) => {
+            let mut body = vec![0; length];
+            connection.read_exact(&mut body).await?;
+            Ok((body, true))
+        }
+        Some(Err(())) => Err(invalid_body("bad content length")),
+        // The body runs until the server closes the connection
+        None => {
+            let mut body = Vec::new();
+            connection
+                .take(max as u64 + 1)
+                .read_to_end(&mut body)
+                .await?;
+            if body.len() > max {
+                return Err(BodyError::TooLarge);
+            }
+            Ok((body, false))
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::error::helpers;
+use crate::mcp::methods;
+use crate::protocol::{JsonRpcId, JsonRpcRequest};
+use crate::transport::base::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Transport wrapper closing connections on which nothing is received for a while
+///
+/// This is distinct from a request timeout: it bounds how long [`receive`](Transport::receive)
+/// waits for the next message, so half-dead connections whose peer vanished
+/// without closing the socket do not pin resources forever. When the timeout
+/// elapses, `receive` fails with an error for which
+/// [`is_idle_timeout`](crate::error::is_idle_timeout) returns true.
+///
+/// With [`with_ping_before_close`](Self::with_ping_before_close), a `ping` request
+/// is sent first and the connection is only given up if nothing arrives within
+/// the grace period. The ping response is consumed by the wrapper.
+pub struct IdleTimeoutTransport<T> {
+    inner: T,
+    idle: Duration,
+    ping_grace: Option<Duration>,
+    next_ping: u64,
+    outstanding_ping: Option<JsonRpcId>,
+}
+
+impl<T: Transport> IdleTimeoutTransport<T> {
+    /// Wrap a transport, failing `receive` after `idle` without any message
+    pub fn new(inner: T, idle: Duration) -> Self {
+        Self {
+            inner,
+            idle,
+            ping_grace: None,
+            next_ping: 1,
+            outstanding_ping: None,
+        }
+    }
+
+    /// Ping the peer when the idle timeout elapses and wait `grace` for any message
+    pub fn with_ping_before_close(mut self, grace: Duration) -> Self {
+        self.ping_grace = Some(grace);
+        self
+    }
+
+    /// Access the wrapped transport
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    async fn send_ping(&mut self) -> McpResult<()> {
+        let id = JsonRpcId::String(format!("idle-ping-{}", self.next_ping));
+        self.next_ping += 1;
+
+        let request = JsonRpcRequest::new(methods::PING, None, id.clone());
+        let message = serde_json::to_string(&request).map_err(helpers::json_error)?;
+        self.inner.send(&message).await?;
+        self.outstanding_ping = Some(id);
+        Ok(())
+    }
+
+    /// Whether the message answers the outstanding ping
+    fn is_ping_response(&self, message: &str) -> bool {
+        let id = match &self.outstanding_ping {
+            Some(id) => id,
+            None => return false,
+        };
+        match serde_json::from_str::<Value>(message) {
+            Ok(value) => {
+                value.get("method").is_none()
+                    && value
+                        .get("id")
+                        .and_then(|v| serde_json::from_value::<JsonRpcId>(v.clone()).ok())
+                        .as_ref()
+                        == Some(id)
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for IdleTimeoutTransport<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        let mut pinged = false;
+        loop {
+            let wait = match (pinged, self.ping_grace) {
+                (true, Some(grace)) => grace,
+                _ => self.idle,
+            };
+
+            match timeout(wait, self.inner.receive()).await {
+                Ok(Ok(message)) if self.is_ping_response(&message) => {
+                    // The peer is alive; start a new idle period
+                    self.outstanding_ping = None;
+                    pinged = false;
+                }
+                Ok(result) => return result,
+                Err(_) if !pinged && self.ping_grace.is_some() => {
+                    self.send_ping().await?;
+                    pinged = true;
+                }
+                Err(_) => return Err(helpers::idle_timeout(self.idle)),
+            }
+        }
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.inner.send(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.inner.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        self.inner.send_part(part, end).await
+    }
+    async fn close(&mut self) -> McpResult<()> {
+        self.inner.close().await
+    }
+}
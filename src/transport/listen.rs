@@ -0,0 +1,229 @@
+//! TCP listening options
+//!
+//! [`TcpTransport::bind`](super::TcpTransport::bind) binds a single address
+//! with the OS defaults. [`ListenConfig`] binds several at once, e.g. the IPv4
+//! and IPv6 loopbacks, and sets the socket options that must be chosen before
+//! binding:
+//!
+//! ```rust,ignore
+//! // One socket accepting both IPv4 and IPv6, on a port picked by the OS
+//! let listeners = ListenConfig::dual_stack(0).bind().await?;
+//! println!("listening on {:?}", listeners.local_addrs());
+//!
+//! // One process per core sharing the port, the kernel balancing connections
+//! let listeners = ListenConfig::new()
+//!     .with_address(([0, 0, 0, 0], 8080))
+//!     .with_reuse_port(true)
+//!     .bind()
+//!     .await?;
+//! let (stream, peer) = listeners.accept().await?;
+//!
+//! // IPv4 and IPv6 on separate sockets, sharing the port picked by the OS
+//! let listeners = TcpTransport::listen(
+//!     &ListenConfig::new()
+//!         .with_address((Ipv6Addr::UNSPECIFIED, 0))
+//!         .with_address((Ipv4Addr::UNSPECIFIED, 0)),
+//! )
+//! .await?;
+//! ```
+
+use crate::error::helpers;
+use mcp_error::Result as McpResult;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::future::poll_fn;
+use std::io;
+use std::net::{Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::Poll;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Default length of the queue of connections waiting to be accepted
+pub const DEFAULT_BACKLOG: u32 = 1024;
+
+/// Addresses and socket options to listen with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenConfig {
+    /// Addresses to bind, each on a socket of its own
+    pub addresses: Vec<SocketAddr>,
+    /// Whether IPv6 sockets refuse IPv4 connections; if `None`, they do when
+    /// IPv4 addresses are also bound, and follow the OS default otherwise
+    pub v6only: Option<bool>,
+    /// Whether other sockets, typically of other processes, may bind the same
+    /// address (`SO_REUSEPORT`, ignored where unsupported)
+    pub reuse_port: bool,
+    /// Length of the queue of connections waiting to be accepted
+    pub backlog: u32,
+}
+
+impl Default for ListenConfig {
+    fn default() -> Self {
+        Self {
+            addresses: Vec::new(),
+            v6only: None,
+            reuse_port: false,
+            backlog: DEFAULT_BACKLOG,
+        }
+    }
+}
+
+impl ListenConfig {
+    /// Create a configuration without addresses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Listen on all interfaces, IPv4 and IPv6, with a single socket
+    pub fn dual_stack(port: u16) -> Self {
+        Self::new()
+            .with_address((Ipv6Addr::UNSPECIFIED, port))
+            .with_v6only(false)
+    }
+
+    /// Also bind an address; port 0 lets the OS pick a free port, which is
+    /// shared by every address bound with port 0
+    pub fn with_address(mut self, address: impl Into<SocketAddr>) -> Self {
+        self.addresses.push(address.into());
+        self
+    }
+
+    /// Also bind several addresses
+    pub fn with_addresses(mut self, addresses: impl IntoIterator<Item = SocketAddr>) -> Self {
+        self.addresses.extend(addresses);
+        self
+    }
+
+    /// Set whether IPv6 sockets refuse IPv4 connections
+    pub fn with_v6only(mut self, v6only: bool) -> Self {
+        self.v6only = Some(v6only);
+        self
+    }
+
+    /// Set whether other sockets may bind the same addresses
+    pub fn with_reuse_port(mut self, reuse_port: bool) -> Self {
+        self.reuse_port = reuse_port;
+        self
+    }
+
+    /// Set the length of the queue of connections waiting to be accepted
+    pub fn with_backlog(mut self, backlog: u32) -> Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Bind every address, failing if any of them cannot be bound
+    ///
+    /// Must be called within a Tokio runtime.
+    pub async fn bind(&self) -> McpResult<TcpListeners> {
+        if self.addresses.is_empty() {
+            return Err(helpers::config_error("No address to listen on"));
+        }
+        // `[::]` and `0.0.0.0` can only share a port if the IPv6 socket
+        // leaves IPv4 connections to the other
+        let v6only = self.v6only.or_else(|| {
+            self.addresses
+                .iter()
+                .any(SocketAddr::is_ipv4)
+                .then_some(true)
+        });
+        let mut picked_port = None;
+        let mut listeners = Vec::with_capacity(self.addresses.len());
+        for address in &self.addresses {
+            let mut address = *address;
+            if address.port() == 0 {
+                address.set_port(picked_port.unwrap_or(0));
+            }
+            let listener = self.bind_one(address, v6only).map_err(|e| {
+                helpers::transport_error(&format!("Failed to bind {}: {}", address, e))
+            })?;
+            if address.port() == 0 {
+                picked_port = listener.local_addr().ok().map(|local| local.port());
+            }
+            listeners.push(listener);
+        }
+        Ok(TcpListeners::from(listeners))
+    }
+
+    fn bind_one(&self, address: SocketAddr, v6only: Option<bool>) -> io::Result<TcpListener> {
+        let socket = Socket::new(
+            Domain::for_address(address),
+            Type::STREAM,
+            Some(Protocol::TCP),
+        )?;
+        if let (SocketAddr::V6(_), Some(v6only)) = (address, v6only) {
+            socket.set_only_v6(v6only)?;
+        }
+        // Like the listeners of the standard library, allow restarting while
+        // connections of the previous run are in TIME_WAIT
+        #[cfg(unix)]
+        socket.set_reuse_address(true)?;
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        if self.reuse_port {
+            socket.set_reuse_port(true)?;
+        }
+        socket.set_nonblocking(true)?;
+        socket.bind(&address.into())?;
+        socket.listen(self.backlog.min(i32::MAX as u32) as i32)?;
+        TcpListener::from_std(socket.into())
+    }
+}
+
+/// Listeners accepted from as one, e.g. those bound from a [`ListenConfig`]
+/// in the order of its addresses
+///
+/// Also a [`Listener`](crate::server::Listener) for a
+/// [`ServerRuntime`](crate::server::ServerRuntime).
+#[derive(Debug)]
+pub struct TcpListeners {
+    listeners: Vec<TcpListener>,
+    /// Listener checked first by the next accept, so none is starved
+    next: AtomicUsize,
+}
+
+impl From<Vec<TcpListener>> for TcpListeners {
+    /// Accept from listeners bound otherwise, e.g. inherited from another
+    /// process
+    fn from(listeners: Vec<TcpListener>) -> Self {
+        Self {
+            listeners,
+            next: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl TcpListeners {
+    /// Addresses actually bound, with the ports picked by the OS
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.listeners
+            .iter()
+            .filter_map(|listener| listener.local_addr().ok())
+            .collect()
+    }
+
+    /// The listeners, e.g. to accept on each from a task of its own
+    pub fn listeners(&self) -> &[TcpListener] {
+        &self.listeners
+    }
+
+    /// Take the listeners
+    pub fn into_inner(self) -> Vec<TcpListener> {
+        self.listeners
+    }
+
+    /// Accept a connection on any of the listeners
+    pub async fn accept(&self) -> McpResult<(TcpStream, SocketAddr)> {
+        poll_fn(|cx| {
+            let count = self.listeners.len();
+            let start = self.next.load(Ordering::Relaxed);
+            for offset in 0..count {
+                let index = (start + offset) % count;
+                if let Poll::Ready(accepted) = self.listeners[index].poll_accept(cx) {
+                    self.next.store((index + 1) % count, Ordering::Relaxed);
+                    return Poll::Ready(accepted);
+                }
+            }
+            Poll::Pending
+        })
+        .await
+        .map_err(|e| helpers::transport_error(&format!("Failed to accept: {}", e)))
+    }
+}
@@ -1,7 +1,86 @@
+pub mod bandwidth;
 pub mod base;
+pub mod binary;
+#[cfg(feature = "msgpack")]
+pub mod codec;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod connect;
+pub mod events;
+#[cfg(feature = "framed")]
+pub mod framed;
+#[cfg(all(unix, feature = "handoff"))]
+pub mod handoff;
+pub mod heartbeat;
+pub mod http;
+pub mod http_client;
+pub mod idle;
+pub mod listen;
+pub mod poll;
+pub mod process;
+pub mod proxy;
+pub mod reconnect;
+pub mod rewrite;
+pub mod split;
+pub mod sse;
+pub mod stdio;
+#[cfg(feature = "stream")]
+pub mod stream;
 pub mod tcp;
+pub mod timeout;
+#[cfg(feature = "tls")]
+pub mod tls;
 pub mod unix;
+#[cfg(unix)]
+pub mod unix_listen;
+#[cfg(all(target_os = "linux", feature = "vsock"))]
+pub mod vsock;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+pub mod write_timeout;
 
-pub use base::{JsonRpcTransport, Transport};
+pub use bandwidth::{BandwidthLimit, BandwidthMeter, BandwidthStats, BandwidthTransport};
+pub use binary::{BytesTransport, Utf8Transport};
+pub use base::{BatchStreamWriter, BoxedTransport, Framing, JsonRpcTransport, Transport};
+#[cfg(feature = "msgpack")]
+pub use codec::{Codec, MessagePackTransport, NegotiatedTransport};
+#[cfg(feature = "compression")]
+pub use compression::Compression;
+pub use connect::{ConnectConfig, ConnectStrategy, IntoTargetAddr, TargetAddr};
+pub use events::{TransportEvent, TransportEventHandler};
+#[cfg(feature = "framed")]
+pub use framed::JsonRpcCodec;
+#[cfg(all(unix, feature = "handoff"))]
+pub use handoff::{
+    inherit_listeners, listeners_from_env, receive_listeners, send_listeners, systemd_listeners,
+    HandoffListener,
+};
+pub use heartbeat::{Heartbeat, HeartbeatTransport};
+pub use http::{HttpConnection, HttpServerTransport};
+pub use http_client::HttpClientTransport;
+pub use idle::IdleTimeoutTransport;
+pub use listen::{ListenConfig, TcpListeners};
+pub use poll::PollingTransport;
+pub use process::ProcessTransport;
+pub use proxy::{Proxy, ProxyKind};
+pub use reconnect::ReconnectingTransport;
+pub use rewrite::RewritingTransport;
+pub use split::{SplitTransport, TransportReceiver, TransportSender};
+pub use sse::EventStream;
+pub use stdio::StdioTransport;
+#[cfg(feature = "stream")]
+pub use stream::TransportStream;
 pub use tcp::TcpTransport;
+pub use timeout::TimeoutTransport;
+#[cfg(feature = "tls")]
+pub use tls::{PeerCertificate, TlsServerConfig, TlsTransport};
+#[cfg(unix)]
+pub use unix::PeerCredentials;
 pub use unix::UnixTransport;
+#[cfg(unix)]
+pub use unix_listen::UnixListenConfig;
+#[cfg(all(target_os = "linux", feature = "vsock"))]
+pub use vsock::{VsockAddr, VsockListener, VsockStream, VsockTransport};
+#[cfg(feature = "websocket")]
+pub use websocket::WebSocketTransport;
+pub use write_timeout::{DeadLetter, DeadLetterQueue, WriteTimeoutTransport};
@@ -0,0 +1,111 @@
+//! Readiness-based receiving
+//!
+//! [`Transport::receive`] suits a task per connection awaiting its next
+//! message. Event loops driving many connections by hand, e.g. `select!` over
+//! a changing set or a poll-based reactor, need to poll instead:
+//! [`PollingTransport`] wraps any transport and adds
+//! [`poll_receive`](PollingTransport::poll_receive). Messages received this
+//! way are answered with
+//! [`JsonRpcProcessor::handle_message`](crate::processor::JsonRpcProcessor::handle_message).
+//!
+//! A receive left pending by the event loop is dropped when sending or
+//! closing; this relies on the wrapped transport's `receive` being cancel
+//! safe, as [`Transport`] requires.
+
+use crate::transport::base::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tokio::sync::Mutex;
+
+type Receiving = Pin<Box<dyn Future<Output = McpResult<String>> + Send>>;
+
+/// Transport wrapper that can be polled for the next message
+pub struct PollingTransport<T> {
+    inner: Arc<Mutex<T>>,
+    /// Receive in progress, owning the lock on the transport
+    receiving: Option<Receiving>,
+}
+
+impl<T: Transport + 'static> PollingTransport<T> {
+    /// Wrap a transport
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            receiving: None,
+        }
+    }
+
+    /// Poll for the next message
+    ///
+    /// Returns `Poll::Pending` and wakes the task once a message can be taken,
+    /// like a future; the receive in progress is kept between calls.
+    pub fn poll_receive(&mut self, cx: &mut Context<'_>) -> Poll<McpResult<String>> {
+        let inner = &self.inner;
+        let receiving = self.receiving.get_or_insert_with(|| {
+            let inner = inner.clone();
+            Box::pin(async move { inner.lock().await.receive().await })
+        });
+        let received = ready!(receiving.as_mut().poll(cx));
+        self.receiving = None;
+        Poll::Ready(received)
+    }
+
+    /// Whether a receive is in progress
+    pub fn is_receiving(&self) -> bool {
+        self.receiving.is_some()
+    }
+
+    /// Wait for the next message
+    ///
+    /// Cancel safe: dropping the future keeps the receive in progress for the
+    /// next call, so it can be raced in `select!`.
+    pub fn next_message(&mut self) -> impl Future<Output = McpResult<String>> + '_ {
+        poll_fn(move |cx| self.poll_receive(cx))
+    }
+
+    /// Unwrap the transport, dropping any receive in progress
+    pub fn into_inner(mut self) -> T {
+        self.receiving = None;
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => inner.into_inner(),
+            Err(_) => unreachable!("only the dropped receive shared the transport"),
+        }
+    }
+
+    /// Lock the transport for a write, dropping any receive in progress
+    async fn lock(&mut self) -> tokio::sync::MutexGuard<'_, T> {
+        self.receiving = None;
+        self.inner.lock().await
+    }
+}
+
+#[async_trait]
+impl<T: Transport + 'static> Transport for PollingTransport<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        self.next_message().await
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.lock().await.send(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        // The lock is only free when no receive is in progress
+        match self.inner.try_lock() {
+            Ok(inner) => inner.supports_partial_send(),
+            Err(_) => false,
+        }
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        self.lock().await.send_part(part, end).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.lock().await.close().await
+    }
+}
@@ -0,0 +1,236 @@
+//! Child-process transport
+//!
+//! MCP hosts launch local servers as child processes and talk to them over
+//! their standard input and output, the other end of a
+//! [`StdioTransport`](super::StdioTransport):
+//!
+//! ```rust,ignore
+//! let transport = ProcessTransport::spawn("mcp-server-files", ["--root", "/srv"])?;
+//! let mut client = McpClient::new(transport, Implementation::new("host", "1.0"));
+//! client.initialize().await?;
+//! ```
+//!
+//! The server's stderr is inherited, so its logs show up with the host's. The
+//! server exiting is reported like a closed connection, with its exit status
+//! in the message, and the server is killed if the transport is dropped while
+//! it still runs.
+
+use crate::error::helpers;
+use crate::transport::base::{Framing, JsonRpcTransport, Transport};
+use async_trait::async_trait;
+use mcp_error::{Error as McpError, Result as McpResult};
+use std::ffi::OsStr;
+use std::io;
+use std::pin::Pin;
+use std::process::{ExitStatus, Stdio};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, ReadHalf, WriteHalf};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+/// Time given to a process to exit once its input is closed, by default
+pub const DEFAULT_EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time waited for the process to exit once its output ended, to report its
+/// status
+const EXIT_GRACE: Duration = Duration::from_millis(100);
+
+/// The standard output and input of a child process as one stream
+struct ProcessStream {
+    stdout: ChildStdout,
+    /// `None` once shut down; only dropping the pipe closes it
+    stdin: Option<ChildStdin>,
+}
+
+impl ProcessStream {
+    fn stdin(&mut self) -> io::Result<Pin<&mut ChildStdin>> {
+        match &mut self.stdin {
+            Some(stdin) => Ok(Pin::new(stdin)),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+}
+
+impl AsyncRead for ProcessStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ProcessStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.stdin()?.poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.stdin {
+            Some(stdin) => Pin::new(stdin).poll_flush(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(stdin) = &mut self.stdin {
+            std::task::ready!(Pin::new(stdin).poll_shutdown(cx))?;
+        }
+        self.stdin = None;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Newline-delimited JSON-RPC over the standard input and output of a child
+/// process
+///
+/// Closing the transport closes the process's input, which tells a stdio
+/// server to exit, then waits for it up to the exit timeout before killing
+/// it.
+pub struct ProcessTransport {
+    transport: JsonRpcTransport<ReadHalf<ProcessStream>, WriteHalf<ProcessStream>>,
+    child: Child,
+    exit_timeout: Duration,
+    status: Option<ExitStatus>,
+}
+
+impl ProcessTransport {
+    /// Launch `program` with the given arguments
+    pub fn spawn<I, S>(program: impl AsRef<OsStr>, args: I) -> McpResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut command = Command::new(program);
+        command.args(args);
+        Self::from_command(command)
+    }
+
+    /// Launch a prepared command, e.g. with environment variables or a
+    /// working directory set
+    ///
+    /// Its standard input and output are replaced by pipes to the transport.
+    pub fn from_command(mut command: Command) -> McpResult<Self> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| helpers::transport_error(&format!("Failed to spawn: {}", e)))?;
+        let stream = match (child.stdout.take(), child.stdin.take()) {
+            (Some(stdout), Some(stdin)) => ProcessStream {
+                stdout,
+                stdin: Some(stdin),
+            },
+            _ => return Err(helpers::transport_error("Failed to spawn: no stdio pipes")),
+        };
+
+        Ok(Self {
+            transport: JsonRpcTransport::new(stream),
+            child,
+            exit_timeout: DEFAULT_EXIT_TIMEOUT,
+            status: None,
+        })
+    }
+
+    /// Delimit messages as given instead of one per line
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.transport = self.transport.with_framing(framing);
+        self
+    }
+
+    /// Give the process `timeout` to exit once closed before killing it
+    pub fn with_exit_timeout(mut self, timeout: Duration) -> Self {
+        self.exit_timeout = timeout;
+        self
+    }
+
+    /// OS id of the process, `None` once it was reaped
+    pub fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Exit status of the process, if it exited
+    pub fn exit_status(&mut self) -> Option<ExitStatus> {
+        if self.status.is_none() {
+            self.status = self.child.try_wait().ok().flatten();
+        }
+        self.status
+    }
+
+    /// Replace an error caused by the process exiting with one giving its
+    /// exit status
+    async fn exited(&mut self, e: McpError) -> McpError {
+        if self.status.is_none() {
+            if let Ok(Ok(status)) = tokio::time::timeout(EXIT_GRACE, self.child.wait()).await {
+                self.status = Some(status);
+            }
+        }
+        match self.status {
+            Some(status) => {
+                helpers::connection_closed_because(&format!("process exited with {}", status))
+            }
+            None => e,
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for ProcessTransport {
+    async fn receive(&mut self) -> McpResult<String> {
+        match self.transport.receive().await {
+            Err(e) if crate::error::is_connection_closed(&e) => Err(self.exited(e).await),
+            result => result,
+        }
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        match self.transport.send(message).await {
+            // A broken pipe means the process no longer reads its input
+            Err(e) if crate::error::is_connection_error(&e) => Err(self.exited(e).await),
+            result => result,
+        }
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.transport.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        match self.transport.send_part(part, end).await {
+            Err(e) if crate::error::is_connection_error(&e) => Err(self.exited(e).await),
+            result => result,
+        }
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        // The process may have exited already, closing the pipe
+        let _ = self.transport.close().await;
+        if self.status.is_some() {
+            return Ok(());
+        }
+        match tokio::time::timeout(self.exit_timeout, self.child.wait()).await {
+            Ok(Ok(status)) => {
+                self.status = Some(status);
+                Ok(())
+            }
+            Ok(Err(e)) => Err(helpers::transport_error(&format!(
+                "Failed to wait for the process: {}",
+                e
+            ))),
+            Err(_) => {
+                self.child
+                    .kill()
+                    .await
+                    .map_err(|e| helpers::transport_error(&format!("Failed to kill: {}", e)))?;
+                self.status = self.child.try_wait().ok().flatten();
+                Ok(())
+            }
+        }
+    }
+}
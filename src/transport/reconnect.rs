@@ -0,0 +1,205 @@
+//! Reconnection for long-lived client connections
+//!
+//! [`ReconnectingTransport`] opens its connection through a
+//! [`Connector`], usually a closure, and opens a new one whenever receiving or
+//! sending fails with a connection error:
+//!
+//! ```rust,ignore
+//! let transport = ReconnectingTransport::new(|| TcpTransport::connect(addr))
+//!     .with_policy(ReconnectPolicy {
+//!         max_attempts: Some(10),
+//!         ..ReconnectPolicy::default()
+//!     });
+//! let mut client = JsonRpcClient::new(transport);
+//! ```
+//!
+//! Only the connection is restored: requests awaiting a response on the lost
+//! connection never get one, and a session the peer keeps per connection,
+//! such as an initialized MCP session, starts over. [`ManagedClient`] restores
+//! MCP sessions on top of reconnection.
+//!
+//! [`ManagedClient`]: crate::client::ManagedClient
+
+use crate::client::managed::{Connector, ReconnectPolicy};
+use crate::error::{self, helpers};
+use crate::transport::base::Transport;
+use crate::transport::events::{TransportEvent, TransportEventHandler};
+use async_trait::async_trait;
+use mcp_error::{Error as McpError, Result as McpResult};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Transport re-establishing its connection with exponential backoff
+///
+/// The first connection is opened by the first receive or send, and a lost
+/// one is replaced after a delay, which keeps doubling while new connections
+/// fail before a message goes through, e.g. when the server accepts then
+/// drops them. A message whose send failed is sent again once reconnected, so
+/// it may reach the peer twice if the connection dropped after delivering it.
+/// Messages cannot be sent in parts, as a part sent on a lost connection could
+/// not be resumed.
+pub struct ReconnectingTransport<C: Connector> {
+    connector: C,
+    policy: ReconnectPolicy,
+    current: Option<C::Transport>,
+    /// Delay before the next connection attempt
+    backoff: Duration,
+    /// Whether a connection or connection attempt failed, so the next
+    /// attempt waits
+    failed: bool,
+    connected: bool,
+    reconnects: u64,
+    closed: bool,
+    events: Option<TransportEventHandler>,
+}
+
+impl<C: Connector> ReconnectingTransport<C> {
+    /// Open connections through the given connector, with the default policy
+    pub fn new(connector: C) -> Self {
+        let policy = ReconnectPolicy::default();
+        Self {
+            connector,
+            backoff: policy.initial_delay,
+            policy,
+            current: None,
+            failed: false,
+            connected: false,
+            reconnects: 0,
+            closed: false,
+            events: None,
+        }
+    }
+
+    /// Set how connection attempts are spaced, and when to give up
+    pub fn with_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.backoff = policy.initial_delay;
+        self.policy = policy;
+        self
+    }
+
+    /// Call the given function for each transport event
+    pub fn with_event_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&TransportEvent) + Send + Sync + 'static,
+    {
+        self.events = Some(Arc::new(handler));
+        self
+    }
+
+    /// Whether a connection is currently open
+    pub fn is_connected(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Number of times the connection was re-established after a failure
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects
+    }
+
+    /// Access the current connection, if any
+    pub fn inner_mut(&mut self) -> Option<&mut C::Transport> {
+        self.current.as_mut()
+    }
+
+    fn emit(&self, event: TransportEvent) {
+        if let Some(handler) = &self.events {
+            handler(&event);
+        }
+    }
+
+    /// Current connection, opened following the policy if there is none
+    async fn connection(&mut self) -> McpResult<&mut C::Transport> {
+        if self.closed {
+            return Err(helpers::connection_closed());
+        }
+        if self.current.is_none() {
+            let transport = self.connect().await?;
+            self.current = Some(transport);
+        }
+        Ok(self.current.as_mut().expect("connection open"))
+    }
+
+    async fn connect(&mut self) -> McpResult<C::Transport> {
+        let mut attempt = 1;
+        loop {
+            if self.failed {
+                tokio::time::sleep(self.backoff).await;
+                self.backoff = (self.backoff * 2).min(self.policy.max_delay);
+            }
+            match self.connector.connect().await {
+                Ok(transport) => {
+                    self.failed = false;
+                    if self.connected {
+                        self.reconnects += 1;
+                        self.emit(TransportEvent::Reconnected { attempts: attempt });
+                    }
+                    self.connected = true;
+                    return Ok(transport);
+                }
+                Err(e) => {
+                    self.failed = true;
+                    if self.policy.max_attempts.is_some_and(|max| attempt >= max) {
+                        return Err(e);
+                    }
+                    self.emit(TransportEvent::ConnectFailed {
+                        attempt,
+                        error: e.to_string(),
+                    });
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Drop a connection that failed, if the error means it is lost
+    ///
+    /// Returns whether the operation should be tried again on a new one.
+    fn lost(&mut self, err: &McpError) -> bool {
+        if !error::is_connection_error(err) || self.current.is_none() {
+            return false;
+        }
+        self.current = None;
+        self.failed = true;
+        self.emit(TransportEvent::ConnectionLost {
+            error: err.to_string(),
+        });
+        true
+    }
+
+    /// Note a message went through, so the connection works
+    fn delivered<R>(&mut self, result: McpResult<R>) -> McpResult<R> {
+        if result.is_ok() {
+            self.backoff = self.policy.initial_delay;
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<C: Connector> Transport for ReconnectingTransport<C> {
+    async fn receive(&mut self) -> McpResult<String> {
+        loop {
+            match self.connection().await?.receive().await {
+                Err(e) if self.lost(&e) => continue,
+                result => return self.delivered(result),
+            }
+        }
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        loop {
+            match self.connection().await?.send(message).await {
+                Err(e) if self.lost(&e) => continue,
+                result => return self.delivered(result),
+            }
+        }
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.closed = true;
+        match self.current.take() {
+            Some(mut transport) => transport.close().await,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::history::Direction;
+use crate::rewrite::RewriteRules;
+use crate::transport::base::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Transport wrapper applying [rewrite rules](crate::rewrite) to the messages
+/// it receives (inbound) and sends (outbound)
+///
+/// Requests are remembered by id until answered, so responses are matched on
+/// the method of their request as it was before the rules changed it.
+/// Messages that are not JSON, or that no rule applies to, pass through
+/// unchanged. Partial sends are not offered, since a batch can only be
+/// rewritten once complete.
+pub struct RewritingTransport<T> {
+    inner: T,
+    rules: RewriteRules,
+    /// Methods of the requests received, by id, until answered
+    received: HashMap<String, String>,
+    /// Methods of the requests sent, by id, until answered
+    sent: HashMap<String, String>,
+}
+
+impl<T: Transport> RewritingTransport<T> {
+    /// Wrap a transport
+    pub fn new(inner: T, rules: RewriteRules) -> Self {
+        Self {
+            inner,
+            rules,
+            received: HashMap::new(),
+            sent: HashMap::new(),
+        }
+    }
+
+    /// The rules applied
+    pub fn rules(&self) -> &RewriteRules {
+        &self.rules
+    }
+
+    /// Access the wrapped transport
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Rewrite a message going in the given direction, if any rule applies
+    fn rewrite(&mut self, direction: Direction, message: &str) -> Option<String> {
+        let mut value: Value = serde_json::from_str(message).ok()?;
+        let (requests, responses) = match direction {
+            Direction::Inbound => (&mut self.received, &mut self.sent),
+            Direction::Outbound => (&mut self.sent, &mut self.received),
+        };
+        let mut applied = 0;
+        let elements = match &mut value {
+            Value::Array(elements) => elements.iter_mut().collect::<Vec<_>>(),
+            value => vec![value],
+        };
+        for element in elements {
+            let id = element.get("id").map(Value::to_string);
+            let method = element
+                .get("method")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            let method = match (id, method) {
+                (Some(id), Some(method)) => {
+                    requests.insert(id, method.clone());
+                    Some(method)
+                }
+                (Some(id), None) => responses.remove(&id),
+                (None, method) => method,
+            };
+            applied += self.rules.apply(direction, method.as_deref(), element);
+        }
+        if applied == 0 {
+            return None;
+        }
+        serde_json::to_string(&value).ok()
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for RewritingTransport<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        let message = self.inner.receive().await?;
+        Ok(self
+            .rewrite(Direction::Inbound, &message)
+            .unwrap_or(message))
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        match self.rewrite(Direction::Outbound, message) {
+            Some(rewritten) => self.inner.send(&rewritten).await,
+            None => self.inner.send(message).await,
+        }
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.inner.close().await
+    }
+}
@@ -0,0 +1,257 @@
+//! Independent sending and receiving halves of a transport
+//!
+//! [`Transport`] methods take `&mut self`, so nothing can be sent while a
+//! receive is waiting. [`Transport::into_split`] hands the transport to a task
+//! of its own and returns a [`TransportReceiver`] and a cloneable
+//! [`TransportSender`], usable from different tasks:
+//!
+//! ```rust,ignore
+//! let (sender, mut receiver) = transport.into_split();
+//! let progress = sender.clone();
+//! tokio::spawn(async move {
+//!     progress.send(r#"{"jsonrpc":"2.0","method":"notifications/progress"}"#).await
+//! });
+//! while let Ok(message) = receiver.receive().await {
+//!     // ...
+//! }
+//! ```
+//!
+//! The task sends messages one at a time, in the order they were handed to
+//! it, and a message sent in parts is never interleaved with another. It
+//! receives ahead of the receiver by at most one message, racing each receive
+//! against the messages to send, so the transport must be cancel safe as the
+//! trait requires. It ends, dropping the transport, once both halves and
+//! every clone of the sender are dropped.
+
+use crate::error::helpers;
+use crate::protocol::JsonRpcMessage;
+use crate::transport::base::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, Mutex, OwnedMutexGuard};
+
+/// Request of a sender to the task owning the transport
+enum Command {
+    Send(String, oneshot::Sender<McpResult<()>>),
+    Part(String, bool, oneshot::Sender<McpResult<()>>),
+    Close(oneshot::Sender<McpResult<()>>),
+}
+
+/// Sending half of a split transport
+///
+/// Clones send through the same transport; each message is written whole,
+/// in the order the sends were made.
+pub struct TransportSender {
+    // Unbounded, as each send waits for its outcome before the next
+    commands: mpsc::UnboundedSender<Command>,
+    partial: bool,
+    /// Held by the clone sending a message in parts, until its last part
+    writing: Arc<Mutex<()>>,
+    message: Option<OwnedMutexGuard<()>>,
+}
+
+impl Clone for TransportSender {
+    fn clone(&self) -> Self {
+        Self {
+            commands: self.commands.clone(),
+            partial: self.partial,
+            writing: self.writing.clone(),
+            message: None,
+        }
+    }
+}
+
+impl TransportSender {
+    /// Send a message
+    pub async fn send(&self, message: &str) -> McpResult<()> {
+        if self.message.is_some() {
+            return Err(helpers::transport_error(
+                "A message is being sent in parts by this sender",
+            ));
+        }
+        let _writing = self.writing.lock().await;
+        self.request(|done| Command::Send(message.to_string(), done))
+            .await
+    }
+
+    /// Whether the transport can write a message in several parts
+    pub fn supports_partial_send(&self) -> bool {
+        self.partial
+    }
+
+    /// Write part of a message; the message is complete once a part is sent
+    /// with `end` set
+    ///
+    /// Other senders wait until the message is complete.
+    pub async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        if self.message.is_none() {
+            self.message = Some(self.writing.clone().lock_owned().await);
+        }
+        let sent = self
+            .request(|done| Command::Part(part.to_string(), end, done))
+            .await;
+        if end || sent.is_err() {
+            self.message = None;
+        }
+        sent
+    }
+
+    /// Flush pending writes and shut down the sending side, see
+    /// [`Transport::close`]
+    ///
+    /// The receiver keeps receiving until the peer closes its side.
+    pub async fn close(&self) -> McpResult<()> {
+        let _writing = self.writing.lock().await;
+        self.request(Command::Close).await
+    }
+
+    async fn request(
+        &self,
+        command: impl FnOnce(oneshot::Sender<McpResult<()>>) -> Command,
+    ) -> McpResult<()> {
+        let (done, outcome) = oneshot::channel();
+        self.commands.send(command(done)).map_err(|_| closed())?;
+        outcome.await.map_err(|_| closed())?
+    }
+}
+
+/// Receiving half of a split transport
+pub struct TransportReceiver {
+    incoming: mpsc::Receiver<McpResult<String>>,
+}
+
+impl TransportReceiver {
+    /// Receive the next message
+    ///
+    /// Cancel safe.
+    pub async fn receive(&mut self) -> McpResult<String> {
+        self.incoming.recv().await.unwrap_or_else(|| Err(closed()))
+    }
+
+    /// Receive the next message, parsed, see [`Transport::receive_message`]
+    ///
+    /// Cancel safe.
+    pub async fn receive_message(&mut self) -> McpResult<JsonRpcMessage> {
+        let message = self.receive().await?;
+        JsonRpcMessage::parse(&message)
+    }
+
+    /// Poll for the next message, for adapters implementing `poll` traits
+    #[cfg(feature = "stream")]
+    pub(crate) fn poll_receive(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<McpResult<String>> {
+        self.incoming
+            .poll_recv(cx)
+            .map(|received| received.unwrap_or_else(|| Err(closed())))
+    }
+}
+
+/// The two halves of a split transport joined back into a [`Transport`]
+///
+/// Clones of the sender keep sending alongside, e.g. notifications pushed
+/// while a processor waits for the next message; see
+/// [`JsonRpcProcessor::new_split`](crate::processor::JsonRpcProcessor::new_split).
+pub struct SplitTransport {
+    sender: TransportSender,
+    receiver: TransportReceiver,
+}
+
+impl SplitTransport {
+    /// Join the halves returned by [`Transport::into_split`]
+    pub fn new(sender: TransportSender, receiver: TransportReceiver) -> Self {
+        Self { sender, receiver }
+    }
+
+    /// The sending half, to clone
+    pub fn sender(&self) -> &TransportSender {
+        &self.sender
+    }
+
+    /// Separate the halves again
+    pub fn into_inner(self) -> (TransportSender, TransportReceiver) {
+        (self.sender, self.receiver)
+    }
+}
+
+#[async_trait]
+impl Transport for SplitTransport {
+    async fn receive(&mut self) -> McpResult<String> {
+        self.receiver.receive().await
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.sender.send(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.sender.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        self.sender.send_part(part, end).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.sender.close().await
+    }
+}
+
+/// Move the transport to a task of its own and return its halves
+pub(crate) fn split<T: Transport + 'static>(transport: T) -> (TransportSender, TransportReceiver) {
+    let (commands, commands_rx) = mpsc::unbounded_channel();
+    let (incoming_tx, incoming) = mpsc::channel(1);
+    let sender = TransportSender {
+        commands,
+        partial: transport.supports_partial_send(),
+        writing: Arc::new(Mutex::new(())),
+        message: None,
+    };
+    tokio::spawn(drive(transport, commands_rx, incoming_tx));
+    (sender, TransportReceiver { incoming })
+}
+
+/// Serve the senders' commands while receiving for the receiver, until both
+/// halves are gone
+async fn drive<T: Transport>(
+    mut transport: T,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    incoming: mpsc::Sender<McpResult<String>>,
+) {
+    let mut sending = true;
+    let mut receiving = true;
+    // Room for the next message received; not receiving without it
+    let mut permit = None;
+    while sending || receiving {
+        tokio::select! {
+            command = commands.recv(), if sending => match command {
+                Some(Command::Send(message, done)) => {
+                    let _ = done.send(transport.send(&message).await);
+                }
+                Some(Command::Part(part, end, done)) => {
+                    let _ = done.send(transport.send_part(&part, end).await);
+                }
+                Some(Command::Close(done)) => {
+                    let _ = done.send(transport.close().await);
+                }
+                None => sending = false,
+            },
+            reserved = incoming.reserve(), if receiving && permit.is_none() => match reserved {
+                Ok(reserved) => permit = Some(reserved),
+                Err(_) => receiving = false,
+            },
+            received = transport.receive(), if receiving && permit.is_some() => {
+                if let Some(permit) = permit.take() {
+                    permit.send(received);
+                }
+            }
+            _ = incoming.closed(), if receiving => receiving = false,
+        }
+    }
+}
+
+fn closed() -> mcp_error::Error {
+    helpers::connection_closed()
+}
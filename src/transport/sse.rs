@@ -0,0 +1,203 @@
+//! Server-Sent Events channel
+//!
+//! An [`EventStream`] pushes messages from the server to HTTP clients, which
+//! have no connection of their own to receive them on: a client opens a
+//! `GET` with `Accept: text/event-stream` and keeps reading events, each a
+//! JSON-RPC message in its `data` field.
+//!
+//! Events are numbered and the latest ones are kept, so a client reconnecting
+//! with the `Last-Event-ID` header its browser sends gets the events it
+//! missed before the live ones. Clients too slow to keep up are disconnected
+//! and catch up the same way on reconnection.
+//!
+//! ```rust,ignore
+//! let events = EventStream::new();
+//! let http = HttpServerTransport::bind(([127, 0, 0, 1], 8080))
+//!     .await?
+//!     .with_event_stream(events.clone());
+//! // Elsewhere, e.g. once a long job completes
+//! events.notify(&JsonRpcNotification::new("jobs/done", Some(json!({"job": 7}))))?;
+//! ```
+//!
+//! With another HTTP server, answer the `GET` with the headers of
+//! [`EventStream::HEADERS`] and hand its body to [`EventStream::serve`].
+
+use crate::error::helpers;
+use crate::protocol::JsonRpcNotification;
+use mcp_error::Result as McpResult;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::broadcast;
+
+/// Events kept for reconnecting clients by default
+pub const DEFAULT_REPLAY_SIZE: usize = 256;
+
+/// Interval between comments keeping idle streams open by default
+pub const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// An event, formatted once for every client
+#[derive(Debug)]
+struct Event {
+    id: u64,
+    text: String,
+}
+
+#[derive(Debug)]
+struct Log {
+    next_id: u64,
+    events: VecDeque<Arc<Event>>,
+}
+
+/// Outbound channel of events shared by all connected clients
+///
+/// Cheap to clone; clones publish to the same clients.
+#[derive(Debug, Clone)]
+pub struct EventStream {
+    log: Arc<Mutex<Log>>,
+    live: broadcast::Sender<Arc<Event>>,
+    replay_size: usize,
+    keepalive: Duration,
+    retry: Option<Duration>,
+}
+
+impl EventStream {
+    /// Headers of the HTTP response carrying the stream
+    pub const HEADERS: &'static [(&'static str, &'static str)] = &[
+        ("Content-Type", "text/event-stream"),
+        ("Cache-Control", "no-cache"),
+    ];
+
+    pub fn new() -> Self {
+        Self::with_replay_size(DEFAULT_REPLAY_SIZE)
+    }
+
+    /// Keep the latest `size` events for reconnecting clients
+    pub fn with_replay_size(size: usize) -> Self {
+        let (live, _) = broadcast::channel(size.max(16));
+        Self {
+            log: Arc::new(Mutex::new(Log {
+                next_id: 1,
+                events: VecDeque::with_capacity(size),
+            })),
+            live,
+            replay_size: size,
+            keepalive: DEFAULT_KEEPALIVE,
+            retry: None,
+        }
+    }
+
+    /// Set the interval between comments keeping idle streams open
+    pub fn with_keepalive(mut self, interval: Duration) -> Self {
+        self.keepalive = interval;
+        self
+    }
+
+    /// Ask clients to wait `delay` before reconnecting, instead of the few
+    /// seconds browsers wait by default
+    pub fn with_retry(mut self, delay: Duration) -> Self {
+        self.retry = Some(delay);
+        self
+    }
+
+    /// Push a notification to every connected client
+    pub fn notify(&self, notification: &JsonRpcNotification) -> McpResult<()> {
+        let message = serde_json::to_string(notification)
+            .map_err(|e| helpers::transport_error(&format!("Failed to serialize: {}", e)))?;
+        self.publish(&message);
+        Ok(())
+    }
+
+    /// Push a serialized JSON-RPC message to every connected client
+    ///
+    /// Returns the id of the event.
+    pub fn publish(&self, message: &str) -> u64 {
+        let mut log = self.log.lock().unwrap();
+        let id = log.next_id;
+        log.next_id += 1;
+
+        let mut text = format!("id: {}\nevent: message\n", id);
+        for line in message.lines() {
+            text.push_str("data: ");
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.push('\n');
+        let event = Arc::new(Event { id, text });
+
+        if self.replay_size > 0 {
+            if log.events.len() == self.replay_size {
+                log.events.pop_front();
+            }
+            log.events.push_back(event.clone());
+        }
+        // Sent under the lock, so a client subscribing sees every event
+        // either in the replay or live, once
+        let _ = self.live.send(event);
+        id
+    }
+
+    /// Number of clients currently connected
+    pub fn clients(&self) -> usize {
+        self.live.receiver_count()
+    }
+
+    /// Write the events to a client until it disconnects
+    ///
+    /// `last_event_id` is the `Last-Event-ID` header of the request: the
+    /// events kept after it are written first. An id this stream never
+    /// issued, e.g. one from before a restart, replays nothing.
+    pub async fn serve<W>(&self, mut writer: W, last_event_id: Option<&str>) -> std::io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let (replay, mut live) = {
+            let log = self.log.lock().unwrap();
+            let after = match last_event_id.map(|id| id.trim().parse::<u64>()) {
+                Some(Ok(id)) if id < log.next_id => Some(id),
+                Some(_) => Some(u64::MAX),
+                None => None,
+            };
+            let replay = match after {
+                Some(after) => log
+                    .events
+                    .iter()
+                    .filter(|event| event.id > after)
+                    .cloned()
+                    .collect(),
+                None => Vec::new(),
+            };
+            (replay, self.live.subscribe())
+        };
+
+        if let Some(retry) = self.retry {
+            let retry = format!("retry: {}\n\n", retry.as_millis());
+            writer.write_all(retry.as_bytes()).await?;
+        }
+        for event in replay {
+            writer.write_all(event.text.as_bytes()).await?;
+        }
+        writer.flush().await?;
+
+        loop {
+            let event = tokio::time::timeout(self.keepalive, live.recv()).await;
+            match event {
+                Ok(Ok(event)) => writer.write_all(event.text.as_bytes()).await?,
+                // Too slow: the client catches up from the replay once
+                // reconnected
+                Ok(Err(broadcast::error::RecvError::Lagged(_))) => return Ok(()),
+                Ok(Err(broadcast::error::RecvError::Closed)) => return Ok(()),
+                // Also detects clients gone away
+                Err(_) => writer.write_all(b": keepalive\n\n").await?,
+            }
+            writer.flush().await?;
+        }
+    }
+}
+
+impl Default for EventStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,103 @@
+use crate::transport::base::{Framing, JsonRpcTransport, Transport};
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, ReadHalf, Stdin, Stdout, WriteHalf};
+
+/// The standard input and output of the process as one stream
+struct StdioStream {
+    stdin: Stdin,
+    stdout: Stdout,
+}
+
+impl AsyncRead for StdioStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stdin).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for StdioStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stdout).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_shutdown(cx)
+    }
+}
+
+/// Newline-delimited JSON-RPC over the standard input and output of the process
+///
+/// This is how servers launched as child processes, by an IDE for instance,
+/// talk to their client. Stdout carries the messages only, so the server must
+/// not print anything else to it; log to stderr, which the transport leaves
+/// alone. The input ending, once the client exits or closes the pipe, is
+/// reported like a closed connection.
+///
+/// ```rust,ignore
+/// JsonRpcProcessor::new(StdioTransport::new(), registry).run().await?;
+/// ```
+pub struct StdioTransport(JsonRpcTransport<ReadHalf<StdioStream>, WriteHalf<StdioStream>>);
+
+impl StdioTransport {
+    /// Use the standard input and output of the process
+    ///
+    /// Create a single instance: several would interleave their reads.
+    pub fn new() -> Self {
+        Self(JsonRpcTransport::new(StdioStream {
+            stdin: tokio::io::stdin(),
+            stdout: tokio::io::stdout(),
+        }))
+    }
+
+    /// Delimit messages as given instead of one per line, e.g.
+    /// [`Framing::ContentLength`] for clients derived from LSP tooling
+    pub fn with_framing(self, framing: Framing) -> Self {
+        Self(self.0.with_framing(framing))
+    }
+}
+
+impl Default for StdioTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Transport for StdioTransport {
+    async fn receive(&mut self) -> McpResult<String> {
+        self.0.receive().await
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        // Complete messages are flushed like the end of a partial send, so
+        // none is left in the buffer of stdout if the process exits
+        self.0.send_part(message, true).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.0.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        self.0.send_part(part, end).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.0.close().await
+    }
+}
@@ -0,0 +1,130 @@
+//! `Stream` and `Sink` adapters
+//!
+//! [`TransportStream`] exposes a transport as a
+//! [`Stream`](futures_util::Stream) of received messages and a
+//! [`Sink`](futures_util::Sink) of messages to send, so it composes with
+//! `StreamExt` and `SinkExt` combinators and `select!` loops:
+//!
+//! ```rust,ignore
+//! let mut messages = TransportStream::new(transport);
+//! loop {
+//!     tokio::select! {
+//!         Some(message) = messages.next() => handle(message?),
+//!         Some(event) = events.recv() => messages.send(event.to_notification()).await?,
+//!     }
+//! }
+//! ```
+//!
+//! The transport is split with [`Transport::into_split`], so receiving and
+//! sending do not wait for each other. The stream ends once the connection
+//! is closed; other receive errors are yielded as items.
+
+use crate::transport::base::Transport;
+use crate::transport::split::{SplitTransport, TransportReceiver, TransportSender};
+use futures_util::{Sink, Stream};
+use mcp_error::{Error as McpError, Result as McpResult};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+type Sending = Pin<Box<dyn Future<Output = McpResult<()>> + Send>>;
+
+/// A transport as a `Stream` of received messages and a `Sink` of messages
+/// to send
+pub struct TransportStream {
+    sender: TransportSender,
+    receiver: TransportReceiver,
+    ended: bool,
+    /// Send or close in progress
+    sending: Option<Sending>,
+    closing: bool,
+}
+
+impl TransportStream {
+    /// Split the transport and adapt its halves
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn new<T: Transport + 'static>(transport: T) -> Self {
+        let (sender, receiver) = transport.into_split();
+        Self::from_split(sender, receiver)
+    }
+
+    /// Adapt the halves returned by [`Transport::into_split`]
+    pub fn from_split(sender: TransportSender, receiver: TransportReceiver) -> Self {
+        Self {
+            sender,
+            receiver,
+            ended: false,
+            sending: None,
+            closing: false,
+        }
+    }
+
+    /// The sending half, to clone for sending from elsewhere
+    pub fn sender(&self) -> &TransportSender {
+        &self.sender
+    }
+
+    /// Wait for the send or close in progress, if any
+    fn poll_sending(&mut self, cx: &mut Context<'_>) -> Poll<McpResult<()>> {
+        let sending = match &mut self.sending {
+            Some(sending) => sending,
+            None => return Poll::Ready(Ok(())),
+        };
+        let sent = ready!(sending.as_mut().poll(cx));
+        self.sending = None;
+        Poll::Ready(sent)
+    }
+}
+
+impl From<SplitTransport> for TransportStream {
+    fn from(transport: SplitTransport) -> Self {
+        let (sender, receiver) = transport.into_inner();
+        Self::from_split(sender, receiver)
+    }
+}
+
+impl Stream for TransportStream {
+    type Item = McpResult<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.ended {
+            return Poll::Ready(None);
+        }
+        match ready!(self.receiver.poll_receive(cx)) {
+            Err(e) if crate::error::is_connection_closed(&e) => {
+                self.ended = true;
+                Poll::Ready(None)
+            }
+            received => Poll::Ready(Some(received)),
+        }
+    }
+}
+
+impl Sink<String> for TransportStream {
+    type Error = McpError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<McpResult<()>> {
+        self.poll_sending(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, message: String) -> McpResult<()> {
+        let sender = self.sender.clone();
+        self.sending = Some(Box::pin(async move { sender.send(&message).await }));
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<McpResult<()>> {
+        self.poll_sending(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<McpResult<()>> {
+        if !self.closing {
+            ready!(self.poll_sending(cx))?;
+            let sender = self.sender.clone();
+            self.sending = Some(Box::pin(async move { sender.close().await }));
+            self.closing = true;
+        }
+        self.poll_sending(cx)
+    }
+}
@@ -1,7 +1,10 @@
 use crate::error::helpers;
-use crate::transport::base::{JsonRpcTransport, Transport};
+use crate::transport::base::{Framing, JsonRpcTransport, Transport};
+use crate::transport::connect::{ConnectConfig, IntoTargetAddr};
+use crate::transport::listen::{ListenConfig, TcpListeners};
 use mcp_error::Result as McpResult;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::io::{ReadHalf, WriteHalf}; // Pour le split si nécessaire
 
@@ -10,8 +13,18 @@ pub struct TcpTransport(JsonRpcTransport<ReadHalf<TcpStream>, WriteHalf<TcpStrea
 
 impl TcpTransport {
     /// Create a new TCP transport by connecting to the given address
-    pub async fn connect(addr: impl Into<SocketAddr>) -> McpResult<Self> {
-        let stream = TcpStream::connect(addr.into())
+    ///
+    /// Host names are resolved, e.g. `"mcp.example.com:9000"`, and the
+    /// resolved addresses tried in turn until one accepts the connection.
+    pub async fn connect(addr: impl IntoTargetAddr) -> McpResult<Self> {
+        Self::connect_with(addr, &ConnectConfig::default()).await
+    }
+
+    /// Create a new TCP transport by connecting to the given address, trying
+    /// the resolved addresses as configured
+    pub async fn connect_with(addr: impl IntoTargetAddr, config: &ConnectConfig) -> McpResult<Self> {
+        let stream = config
+            .connect(addr)
             .await
             .map_err(|e| helpers::transport_error(&format!("Failed to connect: {}", e)))?;
 
@@ -21,12 +34,28 @@ impl TcpTransport {
         Ok(Self(transport))
     }
 
+    /// Delimit messages as given instead of one per line
+    pub fn with_framing(self, framing: Framing) -> Self {
+        Self(self.0.with_framing(framing))
+    }
+
+    /// Fail receives waiting longer than `timeout` for a message, see
+    /// [`JsonRpcTransport::with_read_timeout`]
+    pub fn with_read_timeout(self, timeout: Duration) -> Self {
+        Self(self.0.with_read_timeout(timeout))
+    }
+
     /// Create a TCP listener that can accept JSON-RPC connections
     pub async fn bind(addr: impl Into<SocketAddr>) -> McpResult<TcpListener> {
         TcpListener::bind(addr.into())
             .await
             .map_err(|e| helpers::transport_error(&format!("Failed to bind: {}", e)))
     }
+
+    /// Create TCP listeners for several addresses and socket options
+    pub async fn listen(config: &ListenConfig) -> McpResult<TcpListeners> {
+        config.bind().await
+    }
 }
 
 #[async_trait::async_trait]
@@ -38,4 +67,15 @@ impl Transport for TcpTransport {
     async fn send(&mut self, message: &str) -> McpResult<()> {
         self.0.send(message).await
     }
+
+    fn supports_partial_send(&self) -> bool {
+        self.0.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        self.0.send_part(part, end).await
+    }
+    async fn close(&mut self) -> McpResult<()> {
+        self.0.close().await
+    }
 }
@@ -0,0 +1,123 @@
+use crate::error::helpers;
+use crate::transport::base::Transport;
+use async_trait::async_trait;
+use mcp_error::{Error as McpError, Result as McpResult};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Transport wrapper bounding how long each receive and send may take
+///
+/// Unlike [`IdleTimeoutTransport`](super::IdleTimeoutTransport) and
+/// [`WriteTimeoutTransport`](super::WriteTimeoutTransport), this only fails the
+/// operation that timed out, with an error recognized by
+/// [`is_receive_timeout`](crate::error::is_receive_timeout) or
+/// [`is_send_timeout`](crate::error::is_send_timeout), and leaves deciding
+/// what to do to the caller. Receives are cancel safe, so receiving again after
+/// a receive timeout waits for the same message; a timed-out send may have
+/// written part of the message, so the connection should be dropped.
+///
+/// The send timeout also bounds closing, which flushes pending writes.
+pub struct TimeoutTransport<T> {
+    inner: T,
+    receive_timeout: Option<Duration>,
+    send_timeout: Option<Duration>,
+}
+
+impl<T: Transport> TimeoutTransport<T> {
+    /// Wrap a transport, without timeouts until they are set
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            receive_timeout: None,
+            send_timeout: None,
+        }
+    }
+
+    /// Fail receives waiting longer than `timeout` for a message
+    pub fn with_receive_timeout(mut self, timeout: Duration) -> Self {
+        self.receive_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail sends taking longer than `timeout`
+    pub fn with_send_timeout(mut self, timeout: Duration) -> Self {
+        self.send_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of receives, if any
+    pub fn receive_timeout(&self) -> Option<Duration> {
+        self.receive_timeout
+    }
+
+    /// Timeout of sends, if any
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.send_timeout
+    }
+
+    /// Access the wrapped transport
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+/// Run an operation within the timeout, if any
+async fn within<F, R>(
+    limit: Option<Duration>,
+    operation: F,
+    timed_out: fn(Duration) -> McpError,
+) -> McpResult<R>
+where
+    F: Future<Output = McpResult<R>>,
+{
+    match limit {
+        Some(limit) => timeout(limit, operation)
+            .await
+            .unwrap_or_else(|_| Err(timed_out(limit))),
+        None => operation.await,
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for TimeoutTransport<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        within(
+            self.receive_timeout,
+            self.inner.receive(),
+            helpers::receive_timeout,
+        )
+        .await
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        within(
+            self.send_timeout,
+            self.inner.send(message),
+            helpers::send_timeout,
+        )
+        .await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.inner.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        within(
+            self.send_timeout,
+            self.inner.send_part(part, end),
+            helpers::send_timeout,
+        )
+        .await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        within(self.send_timeout, self.inner.close(), helpers::send_timeout).await
+    }
+}
@@ -0,0 +1,351 @@
+//! TLS transport and client certificate authentication
+//!
+//! [`TlsTransport`] carries newline-delimited JSON-RPC over TLS. Servers
+//! describe their certificate, and the authorities client certificates must
+//! chain to for mutual TLS, with [`TlsServerConfig`]:
+//!
+//! ```rust,ignore
+//! let config = TlsServerConfig::new(server_chain, server_key)
+//!     .with_client_roots(client_ca)
+//!     .build()?;
+//! let listener = TcpTransport::bind(([0, 0, 0, 0], 8443)).await?;
+//! loop {
+//!     let (stream, _) = listener.accept().await?;
+//!     let (config, registry) = (config.clone(), registry.clone());
+//!     tokio::spawn(async move {
+//!         let transport = TlsTransport::accept(stream, config).await?;
+//!         let labels = transport.connection_labels();
+//!         JsonRpcProcessor::new(transport, registry).with_labels(labels).run().await
+//!     });
+//! }
+//! ```
+//!
+//! Clients without a valid certificate fail the handshake, so the processor
+//! never sees them. The subject of the verified certificate becomes the
+//! [`peer`](crate::context::labels::PEER) label, which tools read from their
+//! [`RequestContext`](crate::context::RequestContext), e.g. `CN=billing,O=Acme`.
+//! Certificates and keys are DER; rustls-pemfile reads them from PEM files.
+
+use crate::context::{labels, ConnectionLabels};
+use crate::error::helpers;
+use crate::transport::base::{JsonRpcTransport, Transport};
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use tokio_rustls::{TlsAcceptor, TlsConnector, TlsStream};
+
+/// Server certificate and client authentication of a TLS listener
+pub struct TlsServerConfig {
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    client_roots: Option<RootCertStore>,
+    client_auth_optional: bool,
+}
+
+impl TlsServerConfig {
+    /// Present the given certificate chain, leaf first, and its key
+    pub fn new(chain: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> Self {
+        Self {
+            chain,
+            key,
+            client_roots: None,
+            client_auth_optional: false,
+        }
+    }
+
+    /// Require client certificates issued by one of the given authorities
+    pub fn with_client_roots(mut self, roots: RootCertStore) -> Self {
+        self.client_roots = Some(roots);
+        self
+    }
+
+    /// Also accept clients without a certificate; those presenting one must
+    /// still present a valid one
+    pub fn with_optional_client_auth(mut self) -> Self {
+        self.client_auth_optional = true;
+        self
+    }
+
+    /// Build the rustls configuration, shared by every accepted connection
+    pub fn build(self) -> McpResult<Arc<ServerConfig>> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            helpers::transport_error(&format!("Invalid TLS configuration: {}", e))
+        };
+        let builder = ServerConfig::builder();
+        let builder = match self.client_roots {
+            Some(roots) => {
+                let verifier = WebPkiClientVerifier::builder(Arc::new(roots));
+                let verifier = if self.client_auth_optional {
+                    verifier.allow_unauthenticated()
+                } else {
+                    verifier
+                };
+                builder.with_client_cert_verifier(verifier.build().map_err(|e| invalid(&e))?)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder
+            .with_single_cert(self.chain, self.key)
+            .map_err(|e| invalid(&e))?;
+        Ok(Arc::new(config))
+    }
+}
+
+/// Certificate a peer authenticated with
+#[derive(Debug, Clone)]
+pub struct PeerCertificate {
+    der: CertificateDer<'static>,
+    subject: Option<String>,
+    common_name: Option<String>,
+}
+
+impl PeerCertificate {
+    fn new(der: CertificateDer<'static>) -> Self {
+        let (subject, common_name) = match der::subject(&der) {
+            Some((subject, common_name)) => (Some(subject), common_name),
+            None => (None, None),
+        };
+        Self {
+            der,
+            subject,
+            common_name,
+        }
+    }
+
+    /// Subject of the certificate, e.g. `CN=billing,O=Acme`, unless it could
+    /// not be decoded
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    /// Common name in the subject, if any
+    pub fn common_name(&self) -> Option<&str> {
+        self.common_name.as_deref()
+    }
+
+    /// The certificate itself, to check other fields with an X.509 parser
+    pub fn der(&self) -> &CertificateDer<'static> {
+        &self.der
+    }
+}
+
+/// Newline-delimited JSON-RPC over a TLS connection
+pub struct TlsTransport<S = TcpStream> {
+    transport: JsonRpcTransport<ReadHalf<TlsStream<S>>, WriteHalf<TlsStream<S>>>,
+    peer: Option<PeerCertificate>,
+}
+
+impl<S> TlsTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    /// Complete the server side of the handshake on an accepted stream
+    ///
+    /// Fails if client certificates are required and the client has no
+    /// valid one.
+    pub async fn accept(stream: S, config: Arc<ServerConfig>) -> McpResult<Self> {
+        let stream = TlsAcceptor::from(config)
+            .accept(stream)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to accept: {}", e)))?;
+        let peer = stream.get_ref().1.peer_certificates().and_then(leaf);
+        Ok(Self::new(TlsStream::from(stream), peer))
+    }
+
+    /// Complete the client side of the handshake, checking the server
+    /// certificate is valid for `server_name`
+    ///
+    /// Client certificates for mutual TLS are set on `config`.
+    pub async fn connect(
+        stream: S,
+        config: Arc<ClientConfig>,
+        server_name: &str,
+    ) -> McpResult<Self> {
+        let name = ServerName::try_from(server_name.to_string())
+            .map_err(|e| helpers::transport_error(&format!("Failed to connect: {}", e)))?;
+        let stream = TlsConnector::from(config)
+            .connect(name, stream)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to connect: {}", e)))?;
+        let peer = stream.get_ref().1.peer_certificates().and_then(leaf);
+        Ok(Self::new(TlsStream::from(stream), peer))
+    }
+
+    fn new(stream: TlsStream<S>, peer: Option<PeerCertificate>) -> Self {
+        Self {
+            transport: JsonRpcTransport::new(stream),
+            peer,
+        }
+    }
+
+    /// Certificate the peer authenticated with, `None` for clients allowed
+    /// without one
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.peer.as_ref()
+    }
+
+    /// Labels for [`JsonRpcProcessor::with_labels`](crate::processor::JsonRpcProcessor::with_labels),
+    /// with the subject of the peer certificate as the `peer` label
+    pub fn connection_labels(&self) -> ConnectionLabels {
+        match self.peer.as_ref().and_then(PeerCertificate::subject) {
+            Some(subject) => ConnectionLabels::new().with(labels::PEER, subject),
+            None => ConnectionLabels::new(),
+        }
+    }
+}
+
+fn leaf(chain: &[CertificateDer<'static>]) -> Option<PeerCertificate> {
+    chain.first().cloned().map(PeerCertificate::new)
+}
+
+#[async_trait]
+impl<S> Transport for TlsTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    async fn receive(&mut self) -> McpResult<String> {
+        self.transport.receive().await
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.transport.send(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.transport.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        self.transport.send_part(part, end).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.transport.close().await
+    }
+}
+
+/// Just enough DER to read the subject of a certificate
+mod der {
+    const SEQUENCE: u8 = 0x30;
+    const SET: u8 = 0x31;
+    const OID: u8 = 0x06;
+    const VERSION: u8 = 0xa0;
+
+    /// Tag, content and remaining input of the first element
+    fn element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+        let (&tag, input) = input.split_first()?;
+        let (&first, mut input) = input.split_first()?;
+        let length = if first < 0x80 {
+            first as usize
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 || input.len() < count {
+                return None;
+            }
+            let (bytes, rest) = input.split_at(count);
+            input = rest;
+            bytes
+                .iter()
+                .fold(0usize, |length, &byte| (length << 8) | byte as usize)
+        };
+        if input.len() < length {
+            return None;
+        }
+        let (content, rest) = input.split_at(length);
+        Some((tag, content, rest))
+    }
+
+    fn expect(input: &[u8], expected: u8) -> Option<(&[u8], &[u8])> {
+        let (tag, content, rest) = element(input)?;
+        (tag == expected).then_some((content, rest))
+    }
+
+    /// Subject in the RFC 4514 string form, and its common name
+    pub fn subject(certificate: &[u8]) -> Option<(String, Option<String>)> {
+        let (certificate, _) = expect(certificate, SEQUENCE)?;
+        let (tbs, _) = expect(certificate, SEQUENCE)?;
+        let mut fields = tbs;
+        if fields.first() == Some(&VERSION) {
+            fields = element(fields)?.2;
+        }
+        // Serial number, signature algorithm, issuer, validity
+        for _ in 0..4 {
+            fields = element(fields)?.2;
+        }
+        let (mut name, _) = expect(fields, SEQUENCE)?;
+
+        let mut attributes = Vec::new();
+        let mut common_name = None;
+        while !name.is_empty() {
+            let (mut rdn, rest) = expect(name, SET)?;
+            name = rest;
+            let mut values = Vec::new();
+            while !rdn.is_empty() {
+                let (attribute, rest) = expect(rdn, SEQUENCE)?;
+                rdn = rest;
+                let (oid, value) = expect(attribute, OID)?;
+                let (_, value, _) = element(value)?;
+                let value = String::from_utf8_lossy(value);
+                let name = attribute_name(oid);
+                if name == "CN" {
+                    common_name = Some(value.to_string());
+                }
+                values.push(format!("{}={}", name, escape(&value)));
+            }
+            attributes.push(values.join("+"));
+        }
+        // Most specific first
+        attributes.reverse();
+        Some((attributes.join(","), common_name))
+    }
+
+    fn attribute_name(oid: &[u8]) -> String {
+        match oid {
+            [0x55, 0x04, 0x03] => "CN".to_string(),
+            [0x55, 0x04, 0x06] => "C".to_string(),
+            [0x55, 0x04, 0x07] => "L".to_string(),
+            [0x55, 0x04, 0x08] => "ST".to_string(),
+            [0x55, 0x04, 0x0a] => "O".to_string(),
+            [0x55, 0x04, 0x0b] => "OU".to_string(),
+            _ => dotted(oid),
+        }
+    }
+
+    fn dotted(oid: &[u8]) -> String {
+        let mut arcs = Vec::new();
+        let mut arc = 0u64;
+        for &byte in oid {
+            arc = (arc << 7) | (byte & 0x7f) as u64;
+            if byte & 0x80 == 0 {
+                if arcs.is_empty() {
+                    let first = (arc / 40).min(2);
+                    arcs.push(first);
+                    arcs.push(arc - first * 40);
+                } else {
+                    arcs.push(arc);
+                }
+                arc = 0;
+            }
+        }
+        arcs.iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn escape(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for character in value.chars() {
+            if matches!(character, ',' | '+' | '"' | '\\' | '<' | '>' | ';' | '=') {
+                escaped.push('\\');
+            }
+            escaped.push(character);
+        }
+        escaped
+    }
+}
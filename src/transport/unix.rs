@@ -1,9 +1,13 @@
 use tokio::io::{ReadHalf, WriteHalf}; // pour le split de l'io entre Read et Write
 
+#[cfg(unix)]
+use crate::context::{labels, ConnectionLabels};
 #[cfg(unix)]
 use crate::error::helpers;
 #[cfg(unix)]
-use crate::transport::base::{JsonRpcTransport, Transport};
+use crate::transport::base::{Framing, JsonRpcTransport, Transport};
+#[cfg(unix)]
+use crate::transport::unix_listen::UnixListenConfig;
 #[cfg(unix)]
 use async_trait::async_trait;
 #[cfg(unix)]
@@ -11,29 +15,175 @@ use mcp_error::Result as McpResult;
 #[cfg(unix)]
 use std::path::Path;
 #[cfg(unix)]
+use std::time::Duration;
+#[cfg(unix)]
 use tokio::net::{UnixListener, UnixStream};
 
 #[cfg(unix)]
-pub struct UnixTransport(JsonRpcTransport<ReadHalf<UnixStream>, WriteHalf<UnixStream>>);
+pub struct UnixTransport(
+    JsonRpcTransport<ReadHalf<UnixStream>, WriteHalf<UnixStream>>,
+    Option<PeerCredentials>,
+);
+
+/// Credentials of the process at the other end of a Unix socket, as
+/// reported by the OS when the connection was made (`SO_PEERCRED`)
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    /// Effective user id
+    pub uid: u32,
+    /// Effective group id
+    pub gid: u32,
+    /// Process id, where the OS reports it
+    pub pid: Option<i32>,
+}
+
+#[cfg(unix)]
+impl PeerCredentials {
+    /// Credentials of the peer of a connected stream
+    pub fn of(stream: &UnixStream) -> Option<Self> {
+        let credentials = stream.peer_cred().ok()?;
+        Some(Self {
+            uid: credentials.uid(),
+            gid: credentials.gid(),
+            pid: credentials.pid(),
+        })
+    }
+
+    /// Labels for [`JsonRpcProcessor::with_labels`](crate::processor::JsonRpcProcessor::with_labels):
+    /// the ids as `peer_uid`, `peer_gid` and `peer_pid`, and all of them as
+    /// the `peer` label, e.g. `uid=1000,gid=1000,pid=4242`
+    pub fn connection_labels(&self) -> ConnectionLabels {
+        let mut peer = format!("uid={},gid={}", self.uid, self.gid);
+        let mut connection = ConnectionLabels::new()
+            .with(labels::PEER_UID, self.uid.to_string())
+            .with(labels::PEER_GID, self.gid.to_string());
+        if let Some(pid) = self.pid {
+            peer.push_str(&format!(",pid={}", pid));
+            connection = connection.with(labels::PEER_PID, pid.to_string());
+        }
+        connection.with(labels::PEER, peer)
+    }
+}
 
 
 
 #[cfg(unix)]
 impl UnixTransport {
     /// Create a new Unix domain socket transport by connecting to the given path
+    ///
+    /// On Linux, a path starting with a NUL byte names an abstract socket
+    /// address, which has no file: `"\0mcp-server"`.
     pub async fn connect(path: impl AsRef<Path>) -> McpResult<Self> {
-        let stream = UnixStream::connect(path)
+        let stream = connect_stream(path.as_ref())
             .await
             .map_err(|e| helpers::transport_error(&format!("Failed to connect: {}", e)))?;
 
-        Ok(Self(JsonRpcTransport::new(stream)))
+        Ok(Self::from_stream(stream))
+    }
+
+    /// Create a transport over an already connected stream
+    pub fn from_stream(stream: UnixStream) -> Self {
+        let peer = PeerCredentials::of(&stream);
+        Self(JsonRpcTransport::new(stream), peer)
+    }
+
+    /// Credentials of the peer process, if the OS reported them
+    pub fn peer_credentials(&self) -> Option<PeerCredentials> {
+        self.1
+    }
+
+    /// Labels for [`JsonRpcProcessor::with_labels`](crate::processor::JsonRpcProcessor::with_labels)
+    /// describing the peer process, see [`PeerCredentials::connection_labels`]
+    pub fn connection_labels(&self) -> ConnectionLabels {
+        match &self.1 {
+            Some(peer) => peer.connection_labels(),
+            None => ConnectionLabels::new(),
+        }
+    }
+
+    /// Wait for a connection on the listener and return its transport
+    ///
+    /// Cancel safe, like [`UnixListener::accept`].
+    pub async fn accept(listener: &UnixListener) -> McpResult<Self> {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to accept: {}", e)))?;
+
+        Ok(Self::from_stream(stream))
+    }
+
+    /// Delimit messages as given instead of one per line
+    pub fn with_framing(self, framing: Framing) -> Self {
+        Self(self.0.with_framing(framing), self.1)
+    }
+
+    /// Fail receives waiting longer than `timeout` for a message, see
+    /// [`JsonRpcTransport::with_read_timeout`]
+    pub fn with_read_timeout(self, timeout: Duration) -> Self {
+        Self(self.0.with_read_timeout(timeout), self.1)
     }
 
     /// Create a Unix domain socket listener that can accept JSON-RPC connections
+    ///
+    /// Like [`connect`](Self::connect), accepts abstract socket addresses on
+    /// Linux.
     pub async fn bind(path: impl AsRef<Path>) -> McpResult<UnixListener> {
-        UnixListener::bind(path)
+        bind_listener(path.as_ref())
             .map_err(|e| helpers::transport_error(&format!("Failed to bind: {}", e)))
     }
+
+    /// Create a Unix domain socket listener with file permissions and
+    /// stale-socket cleanup
+    pub async fn listen(config: &UnixListenConfig) -> McpResult<UnixListener> {
+        config.bind().await
+    }
+}
+
+/// Name of the abstract socket address given as a path starting with a NUL byte
+#[cfg(target_os = "linux")]
+pub(crate) fn abstract_name(path: &Path) -> Option<&[u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().strip_prefix(b"\0")
+}
+
+/// Name of the abstract socket address given as a path; there are none
+/// outside Linux
+#[cfg(all(unix, not(target_os = "linux")))]
+pub(crate) fn abstract_name(_path: &Path) -> Option<&[u8]> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn abstract_address(name: &[u8]) -> std::io::Result<std::os::unix::net::SocketAddr> {
+    use std::os::linux::net::SocketAddrExt;
+    std::os::unix::net::SocketAddr::from_abstract_name(name)
+}
+
+/// Connect to a socket file or abstract address
+#[cfg(unix)]
+async fn connect_stream(path: &Path) -> std::io::Result<UnixStream> {
+    #[cfg(target_os = "linux")]
+    if let Some(name) = abstract_name(path) {
+        // Connecting a local socket does not block
+        let stream = std::os::unix::net::UnixStream::connect_addr(&abstract_address(name)?)?;
+        stream.set_nonblocking(true)?;
+        return UnixStream::from_std(stream);
+    }
+    UnixStream::connect(path).await
+}
+
+/// Bind a socket file or abstract address
+#[cfg(unix)]
+pub(crate) fn bind_listener(path: &Path) -> std::io::Result<UnixListener> {
+    #[cfg(target_os = "linux")]
+    if let Some(name) = abstract_name(path) {
+        let listener = std::os::unix::net::UnixListener::bind_addr(&abstract_address(name)?)?;
+        listener.set_nonblocking(true)?;
+        return UnixListener::from_std(listener);
+    }
+    UnixListener::bind(path)
 }
 
 #[cfg(unix)]
@@ -46,6 +196,17 @@ impl Transport for UnixTransport {
     async fn send(&mut self, message: &str) -> McpResult<()> {
         self.0.send(message).await
     }
+
+    fn supports_partial_send(&self) -> bool {
+        self.0.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        self.0.send_part(part, end).await
+    }
+    async fn close(&mut self) -> McpResult<()> {
+        self.0.close().await
+    }
 }
 
 #[cfg(not(unix))]
@@ -69,4 +230,10 @@ impl UnixTransport {
             "Unix domain sockets are not supported on this platform",
         ))
     }
+
+    pub async fn accept<L>(_listener: &L) -> McpResult<Self> {
+        Err(helpers::transport_error(
+            "Unix domain sockets are not supported on this platform",
+        ))
+    }
 }
@@ -0,0 +1,130 @@
+//! Unix socket listening options
+//!
+//! [`UnixTransport::bind`](super::UnixTransport::bind) creates the socket
+//! file with the process umask and fails if the file already exists.
+//! [`UnixListenConfig`] also sets the file's mode and owner, and removes the
+//! socket left behind by a process that did not shut down cleanly:
+//!
+//! ```rust,ignore
+//! let listener = UnixListenConfig::new("/run/mcp/server.sock")
+//!     .with_mode(0o660)
+//!     .with_owner(None, Some(mcp_group_id))
+//!     .with_remove_stale(true)
+//!     .bind()
+//!     .await?;
+//! let transport = UnixTransport::accept(&listener).await?;
+//! ```
+//!
+//! Abstract socket addresses have no file, so the file options do not apply
+//! to them.
+//!
+//! The mode and owner are set right after binding, so a client may connect
+//! in between with the permissions given by the umask; create the socket in a
+//! directory only intended clients can reach when that matters.
+
+use crate::error::helpers;
+use crate::transport::unix::{abstract_name, bind_listener};
+use mcp_error::Result as McpResult;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use tokio::net::UnixListener;
+
+/// Path and file options of a Unix socket to listen on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnixListenConfig {
+    /// Path of the socket file
+    pub path: PathBuf,
+    /// Permission bits of the socket file, left to the umask if `None`
+    pub mode: Option<u32>,
+    /// User owning the socket file, unchanged if `None`
+    pub uid: Option<u32>,
+    /// Group owning the socket file, unchanged if `None`
+    pub gid: Option<u32>,
+    /// Whether an existing socket file nobody listens on is removed
+    pub remove_stale: bool,
+}
+
+impl UnixListenConfig {
+    /// Listen on the given path, with the default file options
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            mode: None,
+            uid: None,
+            gid: None,
+            remove_stale: false,
+        }
+    }
+
+    /// Set the permission bits of the socket file, e.g. `0o660`
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Set the user and group owning the socket file; changing the user
+    /// usually requires privileges
+    pub fn with_owner(mut self, uid: Option<u32>, gid: Option<u32>) -> Self {
+        self.uid = uid;
+        self.gid = gid;
+        self
+    }
+
+    /// Set whether an existing socket file nobody listens on is removed
+    ///
+    /// A file that is not a socket, or a socket another process still accepts
+    /// connections on, is never removed.
+    pub fn with_remove_stale(mut self, remove_stale: bool) -> Self {
+        self.remove_stale = remove_stale;
+        self
+    }
+
+    /// Bind the socket and apply the file options
+    ///
+    /// Must be called within a Tokio runtime.
+    pub async fn bind(&self) -> McpResult<UnixListener> {
+        let fail = |e: io::Error| {
+            helpers::transport_error(&format!("Failed to bind {}: {}", self.path.display(), e))
+        };
+
+        if abstract_name(&self.path).is_some() {
+            return bind_listener(&self.path).map_err(fail);
+        }
+        if self.remove_stale {
+            remove_stale(&self.path).map_err(fail)?;
+        }
+        let listener = bind_listener(&self.path).map_err(fail)?;
+        if let Some(mode) = self.mode {
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(mode))
+                .map_err(fail)?;
+        }
+        if self.uid.is_some() || self.gid.is_some() {
+            std::os::unix::fs::chown(&self.path, self.uid, self.gid).map_err(fail)?;
+        }
+        Ok(listener)
+    }
+}
+
+/// Remove the socket file at `path` if no process listens on it anymore
+fn remove_stale(path: &Path) -> io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "the path exists and is not a socket",
+        ));
+    }
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "another process is listening on the socket",
+        )),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => std::fs::remove_file(path),
+        Err(e) => Err(e),
+    }
+}
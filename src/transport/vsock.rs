@@ -0,0 +1,294 @@
+//! vsock transport
+//!
+//! Virtual machines and enclaves reach their host, and the host reaches them,
+//! over `AF_VSOCK` sockets without any network configured. Addresses are a
+//! context id (CID) naming the machine and a port:
+//!
+//! ```rust,ignore
+//! // In the guest
+//! let listener = VsockTransport::bind(VsockAddr::new(VMADDR_CID_ANY, 5000))?;
+//! server.runtime(listener).run().await?;
+//!
+//! // On the host, the guest being CID 3
+//! let transport = VsockTransport::connect(VsockAddr::new(3, 5000)).await?;
+//! ```
+//!
+//! Messages are newline-delimited JSON-RPC, as over TCP. Only Linux supports
+//! vsock.
+
+use crate::error::helpers;
+use crate::transport::base::{Framing, JsonRpcTransport, Transport};
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use socket2::{Domain, SockAddr, Socket, Type};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, ReadHalf, WriteHalf};
+
+/// CID to bind to accept connections addressed to any CID of this machine
+pub const VMADDR_CID_ANY: u32 = libc::VMADDR_CID_ANY;
+
+/// CID of the local machine, for connections that stay within it
+pub const VMADDR_CID_LOCAL: u32 = libc::VMADDR_CID_LOCAL;
+
+/// CID of the host, as seen from a virtual machine
+pub const VMADDR_CID_HOST: u32 = libc::VMADDR_CID_HOST;
+
+/// Port to bind to let the OS choose one
+pub const VMADDR_PORT_ANY: u32 = libc::VMADDR_PORT_ANY;
+
+/// Context id and port of a vsock socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VsockAddr {
+    /// Context id of the machine
+    pub cid: u32,
+    /// Port on that machine
+    pub port: u32,
+}
+
+impl VsockAddr {
+    /// Address of `port` on the machine with context id `cid`
+    pub fn new(cid: u32, port: u32) -> Self {
+        Self { cid, port }
+    }
+
+    fn from_sock_addr(address: &SockAddr) -> io::Result<Self> {
+        let (cid, port) = address
+            .as_vsock_address()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a vsock address"))?;
+        Ok(Self { cid, port })
+    }
+}
+
+impl fmt::Display for VsockAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "vsock:{}:{}", self.cid, self.port)
+    }
+}
+
+impl From<(u32, u32)> for VsockAddr {
+    fn from((cid, port): (u32, u32)) -> Self {
+        Self::new(cid, port)
+    }
+}
+
+fn vsock_socket() -> io::Result<Socket> {
+    let socket = Socket::new(Domain::VSOCK, Type::STREAM, None)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+/// Connected vsock socket
+pub struct VsockStream {
+    socket: AsyncFd<Socket>,
+}
+
+impl VsockStream {
+    /// Connect to the given address
+    pub async fn connect(address: VsockAddr) -> io::Result<Self> {
+        let socket = vsock_socket()?;
+        match socket.connect(&SockAddr::vsock(address.cid, address.port)) {
+            Ok(()) => {}
+            Err(e) if e.raw_os_error() == Some(libc::EINPROGRESS) => {}
+            Err(e) => return Err(e),
+        }
+        let socket = AsyncFd::new(socket)?;
+        // The socket becomes writable once the connection is made or failed
+        let _ = socket.writable().await?;
+        if let Some(e) = socket.get_ref().take_error()? {
+            return Err(e);
+        }
+        Ok(Self { socket })
+    }
+
+    /// Address of the other end
+    pub fn peer_addr(&self) -> io::Result<VsockAddr> {
+        VsockAddr::from_sock_addr(&self.socket.get_ref().peer_addr()?)
+    }
+
+    /// Address of this end
+    pub fn local_addr(&self) -> io::Result<VsockAddr> {
+        VsockAddr::from_sock_addr(&self.socket.get_ref().local_addr()?)
+    }
+}
+
+impl AsyncRead for VsockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.socket.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match guard.try_io(|socket| socket.get_ref().read(unfilled)) {
+                Ok(Ok(read)) => {
+                    buf.advance(read);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) => return Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for VsockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            let mut guard = ready!(self.socket.poll_write_ready(cx))?;
+            match guard.try_io(|socket| socket.get_ref().write(buf)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.socket.get_ref().shutdown(Shutdown::Write))
+    }
+}
+
+/// vsock socket accepting connections
+pub struct VsockListener {
+    socket: AsyncFd<Socket>,
+}
+
+impl VsockListener {
+    /// Listen on the given address
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn bind(address: VsockAddr) -> io::Result<Self> {
+        let socket = vsock_socket()?;
+        socket.bind(&SockAddr::vsock(address.cid, address.port))?;
+        socket.listen(1024)?;
+        Ok(Self {
+            socket: AsyncFd::new(socket)?,
+        })
+    }
+
+    /// Wait for a connection, returning its stream and the address of the
+    /// peer
+    ///
+    /// Cancel safe.
+    pub async fn accept(&self) -> io::Result<(VsockStream, VsockAddr)> {
+        loop {
+            let mut guard = self.socket.readable().await?;
+            match guard.try_io(|socket| socket.get_ref().accept()) {
+                Ok(Ok((socket, peer))) => {
+                    socket.set_nonblocking(true)?;
+                    let stream = VsockStream {
+                        socket: AsyncFd::new(socket)?,
+                    };
+                    return Ok((stream, VsockAddr::from_sock_addr(&peer)?));
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Address listened on, giving the port chosen by the OS when bound to
+    /// [`VMADDR_PORT_ANY`]
+    pub fn local_addr(&self) -> io::Result<VsockAddr> {
+        VsockAddr::from_sock_addr(&self.socket.get_ref().local_addr()?)
+    }
+}
+
+/// Newline-delimited JSON-RPC over a vsock connection
+pub struct VsockTransport {
+    transport: JsonRpcTransport<ReadHalf<VsockStream>, WriteHalf<VsockStream>>,
+    peer: Option<VsockAddr>,
+}
+
+impl VsockTransport {
+    /// Connect to the given address
+    pub async fn connect(address: impl Into<VsockAddr>) -> McpResult<Self> {
+        let stream = VsockStream::connect(address.into())
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to connect: {}", e)))?;
+
+        Ok(Self::from_stream(stream))
+    }
+
+    /// Create a transport over an already connected stream
+    pub fn from_stream(stream: VsockStream) -> Self {
+        let peer = stream.peer_addr().ok();
+        Self {
+            transport: JsonRpcTransport::new(stream),
+            peer,
+        }
+    }
+
+    /// Wait for a connection on the listener and return its transport
+    ///
+    /// Cancel safe, like [`VsockListener::accept`].
+    pub async fn accept(listener: &VsockListener) -> McpResult<Self> {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to accept: {}", e)))?;
+
+        Ok(Self::from_stream(stream))
+    }
+
+    /// Create a listener that can accept JSON-RPC connections
+    pub fn bind(address: impl Into<VsockAddr>) -> McpResult<VsockListener> {
+        VsockListener::bind(address.into())
+            .map_err(|e| helpers::transport_error(&format!("Failed to bind: {}", e)))
+    }
+
+    /// Delimit messages as given instead of one per line
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.transport = self.transport.with_framing(framing);
+        self
+    }
+
+    /// Fail receives waiting longer than `timeout` for a message, see
+    /// [`JsonRpcTransport::with_read_timeout`]
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.transport = self.transport.with_read_timeout(timeout);
+        self
+    }
+
+    /// Address of the other end, if the OS reported it
+    pub fn peer_addr(&self) -> Option<VsockAddr> {
+        self.peer
+    }
+}
+
+#[async_trait]
+impl Transport for VsockTransport {
+    async fn receive(&mut self) -> McpResult<String> {
+        self.transport.receive().await
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.transport.send(message).await
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.transport.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        self.transport.send_part(part, end).await
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        self.transport.close().await
+    }
+}
@@ -0,0 +1,121 @@
+//! WebSocket transport
+//!
+//! Each JSON-RPC message travels as one WebSocket text message, so there is
+//! no newline framing. [`WebSocketTransport::connect`] opens a client
+//! connection to a `ws://` URL; servers accept connections with
+//! [`WebSocketTransport::accept`], once their listener returned a stream:
+//!
+//! ```rust,ignore
+//! let listener = TcpTransport::bind(([0, 0, 0, 0], 8080)).await?;
+//! loop {
+//!     let (stream, _) = listener.accept().await?;
+//!     let registry = registry.clone();
+//!     tokio::spawn(async move {
+//!         let transport = WebSocketTransport::accept(stream).await?;
+//!         JsonRpcProcessor::new(transport, registry).run().await
+//!     });
+//! }
+//! ```
+//!
+//! Binary messages holding UTF-8 text are accepted as well. Pings are
+//! answered by the WebSocket layer and never reach the processor; a close
+//! message from the peer is reported like a closed connection. `wss://` URLs
+//! need tokio-tungstenite built with one of its TLS features.
+
+use crate::error::helpers;
+use crate::transport::base::Transport;
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use mcp_error::Result as McpResult;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// JSON-RPC over a WebSocket connection, one message per text frame
+pub struct WebSocketTransport<S = MaybeTlsStream<TcpStream>> {
+    stream: WebSocketStream<S>,
+}
+
+impl WebSocketTransport {
+    /// Open a client connection to the given `ws://` or `wss://` URL
+    pub async fn connect(url: &str) -> McpResult<Self> {
+        let (stream, _) = tokio_tungstenite::connect_async(url)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to connect: {}", e)))?;
+        Ok(Self { stream })
+    }
+}
+
+impl<S> WebSocketTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    /// Complete the server side of the handshake on an accepted stream
+    pub async fn accept(stream: S) -> McpResult<Self> {
+        let stream = tokio_tungstenite::accept_async(stream)
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to accept: {}", e)))?;
+        Ok(Self { stream })
+    }
+
+    /// Use a WebSocket connection established elsewhere, e.g. after an HTTP
+    /// upgrade handled by a web framework
+    pub fn from_stream(stream: WebSocketStream<S>) -> Self {
+        Self { stream }
+    }
+
+    /// Access the WebSocket connection
+    pub fn inner_mut(&mut self) -> &mut WebSocketStream<S> {
+        &mut self.stream
+    }
+
+    /// Unwrap the WebSocket connection
+    pub fn into_inner(self) -> WebSocketStream<S> {
+        self.stream
+    }
+}
+
+#[async_trait]
+impl<S> Transport for WebSocketTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn receive(&mut self) -> McpResult<String> {
+        // `next` is cancel safe: a message is either returned or left unread
+        loop {
+            let message = match self.stream.next().await {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => {
+                    return Err(helpers::transport_error(&format!("Failed to read: {}", e)))
+                }
+                None => return Err(helpers::connection_closed()),
+            };
+            match message {
+                Message::Text(text) => return Ok(text),
+                Message::Binary(bytes) => {
+                    return String::from_utf8(bytes)
+                        .map_err(|_| helpers::protocol_error("Invalid UTF-8 in message"))
+                }
+                Message::Close(_) => return Err(helpers::connection_closed()),
+                // Control frames are handled by the WebSocket layer
+                Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+            }
+        }
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        self.stream
+            .send(Message::Text(message.to_string()))
+            .await
+            .map_err(|e| helpers::transport_error(&format!("Failed to send: {}", e)))
+    }
+
+    async fn close(&mut self) -> McpResult<()> {
+        match self.stream.close(None).await {
+            // The peer closed first
+            Ok(()) | Err(WsError::ConnectionClosed) | Err(WsError::AlreadyClosed) => Ok(()),
+            Err(e) => Err(helpers::transport_error(&format!("Failed to close: {}", e))),
+        }
+    }
+}
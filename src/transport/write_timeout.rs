@@ -0,0 +1,243 @@
+use crate::error::helpers;
+use crate::transport::base::Transport;
+use crate::transport::events::{TransportEvent, TransportEventHandler};
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::timeout;
+
+/// Default number of dead letters kept
+pub const DEFAULT_DEAD_LETTER_CAPACITY: usize = 64;
+
+/// A message that could not be delivered
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLetter {
+    /// Milliseconds since the Unix epoch when the message was given up
+    pub timestamp_ms: u64,
+    /// Why the message was not delivered
+    pub reason: String,
+    /// The undelivered message (or message part)
+    pub message: String,
+}
+
+struct DeadLetterInner {
+    entries: VecDeque<DeadLetter>,
+    capacity: usize,
+}
+
+/// Shared, bounded queue of undelivered messages
+///
+/// Clones share the same queue; when full, the oldest entries are dropped.
+#[derive(Clone)]
+pub struct DeadLetterQueue {
+    inner: Arc<Mutex<DeadLetterInner>>,
+}
+
+impl DeadLetterQueue {
+    /// Create a queue keeping at most `capacity` entries
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(DeadLetterInner {
+                entries: VecDeque::new(),
+                capacity,
+            })),
+        }
+    }
+
+    /// Add an entry
+    pub fn push(&self, reason: &str, message: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.capacity == 0 {
+            return;
+        }
+        if inner.entries.len() == inner.capacity {
+            inner.entries.pop_front();
+        }
+        inner.entries.push_back(DeadLetter {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            reason: reason.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Whether the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove and return all entries, oldest first
+    pub fn drain(&self) -> Vec<DeadLetter> {
+        self.inner.lock().unwrap().entries.drain(..).collect()
+    }
+}
+
+impl Default for DeadLetterQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEAD_LETTER_CAPACITY)
+    }
+}
+
+/// Transport wrapper bounding how long a send may block
+///
+/// When a write stalls beyond the timeout (typically because the peer stopped
+/// reading), the writer is considered stuck: a [`TransportEvent::WriteStalled`]
+/// event is emitted and this and every later outgoing message go to the
+/// dead-letter queue instead of blocking the processor. A timed-out write may
+/// have left a partial message on the wire, so the writer never recovers.
+///
+/// By default sends then fail, which ends the processor's run and drops the
+/// connection. With [`keep_connection`](Self::keep_connection), sends succeed
+/// without writing so requests can still be read (e.g. for their side effects).
+pub struct WriteTimeoutTransport<T> {
+    inner: T,
+    timeout: Duration,
+    drop_on_stall: bool,
+    stalled: bool,
+    dead_letters: DeadLetterQueue,
+    events: Option<TransportEventHandler>,
+}
+
+impl<T: Transport> WriteTimeoutTransport<T> {
+    /// Wrap a transport, giving up on writes taking longer than `timeout`
+    pub fn new(inner: T, timeout: Duration) -> Self {
+        Self {
+            inner,
+            timeout,
+            drop_on_stall: true,
+            stalled: false,
+            dead_letters: DeadLetterQueue::default(),
+            events: None,
+        }
+    }
+
+    /// Keep the connection open once the writer is stuck, dropping outgoing messages
+    pub fn keep_connection(mut self) -> Self {
+        self.drop_on_stall = false;
+        self
+    }
+
+    /// Store undelivered messages in the given queue
+    pub fn with_dead_letters(mut self, dead_letters: DeadLetterQueue) -> Self {
+        self.dead_letters = dead_letters;
+        self
+    }
+
+    /// Call the given function for each transport event
+    pub fn with_event_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&TransportEvent) + Send + Sync + 'static,
+    {
+        self.events = Some(Arc::new(handler));
+        self
+    }
+
+    /// Queue of undelivered messages
+    pub fn dead_letters(&self) -> &DeadLetterQueue {
+        &self.dead_letters
+    }
+
+    /// Whether a write has stalled on this connection
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// Unwrap the transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn emit(&self, event: TransportEvent) {
+        if let Some(handler) = &self.events {
+            handler(&event);
+        }
+    }
+
+    /// Dead-letter a message the stuck writer cannot deliver
+    fn dead_letter(&self, message: &str) -> McpResult<()> {
+        self.dead_letters.push("write stalled", message);
+        self.emit(TransportEvent::DeadLettered {
+            bytes: message.len(),
+        });
+
+        if self.drop_on_stall {
+            Err(helpers::transport_error(&format!(
+                "Write stalled for more than {:?}",
+                self.timeout
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn mark_stalled(&mut self, bytes: usize) {
+        self.stalled = true;
+        self.emit(TransportEvent::WriteStalled {
+            timeout: self.timeout,
+            bytes,
+        });
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for WriteTimeoutTransport<T> {
+    async fn receive(&mut self) -> McpResult<String> {
+        self.inner.receive().await
+    }
+
+    async fn send(&mut self, message: &str) -> McpResult<()> {
+        if self.stalled {
+            return self.dead_letter(message);
+        }
+
+        match timeout(self.timeout, self.inner.send(message)).await {
+            Ok(result) => result,
+            Err(_) => {
+                self.mark_stalled(message.len());
+                self.dead_letter(message)
+            }
+        }
+    }
+
+    fn supports_partial_send(&self) -> bool {
+        self.inner.supports_partial_send()
+    }
+
+    async fn send_part(&mut self, part: &str, end: bool) -> McpResult<()> {
+        if self.stalled {
+            return self.dead_letter(part);
+        }
+
+        match timeout(self.timeout, self.inner.send_part(part, end)).await {
+            Ok(result) => result,
+            Err(_) => {
+                self.mark_stalled(part.len());
+                self.dead_letter(part)
+            }
+        }
+    }
+    async fn close(&mut self) -> McpResult<()> {
+        // A stalled writer would only hold the close up for another timeout
+        if self.stalled {
+            return Ok(());
+        }
+
+        match timeout(self.timeout, self.inner.close()).await {
+            Ok(result) => result,
+            Err(_) => Err(helpers::transport_error(&format!(
+                "Failed to close: flush did not complete within {:?}",
+                self.timeout
+            ))),
+        }
+    }
+}
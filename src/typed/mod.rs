@@ -0,0 +1,534 @@
+//! Typed tool registration
+//!
+//! Wraps an async handler taking a deserializable params struct into a [`Tool`],
+//! so handlers no longer parse `serde_json::Value` by hand. With the `schemars`
+//! feature the params type also provides the JSON Schema advertised in `tools/list`,
+//! keeping the schema and the deserialization in lockstep. With the `validator`
+//! feature, declarative field constraints are checked after deserialization and
+//! violations are reported as structured `-32602` errors.
+//!
+//! Params may be named (a JSON object) or, for handlers registered with
+//! [`TypedTool::positional`], positional (a JSON array bound to a tuple). A named
+//! handler can accept both forms by declaring its parameter order with
+//! [`TypedTool::with_param_names`].
+//!
+//! Credentials should be declared as [`Secret`] fields, which are never logged,
+//! serialized or echoed in error data.
+
+use crate::error::helpers;
+use crate::processor::Tool;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::future::Future;
+use std::marker::PhantomData;
+
+pub mod positional;
+pub mod secret;
+
+pub use positional::PositionalArgs;
+#[cfg(feature = "schemars")]
+pub use positional::PositionalSchema;
+pub use secret::Secret;
+
+/// Tool backed by a handler taking typed params
+pub struct TypedTool<P, F> {
+    handler: F,
+    description: Option<String>,
+    input_schema: Option<Value>,
+    binder: fn(Value) -> McpResult<P>,
+    param_names: Option<&'static [&'static str]>,
+    validator: Option<fn(&P) -> McpResult<()>>,
+    dry_run: bool,
+    _params: PhantomData<fn(P)>,
+}
+
+impl<P, F, Fut> TypedTool<P, F>
+where
+    P: DeserializeOwned + Send + 'static,
+    F: Fn(P) -> Fut + Send + Sync,
+    Fut: Future<Output = McpResult<Value>> + Send,
+{
+    /// Create a typed tool from the given handler
+    ///
+    /// Params are deserialized with serde, so a struct accepts both the named
+    /// (object) form and serde's positional (array) form.
+    pub fn new(handler: F) -> Self {
+        Self::with_binder(handler, bind_named::<P>)
+    }
+}
+
+impl<P, F, Fut> TypedTool<P, F>
+where
+    P: PositionalArgs + Send + 'static,
+    F: Fn(P) -> Fut + Send + Sync,
+    Fut: Future<Output = McpResult<Value>> + Send,
+{
+    /// Create a typed tool taking positional params bound to a tuple
+    ///
+    /// Mismatches are reported per argument index, e.g.
+    /// `Invalid params: argument 1: invalid type: string "x", expected i64`.
+    pub fn positional(handler: F) -> Self {
+        Self::with_binder(handler, P::from_positional)
+    }
+}
+
+impl<P, F, Fut> TypedTool<P, F>
+where
+    P: Send + 'static,
+    F: Fn(P) -> Fut + Send + Sync,
+    Fut: Future<Output = McpResult<Value>> + Send,
+{
+    /// Create a typed tool using a custom function to bind raw params
+    pub fn with_binder(handler: F, binder: fn(Value) -> McpResult<P>) -> Self {
+        Self {
+            handler,
+            description: None,
+            input_schema: None,
+            binder,
+            param_names: None,
+            validator: None,
+            dry_run: false,
+            _params: PhantomData,
+        }
+    }
+
+    /// Set the description advertised for this tool
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Declare the handler free of side effects, so it can be called in dry runs
+    pub fn with_dry_run_support(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Set the JSON Schema advertised for this tool's params
+    pub fn with_input_schema(mut self, schema: Value) -> Self {
+        self.input_schema = Some(schema);
+        self
+    }
+
+    /// Check deserialized params with the given function before calling the handler
+    ///
+    /// Errors returned by the validator are passed to the client as-is, so they
+    /// should normally be built with [`helpers::invalid_params`].
+    pub fn with_validator(mut self, validator: fn(&P) -> McpResult<()>) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Accept positional params as well, mapping each position to the given name
+    ///
+    /// With `&["a", "b"]`, the params `[1, 2]` are bound exactly as `{"a": 1, "b": 2}`,
+    /// so the same handler serves clients using either convention. Trailing
+    /// positions may be omitted when the corresponding fields are optional.
+    ///
+    /// The advertised input schema then accepts both forms: the object schema
+    /// and an array whose items are the schemas of the named properties, under
+    /// `anyOf`.
+    pub fn with_param_names(mut self, names: &'static [&'static str]) -> Self {
+        self.param_names = Some(names);
+        self
+    }
+
+    /// Bind raw params to the handler's params type and run the validator
+    fn bind(&self, params: Value) -> McpResult<P> {
+        let params = match (self.param_names, params) {
+            (Some(names), Value::Array(items)) => positional_to_named(names, items)?,
+            (_, params) => params,
+        };
+        let params = (self.binder)(params)?;
+
+        if let Some(validator) = self.validator {
+            validator(&params)?;
+        }
+
+        Ok(params)
+    }
+}
+
+/// Convert positional params to an object keyed by the declared parameter names
+fn positional_to_named(names: &[&str], items: Vec<Value>) -> McpResult<Value> {
+    if items.len() > names.len() {
+        return Err(helpers::invalid_params(&format!(
+            "Invalid params: expected at most {} argument(s), got {}",
+            names.len(),
+            items.len()
+        )));
+    }
+
+    let object = names
+        .iter()
+        .zip(items)
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+    Ok(Value::Object(object))
+}
+
+/// Keywords describing a whole schema document rather than the object form
+const SCHEMA_ROOT_KEYWORDS: &[&str] = &["$schema", "$id", "title", "description", "$defs"];
+
+/// Extend an object schema to also accept its properties by position
+///
+/// Root keywords (`$schema`, `title`, `$defs`) stay at the root, so `$ref`s into
+/// `$defs` keep resolving from either alternative.
+fn named_or_positional_schema(mut object: Value, names: &[&str]) -> Value {
+    let mut root = serde_json::Map::new();
+    if let Value::Object(map) = &mut object {
+        for keyword in SCHEMA_ROOT_KEYWORDS {
+            if let Some(value) = map.remove(*keyword) {
+                root.insert(keyword.to_string(), value);
+            }
+        }
+    }
+
+    let items: Vec<Value> = names
+        .iter()
+        .map(|name| {
+            object
+                .get("properties")
+                .and_then(|properties| properties.get(*name))
+                .cloned()
+                .unwrap_or_else(|| serde_json::json!({}))
+        })
+        .collect();
+    // Positions up to the last required name must be given
+    let min_items = object
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|required| {
+            names
+                .iter()
+                .position(|name| Some(*name) == required.as_str())
+        })
+        .map(|index| index + 1)
+        .max()
+        .unwrap_or(0);
+    let array = serde_json::json!({
+        "type": "array",
+        "prefixItems": items,
+        "minItems": min_items,
+        "maxItems": names.len(),
+    });
+
+    root.insert("anyOf".to_string(), serde_json::json!([object, array]));
+    Value::Object(root)
+}
+
+/// Deserialize params with serde, mapping failures to an invalid params error
+fn bind_named<P: DeserializeOwned>(params: Value) -> McpResult<P> {
+    serde_json::from_value(params)
+        .map_err(|e| helpers::invalid_params(&format!("Invalid params: {}", e)))
+}
+
+#[cfg(feature = "validator")]
+impl<P, F, Fut> TypedTool<P, F>
+where
+    P: validator::Validate + Send + 'static,
+    F: Fn(P) -> Fut + Send + Sync,
+    Fut: Future<Output = McpResult<Value>> + Send,
+{
+    /// Run the `validator` constraints declared on `P` after deserialization
+    pub fn with_validation(self) -> Self {
+        self.with_validator(validate_params::<P>)
+    }
+}
+
+/// Validate params with `validator`, mapping violations to a structured invalid params error
+///
+/// The error details hold one entry per violated constraint:
+/// `{"violations": [{"field": "name", "code": "length", "message": ..., "params": {...}}]}`.
+/// Violations in nested structs and lists are reported with their path, such
+/// as `address.city` or `items[2].name`.
+#[cfg(feature = "validator")]
+pub fn validate_params<P: validator::Validate>(params: &P) -> McpResult<()> {
+    let errors = match params.validate() {
+        Ok(()) => return Ok(()),
+        Err(errors) => errors,
+    };
+
+    let mut violations = Vec::new();
+    collect_violations(&errors, "", &mut violations);
+
+    Err(helpers::with_details(
+        helpers::invalid_params(&format!(
+            "Invalid params: {} constraint violation(s)",
+            violations.len()
+        )),
+        serde_json::json!({ "violations": violations }),
+    ))
+}
+
+/// Flatten the violations of `errors`, whose fields are below `path`
+#[cfg(feature = "validator")]
+fn collect_violations(
+    errors: &validator::ValidationErrors,
+    path: &str,
+    violations: &mut Vec<Value>,
+) {
+    use validator::ValidationErrorsKind;
+
+    let mut fields: Vec<_> = errors.errors().iter().collect();
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    for (field, kind) in fields {
+        let path = if path.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", path, field)
+        };
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                for error in field_errors {
+                    violations.push(serde_json::json!({
+                        "field": path,
+                        "code": error.code,
+                        "message": error.message,
+                        "params": error.params,
+                    }));
+                }
+            }
+            ValidationErrorsKind::Struct(nested) => collect_violations(nested, &path, violations),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_violations(nested, &format!("{}[{}]", path, index), violations);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl<P, F, Fut> TypedTool<P, F>
+where
+    P: DeserializeOwned + schemars::JsonSchema + Send + 'static,
+    F: Fn(P) -> Fut + Send + Sync,
+    Fut: Future<Output = McpResult<Value>> + Send,
+{
+    /// Create a typed tool whose input schema is derived from `P`
+    pub fn with_derived_schema(handler: F) -> Self {
+        Self::new(handler).with_input_schema(derive_schema::<P>())
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl<P, F, Fut> TypedTool<P, F>
+where
+    P: PositionalSchema + Send + 'static,
+    F: Fn(P) -> Fut + Send + Sync,
+    Fut: Future<Output = McpResult<Value>> + Send,
+{
+    /// Create a positional typed tool advertising an array schema derived from `P`
+    pub fn positional_with_derived_schema(handler: F) -> Self {
+        Self::positional(handler).with_input_schema(P::schema())
+    }
+}
+
+/// Derive the JSON Schema for a params type
+#[cfg(feature = "schemars")]
+pub fn derive_schema<P: schemars::JsonSchema>() -> Value {
+    schemars::schema_for!(P).to_value()
+}
+
+#[async_trait]
+impl<P, F, Fut> Tool for TypedTool<P, F>
+where
+    P: Send + 'static,
+    F: Fn(P) -> Fut + Send + Sync,
+    Fut: Future<Output = McpResult<Value>> + Send,
+{
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let params = self.bind(params)?;
+        (self.handler)(params).await
+    }
+
+    fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    fn input_schema(&self) -> Option<Value> {
+        let schema = self.input_schema.clone()?;
+        Some(match self.param_names {
+            Some(names) => named_or_positional_schema(schema, names),
+            None => schema,
+        })
+    }
+
+    fn supports_dry_run(&self) -> bool {
+        self.dry_run
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Deserialize)]
+    struct Span {
+        start: i64,
+        #[serde(default)]
+        end: Option<i64>,
+    }
+
+    fn span_tool() -> TypedTool<Span, impl Fn(Span) -> std::future::Ready<McpResult<Value>>> {
+        TypedTool::new(|span: Span| std::future::ready(Ok(json!([span.start, span.end]))))
+            .with_param_names(&["start", "end"])
+    }
+
+    #[tokio::test]
+    async fn binds_named_and_positional_params() {
+        let tool = span_tool();
+        assert_eq!(
+            tool.execute(json!({"start": 1, "end": 2})).await.unwrap(),
+            json!([1, 2])
+        );
+        assert_eq!(tool.execute(json!([1, 2])).await.unwrap(), json!([1, 2]));
+        assert_eq!(tool.execute(json!([1])).await.unwrap(), json!([1, null]));
+        assert!(tool.execute(json!([1, 2, 3])).await.is_err());
+    }
+
+    #[test]
+    fn schema_describes_both_forms() {
+        let tool = span_tool().with_input_schema(json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Span",
+            "type": "object",
+            "properties": {
+                "start": {"type": "integer"},
+                "end": {"type": ["integer", "null"]},
+            },
+            "required": ["start"],
+        }));
+
+        let schema = tool.input_schema().unwrap();
+        assert_eq!(schema["title"], "Span");
+        let forms = schema["anyOf"].as_array().unwrap();
+        assert_eq!(forms[0]["type"], "object");
+        assert!(forms[0].get("title").is_none());
+        assert_eq!(
+            forms[1],
+            json!({
+                "type": "array",
+                "prefixItems": [{"type": "integer"}, {"type": ["integer", "null"]}],
+                "minItems": 1,
+                "maxItems": 2,
+            })
+        );
+    }
+
+    #[test]
+    fn schema_without_param_names_is_unchanged() {
+        let schema = json!({"type": "object"});
+        let tool = TypedTool::new(|span: Span| std::future::ready(Ok(json!(span.start))))
+            .with_input_schema(schema.clone());
+        assert_eq!(tool.input_schema(), Some(schema));
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn derived_schema_keeps_refs_resolvable() {
+        #[derive(Deserialize, schemars::JsonSchema)]
+        struct Point {
+            x: i64,
+        }
+
+        #[derive(Deserialize, schemars::JsonSchema)]
+        struct Line {
+            from: Point,
+            to: Point,
+        }
+
+        let tool = TypedTool::with_derived_schema(|line: Line| {
+            std::future::ready(Ok(json!(line.to.x - line.from.x)))
+        })
+        .with_param_names(&["from", "to"]);
+
+        let schema = tool.input_schema().unwrap();
+        assert!(schema["$defs"]["Point"].is_object());
+        let positional = &schema["anyOf"][1];
+        assert_eq!(positional["prefixItems"][0]["$ref"], "#/$defs/Point");
+        assert_eq!(positional["minItems"], 2);
+    }
+
+    #[cfg(feature = "validator")]
+    mod validation {
+        use super::*;
+        use crate::error::error_details;
+        use serde::Deserialize;
+        use validator::Validate;
+
+        #[derive(Deserialize, Validate)]
+        struct Address {
+            #[validate(length(min = 1))]
+            city: String,
+        }
+
+        #[derive(Deserialize, Validate)]
+        struct Item {
+            #[validate(range(min = 1))]
+            quantity: u32,
+        }
+
+        #[derive(Deserialize, Validate)]
+        struct Order {
+            #[validate(length(min = 1))]
+            name: String,
+            #[validate(nested)]
+            address: Address,
+            #[validate(nested)]
+            items: Vec<Item>,
+        }
+
+        fn violations(order: Value) -> Vec<String> {
+            let order: Order = serde_json::from_value(order).unwrap();
+            let error = validate_params(&order).unwrap_err();
+            let details = error_details(&error).unwrap();
+            let violations = details["violations"].as_array().unwrap();
+            assert!(error
+                .to_string()
+                .contains(&format!("{} constraint violation(s)", violations.len())));
+            violations
+                .iter()
+                .map(|v| v["field"].as_str().unwrap().to_string())
+                .collect()
+        }
+
+        #[test]
+        fn reports_nested_paths() {
+            let fields = violations(serde_json::json!({
+                "name": "",
+                "address": {"city": ""},
+                "items": [{"quantity": 1}, {"quantity": 0}],
+            }));
+            assert_eq!(fields, vec!["address.city", "items[1].quantity", "name"]);
+        }
+
+        #[test]
+        fn reports_only_nested_violations() {
+            let fields = violations(serde_json::json!({
+                "name": "order",
+                "address": {"city": ""},
+                "items": [],
+            }));
+            assert_eq!(fields, vec!["address.city"]);
+        }
+
+        #[test]
+        fn accepts_valid_params() {
+            let order: Order = serde_json::from_value(serde_json::json!({
+                "name": "order",
+                "address": {"city": "Paris"},
+                "items": [{"quantity": 2}],
+            }))
+            .unwrap();
+            assert!(validate_params(&order).is_ok());
+        }
+    }
+}
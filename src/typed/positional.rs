@@ -0,0 +1,186 @@
+//! Positional (array) params binding
+//!
+//! JSON-RPC allows params to be passed by position. [`PositionalArgs`] binds a
+//! JSON array to a tuple, deserializing each element on its own so that errors
+//! point at the offending argument index.
+
+use crate::error::helpers;
+use mcp_error::Result as McpResult;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Types that can be bound from positional params
+pub trait PositionalArgs: Sized {
+    /// Number of positional arguments expected
+    const ARITY: usize;
+
+    /// Bind a JSON array (or `null` when there are no arguments) to `Self`
+    fn from_positional(params: Value) -> McpResult<Self>;
+}
+
+/// Positional argument lists whose array schema can be derived
+#[cfg(feature = "schemars")]
+pub trait PositionalSchema: PositionalArgs {
+    /// JSON Schema describing the expected array
+    fn schema() -> Value;
+}
+
+/// Check that params are an array of the expected length and return its items
+pub fn expect_array(params: Value, arity: usize) -> McpResult<Vec<Value>> {
+    let items = match params {
+        Value::Array(items) => items,
+        Value::Null if arity == 0 => Vec::new(),
+        Value::Object(_) => {
+            return Err(helpers::invalid_params(
+                "Invalid params: expected positional params (array)",
+            ))
+        }
+        other => {
+            return Err(helpers::invalid_params(&format!(
+                "Invalid params: expected an array of {} argument(s), got {}",
+                arity, other
+            )))
+        }
+    };
+
+    if items.len() != arity {
+        return Err(helpers::invalid_params(&format!(
+            "Invalid params: expected {} argument(s), got {}",
+            arity,
+            items.len()
+        )));
+    }
+
+    Ok(items)
+}
+
+/// Deserialize a single positional argument, reporting its index on failure
+pub fn bind_argument<T: DeserializeOwned>(index: usize, value: Value) -> McpResult<T> {
+    serde_json::from_value(value).map_err(|e| {
+        helpers::invalid_params(&format!("Invalid params: argument {}: {}", index, e))
+    })
+}
+
+/// Build the array schema for the given per-argument schemas
+#[cfg(feature = "schemars")]
+pub fn array_schema(items: Vec<Value>) -> Value {
+    let len = items.len();
+    serde_json::json!({
+        "type": "array",
+        "prefixItems": items,
+        "minItems": len,
+        "maxItems": len,
+    })
+}
+
+/// Inline JSON Schema for a single argument type
+#[cfg(feature = "schemars")]
+pub fn argument_schema<T: schemars::JsonSchema>() -> Value {
+    schemars::generate::SchemaSettings::draft2020_12()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator()
+        .subschema_for::<T>()
+        .to_value()
+}
+
+impl PositionalArgs for () {
+    const ARITY: usize = 0;
+
+    fn from_positional(params: Value) -> McpResult<Self> {
+        expect_array(params, 0).map(|_| ())
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl PositionalSchema for () {
+    fn schema() -> Value {
+        array_schema(Vec::new())
+    }
+}
+
+macro_rules! impl_positional_args {
+    ($arity:expr; $($ty:ident $idx:tt),+) => {
+        impl<$($ty: DeserializeOwned),+> PositionalArgs for ($($ty,)+) {
+            const ARITY: usize = $arity;
+
+            fn from_positional(params: Value) -> McpResult<Self> {
+                let mut items = expect_array(params, $arity)?.into_iter();
+                Ok(($(bind_argument::<$ty>($idx, items.next().unwrap_or(Value::Null))?,)+))
+            }
+        }
+
+        #[cfg(feature = "schemars")]
+        impl<$($ty: DeserializeOwned + schemars::JsonSchema),+> PositionalSchema for ($($ty,)+) {
+            fn schema() -> Value {
+                array_schema(vec![$(argument_schema::<$ty>()),+])
+            }
+        }
+    };
+}
+
+impl_positional_args!(1; A 0);
+impl_positional_args!(2; A 0, B 1);
+impl_positional_args!(3; A 0, B 1, C 2);
+impl_positional_args!(4; A 0, B 1, C 2, D 3);
+impl_positional_args!(5; A 0, B 1, C 2, D 3, E 4);
+impl_positional_args!(6; A 0, B 1, C 2, D 3, E 4, G 5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{error_codes, error_to_json_rpc};
+    use serde_json::json;
+
+    fn invalid_params_message<T: std::fmt::Debug>(result: McpResult<T>) -> String {
+        let error = result.unwrap_err();
+        assert_eq!(error_to_json_rpc(&error).0, error_codes::INVALID_PARAMS);
+        error.to_string()
+    }
+
+    #[test]
+    fn binds_an_array_to_a_tuple() {
+        let (name, count, flag) =
+            <(String, u32, Option<bool>)>::from_positional(json!(["a", 2, null])).unwrap();
+        assert_eq!((name.as_str(), count, flag), ("a", 2, None));
+        assert_eq!(<(String, u32, Option<bool>)>::ARITY, 3);
+    }
+
+    #[test]
+    fn binds_no_arguments_from_null_or_empty_array() {
+        assert!(<()>::from_positional(Value::Null).is_ok());
+        assert!(<()>::from_positional(json!([])).is_ok());
+        assert!(<(u32,)>::from_positional(Value::Null).is_err());
+    }
+
+    #[test]
+    fn rejects_named_params() {
+        let message = invalid_params_message(<(u32,)>::from_positional(json!({"a": 1})));
+        assert!(message.contains("expected positional params"));
+    }
+
+    #[test]
+    fn rejects_the_wrong_number_of_arguments() {
+        let message = invalid_params_message(<(u32, u32)>::from_positional(json!([1])));
+        assert!(message.contains("expected 2 argument(s), got 1"));
+        let message = invalid_params_message(<(u32, u32)>::from_positional(json!([1, 2, 3])));
+        assert!(message.contains("expected 2 argument(s), got 3"));
+    }
+
+    #[test]
+    fn reports_the_index_of_a_bad_argument() {
+        let message = invalid_params_message(<(u32, u32)>::from_positional(json!([1, "two"])));
+        assert!(message.contains("argument 1"));
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn schema_describes_each_position() {
+        let schema = <(String, u32)>::schema();
+        assert_eq!(schema["type"], "array");
+        assert_eq!(schema["minItems"], 2);
+        assert_eq!(schema["maxItems"], 2);
+        assert_eq!(schema["prefixItems"][0]["type"], "string");
+        assert_eq!(schema["prefixItems"][1]["type"], "integer");
+        assert_eq!(<()>::schema()["maxItems"], 0);
+    }
+}
@@ -0,0 +1,117 @@
+//! Secret params
+//!
+//! Wrapping a credential-bearing param in [`Secret`] keeps it out of every
+//! output the crate produces: it formats and serializes as `[REDACTED]`, a
+//! value that fails to deserialize is reported without being echoed, and with
+//! the `schemars` feature its schema is marked `writeOnly`, which processors
+//! use to redact the field from their message history.
+
+use crate::history::REDACTED;
+use serde::de::{DeserializeOwned, Deserializer, Error as _};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Param value that is never logged, serialized or echoed in error data
+///
+/// The value is only reachable through [`expose`](Self::expose) and
+/// [`into_inner`](Self::into_inner), so reading it is always explicit.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    /// Wrap a value
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Borrow the value
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// Unwrap the value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Deserialization errors usually quote the offending value
+        let value = Value::deserialize(deserializer)?;
+        T::deserialize(value)
+            .map(Secret)
+            .map_err(|_| D::Error::custom("invalid secret value"))
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl<T: schemars::JsonSchema> schemars::JsonSchema for Secret<T> {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        format!("Secret_{}", T::schema_name()).into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let mut schema = T::json_schema(generator);
+        schema.insert("writeOnly".to_string(), Value::Bool(true));
+        schema
+    }
+}
+
+/// Names of the properties marked `writeOnly` anywhere in a JSON Schema
+pub fn secret_fields(schema: &Value) -> BTreeSet<String> {
+    let mut fields = BTreeSet::new();
+    collect_secret_fields(schema, &mut fields);
+    fields
+}
+
+fn collect_secret_fields(schema: &Value, fields: &mut BTreeSet<String>) {
+    match schema {
+        Value::Object(object) => {
+            if let Some(Value::Object(properties)) = object.get("properties") {
+                for (name, property) in properties {
+                    if property.get("writeOnly") == Some(&Value::Bool(true)) {
+                        fields.insert(name.clone());
+                    }
+                }
+            }
+            object
+                .values()
+                .for_each(|child| collect_secret_fields(child, fields));
+        }
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| collect_secret_fields(item, fields)),
+        _ => {}
+    }
+}
@@ -0,0 +1,639 @@
+//! Chunked uploads
+//!
+//! A request carries its whole body in one message, which the transports
+//! bound and which stalls every other call on the connection while it is
+//! written. This module defines an upload extension sending large bodies in
+//! pieces instead:
+//!
+//! 1. the client opens an upload with `uploads/open`, describing the content,
+//!    and gets back a token and the largest chunk the server accepts;
+//! 2. it streams the content as `notifications/uploads/chunk` notifications,
+//!    each holding the token, a sequence number from 0 and base64 data;
+//! 3. it finishes with `uploads/finish`, giving the number of chunks sent. The
+//!    server hands the reassembled content to its [`UploadHandler`], whose
+//!    result answers the request.
+//!
+//! ```text
+//! {"jsonrpc":"2.0","method":"uploads/open","params":{"name":"report.pdf","contentType":"application/pdf","size":5242880},"id":1}
+//! {"jsonrpc":"2.0","result":{"token":"up-...","maxChunkBytes":262144},"id":1}
+//! {"jsonrpc":"2.0","method":"notifications/uploads/chunk","params":{"token":"up-...","seq":0,"data":"JVBERi0x..."}}
+//! {"jsonrpc":"2.0","method":"uploads/finish","params":{"token":"up-...","chunks":20},"id":2}
+//! ```
+//!
+//! Notifications cannot be answered, so a chunk the server rejects (over the
+//! limits, bad base64, unknown token) fails the upload, and the failure is
+//! reported by `uploads/finish`. Chunks may arrive out of order, e.g. with a
+//! pipelined processor; the finish request waits for missing chunks up to the
+//! idle timeout. `notifications/uploads/cancel` discards an upload, and
+//! uploads left idle are dropped when the next one is opened.
+//!
+//! [`Uploads`] is given to a processor with
+//! [`with_uploads`](crate::processor::JsonRpcProcessor::with_uploads);
+//! [`Upload`] sends content from a client:
+//!
+//! ```rust,ignore
+//! // Server
+//! let uploads = Uploads::new(|upload: CompletedUpload| async move {
+//!     let id = store.put(upload.name.as_deref(), &upload.data).await?;
+//!     Ok(json!({ "id": id }))
+//! });
+//! let processor = server.processor(transport).with_uploads(uploads);
+//!
+//! // Client
+//! let file = tokio::fs::File::open("report.pdf").await?;
+//! let stored = Upload::new()
+//!     .with_name("report.pdf")
+//!     .with_content_type("application/pdf")
+//!     .send(&mut client, file)
+//!     .await?;
+//! ```
+
+use crate::client::JsonRpcClient;
+use crate::error::helpers;
+use crate::processor::Tool;
+use crate::transport::Transport;
+use async_trait::async_trait;
+use mcp_error::Result as McpResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::Notify;
+
+/// Methods of the upload extension
+pub mod upload_methods {
+    /// Open an upload, returning its token
+    pub const OPEN: &str = "uploads/open";
+    /// Notification carrying a chunk of an upload
+    pub const CHUNK: &str = "notifications/uploads/chunk";
+    /// Finish an upload, returning the result of the upload handler
+    pub const FINISH: &str = "uploads/finish";
+    /// Notification discarding an upload
+    pub const CANCEL: &str = "notifications/uploads/cancel";
+}
+
+/// Default limit on the size of an upload
+pub const DEFAULT_MAX_UPLOAD_BYTES: usize = 64 * 1024 * 1024;
+/// Default limit on the decoded size of a chunk
+pub const DEFAULT_MAX_CHUNK_BYTES: usize = 256 * 1024;
+/// Default limit on the uploads open at once
+pub const DEFAULT_MAX_OPEN_UPLOADS: usize = 8;
+/// Default time after which an upload without chunks is dropped
+pub const DEFAULT_UPLOAD_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Params of `uploads/open`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenUpload {
+    /// Name of the content, such as a file name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Media type of the content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Size of the content, if known, checked against the limit up front and
+    /// against the content received on finish
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Anything else the upload handler needs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+/// Content of a finished upload, given to the [`UploadHandler`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletedUpload {
+    /// Token the upload was sent under
+    pub token: String,
+    /// How the client described the content
+    pub description: OpenUpload,
+    /// The content
+    pub data: Vec<u8>,
+}
+
+impl CompletedUpload {
+    /// Name of the content, if given
+    pub fn name(&self) -> Option<&str> {
+        self.description.name.as_deref()
+    }
+}
+
+/// Receives the content of finished uploads
+#[async_trait]
+pub trait UploadHandler: Send + Sync {
+    /// Store or process the content; the result answers `uploads/finish`
+    async fn complete(&self, upload: CompletedUpload) -> McpResult<Value>;
+}
+
+#[async_trait]
+impl<F, Fut> UploadHandler for F
+where
+    F: Fn(CompletedUpload) -> Fut + Send + Sync,
+    Fut: Future<Output = McpResult<Value>> + Send,
+{
+    async fn complete(&self, upload: CompletedUpload) -> McpResult<Value> {
+        self(upload).await
+    }
+}
+
+/// An upload being received
+struct Pending {
+    description: OpenUpload,
+    data: Vec<u8>,
+    /// Chunks received ahead of a missing one, by sequence number
+    ahead: BTreeMap<u64, Vec<u8>>,
+    /// Bytes in `data` and `ahead`
+    bytes: usize,
+    next_seq: u64,
+    last_activity: Instant,
+    failure: Option<String>,
+}
+
+impl Pending {
+    fn add_chunk(&mut self, seq: u64, chunk: Vec<u8>, max_bytes: usize) {
+        self.last_activity = Instant::now();
+        if self.failure.is_some() || seq < self.next_seq || self.ahead.contains_key(&seq) {
+            return;
+        }
+        self.bytes += chunk.len();
+        if self.bytes > max_bytes {
+            self.fail(format!("Upload is over the limit of {} bytes", max_bytes));
+            return;
+        }
+        if seq > self.next_seq {
+            self.ahead.insert(seq, chunk);
+            return;
+        }
+        self.data.extend_from_slice(&chunk);
+        self.next_seq += 1;
+        while let Some(chunk) = self.ahead.remove(&self.next_seq) {
+            self.data.extend_from_slice(&chunk);
+            self.next_seq += 1;
+        }
+    }
+
+    /// Record the first failure and drop the content received
+    fn fail(&mut self, reason: String) {
+        self.failure.get_or_insert(reason);
+        self.data = Vec::new();
+        self.ahead.clear();
+    }
+}
+
+struct State {
+    pending: Mutex<HashMap<String, Pending>>,
+    /// Woken whenever a chunk arrives, for finish requests waiting for chunks
+    arrived: Notify,
+    next_token: AtomicU64,
+    random: RandomState,
+}
+
+/// Server side of the upload extension
+///
+/// Clones share the uploads in progress. Tokens are not guessable, but an
+/// instance shared between connections lets any of them finish an upload
+/// opened by another; give each connection its own unless that is intended.
+#[derive(Clone)]
+pub struct Uploads {
+    handler: Arc<dyn UploadHandler>,
+    state: Arc<State>,
+    max_upload_bytes: usize,
+    max_chunk_bytes: usize,
+    max_open: usize,
+    idle_timeout: Duration,
+}
+
+impl Uploads {
+    /// Accept uploads, handing finished ones to `handler`
+    pub fn new<H: UploadHandler + 'static>(handler: H) -> Self {
+        Self {
+            handler: Arc::new(handler),
+            state: Arc::new(State {
+                pending: Mutex::new(HashMap::new()),
+                arrived: Notify::new(),
+                next_token: AtomicU64::new(1),
+                random: RandomState::new(),
+            }),
+            max_upload_bytes: DEFAULT_MAX_UPLOAD_BYTES,
+            max_chunk_bytes: DEFAULT_MAX_CHUNK_BYTES,
+            max_open: DEFAULT_MAX_OPEN_UPLOADS,
+            idle_timeout: DEFAULT_UPLOAD_IDLE_TIMEOUT,
+        }
+    }
+
+    /// Set the limit on the size of an upload
+    pub fn with_max_upload_bytes(mut self, max: usize) -> Self {
+        self.max_upload_bytes = max;
+        self
+    }
+
+    /// Set the limit on the decoded size of a chunk, advertised to clients
+    pub fn with_max_chunk_bytes(mut self, max: usize) -> Self {
+        self.max_chunk_bytes = max;
+        self
+    }
+
+    /// Set the limit on the uploads open at once
+    pub fn with_max_open(mut self, max: usize) -> Self {
+        self.max_open = max;
+        self
+    }
+
+    /// Set the time after which an upload without chunks is dropped
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Number of uploads in progress
+    pub fn open_uploads(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Handlers of the extension methods, to register on a processor
+    pub(crate) fn methods(&self) -> Vec<(&'static str, Arc<dyn Tool>)> {
+        vec![
+            (upload_methods::OPEN, Arc::new(OpenMethod(self.clone()))),
+            (upload_methods::CHUNK, Arc::new(ChunkMethod(self.clone()))),
+            (upload_methods::FINISH, Arc::new(FinishMethod(self.clone()))),
+            (upload_methods::CANCEL, Arc::new(CancelMethod(self.clone()))),
+        ]
+    }
+
+    fn open(&self, description: OpenUpload) -> McpResult<Value> {
+        if description
+            .size
+            .is_some_and(|size| size > self.max_upload_bytes as u64)
+        {
+            return Err(helpers::invalid_params(&format!(
+                "Upload is over the limit of {} bytes",
+                self.max_upload_bytes
+            )));
+        }
+
+        let mut pending = self.lock();
+        let now = Instant::now();
+        pending.retain(|_, upload| now.duration_since(upload.last_activity) < self.idle_timeout);
+        if pending.len() >= self.max_open {
+            return Err(helpers::overloaded(&format!(
+                "Too many uploads in progress (limit {})",
+                self.max_open
+            )));
+        }
+
+        let token = self.new_token();
+        pending.insert(
+            token.clone(),
+            Pending {
+                description,
+                data: Vec::new(),
+                ahead: BTreeMap::new(),
+                bytes: 0,
+                next_seq: 0,
+                last_activity: now,
+                failure: None,
+            },
+        );
+        Ok(json!({ "token": token, "maxChunkBytes": self.max_chunk_bytes }))
+    }
+
+    fn chunk(&self, chunk: Chunk) {
+        let mut pending = self.lock();
+        let upload = match pending.get_mut(&chunk.token) {
+            Some(upload) => upload,
+            None => return,
+        };
+        match decode_base64(&chunk.data) {
+            Some(data) if data.len() > self.max_chunk_bytes => upload.fail(format!(
+                "Chunk {} is over the limit of {} bytes",
+                chunk.seq, self.max_chunk_bytes
+            )),
+            Some(data) => upload.add_chunk(chunk.seq, data, self.max_upload_bytes),
+            None => upload.fail(format!("Chunk {} is not valid base64", chunk.seq)),
+        }
+        drop(pending);
+        self.state.arrived.notify_waiters();
+    }
+
+    async fn finish(&self, finish: Finish) -> McpResult<Value> {
+        let deadline = Instant::now() + self.idle_timeout;
+        let upload = loop {
+            let arrived = self.state.arrived.notified();
+            {
+                let mut pending = self.lock();
+                let upload = pending.get(&finish.token).ok_or_else(|| {
+                    helpers::invalid_params(&format!("Unknown upload '{}'", finish.token))
+                })?;
+                if upload.failure.is_some() || upload.next_seq >= finish.chunks {
+                    break pending.remove(&finish.token).expect("upload is pending");
+                }
+            }
+            if tokio::time::timeout_at(deadline.into(), arrived)
+                .await
+                .is_err()
+            {
+                let upload = self.lock().remove(&finish.token);
+                let received = upload.map_or(0, |upload| upload.next_seq);
+                return Err(helpers::invalid_params(&format!(
+                    "Upload '{}' is missing chunks: {} of {} received",
+                    finish.token, received, finish.chunks
+                )));
+            }
+        };
+
+        if let Some(failure) = upload.failure {
+            return Err(helpers::invalid_params(&failure));
+        }
+        if upload.next_seq > finish.chunks || !upload.ahead.is_empty() {
+            return Err(helpers::invalid_params(&format!(
+                "Upload '{}' has more chunks than the {} announced",
+                finish.token, finish.chunks
+            )));
+        }
+        if let Some(size) = upload.description.size {
+            if size != upload.data.len() as u64 {
+                return Err(helpers::invalid_params(&format!(
+                    "Upload '{}' is {} bytes long, {} announced",
+                    finish.token,
+                    upload.data.len(),
+                    size
+                )));
+            }
+        }
+
+        self.handler
+            .complete(CompletedUpload {
+                token: finish.token,
+                description: upload.description,
+                data: upload.data,
+            })
+            .await
+    }
+
+    fn cancel(&self, token: &str) {
+        self.lock().remove(token);
+        self.state.arrived.notify_waiters();
+    }
+
+    fn new_token(&self) -> String {
+        let counter = self.state.next_token.fetch_add(1, Ordering::Relaxed);
+        let mut hasher = self.state.random.build_hasher();
+        hasher.write_u64(counter);
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        format!("up-{}-{:016x}", counter, hasher.finish())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Pending>> {
+        self.state.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Deserialize)]
+struct Chunk {
+    token: String,
+    seq: u64,
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct Finish {
+    token: String,
+    chunks: u64,
+}
+
+#[derive(Deserialize)]
+struct Cancel {
+    token: String,
+}
+
+fn parse_params<P: serde::de::DeserializeOwned>(params: Value) -> McpResult<P> {
+    serde_json::from_value(params)
+        .map_err(|e| helpers::invalid_params(&format!("Invalid params: {}", e)))
+}
+
+/// Answers `uploads/open`
+struct OpenMethod(Uploads);
+
+#[async_trait]
+impl Tool for OpenMethod {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        self.0.open(parse_params(params)?)
+    }
+}
+
+/// Handles `notifications/uploads/chunk`
+struct ChunkMethod(Uploads);
+
+#[async_trait]
+impl Tool for ChunkMethod {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        self.0.chunk(parse_params(params)?);
+        Ok(Value::Null)
+    }
+}
+
+/// Answers `uploads/finish`
+struct FinishMethod(Uploads);
+
+#[async_trait]
+impl Tool for FinishMethod {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        self.0.finish(parse_params(params)?).await
+    }
+}
+
+/// Handles `notifications/uploads/cancel`
+struct CancelMethod(Uploads);
+
+#[async_trait]
+impl Tool for CancelMethod {
+    async fn execute(&self, params: Value) -> McpResult<Value> {
+        let cancel: Cancel = parse_params(params)?;
+        self.0.cancel(&cancel.token);
+        Ok(Value::Null)
+    }
+}
+
+/// Client side of the upload extension
+#[derive(Debug, Clone, Default)]
+pub struct Upload {
+    description: OpenUpload,
+    chunk_bytes: Option<usize>,
+}
+
+impl Upload {
+    /// Describe an upload; every field is optional
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the name of the content
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.description.name = Some(name.into());
+        self
+    }
+
+    /// Set the media type of the content
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.description.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Announce the size of the content
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.description.size = Some(size);
+        self
+    }
+
+    /// Pass anything else the server's upload handler needs
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.description.metadata = Some(metadata);
+        self
+    }
+
+    /// Send chunks of at most `bytes`, if the server accepts chunks that large
+    pub fn with_chunk_bytes(mut self, bytes: usize) -> Self {
+        self.chunk_bytes = Some(bytes);
+        self
+    }
+
+    /// Open an upload, stream the content read from `content` and finish it,
+    /// returning the result of the server's upload handler
+    ///
+    /// The upload is cancelled if reading the content fails.
+    pub async fn send<T, R>(
+        &self,
+        client: &mut JsonRpcClient<T>,
+        mut content: R,
+    ) -> McpResult<Value>
+    where
+        T: Transport,
+        R: AsyncRead + Unpin + Send,
+    {
+        let params = serde_json::to_value(&self.description).map_err(helpers::json_error)?;
+        let opened = client.call(upload_methods::OPEN, Some(params)).await?;
+        let token = opened
+            .get("token")
+            .and_then(Value::as_str)
+            .ok_or_else(|| helpers::protocol_error("uploads/open returned no token"))?
+            .to_string();
+        let max = opened
+            .get("maxChunkBytes")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_MAX_CHUNK_BYTES, |max| max as usize);
+        let chunk_bytes = self.chunk_bytes.map_or(max, |bytes| bytes.min(max)).max(1);
+
+        let mut buffer = vec![0; chunk_bytes];
+        let mut seq = 0;
+        loop {
+            let filled = match read_full(&mut content, &mut buffer).await {
+                Ok(filled) => filled,
+                Err(e) => {
+                    let cancel = json!({ "token": token });
+                    let _ = client.notify(upload_methods::CANCEL, Some(cancel)).await;
+                    return Err(helpers::transport_error(&format!(
+                        "Failed to read upload content: {}",
+                        e
+                    )));
+                }
+            };
+            if filled == 0 {
+                break;
+            }
+            let chunk = json!({
+                "token": token,
+                "seq": seq,
+                "data": encode_base64(&buffer[..filled]),
+            });
+            client.notify(upload_methods::CHUNK, Some(chunk)).await?;
+            seq += 1;
+            if filled < buffer.len() {
+                break;
+            }
+        }
+
+        let finish = json!({ "token": token, "chunks": seq });
+        client.call(upload_methods::FINISH, Some(finish)).await
+    }
+}
+
+/// Fill the buffer unless the content ends first, returning the bytes read
+async fn read_full<R: AsyncRead + Unpin>(
+    content: &mut R,
+    buffer: &mut [u8],
+) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match content.read(&mut buffer[filled..]).await? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64, with padding
+pub(crate) fn encode_base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for group in data.chunks(3) {
+        let bytes = [
+            group[0],
+            *group.get(1).unwrap_or(&0),
+            *group.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for index in 0..4 {
+            if index <= group.len() {
+                let sextet = (bits >> (18 - 6 * index)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[sextet as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode standard base64, with or without padding
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    if encoded.len() % 4 == 1 {
+        return None;
+    }
+    let mut data = Vec::with_capacity(encoded.len() * 3 / 4);
+    for group in encoded.chunks(4) {
+        let mut bits = 0u32;
+        for (index, byte) in group.iter().enumerate() {
+            bits |= sextet(*byte)? << (18 - 6 * index);
+        }
+        let bytes = bits.to_be_bytes();
+        data.extend_from_slice(&bytes[1..group.len()]);
+    }
+    Some(data)
+}
+
+fn sextet(byte: u8) -> Option<u32> {
+    let value = match byte {
+        b'A'..=b'Z' => byte - b'A',
+        b'a'..=b'z' => byte - b'a' + 26,
+        b'0'..=b'9' => byte - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => return None,
+    };
+    Some(value as u32)
+}
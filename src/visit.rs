@@ -0,0 +1,242 @@
+//! In-place traversal of JSON-RPC messages
+//!
+//! Middlewares that redact, rewrite or sign messages usually care about a few
+//! parts of a message: the method name, the id, and some nodes inside the
+//! params, result or error data. A [`VisitMut`] implementation receives mutable
+//! access to exactly those parts, so changes are made where the values live
+//! instead of cloning and rebuilding the whole `Value` tree.
+//!
+//! ```rust,no_run
+//! use mcp_jsonrpc::visit::{Walk, ValuePath, VisitMut};
+//! use mcp_jsonrpc::JsonRpcMessage;
+//! use serde_json::Value;
+//!
+//! struct RedactPasswords;
+//!
+//! impl VisitMut for RedactPasswords {
+//!     fn visit_value(&mut self, path: &ValuePath, value: &mut Value) -> Walk {
+//!         if path.last_key() == Some("password") {
+//!             *value = Value::String("***".into());
+//!             return Walk::Skip;
+//!         }
+//!         Walk::Continue
+//!     }
+//! }
+//!
+//! let mut message = JsonRpcMessage::parse(
+//!     r#"{"jsonrpc":"2.0","method":"login","params":{"password":"hunter2"},"id":1}"#,
+//! ).unwrap();
+//! message.visit_mut(&mut RedactPasswords);
+//! ```
+
+use crate::protocol::{
+    JsonRpcError, JsonRpcId, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+};
+use serde_json::Value;
+use std::fmt;
+
+/// The payload of a message a value belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Payload {
+    /// Request or notification params
+    Params,
+    /// Successful response result
+    Result,
+    /// Error object `data`
+    ErrorData,
+}
+
+/// One step from a container to one of its members
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// Object member
+    Key(String),
+    /// Array element
+    Index(usize),
+}
+
+/// Location of a value inside a message payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValuePath {
+    payload: Payload,
+    segments: Vec<PathSegment>,
+}
+
+impl ValuePath {
+    /// Path to the root of the given payload
+    pub fn new(payload: Payload) -> Self {
+        Self {
+            payload,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Payload the path starts from
+    pub fn payload(&self) -> Payload {
+        self.payload
+    }
+
+    /// Steps from the payload root, outermost first
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// Whether the path designates the payload root
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Name of the member holding the value, if its parent is an object
+    pub fn last_key(&self) -> Option<&str> {
+        match self.segments.last() {
+            Some(PathSegment::Key(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// JSON Pointer (RFC 6901) to the value, relative to the payload root
+    pub fn pointer(&self) -> String {
+        let mut pointer = String::new();
+        for segment in &self.segments {
+            pointer.push('/');
+            match segment {
+                PathSegment::Key(key) => {
+                    pointer.push_str(&key.replace('~', "~0").replace('/', "~1"))
+                }
+                PathSegment::Index(index) => pointer.push_str(&index.to_string()),
+            }
+        }
+        pointer
+    }
+}
+
+impl fmt::Display for ValuePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let payload = match self.payload {
+            Payload::Params => "params",
+            Payload::Result => "result",
+            Payload::ErrorData => "error.data",
+        };
+        write!(f, "{}{}", payload, self.pointer())
+    }
+}
+
+/// Whether to descend into the members of a visited value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    /// Visit the value's members
+    Continue,
+    /// Leave the value's members alone
+    Skip,
+}
+
+/// Visitor with mutable access to the parts of a message
+///
+/// Every method has a default, so implementations override only what they
+/// need. By default [`visit_payload`](Self::visit_payload) walks the whole
+/// payload depth-first, calling [`visit_value`](Self::visit_value) on each node
+/// before its members.
+pub trait VisitMut {
+    /// Called with the method name of each request and notification
+    fn visit_method(&mut self, _method: &mut String) {}
+
+    /// Called with the id of each request and response
+    fn visit_id(&mut self, _id: &mut JsonRpcId) {}
+
+    /// Called with the error object of each failed response, before its data
+    fn visit_error(&mut self, _error: &mut JsonRpcError) {}
+
+    /// Called with the root of each params, result and error data payload
+    ///
+    /// `method` is known for requests and notifications only; responses do not
+    /// carry the method they answer.
+    fn visit_payload(&mut self, payload: Payload, _method: Option<&str>, value: &mut Value) {
+        walk_value(self, &mut ValuePath::new(payload), value);
+    }
+
+    /// Called with each node of a payload during the default walk
+    fn visit_value(&mut self, _path: &ValuePath, _value: &mut Value) -> Walk {
+        Walk::Continue
+    }
+}
+
+/// Walk a value depth-first, calling [`VisitMut::visit_value`] on each node
+///
+/// `path` designates `value` on entry and is restored before returning, so
+/// custom [`VisitMut::visit_payload`] implementations can walk subtrees with it.
+pub fn walk_value<V: VisitMut + ?Sized>(visitor: &mut V, path: &mut ValuePath, value: &mut Value) {
+    if visitor.visit_value(path, value) == Walk::Skip {
+        return;
+    }
+
+    match value {
+        Value::Object(object) => {
+            for (key, member) in object.iter_mut() {
+                path.segments.push(PathSegment::Key(key.clone()));
+                walk_value(visitor, path, member);
+                path.segments.pop();
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                path.segments.push(PathSegment::Index(index));
+                walk_value(visitor, path, item);
+                path.segments.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+impl JsonRpcRequest {
+    /// Pass the method, id and params to a visitor
+    pub fn visit_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        visitor.visit_method(&mut self.method);
+        visitor.visit_id(&mut self.id);
+        if let Some(params) = &mut self.params {
+            visitor.visit_payload(Payload::Params, Some(&self.method), params);
+        }
+    }
+}
+
+impl JsonRpcNotification {
+    /// Pass the method and params to a visitor
+    pub fn visit_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        visitor.visit_method(&mut self.method);
+        if let Some(params) = &mut self.params {
+            visitor.visit_payload(Payload::Params, Some(&self.method), params);
+        }
+    }
+}
+
+impl JsonRpcResponse {
+    /// Pass the id and the result or error to a visitor
+    pub fn visit_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        visitor.visit_id(&mut self.id);
+        if let Some(result) = &mut self.result {
+            visitor.visit_payload(Payload::Result, None, result);
+        }
+        if let Some(error) = &mut self.error {
+            visitor.visit_error(error);
+            if let Some(data) = &mut error.data {
+                visitor.visit_payload(Payload::ErrorData, None, data);
+            }
+        }
+    }
+}
+
+impl JsonRpcMessage {
+    /// Pass the parts of the message, or of each batch element, to a visitor
+    pub fn visit_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        match self {
+            JsonRpcMessage::Request(request) => request.visit_mut(visitor),
+            JsonRpcMessage::Notification(notification) => notification.visit_mut(visitor),
+            JsonRpcMessage::Response(response) => response.visit_mut(visitor),
+            JsonRpcMessage::Batch(messages) => {
+                for message in messages {
+                    message.visit_mut(visitor);
+                }
+            }
+        }
+    }
+}